@@ -0,0 +1,221 @@
+//! Looks up fingerprinted local files on AcoustID to find the MusicBrainz
+//! recording they contain, which helps with files whose tags are missing or
+//! wrong.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::time::sleep;
+
+use crate::endpoints::{env_value, ClientIdentity, Endpoint, EndpointSettings, ACOUSTID_PROVIDER};
+use crate::errors::CommandError;
+use crate::fingerprint;
+use crate::library::{
+    lock, AcoustidMatchRecord, AcoustidMatchStatus, FingerprintTarget, LibraryStore,
+};
+
+const API_KEY_ENV: &str = "ACOUSTID_API_KEY";
+/// The public service allows three requests per second.
+const OFFICIAL_INTERVAL: Duration = Duration::from_millis(334);
+/// Servers on this machine or the local network have no rate limit of their
+/// own.
+const MIRROR_INTERVAL: Duration = Duration::ZERO;
+/// Results scored lower than this are too uncertain to tag a file with.
+const MIN_SCORE: f64 = 0.8;
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcoustidSummary {
+    pub looked_up: u32,
+    pub matched: u32,
+    pub unmatched: u32,
+    pub failed: u32,
+}
+
+pub struct AcoustidClient {
+    library: Arc<Mutex<LibraryStore>>,
+    client: Client,
+    api_key: Option<String>,
+}
+
+impl AcoustidClient {
+    pub fn new(
+        library: Arc<Mutex<LibraryStore>>,
+        identity: &ClientIdentity,
+    ) -> Result<Self, CommandError> {
+        let client = Client::builder()
+            .user_agent(format!("{}/{}", identity.name, identity.version))
+            .build()
+            .map_err(|error| {
+                CommandError::internal(format!("failed to build AcoustID client: {error}"))
+            })?;
+        let api_key = env_value(API_KEY_ENV);
+        Ok(Self {
+            library,
+            client,
+            api_key,
+        })
+    }
+
+    /// Looks up `track_ids`, or every fingerprinted track that has not been
+    /// looked up successfully yet, and records the best recording of each.
+    pub async fn lookup(&self, track_ids: &[String]) -> Result<AcoustidSummary, CommandError> {
+        let api_key = self
+            .api_key
+            .as_deref()
+            .ok_or_else(|| CommandError::validation(format!("{API_KEY_ENV} is not set")))?;
+        let targets = self.lock()?.list_acoustid_targets(track_ids)?;
+        let endpoint = self.load_endpoint();
+        let interval = request_interval(&endpoint);
+
+        let mut summary = AcoustidSummary::default();
+        let mut last_request: Option<Instant> = None;
+        for target in targets {
+            if let Some(last_request) = last_request {
+                let elapsed = last_request.elapsed();
+                if elapsed < interval {
+                    sleep(interval - elapsed).await;
+                }
+            }
+            last_request = Some(Instant::now());
+
+            let record = match self.lookup_target(&endpoint, api_key, &target).await {
+                Ok(record) => record,
+                Err(message) => {
+                    log::warn!("lookup failed for {}: {message}", target.track_id);
+                    AcoustidMatchRecord {
+                        track_id: target.track_id.clone(),
+                        status: AcoustidMatchStatus::Error,
+                        acoustid_id: None,
+                        recording_id: None,
+                        score: None,
+                        message: Some(message),
+                    }
+                }
+            };
+            summary.looked_up += 1;
+            match record.status {
+                AcoustidMatchStatus::Success => summary.matched += 1,
+                AcoustidMatchStatus::NoMatch => summary.unmatched += 1,
+                AcoustidMatchStatus::Error => summary.failed += 1,
+            }
+            self.lock()?.record_acoustid_match(&record)?;
+        }
+
+        Ok(summary)
+    }
+
+    async fn lookup_target(
+        &self,
+        endpoint: &Endpoint,
+        api_key: &str,
+        target: &FingerprintTarget,
+    ) -> Result<AcoustidMatchRecord, String> {
+        let duration = target
+            .duration_ms
+            .filter(|value| *value > 0)
+            .ok_or_else(|| "the file has no known duration".to_string())?;
+        let form = [
+            ("client", api_key.to_string()),
+            ("meta", "recordings".to_string()),
+            ("duration", (duration / 1000).to_string()),
+            ("fingerprint", fingerprint::encode(&target.fingerprint)),
+        ];
+        let response: Value = self
+            .client
+            .post(endpoint.url("lookup"))
+            .form(&form)
+            .timeout(endpoint.timeout)
+            .send()
+            .await
+            .map_err(|error| error.to_string())?
+            .json()
+            .await
+            .map_err(|error| error.to_string())?;
+
+        if response.get("status").and_then(Value::as_str) != Some("ok") {
+            let message = response
+                .pointer("/error/message")
+                .and_then(Value::as_str)
+                .unwrap_or("unexpected response");
+            return Err(format!("AcoustID error: {message}"));
+        }
+
+        Ok(best_match(&target.track_id, &response))
+    }
+
+    fn load_endpoint(&self) -> Endpoint {
+        match self.library.lock() {
+            Ok(store) => store
+                .load_endpoint_settings(ACOUSTID_PROVIDER)
+                .unwrap_or_else(|error| {
+                    log::warn!("failed to load endpoint settings, using defaults: {error}");
+                    EndpointSettings::unset(ACOUSTID_PROVIDER)
+                }),
+            Err(_) => EndpointSettings::unset(ACOUSTID_PROVIDER),
+        }
+        .resolve()
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, LibraryStore>, CommandError> {
+        Ok(lock(&self.library)?)
+    }
+}
+
+/// Picks the highest scored result that links to at least one recording.
+fn best_match(track_id: &str, response: &Value) -> AcoustidMatchRecord {
+    let best = response
+        .get("results")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|result| {
+            let score = result.get("score").and_then(Value::as_f64)?;
+            let recording = result
+                .get("recordings")
+                .and_then(Value::as_array)
+                .and_then(|recordings| recordings.first())
+                .and_then(|recording| recording.get("id"))
+                .and_then(Value::as_str)?;
+            let id = result.get("id").and_then(Value::as_str);
+            Some((score, id, recording))
+        })
+        .max_by(|left, right| left.0.total_cmp(&right.0));
+
+    match best {
+        Some((score, acoustid_id, recording_id)) if score >= MIN_SCORE => AcoustidMatchRecord {
+            track_id: track_id.to_string(),
+            status: AcoustidMatchStatus::Success,
+            acoustid_id: acoustid_id.map(str::to_string),
+            recording_id: Some(recording_id.to_string()),
+            score: Some(score as f32),
+            message: None,
+        },
+        best => AcoustidMatchRecord {
+            track_id: track_id.to_string(),
+            status: AcoustidMatchStatus::NoMatch,
+            acoustid_id: None,
+            recording_id: None,
+            score: best.map(|(score, _, _)| score as f32),
+            message: Some("no recording scored high enough".to_string()),
+        },
+    }
+}
+
+/// Uses the configured interval when there is one. Otherwise a server on a
+/// loopback or private address is not rate limited, and every other host gets
+/// the public service's documented three requests per second.
+fn request_interval(endpoint: &Endpoint) -> Duration {
+    if let Some(interval) = endpoint.min_interval {
+        return interval;
+    }
+
+    if endpoint.is_private_host() {
+        MIRROR_INTERVAL
+    } else {
+        OFFICIAL_INTERVAL
+    }
+}
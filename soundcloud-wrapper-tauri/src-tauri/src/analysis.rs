@@ -0,0 +1,638 @@
+//! Offline BPM and key analysis of local files. Tempo comes from the
+//! autocorrelation of a spectral-flux onset envelope, the key from a chroma
+//! profile matched against the Krumhansl-Kessler key profiles. Loudness is
+//! measured in the same pass. Results are stored per track so the status
+//! view can filter for harmonic mixing.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use serde::Serialize;
+use symphonia::core::audio::{AudioBufferRef, SampleBuffer, SignalSpec};
+
+use crate::background::{JobKind, JobQueue};
+use crate::errors::CommandError;
+use crate::events::EventSink;
+use crate::library::{lock, AudioAnalysisRecord, AudioAnalysisStatus, LibraryStore};
+use crate::loudness::{Loudness, LoudnessMeter};
+use crate::rekordbox::analyze_file;
+
+const JOB_ID: &str = "audio-analysis";
+const JOB_LABEL: &str = "Análisis de BPM, tonalidad y sonoridad";
+
+/// Stored with each result. Files analyzed by an older version are analyzed
+/// again, so measurements added later reach the whole library.
+pub(crate) const ANALYSIS_VERSION: i64 = 2;
+
+/// Audio is downmixed to mono and decimated to roughly this rate before it
+/// is analyzed; neither tempo nor key needs more bandwidth.
+const ANALYSIS_RATE: u32 = 11_025;
+/// Files shorter than this get no BPM or key.
+const MIN_ANALYSIS_SECONDS: f32 = 10.0;
+
+const TEMPO_FRAME: usize = 1024;
+const TEMPO_HOP: usize = 128;
+const MIN_BPM: f32 = 60.0;
+const MAX_BPM: f32 = 200.0;
+/// Tempo preferred when two candidates score alike, as in most dance music.
+const PREFERRED_BPM: f32 = 120.0;
+/// The beat period is refined on the lag of this many beats, where one
+/// frame of error weighs less.
+const REFINE_BEATS: usize = 4;
+
+const KEY_FRAME: usize = 8192;
+const KEY_HOP: usize = 4096;
+const KEY_MIN_HZ: f32 = 55.0;
+const KEY_MAX_HZ: f32 = 2_000.0;
+
+/// Krumhansl-Kessler probe-tone profiles, starting on the tonic.
+const MAJOR_PROFILE: [f32; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f32; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+const NOTE_NAMES: [&str; 12] = [
+    "C", "Db", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B",
+];
+
+/// A key as a pitch class (C = 0) and mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MusicalKey {
+    pub tonic: u8,
+    pub minor: bool,
+}
+
+impl MusicalKey {
+    /// Standard notation, e.g. `Am` or `F#`.
+    pub fn name(&self) -> String {
+        let note = NOTE_NAMES[usize::from(self.tonic % 12)];
+        if self.minor {
+            format!("{note}m")
+        } else {
+            note.to_string()
+        }
+    }
+
+    /// Camelot wheel code, e.g. `8A` for A minor and `8B` for C major.
+    pub fn camelot(&self) -> String {
+        // Minor keys share their number with the relative major.
+        let major_tonic = if self.minor {
+            (self.tonic + 3) % 12
+        } else {
+            self.tonic
+        };
+        let number = (7 * u32::from(major_tonic) + 8) % 12;
+        let number = if number == 0 { 12 } else { number };
+        format!("{number}{}", if self.minor { 'A' } else { 'B' })
+    }
+}
+
+/// The Camelot codes that mix harmonically with `code`: the same key, its
+/// neighbours on the wheel and its relative major or minor. Returns `None`
+/// when `code` is not a Camelot code.
+pub fn compatible_camelot_keys(code: &str) -> Option<Vec<String>> {
+    let code = code.trim().to_uppercase();
+    let letter = code.chars().last().filter(|c| *c == 'A' || *c == 'B')?;
+    let number: u32 = code[..code.len() - 1].parse().ok()?;
+    if !(1..=12).contains(&number) {
+        return None;
+    }
+    let other = if letter == 'A' { 'B' } else { 'A' };
+    let previous = if number == 1 { 12 } else { number - 1 };
+    let next = if number == 12 { 1 } else { number + 1 };
+    Some(vec![
+        format!("{number}{letter}"),
+        format!("{previous}{letter}"),
+        format!("{next}{letter}"),
+        format!("{number}{other}"),
+    ])
+}
+
+#[derive(Debug, Clone)]
+pub struct AudioAnalysis {
+    pub bpm: Option<f32>,
+    pub key: Option<MusicalKey>,
+    /// Correlation of the chroma profile with the key profile, from -1 to 1.
+    pub key_confidence: Option<f32>,
+    pub loudness: Option<Loudness>,
+}
+
+/// Collects a mono, decimated copy of the decoded audio for the BPM and key
+/// analysis, and feeds the full audio to the loudness meter.
+pub(crate) struct AnalysisBuilder {
+    meter: Option<LoudnessMeter>,
+    factor: usize,
+    rate: f32,
+    pending: f32,
+    pending_frames: usize,
+    samples: Vec<f32>,
+    buffer: Option<(usize, SampleBuffer<f32>)>,
+}
+
+impl AnalysisBuilder {
+    pub(crate) fn start(spec: &SignalSpec) -> Option<Self> {
+        if spec.rate == 0 || spec.channels.count() == 0 {
+            return None;
+        }
+        let factor = (spec.rate / ANALYSIS_RATE).max(1) as usize;
+        Some(Self {
+            meter: LoudnessMeter::new(spec),
+            factor,
+            rate: spec.rate as f32 / factor as f32,
+            pending: 0.0,
+            pending_frames: 0,
+            samples: Vec::new(),
+            buffer: None,
+        })
+    }
+
+    pub(crate) fn consume(&mut self, decoded: AudioBufferRef<'_>) {
+        let spec = *decoded.spec();
+        let channels = spec.channels.count();
+        let capacity = decoded.capacity();
+        let frames = decoded.frames();
+        if channels == 0 {
+            return;
+        }
+
+        if !matches!(&self.buffer, Some((size, _)) if *size >= capacity) {
+            self.buffer = Some((capacity, SampleBuffer::new(capacity as u64, spec)));
+        }
+        let Some((_, buffer)) = &mut self.buffer else {
+            return;
+        };
+        buffer.copy_interleaved_ref(decoded);
+        let samples = &buffer.samples()[..frames * channels];
+        if let Some(meter) = self.meter.as_mut() {
+            meter.consume(samples);
+        }
+
+        for frame in samples.chunks_exact(channels) {
+            self.pending += frame.iter().sum::<f32>() / channels as f32;
+            self.pending_frames += 1;
+            if self.pending_frames == self.factor {
+                self.samples.push(self.pending / self.factor as f32);
+                self.pending = 0.0;
+                self.pending_frames = 0;
+            }
+        }
+    }
+
+    /// Returns `None` when nothing could be measured at all.
+    pub(crate) fn finish(self) -> Option<AudioAnalysis> {
+        let loudness = self.meter.and_then(LoudnessMeter::finish);
+        let (bpm, key) = if (self.samples.len() as f32) < self.rate * MIN_ANALYSIS_SECONDS {
+            (None, None)
+        } else {
+            let mut planner = FftPlanner::new();
+            (
+                estimate_tempo(&mut planner, &self.samples, self.rate),
+                estimate_key(&mut planner, &self.samples, self.rate),
+            )
+        };
+        if bpm.is_none() && key.is_none() && loudness.is_none() {
+            return None;
+        }
+        Some(AudioAnalysis {
+            bpm,
+            key: key.map(|(key, _)| key),
+            key_confidence: key.map(|(_, confidence)| confidence),
+            loudness,
+        })
+    }
+}
+
+/// Calls `visit` with the magnitude spectrum of each Hann-windowed frame.
+fn for_each_spectrum(
+    planner: &mut FftPlanner<f32>,
+    samples: &[f32],
+    frame: usize,
+    hop: usize,
+    mut visit: impl FnMut(&[f32]),
+) {
+    let fft: Arc<dyn Fft<f32>> = planner.plan_fft_forward(frame);
+    let window: Vec<f32> = (0..frame)
+        .map(|index| {
+            let phase = 2.0 * std::f32::consts::PI * index as f32 / frame as f32;
+            0.5 - 0.5 * phase.cos()
+        })
+        .collect();
+    let mut buffer = vec![Complex::new(0.0, 0.0); frame];
+    let mut magnitudes = vec![0.0; frame / 2 + 1];
+
+    let mut start = 0;
+    while start + frame <= samples.len() {
+        for ((slot, sample), weight) in buffer
+            .iter_mut()
+            .zip(&samples[start..start + frame])
+            .zip(&window)
+        {
+            *slot = Complex::new(sample * weight, 0.0);
+        }
+        fft.process(&mut buffer);
+        for (magnitude, value) in magnitudes.iter_mut().zip(&buffer) {
+            *magnitude = value.norm();
+        }
+        visit(&magnitudes);
+        start += hop;
+    }
+}
+
+fn estimate_tempo(planner: &mut FftPlanner<f32>, samples: &[f32], rate: f32) -> Option<f32> {
+    // Onset strength: the summed rise of the log-compressed spectrum.
+    let mut envelope = Vec::new();
+    let mut previous: Option<Vec<f32>> = None;
+    for_each_spectrum(planner, samples, TEMPO_FRAME, TEMPO_HOP, |magnitudes| {
+        let current: Vec<f32> = magnitudes
+            .iter()
+            .map(|value| (1.0 + 100.0 * value).ln())
+            .collect();
+        if let Some(previous) = &previous {
+            let flux = current
+                .iter()
+                .zip(previous)
+                .map(|(now, before)| (now - before).max(0.0))
+                .sum::<f32>();
+            envelope.push(flux);
+        }
+        previous = Some(current);
+    });
+
+    // Subtracting a moving average keeps the peaks and drops the loudness.
+    let frames_per_second = rate / TEMPO_HOP as f32;
+    let half_window = (frames_per_second / 4.0) as usize;
+    let onsets: Vec<f32> = (0..envelope.len())
+        .map(|index| {
+            let from = index.saturating_sub(half_window);
+            let to = (index + half_window + 1).min(envelope.len());
+            let mean = envelope[from..to].iter().sum::<f32>() / (to - from) as f32;
+            (envelope[index] - mean).max(0.0)
+        })
+        .collect();
+
+    let min_lag = (60.0 * frames_per_second / MAX_BPM).floor().max(1.0) as usize;
+    let max_lag = (60.0 * frames_per_second / MIN_BPM).ceil() as usize;
+    if onsets.len() <= max_lag * REFINE_BEATS * 2 {
+        return None;
+    }
+    let autocorrelation = |lag: usize| {
+        let products: f32 = onsets
+            .iter()
+            .zip(&onsets[lag..])
+            .map(|(left, right)| left * right)
+            .sum();
+        products / (onsets.len() - lag) as f32
+    };
+
+    let mut best: Option<(usize, f32)> = None;
+    for lag in min_lag..=max_lag {
+        let bpm = 60.0 * frames_per_second / lag as f32;
+        let octaves = (bpm / PREFERRED_BPM).log2();
+        let weight = (-0.5 * (octaves / 0.9).powi(2)).exp();
+        let score = weight * (autocorrelation(lag) + 0.5 * autocorrelation(lag * 2));
+        if best.is_none_or(|(_, best_score)| score > best_score) {
+            best = Some((lag, score));
+        }
+    }
+    let (lag, score) = best?;
+    if score <= 0.0 {
+        return None;
+    }
+
+    // Refine on the period of several beats and interpolate between frames.
+    let target = lag * REFINE_BEATS;
+    let (peak, _) = (target - 2..=target + 2)
+        .map(|lag| (lag, autocorrelation(lag)))
+        .max_by(|left, right| left.1.total_cmp(&right.1))?;
+    let (before, at, after) = (
+        autocorrelation(peak - 1),
+        autocorrelation(peak),
+        autocorrelation(peak + 1),
+    );
+    let curvature = before - 2.0 * at + after;
+    let offset = if curvature < 0.0 {
+        (0.5 * (before - after) / curvature).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    let period = (peak as f32 + offset) / REFINE_BEATS as f32;
+    let bpm = 60.0 * frames_per_second / period;
+    Some((bpm * 10.0).round() / 10.0)
+}
+
+fn estimate_key(
+    planner: &mut FftPlanner<f32>,
+    samples: &[f32],
+    rate: f32,
+) -> Option<(MusicalKey, f32)> {
+    let bin_hz = rate / KEY_FRAME as f32;
+    let pitch_classes: Vec<Option<usize>> = (0..=KEY_FRAME / 2)
+        .map(|bin| {
+            let frequency = bin as f32 * bin_hz;
+            if !(KEY_MIN_HZ..=KEY_MAX_HZ).contains(&frequency) {
+                return None;
+            }
+            let midi = 69.0 + 12.0 * (frequency / 440.0).log2();
+            Some((midi.round() as i32).rem_euclid(12) as usize)
+        })
+        .collect();
+
+    let mut chroma = [0.0f32; 12];
+    for_each_spectrum(planner, samples, KEY_FRAME, KEY_HOP, |magnitudes| {
+        for (magnitude, pitch_class) in magnitudes.iter().zip(&pitch_classes) {
+            if let Some(pitch_class) = pitch_class {
+                chroma[*pitch_class] += magnitude;
+            }
+        }
+    });
+    if chroma.iter().sum::<f32>() <= 0.0 {
+        return None;
+    }
+
+    let mut best: Option<(MusicalKey, f32)> = None;
+    for tonic in 0..12u8 {
+        for (profile, minor) in [(&MAJOR_PROFILE, false), (&MINOR_PROFILE, true)] {
+            let rotated: [f32; 12] =
+                std::array::from_fn(|pitch| profile[(pitch + 12 - usize::from(tonic)) % 12]);
+            let correlation = pearson(&chroma, &rotated);
+            if best.is_none_or(|(_, best_correlation)| correlation > best_correlation) {
+                best = Some((MusicalKey { tonic, minor }, correlation));
+            }
+        }
+    }
+    best
+}
+
+fn pearson(left: &[f32; 12], right: &[f32; 12]) -> f32 {
+    let mean = |values: &[f32; 12]| values.iter().sum::<f32>() / 12.0;
+    let (left_mean, right_mean) = (mean(left), mean(right));
+    let mut covariance = 0.0;
+    let mut left_variance = 0.0;
+    let mut right_variance = 0.0;
+    for (a, b) in left.iter().zip(right) {
+        covariance += (a - left_mean) * (b - right_mean);
+        left_variance += (a - left_mean).powi(2);
+        right_variance += (b - right_mean).powi(2);
+    }
+    let denominator = (left_variance * right_variance).sqrt();
+    if denominator > 0.0 {
+        covariance / denominator
+    } else {
+        0.0
+    }
+}
+
+/// Counts reported back after an analysis run.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalysisSummary {
+    pub analyzed: u32,
+    pub failed: u32,
+}
+
+/// Analyzes `track_ids`, or every available local file that has not been
+/// analyzed at its current location, and stores the results. `progress` is
+/// called with the number of finished and total tracks after each one.
+pub fn analyze_tracks(
+    library: &Arc<Mutex<LibraryStore>>,
+    track_ids: &[String],
+    mut progress: impl FnMut(u32, u32),
+) -> Result<AnalysisSummary, CommandError> {
+    let targets = lock(library)?.list_analysis_targets(track_ids)?;
+    let total = targets.len() as u32;
+    progress(0, total);
+
+    let mut summary = AnalysisSummary::default();
+    for (index, target) in targets.into_iter().enumerate() {
+        let outcome = match analyze_file(Path::new(&target.location)) {
+            Ok(Some(analysis)) => Ok(analysis),
+            Ok(None) => Err("no audio to analyze".to_string()),
+            Err(error) => {
+                log::warn!("failed to decode {}: {error}", target.location);
+                Err(error.to_string())
+            }
+        };
+        let record = match outcome {
+            Ok(analysis) => {
+                summary.analyzed += 1;
+                let loudness = analysis.loudness.as_ref();
+                AudioAnalysisRecord {
+                    track_id: target.track_id,
+                    location: target.location,
+                    status: AudioAnalysisStatus::Success,
+                    bpm: analysis.bpm,
+                    musical_key: analysis.key.map(|key| key.name()),
+                    camelot: analysis.key.map(|key| key.camelot()),
+                    key_confidence: analysis.key_confidence,
+                    integrated_lufs: loudness.and_then(|loudness| loudness.integrated_lufs),
+                    loudness_range: loudness.and_then(|loudness| loudness.loudness_range),
+                    true_peak_dbtp: loudness.map(|loudness| loudness.true_peak_dbtp),
+                    replaygain_gain: loudness.and_then(Loudness::replaygain_gain),
+                    replaygain_peak: loudness.map(Loudness::replaygain_peak),
+                    message: None,
+                }
+            }
+            Err(message) => {
+                summary.failed += 1;
+                AudioAnalysisRecord {
+                    track_id: target.track_id,
+                    location: target.location,
+                    status: AudioAnalysisStatus::Error,
+                    bpm: None,
+                    musical_key: None,
+                    camelot: None,
+                    key_confidence: None,
+                    integrated_lufs: None,
+                    loudness_range: None,
+                    true_peak_dbtp: None,
+                    replaygain_gain: None,
+                    replaygain_peak: None,
+                    message: Some(message),
+                }
+            }
+        };
+        lock(library)?.record_audio_analysis(&record)?;
+        progress(index as u32 + 1, total);
+    }
+    Ok(summary)
+}
+
+/// Runs analysis jobs one at a time in the background and reports their
+/// progress as job events.
+#[derive(Clone)]
+pub struct AnalysisService {
+    jobs: JobQueue<AnalysisSummary>,
+}
+
+impl AnalysisService {
+    pub fn new(events: Arc<dyn EventSink>, library: Arc<Mutex<LibraryStore>>) -> Self {
+        let kind = JobKind {
+            id: JOB_ID,
+            label: JOB_LABEL,
+            name: "analysis",
+            run: |library, track_ids, progress| analyze_tracks(library, track_ids, progress),
+            describe: |summary| {
+                format!(
+                    "{} analizados, {} fallidos",
+                    summary.analyzed, summary.failed
+                )
+            },
+        };
+        Self {
+            jobs: JobQueue::start(kind, events, library),
+        }
+    }
+
+    /// Queues an analysis of `track_ids`, or of every pending file when
+    /// empty, without waiting for it.
+    pub fn queue_analysis(&self, track_ids: Vec<String>) {
+        self.jobs.queue(track_ids);
+    }
+
+    /// Runs an analysis through the worker queue and resolves once its
+    /// results have been stored.
+    pub async fn analyze(&self, track_ids: Vec<String>) -> Result<AnalysisSummary, CommandError> {
+        self.jobs.run(track_ids).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f32 = ANALYSIS_RATE as f32;
+
+    /// `seconds` of 10 ms, 1 kHz clicks at `bpm`.
+    fn click_track(bpm: f32, seconds: f32) -> Vec<f32> {
+        let period = 60.0 / bpm;
+        let click = (0.01 * RATE) as usize;
+        (0..(seconds * RATE) as usize)
+            .map(|index| {
+                let time = index as f32 / RATE;
+                let since_beat = ((time % period) * RATE) as usize;
+                if since_beat < click {
+                    (2.0 * std::f32::consts::PI * 1_000.0 * time).sin()
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    /// `seconds` of the notes in `frequencies` played together.
+    fn chord(frequencies: &[f32], seconds: f32) -> Vec<f32> {
+        (0..(seconds * RATE) as usize)
+            .map(|index| {
+                let time = index as f32 / RATE;
+                frequencies
+                    .iter()
+                    .map(|frequency| (2.0 * std::f32::consts::PI * frequency * time).sin())
+                    .sum::<f32>()
+                    / frequencies.len() as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tempo_of_a_click_track() {
+        let mut planner = FftPlanner::new();
+        for bpm in [96.0, 128.0, 174.0] {
+            let estimate = estimate_tempo(&mut planner, &click_track(bpm, 30.0), RATE).unwrap();
+            assert!(
+                (estimate - bpm).abs() <= 0.5,
+                "{bpm} estimated as {estimate}"
+            );
+        }
+    }
+
+    #[test]
+    fn short_audio_has_no_tempo() {
+        let mut planner = FftPlanner::new();
+        assert_eq!(
+            estimate_tempo(&mut planner, &click_track(128.0, 2.0), RATE),
+            None
+        );
+    }
+
+    #[test]
+    fn key_of_a_triad() {
+        let mut planner = FftPlanner::new();
+        // C major: C4, E4, G4.
+        let (key, _) =
+            estimate_key(&mut planner, &chord(&[261.63, 329.63, 392.0], 12.0), RATE).unwrap();
+        assert_eq!(
+            key,
+            MusicalKey {
+                tonic: 0,
+                minor: false
+            }
+        );
+        // A minor: A3, C4, E4.
+        let (key, _) =
+            estimate_key(&mut planner, &chord(&[220.0, 261.63, 329.63], 12.0), RATE).unwrap();
+        assert_eq!(
+            key,
+            MusicalKey {
+                tonic: 9,
+                minor: true
+            }
+        );
+        assert!(estimate_key(&mut planner, &vec![0.0; RATE as usize * 12], RATE).is_none());
+    }
+
+    #[test]
+    fn keys_have_names_and_camelot_codes() {
+        let a_minor = MusicalKey {
+            tonic: 9,
+            minor: true,
+        };
+        let c_major = MusicalKey {
+            tonic: 0,
+            minor: false,
+        };
+        let f_sharp = MusicalKey {
+            tonic: 6,
+            minor: false,
+        };
+        assert_eq!(a_minor.name(), "Am");
+        assert_eq!(a_minor.camelot(), "8A");
+        assert_eq!(c_major.camelot(), "8B");
+        assert_eq!(
+            MusicalKey {
+                tonic: 4,
+                minor: true
+            }
+            .camelot(),
+            "9A"
+        );
+        assert_eq!(
+            MusicalKey {
+                tonic: 2,
+                minor: true
+            }
+            .camelot(),
+            "7A"
+        );
+        assert_eq!(f_sharp.name(), "F#");
+        assert_eq!(f_sharp.camelot(), "2B");
+    }
+
+    #[test]
+    fn compatible_keys_follow_the_camelot_wheel() {
+        assert_eq!(
+            compatible_camelot_keys("8A").unwrap(),
+            ["8A", "7A", "9A", "8B"]
+        );
+        assert_eq!(
+            compatible_camelot_keys(" 12b ").unwrap(),
+            ["12B", "11B", "1B", "12A"]
+        );
+        assert_eq!(compatible_camelot_keys("1A").unwrap()[1], "12A");
+        for invalid in ["", "A", "13A", "0B", "8C", "8A'"] {
+            assert_eq!(compatible_camelot_keys(invalid), None, "{invalid}");
+        }
+    }
+}
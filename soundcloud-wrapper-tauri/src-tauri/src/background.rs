@@ -0,0 +1,228 @@
+//! Queue shared by the background jobs that work through local files, such
+//! as audio analysis and integrity checks. Jobs run one at a time on a
+//! blocking thread and report their progress as job events.
+
+use std::sync::{Arc, Mutex};
+
+use serde_json::json;
+use tauri::async_runtime;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::errors::CommandError;
+use crate::events::{EventSink, JOB_PROGRESS_EVENT};
+use crate::library::LibraryStore;
+
+/// Processes the local files of `track_ids`, or every pending file when
+/// empty, calling back with the completed and total counts.
+pub(crate) type RunJob<S> =
+    fn(&Arc<Mutex<LibraryStore>>, &[String], &mut dyn FnMut(u32, u32)) -> Result<S, CommandError>;
+
+/// What a queue runs and how its progress is shown.
+pub(crate) struct JobKind<S> {
+    pub id: &'static str,
+    pub label: &'static str,
+    /// Names the job in log and error messages, e.g. `analysis`.
+    pub name: &'static str,
+    pub run: RunJob<S>,
+    /// Message shown when the job completes.
+    pub describe: fn(&S) -> String,
+}
+
+struct QueuedJob<S> {
+    track_ids: Vec<String>,
+    done: Option<oneshot::Sender<Result<S, CommandError>>>,
+}
+
+pub(crate) struct JobQueue<S> {
+    sender: mpsc::Sender<QueuedJob<S>>,
+    name: &'static str,
+}
+
+impl<S> Clone for JobQueue<S> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            name: self.name,
+        }
+    }
+}
+
+impl<S: Send + 'static> JobQueue<S> {
+    pub(crate) fn start(
+        kind: JobKind<S>,
+        events: Arc<dyn EventSink>,
+        library: Arc<Mutex<LibraryStore>>,
+    ) -> Self {
+        let name = kind.name;
+        let kind = Arc::new(kind);
+        let (sender, mut receiver) = mpsc::channel::<QueuedJob<S>>(8);
+        async_runtime::spawn(async move {
+            while let Some(QueuedJob { track_ids, done }) = receiver.recv().await {
+                let kind = Arc::clone(&kind);
+                let events = Arc::clone(&events);
+                let library = Arc::clone(&library);
+                let result = async_runtime::spawn_blocking(move || {
+                    run_job(&kind, events.as_ref(), &library, &track_ids)
+                })
+                .await
+                .unwrap_or_else(|error| {
+                    Err(CommandError::internal(format!(
+                        "failed to join {name} task: {error}"
+                    )))
+                });
+                if let Err(error) = &result {
+                    log::error!("{name} job failed: {error}");
+                }
+                if let Some(done) = done {
+                    let _ = done.send(result);
+                }
+            }
+        });
+
+        Self { sender, name }
+    }
+
+    /// Queues a job for `track_ids`, or for every pending file when empty,
+    /// without waiting for it.
+    pub(crate) fn queue(&self, track_ids: Vec<String>) {
+        let sender = self.sender.clone();
+        let name = self.name;
+        async_runtime::spawn(async move {
+            let job = QueuedJob {
+                track_ids,
+                done: None,
+            };
+            if let Err(error) = sender.send(job).await {
+                log::error!("failed to enqueue {name}: {error}");
+            }
+        });
+    }
+
+    /// Runs a job through the queue and resolves once its results have been
+    /// stored.
+    pub(crate) async fn run(&self, track_ids: Vec<String>) -> Result<S, CommandError> {
+        let (done, finished) = oneshot::channel();
+        self.sender
+            .send(QueuedJob {
+                track_ids,
+                done: Some(done),
+            })
+            .await
+            .map_err(|_| CommandError::internal(format!("{} worker is not running", self.name)))?;
+        finished.await.map_err(|_| {
+            CommandError::internal(format!("{} worker dropped the request", self.name))
+        })?
+    }
+}
+
+fn run_job<S>(
+    kind: &JobKind<S>,
+    events: &dyn EventSink,
+    library: &Arc<Mutex<LibraryStore>>,
+    track_ids: &[String],
+) -> Result<S, CommandError> {
+    let emit = |payload| {
+        if let Err(error) = events.emit(JOB_PROGRESS_EVENT, payload) {
+            log::error!("failed to emit progress event: {error}");
+        }
+    };
+
+    let result = (kind.run)(library, track_ids, &mut |completed, total| {
+        emit(json!({
+            "id": kind.id,
+            "label": kind.label,
+            "state": "running",
+            "completed": completed,
+            "total": total,
+        }));
+    });
+    match &result {
+        Ok(summary) => emit(json!({
+            "id": kind.id,
+            "state": "completed",
+            "message": (kind.describe)(summary),
+        })),
+        Err(error) => emit(json!({
+            "id": kind.id,
+            "state": "error",
+            "message": error.to_string(),
+        })),
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{memory_library, RecordingSink};
+
+    fn count_tracks(
+        _library: &Arc<Mutex<LibraryStore>>,
+        track_ids: &[String],
+        progress: &mut dyn FnMut(u32, u32),
+    ) -> Result<usize, CommandError> {
+        if track_ids.iter().any(|id| id == "broken") {
+            return Err(CommandError::not_found("track", "broken"));
+        }
+        let total = track_ids.len() as u32;
+        for completed in 0..=total {
+            progress(completed, total);
+        }
+        Ok(track_ids.len())
+    }
+
+    fn queue(events: &Arc<RecordingSink>) -> JobQueue<usize> {
+        JobQueue::start(
+            JobKind {
+                id: "count",
+                label: "Counting",
+                name: "count",
+                run: count_tracks,
+                describe: |count| format!("{count} counted"),
+            },
+            Arc::clone(events) as Arc<dyn EventSink>,
+            memory_library(),
+        )
+    }
+
+    #[tokio::test]
+    async fn jobs_report_progress_and_completion() {
+        let events = Arc::new(RecordingSink::default());
+        let jobs = queue(&events);
+        let ids = vec!["sc-1".to_string(), "sc-2".to_string()];
+        assert_eq!(jobs.run(ids).await, Ok(2));
+
+        let progress = events.events(JOB_PROGRESS_EVENT);
+        let states: Vec<_> = progress
+            .iter()
+            .map(|event| (event["state"].clone(), event["completed"].clone()))
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                (json!("running"), json!(0)),
+                (json!("running"), json!(1)),
+                (json!("running"), json!(2)),
+                (json!("completed"), serde_json::Value::Null),
+            ]
+        );
+        assert_eq!(progress[0]["label"], "Counting");
+        assert_eq!(progress[3]["message"], "2 counted");
+    }
+
+    #[tokio::test]
+    async fn failed_jobs_report_an_error_and_the_queue_keeps_running() {
+        let events = Arc::new(RecordingSink::default());
+        let jobs = queue(&events);
+        let result = jobs.run(vec!["broken".to_string()]).await;
+        assert_eq!(result, Err(CommandError::not_found("track", "broken")));
+        let progress = events.events(JOB_PROGRESS_EVENT);
+        assert_eq!(progress.last().unwrap()["state"], "error");
+        assert_eq!(
+            progress.last().unwrap()["message"],
+            "track 'broken' not found"
+        );
+
+        assert_eq!(jobs.run(vec!["sc-1".to_string()]).await, Ok(1));
+    }
+}
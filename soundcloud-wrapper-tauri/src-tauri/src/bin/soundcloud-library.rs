@@ -0,0 +1,1150 @@
+//! Headless companion to the desktop app. Works directly on `library.sqlite3`
+//! so library maintenance can run from cron or CI without a webview.
+
+use std::collections::HashSet;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+use soundcloud_wrapper_tauri_lib::acoustid::AcoustidClient;
+use soundcloud_wrapper_tauri_lib::discogs::DiscogsService;
+use soundcloud_wrapper_tauri_lib::dj_library::{self, DjSoftware};
+use soundcloud_wrapper_tauri_lib::endpoints::ClientIdentity;
+use soundcloud_wrapper_tauri_lib::errors::CommandError;
+use soundcloud_wrapper_tauri_lib::events::EventSink;
+use soundcloud_wrapper_tauri_lib::library::{
+    lock, DjLibrarySync, LibraryStatusRow, LibraryStore, RekordboxSync, StatusFilter,
+    DATABASE_FILE_NAME,
+};
+use soundcloud_wrapper_tauri_lib::matching::{DISCOGS_PROVIDER, MUSICBRAINZ_PROVIDER, PROVIDERS};
+use soundcloud_wrapper_tauri_lib::musicbrainz::MusicbrainzService;
+use soundcloud_wrapper_tauri_lib::rekordbox::{self, load_tracks, DEFAULT_SOURCE_ID};
+use soundcloud_wrapper_tauri_lib::settings::{
+    validate_source_id, MusicbrainzSettings, SettingsStore,
+};
+use soundcloud_wrapper_tauri_lib::SoundcloudTrackPayload;
+use soundcloud_wrapper_tauri_lib::{
+    analysis, duplicates, fingerprint, integrity, logging, relocate, scanner,
+};
+use tauri::async_runtime;
+
+/// Matches `identifier` in `tauri.conf.json`, which names the directory the
+/// desktop app keeps its data in.
+const APP_IDENTIFIER: &str = "com.soundcloud.desktop";
+const DATABASE_ENV: &str = "SOUNDCLOUD_LIBRARY_DB";
+const STATUS_PAGE_SIZE: u32 = 500;
+
+const USAGE: &str = "\
+Usage: soundcloud-library [--db <path>] <command> [options]
+
+Commands:
+  import-rekordbox [--source <id>] <path>
+                                 Import a Rekordbox master.db, XML export or
+                                 USB export (drive root or export.pdb)
+                                 into a source (the default source if not given)
+  import-traktor [--source <id>] <path>
+                                 Import a Traktor collection.nml, or the folder
+                                 holding it, into a source
+  import-serato [--source <id>] <path>
+                                 Import a Serato library (the _Serato_ folder
+                                 or the folder holding it) into a source
+  export-cues [--from <software>] --to <software> <track-id>
+                                 Print the cues of a track converted for
+                                 rekordbox, traktor or serato
+  missing-assets                 List tracks without an available local file
+  scan-folders [path...]         Watch the given folders, then scan all watched
+                                 folders and link files to liked tracks
+  relocate [--apply] [root...]   Find moved files of missing local assets in the
+                                 watched folders and the given roots
+  duplicates                     List groups of tracks that look like duplicates
+  merge <id> <duplicate-id...>   Merge duplicate tracks into track <id>
+  fingerprint                    Fingerprint local assets that have no
+                                 fingerprint yet
+  acoustid [track-id...]         Look up fingerprinted tracks on AcoustID
+                                 (needs $ACOUSTID_API_KEY)
+  analyze [track-id...]          Estimate BPM, key and loudness of local files
+                                 that were not analyzed yet, or of the given
+                                 tracks
+  check-integrity [track-id...]  Decode local files that were not checked yet,
+                                 or of the given tracks, and record damage and
+                                 suspected upsampling
+  lookup [options] [track-id...] Run Discogs/MusicBrainz lookups
+      --provider <name>          Only query discogs or musicbrainz
+      --retry                    Also retry ambiguous and failed lookups
+  export-status [options]        Write the library status report
+      --format <csv|json>        Output format (default: csv)
+      --output <path>            Write to a file instead of stdout
+      --missing-assets           Only tracks without an available local file
+      --unresolved-discogs       Only tracks without a Discogs match
+      --liked                    Only liked tracks
+      --rekordbox                Only tracks present in Rekordbox
+      --key <camelot>            Only tracks that mix with the key (e.g. 8A)
+      --min-bpm <bpm>            Only tracks at or above the tempo
+      --max-bpm <bpm>            Only tracks at or below the tempo
+      --unhealthy                Only tracks whose local file is damaged or
+                                 suspected to be upsampled
+
+The database defaults to $SOUNDCLOUD_LIBRARY_DB, then to the desktop app's
+data directory.";
+
+enum Command {
+    ImportRekordbox {
+        source_id: String,
+        path: PathBuf,
+    },
+    ImportDjLibrary {
+        software: DjSoftware,
+        source_id: String,
+        path: PathBuf,
+    },
+    ExportCues {
+        track_id: String,
+        to: DjSoftware,
+        from: Option<DjSoftware>,
+    },
+    MissingAssets,
+    ScanFolders(Vec<PathBuf>),
+    Relocate {
+        apply: bool,
+        roots: Vec<String>,
+    },
+    Duplicates,
+    Merge {
+        canonical: String,
+        duplicates: Vec<String>,
+    },
+    Fingerprint,
+    Acoustid(Vec<String>),
+    Analyze(Vec<String>),
+    CheckIntegrity(Vec<String>),
+    Lookup {
+        providers: Vec<&'static str>,
+        retry: bool,
+        track_ids: Vec<String>,
+    },
+    ExportStatus {
+        format: ExportFormat,
+        output: Option<PathBuf>,
+        filter: StatusFilter,
+    },
+}
+
+#[derive(Clone, Copy)]
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// Headless runs have no webview to notify; surface events on stderr.
+struct StderrSink;
+
+impl EventSink for StderrSink {
+    fn emit(&self, event: &str, payload: Value) -> Result<(), String> {
+        let track_id = payload
+            .get("trackId")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        eprintln!("[{event}] {track_id}");
+        Ok(())
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    // Without a log directory, library records only go to stderr.
+    if let Err(error) = logging::init(None) {
+        eprintln!("failed to initialize logging: {error}");
+    }
+
+    match parse_args(args).and_then(|(database, command)| run(&database, command)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: Vec<String>) -> Result<(PathBuf, Command), String> {
+    let mut args = args.into_iter();
+    let mut database = None;
+    let mut command = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--db" => database = Some(PathBuf::from(expect_value(&mut args, "--db")?)),
+            _ => {
+                command = Some(arg);
+                break;
+            }
+        }
+    }
+
+    let command = match command.as_deref() {
+        Some("import-rekordbox") => {
+            let (source_id, path) = parse_import("import-rekordbox", args)?;
+            Command::ImportRekordbox { source_id, path }
+        }
+        Some(name @ ("import-traktor" | "import-serato")) => {
+            let software = if name == "import-traktor" {
+                DjSoftware::Traktor
+            } else {
+                DjSoftware::Serato
+            };
+            let (source_id, path) = parse_import(name, args)?;
+            Command::ImportDjLibrary {
+                software,
+                source_id,
+                path,
+            }
+        }
+        Some("export-cues") => {
+            let mut to = None;
+            let mut from = None;
+            let mut track_id = None;
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--to" => to = Some(parse_software(&expect_value(&mut args, "--to")?)?),
+                    "--from" => from = Some(parse_software(&expect_value(&mut args, "--from")?)?),
+                    flag if flag.starts_with("--") => {
+                        return Err(format!("unknown option '{flag}'"))
+                    }
+                    _ if track_id.is_none() => track_id = Some(arg),
+                    _ => return Err(format!("unexpected argument '{arg}'")),
+                }
+            }
+            Command::ExportCues {
+                track_id: track_id.ok_or_else(|| "export-cues needs a track id".to_string())?,
+                to: to.ok_or_else(|| "export-cues needs --to".to_string())?,
+                from,
+            }
+        }
+        Some("missing-assets") => {
+            reject_extra(args)?;
+            Command::MissingAssets
+        }
+        Some("scan-folders") => Command::ScanFolders(args.map(PathBuf::from).collect()),
+        Some("relocate") => {
+            let (flags, roots): (Vec<String>, Vec<String>) =
+                args.partition(|arg| arg.starts_with("--"));
+            if let Some(flag) = flags.iter().find(|flag| *flag != "--apply") {
+                return Err(format!("unknown option '{flag}'"));
+            }
+            Command::Relocate {
+                apply: !flags.is_empty(),
+                roots,
+            }
+        }
+        Some("duplicates") => {
+            reject_extra(args)?;
+            Command::Duplicates
+        }
+        Some("merge") => {
+            let canonical = args
+                .next()
+                .ok_or_else(|| "merge needs a track id".to_string())?;
+            let duplicates: Vec<String> = args.collect();
+            if duplicates.is_empty() {
+                return Err("merge needs at least one duplicate track id".to_string());
+            }
+            Command::Merge {
+                canonical,
+                duplicates,
+            }
+        }
+        Some("fingerprint") => {
+            reject_extra(args)?;
+            Command::Fingerprint
+        }
+        Some("acoustid") => Command::Acoustid(args.collect()),
+        Some("analyze") => Command::Analyze(args.collect()),
+        Some("check-integrity") => Command::CheckIntegrity(args.collect()),
+        Some("lookup") => parse_lookup(args)?,
+        Some("export-status") => parse_export(args)?,
+        Some(other) => return Err(format!("unknown command '{other}'\n\n{USAGE}")),
+        None => return Err(format!("missing command\n\n{USAGE}")),
+    };
+
+    let database = match database.or_else(|| env::var_os(DATABASE_ENV).map(PathBuf::from)) {
+        Some(path) => path,
+        None => default_database_path()?,
+    };
+
+    Ok((database, command))
+}
+
+/// Parses `[--source <id>] <path>`.
+fn parse_import(
+    command: &str,
+    mut args: impl Iterator<Item = String>,
+) -> Result<(String, PathBuf), String> {
+    let mut source_id = DEFAULT_SOURCE_ID.to_string();
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--source" => source_id = expect_value(&mut args, "--source")?,
+            flag if flag.starts_with("--") => return Err(format!("unknown option '{flag}'")),
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{arg}'")),
+        }
+    }
+    validate_source_id(&source_id)?;
+    let path = path.ok_or_else(|| format!("{command} needs a path"))?;
+    Ok((source_id, path))
+}
+
+fn parse_software(name: &str) -> Result<DjSoftware, String> {
+    DjSoftware::parse(name).ok_or_else(|| {
+        format!("unknown DJ software '{name}', expected rekordbox, traktor or serato")
+    })
+}
+
+fn parse_lookup(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut providers = PROVIDERS.to_vec();
+    let mut retry = false;
+    let mut track_ids = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--provider" => {
+                let value = expect_value(&mut args, "--provider")?;
+                let provider = PROVIDERS
+                    .iter()
+                    .find(|provider| **provider == value)
+                    .ok_or_else(|| format!("unknown provider '{value}'"))?;
+                providers = vec![*provider];
+            }
+            "--retry" => retry = true,
+            flag if flag.starts_with("--") => return Err(format!("unknown option '{flag}'")),
+            _ => track_ids.push(arg),
+        }
+    }
+
+    Ok(Command::Lookup {
+        providers,
+        retry,
+        track_ids,
+    })
+}
+
+fn parse_export(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut format = ExportFormat::Csv;
+    let mut output = None;
+    let mut filter = StatusFilter::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = match expect_value(&mut args, "--format")?.as_str() {
+                    "csv" => ExportFormat::Csv,
+                    "json" => ExportFormat::Json,
+                    other => return Err(format!("unknown format '{other}'")),
+                }
+            }
+            "--output" => output = Some(PathBuf::from(expect_value(&mut args, "--output")?)),
+            "--missing-assets" => filter.missing_assets_only = true,
+            "--unresolved-discogs" => filter.unresolved_discogs_only = true,
+            "--liked" => filter.liked_only = true,
+            "--rekordbox" => filter.rekordbox_only = true,
+            "--key" => {
+                let value = expect_value(&mut args, "--key")?;
+                if analysis::compatible_camelot_keys(&value).is_none() {
+                    return Err(format!("'{value}' is not a Camelot key"));
+                }
+                filter.harmonic_key = Some(value);
+            }
+            "--min-bpm" => filter.min_bpm = Some(parse_bpm(&mut args, "--min-bpm")?),
+            "--max-bpm" => filter.max_bpm = Some(parse_bpm(&mut args, "--max-bpm")?),
+            "--unhealthy" => filter.unhealthy_only = true,
+            other => return Err(format!("unknown option '{other}'")),
+        }
+    }
+
+    Ok(Command::ExportStatus {
+        format,
+        output,
+        filter,
+    })
+}
+
+fn expect_value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{flag} expects a value"))
+}
+
+fn parse_bpm(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<f32, String> {
+    let value = expect_value(args, flag)?;
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|bpm| bpm.is_finite() && *bpm > 0.0)
+        .ok_or_else(|| format!("{flag} expects a tempo, got '{value}'"))
+}
+
+fn reject_extra(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    match args.next() {
+        Some(arg) => Err(format!("unexpected argument '{arg}'")),
+        None => Ok(()),
+    }
+}
+
+/// Mirrors where Tauri places the app data directory on each platform.
+fn default_database_path() -> Result<PathBuf, String> {
+    platform_dir("XDG_DATA_HOME", Path::new(".local/share"))
+        .map(|base| base.join(APP_IDENTIFIER).join(DATABASE_FILE_NAME))
+        .ok_or_else(|| {
+            format!("unable to resolve the app data directory; pass --db or set {DATABASE_ENV}")
+        })
+}
+
+/// Mirrors where Tauri places the app config directory, which holds the
+/// settings file, on each platform.
+fn default_config_dir() -> Option<PathBuf> {
+    platform_dir("XDG_CONFIG_HOME", Path::new(".config")).map(|base| base.join(APP_IDENTIFIER))
+}
+
+/// The per-user base directory for app files. On Linux it comes from
+/// `xdg_variable`, falling back to `home_fallback` inside the home directory.
+fn platform_dir(xdg_variable: &str, home_fallback: &Path) -> Option<PathBuf> {
+    let home = || env::var_os("HOME").map(PathBuf::from);
+    if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env::var_os(xdg_variable)
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| home().map(|home| home.join(home_fallback)))
+    }
+}
+
+fn run(database: &Path, command: Command) -> Result<(), String> {
+    let store = LibraryStore::open(database).map_err(|error| error.to_string())?;
+    let library = Arc::new(Mutex::new(store));
+
+    match command {
+        Command::ImportRekordbox { source_id, path } => {
+            import_rekordbox(&library, &source_id, &path)
+        }
+        Command::ImportDjLibrary {
+            software,
+            source_id,
+            path,
+        } => import_dj_library(&library, software, &source_id, &path),
+        Command::ExportCues { track_id, to, from } => export_cues(&library, &track_id, to, from),
+        Command::MissingAssets => missing_assets(&library),
+        Command::ScanFolders(folders) => scan_folders(&library, &folders),
+        Command::Relocate { apply, roots } => relocate(&library, apply, &roots),
+        Command::Duplicates => duplicates(&library),
+        Command::Merge {
+            canonical,
+            duplicates,
+        } => merge(&library, &canonical, &duplicates),
+        Command::Fingerprint => fingerprint_assets(&library),
+        Command::Acoustid(track_ids) => async_runtime::block_on(acoustid(library, &track_ids)),
+        Command::Analyze(track_ids) => analyze(&library, &track_ids),
+        Command::CheckIntegrity(track_ids) => check_integrity(&library, &track_ids),
+        Command::Lookup {
+            providers,
+            retry,
+            track_ids,
+        } => async_runtime::block_on(lookup(library, providers, retry, track_ids)),
+        Command::ExportStatus {
+            format,
+            output,
+            filter,
+        } => export_status(&library, format, output.as_deref(), filter),
+    }
+}
+
+fn import_rekordbox(
+    library: &Arc<Mutex<LibraryStore>>,
+    source_id: &str,
+    path: &Path,
+) -> Result<(), String> {
+    let modified_at = rekordbox::modified_at(path);
+    let tracks = load_tracks(path).map_err(|error| error.to_string())?;
+    let sync = RekordboxSync {
+        source_id: source_id.to_string(),
+        path: path.display().to_string(),
+        modified_at,
+    };
+    lock(library)
+        .map_err(|error| error.to_string())?
+        .sync_rekordbox_tracks(&sync, &tracks)
+        .map_err(|error| error.to_string())?;
+    eprintln!(
+        "imported {} tracks from {} into source {source_id}",
+        tracks.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn import_dj_library(
+    library: &Arc<Mutex<LibraryStore>>,
+    software: DjSoftware,
+    source_id: &str,
+    path: &Path,
+) -> Result<(), String> {
+    let importer = dj_library::importer(software)
+        .ok_or_else(|| format!("{software} libraries are imported with import-rekordbox"))?;
+    let modified_at = rekordbox::modified_at(path);
+    let tracks = importer
+        .load_tracks(path)
+        .map_err(|error| error.to_string())?;
+    let sync = DjLibrarySync {
+        software,
+        source_id: source_id.to_string(),
+        path: path.display().to_string(),
+        modified_at,
+    };
+    lock(library)
+        .map_err(|error| error.to_string())?
+        .sync_dj_tracks(&sync, &tracks)
+        .map_err(|error| error.to_string())?;
+    eprintln!(
+        "imported {} tracks from {} into {software} source {source_id}",
+        tracks.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn export_cues(
+    library: &Arc<Mutex<LibraryStore>>,
+    track_id: &str,
+    to: DjSoftware,
+    from: Option<DjSoftware>,
+) -> Result<(), String> {
+    let track = lock(library)
+        .map_err(|error| error.to_string())?
+        .load_track_cues(track_id)
+        .map_err(|error| error.to_string())?
+        .ok_or_else(|| format!("track '{track_id}' not found"))?;
+    let layout = match &track.location {
+        Some(location) => {
+            dj_library::read_mp3_layout(Path::new(location)).map_err(|error| error.to_string())?
+        }
+        None => None,
+    };
+    let export = dj_library::export_cues(&track.sources, to, from, layout)
+        .ok_or_else(|| format!("track '{track_id}' has no cues to export"))?;
+    println!("{}", export.data);
+    eprintln!(
+        "converted {} cues from {} to {to}, moved by {:.1} ms",
+        export.cues.len(),
+        export.source,
+        export.offset_ms
+    );
+    Ok(())
+}
+
+fn missing_assets(library: &Arc<Mutex<LibraryStore>>) -> Result<(), String> {
+    let track_ids = lock(library)
+        .map_err(|error| error.to_string())?
+        .list_missing_assets()
+        .map_err(|error| error.to_string())?;
+    let mut stdout = io::stdout().lock();
+    for track_id in track_ids {
+        writeln!(stdout, "{track_id}").map_err(|error| error.to_string())?;
+    }
+    Ok(())
+}
+
+fn scan_folders(library: &Arc<Mutex<LibraryStore>>, folders: &[PathBuf]) -> Result<(), String> {
+    for folder in folders {
+        let folder =
+            fs::canonicalize(folder).map_err(|error| format!("{}: {error}", folder.display()))?;
+        if !folder.is_dir() {
+            return Err(format!("{} is not a directory", folder.display()));
+        }
+        lock(library)
+            .map_err(|error| error.to_string())?
+            .add_watched_folder(&folder.to_string_lossy())
+            .map_err(|error| error.to_string())?;
+    }
+
+    let summary = scanner::scan_watched_folders(library).map_err(|error| error.to_string())?;
+    eprintln!(
+        "scanned {} files in {} folders: {} linked, {} ambiguous, {} unmatched, {} unchanged, {} failed",
+        summary.scanned,
+        summary.folders,
+        summary.linked,
+        summary.ambiguous,
+        summary.unmatched,
+        summary.unchanged,
+        summary.failed
+    );
+    Ok(())
+}
+
+fn relocate(
+    library: &Arc<Mutex<LibraryStore>>,
+    apply: bool,
+    roots: &[String],
+) -> Result<(), String> {
+    let report = relocate::find_relocations(library, roots).map_err(|error| error.to_string())?;
+    eprintln!(
+        "{} of {} local assets are missing",
+        report.missing, report.checked
+    );
+    for asset in &report.unresolved {
+        eprintln!(
+            "unresolved {}: {} ({})",
+            asset.track_id, asset.location, asset.reason
+        );
+    }
+
+    let relocations = if apply {
+        relocate::apply_relocations(library, &report.proposals)
+            .map_err(|error| error.to_string())?
+    } else {
+        report.proposals
+    };
+    let mut stdout = io::stdout().lock();
+    for relocation in &relocations {
+        writeln!(
+            stdout,
+            "{}\t{}\t{}",
+            relocation.track_id, relocation.old_location, relocation.new_location
+        )
+        .map_err(|error| error.to_string())?;
+    }
+    if apply {
+        eprintln!("relinked {} assets", relocations.len());
+    } else if !relocations.is_empty() {
+        eprintln!(
+            "run again with --apply to relink {} assets",
+            relocations.len()
+        );
+    }
+    Ok(())
+}
+
+/// Prints one group per line: the suggested canonical track, the other
+/// tracks and the reasons they were grouped, separated by tabs.
+fn duplicates(library: &Arc<Mutex<LibraryStore>>) -> Result<(), String> {
+    let groups =
+        duplicates::find_duplicate_groups(&*lock(library).map_err(|error| error.to_string())?)
+            .map_err(|error| error.to_string())?;
+    let mut stdout = io::stdout().lock();
+    for group in &groups {
+        let others: Vec<&str> = group
+            .tracks
+            .iter()
+            .map(|track| track.track_id.as_str())
+            .filter(|track_id| *track_id != group.canonical_track_id)
+            .collect();
+        let reasons: Vec<&str> = group.reasons.iter().map(|reason| reason.as_str()).collect();
+        writeln!(
+            stdout,
+            "{}\t{}\t{}",
+            group.canonical_track_id,
+            others.join(","),
+            reasons.join(",")
+        )
+        .map_err(|error| error.to_string())?;
+    }
+    eprintln!("found {} duplicate groups", groups.len());
+    Ok(())
+}
+
+fn merge(
+    library: &Arc<Mutex<LibraryStore>>,
+    canonical: &str,
+    duplicates: &[String],
+) -> Result<(), String> {
+    let mut store = lock(library).map_err(|error| error.to_string())?;
+    let exists = store
+        .load_soundcloud_lookup(canonical)
+        .map_err(|error| error.to_string())?
+        .is_some();
+    if !exists {
+        return Err(format!("track '{canonical}' does not exist"));
+    }
+    let merged = store
+        .merge_tracks(canonical, duplicates)
+        .map_err(|error| error.to_string())?;
+    eprintln!("merged {merged} tracks into {canonical}");
+    Ok(())
+}
+
+fn fingerprint_assets(library: &Arc<Mutex<LibraryStore>>) -> Result<(), String> {
+    let summary =
+        fingerprint::fingerprint_local_assets(library).map_err(|error| error.to_string())?;
+    eprintln!(
+        "fingerprinted {} files, {} failed",
+        summary.fingerprinted, summary.failed
+    );
+    Ok(())
+}
+
+async fn acoustid(library: Arc<Mutex<LibraryStore>>, track_ids: &[String]) -> Result<(), String> {
+    let identity = ClientIdentity::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    let summary = AcoustidClient::new(library, &identity)
+        .map_err(|error| error.to_string())?
+        .lookup(track_ids)
+        .await
+        .map_err(|error| error.to_string())?;
+    eprintln!(
+        "looked up {} tracks on AcoustID: {} matched, {} unmatched, {} failed",
+        summary.looked_up, summary.matched, summary.unmatched, summary.failed
+    );
+    Ok(())
+}
+
+fn analyze(library: &Arc<Mutex<LibraryStore>>, track_ids: &[String]) -> Result<(), String> {
+    let summary = analysis::analyze_tracks(library, track_ids, |completed, total| {
+        if completed > 0 {
+            eprintln!("analyzed {completed}/{total}");
+        }
+    })
+    .map_err(|error| error.to_string())?;
+    eprintln!(
+        "analyzed {} files, {} failed",
+        summary.analyzed, summary.failed
+    );
+    Ok(())
+}
+
+fn check_integrity(library: &Arc<Mutex<LibraryStore>>, track_ids: &[String]) -> Result<(), String> {
+    let summary = integrity::check_tracks(library, track_ids, |completed, total| {
+        if completed > 0 {
+            eprintln!("checked {completed}/{total}");
+        }
+    })
+    .map_err(|error| error.to_string())?;
+    eprintln!(
+        "checked {} files: {} healthy, {} suspect, {} damaged",
+        summary.checked, summary.healthy, summary.suspect, summary.damaged
+    );
+    Ok(())
+}
+
+/// Runs each provider's lookups through its worker and waits for every
+/// outcome to be recorded before summarising them.
+async fn lookup(
+    library: Arc<Mutex<LibraryStore>>,
+    providers: Vec<&'static str>,
+    retry: bool,
+    track_ids: Vec<String>,
+) -> Result<(), String> {
+    let identity = ClientIdentity::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    let events: Arc<dyn EventSink> = Arc::new(StderrSink);
+    let musicbrainz = default_config_dir()
+        .map(|config_dir| SettingsStore::load(&config_dir).get().musicbrainz)
+        .unwrap_or_default();
+
+    let mut tasks = Vec::new();
+    for provider in providers {
+        let targets = if track_ids.is_empty() {
+            lock(&library)
+                .map_err(|error| error.to_string())?
+                .list_lookup_candidates(provider, retry)
+                .map_err(|error| error.to_string())?
+        } else {
+            track_ids.clone()
+        };
+        let payloads = load_payloads(&library, &targets)?;
+        eprintln!("[{provider}] looking up {} tracks", payloads.len());
+
+        let lookup = ProviderLookup::new(
+            provider,
+            Arc::clone(&events),
+            &library,
+            &identity,
+            &musicbrainz,
+        )?;
+        tasks.push(async_runtime::spawn(async move {
+            for payload in payloads {
+                match lookup.run(payload).await {
+                    Ok(()) => {}
+                    // The failed request is recorded with the track.
+                    Err(error @ CommandError::Network { .. }) => {
+                        eprintln!("[{provider}] {error}")
+                    }
+                    Err(error) => return Err(error.to_string()),
+                }
+            }
+            Ok::<_, String>((provider, targets))
+        }));
+    }
+
+    let mut looked_up = Vec::new();
+    for task in tasks {
+        looked_up.push(
+            task.await
+                .map_err(|error| format!("lookup task failed: {error}"))??,
+        );
+    }
+
+    let rows = collect_status(&library, &StatusFilter::default())?;
+    for (provider, targets) in looked_up {
+        let targets: HashSet<&str> = targets.iter().map(String::as_str).collect();
+        let mut counts = [0usize; 3];
+        for row in rows
+            .iter()
+            .filter(|row| targets.contains(row.track_id.as_str()))
+        {
+            let status = if provider == DISCOGS_PROVIDER {
+                row.discogs_status.as_deref()
+            } else {
+                row.musicbrainz_status.as_deref()
+            };
+            match status {
+                Some("success") => counts[0] += 1,
+                Some("ambiguous") => counts[1] += 1,
+                _ => counts[2] += 1,
+            }
+        }
+        eprintln!(
+            "[{provider}] {} matched, {} ambiguous, {} failed",
+            counts[0], counts[1], counts[2]
+        );
+    }
+    Ok(())
+}
+
+enum ProviderLookup {
+    Discogs(DiscogsService),
+    Musicbrainz(MusicbrainzService),
+}
+
+impl ProviderLookup {
+    fn new(
+        provider: &str,
+        events: Arc<dyn EventSink>,
+        library: &Arc<Mutex<LibraryStore>>,
+        identity: &ClientIdentity,
+        musicbrainz: &MusicbrainzSettings,
+    ) -> Result<Self, String> {
+        let lookup = match provider {
+            MUSICBRAINZ_PROVIDER => {
+                MusicbrainzService::new(events, Arc::clone(library), identity, musicbrainz)
+                    .map(Self::Musicbrainz)
+            }
+            _ => DiscogsService::new(events, Arc::clone(library), identity).map(Self::Discogs),
+        };
+        lookup.map_err(|error| error.to_string())
+    }
+
+    async fn run(&self, payload: SoundcloudTrackPayload) -> Result<(), CommandError> {
+        match self {
+            Self::Discogs(service) => service.lookup(payload).await,
+            Self::Musicbrainz(service) => service.lookup(payload).await,
+        }
+    }
+}
+
+fn load_payloads(
+    library: &Arc<Mutex<LibraryStore>>,
+    track_ids: &[String],
+) -> Result<Vec<SoundcloudTrackPayload>, String> {
+    let store = lock(library).map_err(|error| error.to_string())?;
+    let mut payloads = Vec::with_capacity(track_ids.len());
+    for track_id in track_ids {
+        match store
+            .load_soundcloud_lookup(track_id)
+            .map_err(|error| error.to_string())?
+        {
+            Some(record) => payloads.push(SoundcloudTrackPayload::from_lookup(record)),
+            None => eprintln!("skipping '{track_id}': not found in library"),
+        }
+    }
+    Ok(payloads)
+}
+
+fn collect_status(
+    library: &Arc<Mutex<LibraryStore>>,
+    filter: &StatusFilter,
+) -> Result<Vec<LibraryStatusRow>, String> {
+    let store = lock(library).map_err(|error| error.to_string())?;
+    let mut rows = Vec::new();
+    loop {
+        let page = store
+            .list_library_status(&StatusFilter {
+                limit: Some(STATUS_PAGE_SIZE),
+                offset: Some(rows.len() as u32),
+                ..filter.clone()
+            })
+            .map_err(|error| error.to_string())?;
+        let fetched = page.rows.len();
+        rows.extend(page.rows);
+        if fetched == 0 || rows.len() as u32 >= page.total {
+            return Ok(rows);
+        }
+    }
+}
+
+fn export_status(
+    library: &Arc<Mutex<LibraryStore>>,
+    format: ExportFormat,
+    output: Option<&Path>,
+    filter: StatusFilter,
+) -> Result<(), String> {
+    let rows = collect_status(library, &filter)?;
+    let mut writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).map_err(|error| format!("{}: {error}", path.display()))?,
+        )),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, &rows).map_err(|error| error.to_string())?;
+            writeln!(writer).map_err(|error| error.to_string())?;
+        }
+        ExportFormat::Csv => write_csv(&mut writer, &rows).map_err(|error| error.to_string())?,
+    }
+    writer.flush().map_err(|error| error.to_string())
+}
+
+const CSV_COLUMNS: [&str; 30] = [
+    "track_id",
+    "artist",
+    "title",
+    "album",
+    "liked",
+    "has_local_file",
+    "local_available",
+    "local_location",
+    "in_rekordbox",
+    "discogs_status",
+    "discogs_release_id",
+    "discogs_confidence",
+    "discogs_checked_at",
+    "musicbrainz_status",
+    "musicbrainz_release_id",
+    "musicbrainz_confidence",
+    "musicbrainz_checked_at",
+    "discogs_owned",
+    "discogs_owned_vinyl",
+    "discogs_wanted",
+    "soundcloud_permalink_url",
+    "bpm",
+    "key",
+    "camelot",
+    "integrated_lufs",
+    "loudness_range",
+    "true_peak_dbtp",
+    "replaygain_gain",
+    "health_status",
+    "health_message",
+];
+
+fn write_csv(writer: &mut dyn Write, rows: &[LibraryStatusRow]) -> io::Result<()> {
+    writeln!(writer, "{}", CSV_COLUMNS.join(","))?;
+    for row in rows {
+        let text = |value: &Option<String>| value.clone().unwrap_or_default();
+        let number = |value: Option<f32>| value.map(|value| value.to_string()).unwrap_or_default();
+        let fields = [
+            row.track_id.clone(),
+            text(&row.artist),
+            text(&row.title),
+            text(&row.album),
+            row.liked.to_string(),
+            row.has_local_file.to_string(),
+            row.local_available.to_string(),
+            text(&row.local_location),
+            row.in_rekordbox.to_string(),
+            text(&row.discogs_status),
+            text(&row.discogs_release_id),
+            number(row.discogs_confidence),
+            text(&row.discogs_checked_at),
+            text(&row.musicbrainz_status),
+            text(&row.musicbrainz_release_id),
+            number(row.musicbrainz_confidence),
+            text(&row.musicbrainz_checked_at),
+            row.discogs_owned.to_string(),
+            row.discogs_owned_vinyl.to_string(),
+            row.discogs_wanted.to_string(),
+            text(&row.soundcloud_permalink_url),
+            number(row.bpm),
+            text(&row.musical_key),
+            text(&row.camelot),
+            number(row.integrated_lufs),
+            number(row.loudness_range),
+            number(row.true_peak_dbtp),
+            number(row.replaygain_gain),
+            text(&row.health_status),
+            text(&row.health_message),
+        ];
+        let line: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        writeln!(writer, "{}", line.join(","))?;
+    }
+    Ok(())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use soundcloud_wrapper_tauri_lib::library::TrackRecord;
+    use soundcloud_wrapper_tauri_lib::test_support::{fixture, memory_library};
+
+    fn parse(args: &[&str]) -> Result<(PathBuf, Command), String> {
+        parse_args(args.iter().map(|arg| arg.to_string()).collect())
+    }
+
+    fn parse_error(args: &[&str]) -> String {
+        match parse(args) {
+            Ok(_) => panic!("{args:?} should be rejected"),
+            Err(error) => error,
+        }
+    }
+
+    #[test]
+    fn incomplete_commands_are_usage_errors() {
+        assert!(parse_error(&[]).starts_with("missing command"));
+        assert!(parse_error(&["--db", "/tmp/library.sqlite3"]).starts_with("missing command"));
+        assert_eq!(parse_error(&["--db"]), "--db expects a value");
+        assert!(parse_error(&["sync"]).starts_with("unknown command 'sync'"));
+        assert_eq!(parse_error(&["merge"]), "merge needs a track id");
+        assert_eq!(
+            parse_error(&["merge", "sc-1"]),
+            "merge needs at least one duplicate track id"
+        );
+        assert_eq!(
+            parse_error(&["export-cues", "sc-1"]),
+            "export-cues needs --to"
+        );
+        assert_eq!(
+            parse_error(&["import-rekordbox"]),
+            "import-rekordbox needs a path"
+        );
+        assert_eq!(
+            parse_error(&["duplicates", "sc-1"]),
+            "unexpected argument 'sc-1'"
+        );
+    }
+
+    #[test]
+    fn unknown_options_are_rejected() {
+        assert_eq!(
+            parse_error(&["lookup", "--fast"]),
+            "unknown option '--fast'"
+        );
+        assert_eq!(
+            parse_error(&["lookup", "--provider", "spotify"]),
+            "unknown provider 'spotify'"
+        );
+        assert_eq!(
+            parse_error(&["relocate", "--force", "/music"]),
+            "unknown option '--force'"
+        );
+        assert_eq!(
+            parse_error(&["export-status", "--format", "xml"]),
+            "unknown format 'xml'"
+        );
+        assert_eq!(
+            parse_error(&["export-status", "--key", "13A"]),
+            "'13A' is not a Camelot key"
+        );
+        assert_eq!(
+            parse_error(&["export-status", "--min-bpm", "fast"]),
+            "--min-bpm expects a tempo, got 'fast'"
+        );
+    }
+
+    #[test]
+    fn the_database_defaults_to_the_app_data_directory() {
+        let (database, command) = parse(&["--db", "/tmp/library.sqlite3", "duplicates"]).unwrap();
+        assert_eq!(database, PathBuf::from("/tmp/library.sqlite3"));
+        assert!(matches!(command, Command::Duplicates));
+
+        let default = default_database_path().unwrap();
+        assert!(default.ends_with(Path::new(APP_IDENTIFIER).join(DATABASE_FILE_NAME)));
+        if env::var_os(DATABASE_ENV).is_none() {
+            let (database, _) = parse(&["duplicates"]).unwrap();
+            assert_eq!(database, default);
+        }
+    }
+
+    #[test]
+    fn lookups_query_every_provider_unless_one_is_chosen() {
+        match parse(&["lookup", "--retry", "sc-1"]).unwrap().1 {
+            Command::Lookup {
+                providers,
+                retry,
+                track_ids,
+            } => {
+                assert_eq!(providers, PROVIDERS.to_vec());
+                assert!(retry);
+                assert_eq!(track_ids, vec!["sc-1".to_string()]);
+            }
+            _ => panic!("expected a lookup"),
+        }
+        match parse(&["lookup", "--provider", "musicbrainz"]).unwrap().1 {
+            Command::Lookup { providers, .. } => {
+                assert_eq!(providers, vec![MUSICBRAINZ_PROVIDER]);
+            }
+            _ => panic!("expected a lookup"),
+        }
+    }
+
+    fn matched_library() -> Arc<Mutex<LibraryStore>> {
+        let library = memory_library();
+        {
+            let mut store = library.lock().unwrap();
+            let track: TrackRecord = serde_json::from_value(json!({
+                "track_id": "sc-1",
+                "artist": "Bicep",
+                "title": "Glue, Pt. 1",
+            }))
+            .unwrap();
+            store.upsert_track(&track).unwrap();
+            let release: Value =
+                serde_json::from_str(&fixture("discogs/release_4321.json")).unwrap();
+            store
+                .record_discogs_success("sc-1", "Bicep Glue", &release, 90.0)
+                .unwrap();
+        }
+        library
+    }
+
+    fn export(library: &Arc<Mutex<LibraryStore>>, format: ExportFormat, name: &str) -> String {
+        let path = env::temp_dir().join(format!("export-{name}-{}", std::process::id()));
+        export_status(library, format, Some(&path), StatusFilter::default()).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        contents
+    }
+
+    #[test]
+    fn status_exports_hold_every_track() {
+        let library = matched_library();
+
+        let csv = export(&library, ExportFormat::Csv, "csv");
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], CSV_COLUMNS.join(","));
+        assert!(lines[1].starts_with("sc-1,Bicep,\"Glue, Pt. 1\","));
+        assert!(lines[1].contains(",success,4321,90,"));
+
+        let json = export(&library, ExportFormat::Json, "json");
+        let rows: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(rows.as_array().unwrap().len(), 1);
+        assert_eq!(rows[0]["trackId"], "sc-1");
+        assert_eq!(rows[0]["title"], "Glue, Pt. 1");
+        assert_eq!(rows[0]["discogsStatus"], "success");
+        assert_eq!(rows[0]["discogsReleaseId"], "4321");
+    }
+}
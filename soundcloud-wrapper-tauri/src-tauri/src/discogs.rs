@@ -1,93 +1,459 @@
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use reqwest::Client;
-use serde::Deserialize;
+use reqwest::header::HeaderMap;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use tauri::async_runtime;
-use tauri::AppHandle;
-use tauri::Emitter;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 use tokio::time::sleep;
 
-use crate::library::LibraryStore;
+use crate::endpoints::{env_value, ClientIdentity, Endpoint, EndpointSettings, PROJECT_URL};
+use crate::errors::CommandError;
+use crate::events::EventSink;
+use crate::library::{lock, DiscogsListEntry, DiscogsListKind, DiscogsReleaseTarget, LibraryStore};
+use crate::matching::{MatchSettings, TrackFeatures, DISCOGS_PROVIDER};
+use crate::normalize::normalize_payload;
 use crate::SoundcloudTrackPayload;
 
-const SEARCH_URL: &str = "https://api.discogs.com/database/search";
 const DISCOGS_AMBIGUITY_EVENT: &str = "app://discogs/lookup-ambiguous";
-const USER_AGENT: &str = "SoundCloudWrapper/0.1 (+https://github.com/your-org/desktop-soundcloud)";
+const DISCOGS_LISTS_EVENT: &str = "app://discogs/lists-updated";
+const MAX_ATTEMPTS: usize = 3;
+const LIST_PAGE_SIZE: u32 = 100;
+/// The "Uncategorized" folder every Discogs collection has.
+const DEFAULT_COLLECTION_FOLDER: u64 = 1;
+/// Discogs counts requests over a moving one-minute window.
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+const AUTHENTICATED_RATE_LIMIT: u32 = 60;
+const ANONYMOUS_RATE_LIMIT: u32 = 25;
 
 #[derive(Clone)]
 pub struct DiscogsService {
-    sender: mpsc::Sender<SoundcloudTrackPayload>,
+    sender: mpsc::Sender<DiscogsJob>,
+}
+
+/// Work handled by the Discogs worker. Collection and wantlist requests share
+/// the lookup queue so every call goes through the same rate limiter.
+enum DiscogsJob {
+    Lookup {
+        payload: Box<SoundcloudTrackPayload>,
+        done: Option<oneshot::Sender<Result<(), CommandError>>>,
+    },
+    ImportLists(oneshot::Sender<Result<DiscogsListSummary, CommandError>>),
+    AddToList {
+        kind: DiscogsListKind,
+        folder_id: u64,
+        targets: Vec<DiscogsReleaseTarget>,
+        reply: oneshot::Sender<Result<DiscogsListUpdate, CommandError>>,
+    },
+}
+
+/// Number of releases imported from the user's Discogs account.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscogsListSummary {
+    pub username: String,
+    pub collection: u32,
+    pub wantlist: u32,
+}
+
+/// Outcome of pushing matched tracks to the collection or wantlist.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscogsListUpdate {
+    pub added: Vec<String>,
+    pub failed: Vec<DiscogsListFailure>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscogsListFailure {
+    pub track_id: String,
+    pub release_id: String,
+    pub message: String,
 }
 
 impl DiscogsService {
-    pub fn new(app: &AppHandle, library: Arc<Mutex<LibraryStore>>) -> Self {
-        let (sender, mut receiver) = mpsc::channel::<SoundcloudTrackPayload>(32);
+    pub fn new(
+        events: Arc<dyn EventSink>,
+        library: Arc<Mutex<LibraryStore>>,
+        identity: &ClientIdentity,
+    ) -> Result<Self, CommandError> {
+        Self::spawn(events, library, DiscogsCredentials::load(identity))
+    }
+
+    fn spawn(
+        events: Arc<dyn EventSink>,
+        library: Arc<Mutex<LibraryStore>>,
+        credentials: DiscogsCredentials,
+    ) -> Result<Self, CommandError> {
+        let (sender, mut receiver) = mpsc::channel::<DiscogsJob>(32);
+        let credentials = Arc::new(credentials);
         let client = Client::builder()
-            .user_agent(USER_AGENT)
+            .user_agent(credentials.user_agent.clone())
             .build()
-            .expect("failed to build Discogs client");
-        let app_handle = app.clone();
+            .map_err(|error| {
+                CommandError::internal(format!("failed to build Discogs client: {error}"))
+            })?;
         async_runtime::spawn(async move {
-            let mut rate_limiter = RateLimiter::new(Duration::from_millis(1100));
-            while let Some(payload) = receiver.recv().await {
-                if payload.track_id.is_empty() {
-                    continue;
+            let mut rate_limiter = RateLimiter::new(credentials.auth.expected_rate_limit());
+            let worker_credentials = Arc::clone(&credentials);
+            let mut username: Option<String> = None;
+            while let Some(job) = receiver.recv().await {
+                match job {
+                    DiscogsJob::Lookup { payload, done } => {
+                        if !payload.track_id.is_empty() {
+                            process_job(
+                                events.as_ref(),
+                                Arc::clone(&library),
+                                &client,
+                                worker_credentials.as_ref(),
+                                &mut rate_limiter,
+                                *payload,
+                            )
+                            .await;
+                        }
+                        if let Some(done) = done {
+                            let _ = done.send(Ok(()));
+                        }
+                    }
+                    DiscogsJob::ImportLists(reply) => {
+                        let endpoint = load_endpoint(&library);
+                        rate_limiter.set_min_interval(endpoint.min_interval);
+                        let mut session = AccountSession {
+                            client: &client,
+                            credentials: worker_credentials.as_ref(),
+                            endpoint,
+                            rate_limiter: &mut rate_limiter,
+                            username: &mut username,
+                        };
+                        let result = import_lists(&mut session, &library).await;
+                        if result.is_ok() {
+                            emit_lists_updated(events.as_ref());
+                        }
+                        let _ = reply.send(result);
+                    }
+                    DiscogsJob::AddToList {
+                        kind,
+                        folder_id,
+                        targets,
+                        reply,
+                    } => {
+                        let endpoint = load_endpoint(&library);
+                        rate_limiter.set_min_interval(endpoint.min_interval);
+                        let mut session = AccountSession {
+                            client: &client,
+                            credentials: worker_credentials.as_ref(),
+                            endpoint,
+                            rate_limiter: &mut rate_limiter,
+                            username: &mut username,
+                        };
+                        let result =
+                            add_to_list(&mut session, &library, kind, folder_id, &targets).await;
+                        if result.as_ref().is_ok_and(|update| !update.added.is_empty()) {
+                            emit_lists_updated(events.as_ref());
+                        }
+                        let _ = reply.send(result);
+                    }
                 }
-                process_job(
-                    &app_handle,
-                    Arc::clone(&library),
-                    &client,
-                    &mut rate_limiter,
-                    payload,
-                )
-                .await;
             }
         });
 
-        Self { sender }
+        Ok(Self { sender })
     }
 
     pub fn queue_lookup(&self, payload: SoundcloudTrackPayload) {
         let mut sender = self.sender.clone();
         async_runtime::spawn(async move {
-            if let Err(error) = sender.send(payload).await {
-                eprintln!("[discogs] failed to enqueue lookup: {error}");
+            let job = DiscogsJob::Lookup {
+                payload: Box::new(payload),
+                done: None,
+            };
+            if let Err(error) = sender.send(job).await {
+                log::error!("failed to enqueue lookup: {error}");
             }
         });
     }
+
+    /// Runs a lookup through the worker queue and resolves once its outcome
+    /// has been recorded in the library.
+    pub async fn lookup(&self, payload: SoundcloudTrackPayload) -> Result<(), CommandError> {
+        let (reply, response) = oneshot::channel();
+        let job = DiscogsJob::Lookup {
+            payload: Box::new(payload),
+            done: Some(reply),
+        };
+        self.submit(job, response).await
+    }
+
+    /// Imports the user's full collection and wantlist into the library.
+    pub async fn import_lists(&self) -> Result<DiscogsListSummary, CommandError> {
+        let (reply, response) = oneshot::channel();
+        self.submit(DiscogsJob::ImportLists(reply), response).await
+    }
+
+    pub async fn add_to_wantlist(
+        &self,
+        targets: Vec<DiscogsReleaseTarget>,
+    ) -> Result<DiscogsListUpdate, CommandError> {
+        let (reply, response) = oneshot::channel();
+        let job = DiscogsJob::AddToList {
+            kind: DiscogsListKind::Wantlist,
+            folder_id: DEFAULT_COLLECTION_FOLDER,
+            targets,
+            reply,
+        };
+        self.submit(job, response).await
+    }
+
+    pub async fn add_to_collection(
+        &self,
+        targets: Vec<DiscogsReleaseTarget>,
+        folder_id: Option<u64>,
+    ) -> Result<DiscogsListUpdate, CommandError> {
+        let (reply, response) = oneshot::channel();
+        let job = DiscogsJob::AddToList {
+            kind: DiscogsListKind::Collection,
+            folder_id: folder_id.unwrap_or(DEFAULT_COLLECTION_FOLDER),
+            targets,
+            reply,
+        };
+        self.submit(job, response).await
+    }
+
+    async fn submit<T>(
+        &self,
+        job: DiscogsJob,
+        response: oneshot::Receiver<Result<T, CommandError>>,
+    ) -> Result<T, CommandError> {
+        self.sender
+            .send(job)
+            .await
+            .map_err(|_| CommandError::internal("Discogs worker is not running"))?;
+        response
+            .await
+            .map_err(|_| CommandError::internal("Discogs worker dropped the request"))?
+    }
+}
+
+struct DiscogsCredentials {
+    user_agent: String,
+    auth: DiscogsAuth,
+}
+
+/// How requests are authenticated against the Discogs API, from most to least
+/// privileged. Anything other than `Anonymous` unlocks image URLs and the
+/// 60 requests/minute tier.
+enum DiscogsAuth {
+    OAuth {
+        consumer_key: String,
+        consumer_secret: String,
+        token: String,
+        token_secret: String,
+    },
+    Token(String),
+    Consumer {
+        key: String,
+        secret: String,
+    },
+    Anonymous,
+}
+
+impl DiscogsCredentials {
+    fn load(identity: &ClientIdentity) -> Self {
+        let app_name = env_value("DISCOGS_APP_NAME").unwrap_or_else(|| identity.name.clone());
+        let app_version =
+            env_value("DISCOGS_APP_VERSION").unwrap_or_else(|| identity.version.clone());
+        let contact = env_value("DISCOGS_APP_CONTACT").unwrap_or_else(|| PROJECT_URL.to_string());
+        let user_agent = format!("{app_name}/{app_version} ({contact})");
+
+        let consumer = env_value("DISCOGS_CONSUMER_KEY").zip(env_value("DISCOGS_CONSUMER_SECRET"));
+        let oauth_token =
+            env_value("DISCOGS_OAUTH_TOKEN").zip(env_value("DISCOGS_OAUTH_TOKEN_SECRET"));
+
+        let auth = match (consumer, oauth_token, env_value("DISCOGS_TOKEN")) {
+            (Some((consumer_key, consumer_secret)), Some((token, token_secret)), _) => {
+                DiscogsAuth::OAuth {
+                    consumer_key,
+                    consumer_secret,
+                    token,
+                    token_secret,
+                }
+            }
+            (_, _, Some(token)) => DiscogsAuth::Token(token),
+            (Some((key, secret)), None, None) => DiscogsAuth::Consumer { key, secret },
+            (None, _, None) => DiscogsAuth::Anonymous,
+        };
+
+        if matches!(auth, DiscogsAuth::Anonymous) {
+            log::info!("no credentials configured, using anonymous rate limits");
+        }
+
+        Self { user_agent, auth }
+    }
+}
+
+impl DiscogsAuth {
+    fn expected_rate_limit(&self) -> u32 {
+        match self {
+            DiscogsAuth::Anonymous => ANONYMOUS_RATE_LIMIT,
+            _ => AUTHENTICATED_RATE_LIMIT,
+        }
+    }
+
+    /// Collection and wantlist endpoints act on behalf of a user, which a bare
+    /// consumer key cannot do.
+    fn identifies_user(&self) -> bool {
+        matches!(self, DiscogsAuth::OAuth { .. } | DiscogsAuth::Token(_))
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match self {
+            DiscogsAuth::OAuth {
+                consumer_key,
+                consumer_secret,
+                token,
+                token_secret,
+            } => request.header(
+                "Authorization",
+                oauth_plaintext_header(consumer_key, consumer_secret, token, token_secret),
+            ),
+            DiscogsAuth::Token(token) => {
+                request.header("Authorization", format!("Discogs token={token}"))
+            }
+            DiscogsAuth::Consumer { key, secret } => request.header(
+                "Authorization",
+                format!("Discogs key={key}, secret={secret}"),
+            ),
+            DiscogsAuth::Anonymous => request,
+        }
+    }
+}
+
+/// Builds an OAuth 1.0a header using the PLAINTEXT signature method, which
+/// Discogs accepts over HTTPS and which avoids per-request HMAC signing.
+fn oauth_plaintext_header(
+    consumer_key: &str,
+    consumer_secret: &str,
+    token: &str,
+    token_secret: &str,
+) -> String {
+    static NONCE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let nonce = format!(
+        "{:x}{:x}",
+        now.as_nanos(),
+        NONCE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+
+    let signature = format!(
+        "{}&{}",
+        oauth_encode(consumer_secret),
+        oauth_encode(token_secret)
+    );
+
+    format!(
+        "OAuth oauth_consumer_key=\"{}\", oauth_nonce=\"{nonce}\", oauth_token=\"{}\", \
+         oauth_signature=\"{}\", oauth_signature_method=\"PLAINTEXT\", \
+         oauth_timestamp=\"{}\", oauth_version=\"1.0\"",
+        oauth_encode(consumer_key),
+        oauth_encode(token),
+        oauth_encode(&signature),
+        now.as_secs()
+    )
+}
+
+/// RFC 5849 percent-encoding: everything but unreserved characters.
+fn oauth_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
 }
 
+/// Spaces requests according to the `X-Discogs-Ratelimit*` headers returned
+/// with every response. While plenty of the window remains, requests go out
+/// at the steady rate of the account tier (or the configured minimum
+/// interval); as the remaining budget drops below half, the interval
+/// stretches so the window has time to recover.
 struct RateLimiter {
     last: Option<Instant>,
-    interval: Duration,
+    limit: u32,
+    remaining: Option<u32>,
+    min_interval: Option<Duration>,
 }
 
 impl RateLimiter {
-    fn new(interval: Duration) -> Self {
+    fn new(limit: u32) -> Self {
         Self {
             last: None,
-            interval,
+            limit: limit.max(1),
+            remaining: None,
+            min_interval: None,
+        }
+    }
+
+    fn set_min_interval(&mut self, min_interval: Option<Duration>) {
+        self.min_interval = min_interval;
+    }
+
+    fn interval(&self) -> Duration {
+        let tier = RATE_LIMIT_WINDOW / self.limit;
+        let steady = self.min_interval.unwrap_or(tier);
+        let half = (self.limit / 2).max(1);
+        match self.remaining {
+            None => steady,
+            Some(remaining) if remaining >= half => steady,
+            Some(0) => RATE_LIMIT_WINDOW / 4,
+            Some(remaining) => (tier * half / remaining)
+                .min(RATE_LIMIT_WINDOW / 4)
+                .max(steady),
         }
     }
 
     async fn wait(&mut self) {
         if let Some(last) = self.last {
+            let interval = self.interval();
             let elapsed = last.elapsed();
-            if elapsed < self.interval {
-                sleep(self.interval - elapsed).await;
+            if elapsed < interval {
+                sleep(interval - elapsed).await;
             }
         }
         self.last = Some(Instant::now());
     }
+
+    fn observe(&mut self, headers: &HeaderMap) {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<u32>().ok())
+        };
+
+        if let Some(limit) = header("X-Discogs-Ratelimit").filter(|limit| *limit > 0) {
+            self.limit = limit;
+        }
+        if let Some(remaining) = header("X-Discogs-Ratelimit-Remaining") {
+            self.remaining = Some(remaining);
+        }
+    }
 }
 
 async fn process_job(
-    app: &AppHandle,
+    events: &dyn EventSink,
     library: Arc<Mutex<LibraryStore>>,
     client: &Client,
+    credentials: &DiscogsCredentials,
     rate_limiter: &mut RateLimiter,
     payload: SoundcloudTrackPayload,
 ) {
@@ -99,13 +465,27 @@ async fn process_job(
             if let Err(error) =
                 store.record_discogs_failure(&track_id, &query, "missing title or artist")
             {
-                eprintln!("[discogs] failed to persist lookup failure for {track_id}: {error}");
+                log::error!("failed to persist lookup failure for {track_id}: {error}");
             }
         }
         return;
     }
 
-    match perform_lookup(client, rate_limiter, &payload, &query).await {
+    let settings = load_settings(&library);
+    let endpoint = load_endpoint(&library);
+    rate_limiter.set_min_interval(endpoint.min_interval);
+
+    match perform_lookup(
+        client,
+        credentials,
+        &endpoint,
+        rate_limiter,
+        &settings,
+        &payload,
+        &query,
+    )
+    .await
+    {
         Ok(LookupResult::Success {
             release,
             confidence,
@@ -114,20 +494,18 @@ async fn process_job(
                 if let Err(error) =
                     store.record_discogs_success(&track_id, &query, &release, confidence)
                 {
-                    eprintln!("[discogs] failed to persist lookup success for {track_id}: {error}");
+                    log::error!("failed to persist lookup success for {track_id}: {error}");
                 }
             }
         }
         Ok(LookupResult::Ambiguous { candidates }) => {
             if let Ok(mut store) = library.lock() {
                 if let Err(error) = store.record_discogs_ambiguity(&track_id, &query, &candidates) {
-                    eprintln!(
-                        "[discogs] failed to persist lookup ambiguity for {track_id}: {error}"
-                    );
+                    log::error!("failed to persist lookup ambiguity for {track_id}: {error}");
                 }
             }
 
-            if let Err(error) = app.emit(
+            if let Err(error) = events.emit(
                 DISCOGS_AMBIGUITY_EVENT,
                 json!({
                     "trackId": track_id,
@@ -135,7 +513,7 @@ async fn process_job(
                     "candidates": candidates,
                 }),
             ) {
-                eprintln!("[discogs] failed to emit ambiguity event: {error}");
+                log::error!("failed to emit ambiguity event: {error}");
             }
         }
         Err(failure) => {
@@ -143,13 +521,251 @@ async fn process_job(
                 if let Err(error) =
                     store.record_discogs_failure(&track_id, &query, &failure.into_message())
                 {
-                    eprintln!("[discogs] failed to persist lookup failure for {track_id}: {error}");
+                    log::error!("failed to persist lookup failure for {track_id}: {error}");
                 }
             }
         }
     }
 }
 
+fn load_settings(library: &Arc<Mutex<LibraryStore>>) -> MatchSettings {
+    match library.lock() {
+        Ok(store) => store
+            .load_match_settings(DISCOGS_PROVIDER)
+            .unwrap_or_else(|error| {
+                log::warn!("failed to load match settings, using defaults: {error}");
+                MatchSettings::defaults_for(DISCOGS_PROVIDER)
+            }),
+        Err(_) => MatchSettings::defaults_for(DISCOGS_PROVIDER),
+    }
+}
+
+fn load_endpoint(library: &Arc<Mutex<LibraryStore>>) -> Endpoint {
+    match library.lock() {
+        Ok(store) => store
+            .load_endpoint_settings(DISCOGS_PROVIDER)
+            .unwrap_or_else(|error| {
+                log::warn!("failed to load endpoint settings, using defaults: {error}");
+                EndpointSettings::unset(DISCOGS_PROVIDER)
+            }),
+        Err(_) => EndpointSettings::unset(DISCOGS_PROVIDER),
+    }
+    .resolve()
+}
+
+struct AccountSession<'a> {
+    client: &'a Client,
+    credentials: &'a DiscogsCredentials,
+    endpoint: Endpoint,
+    rate_limiter: &'a mut RateLimiter,
+    username: &'a mut Option<String>,
+}
+
+impl AccountSession<'_> {
+    async fn username(&mut self) -> Result<String, CommandError> {
+        if let Some(username) = self.username.as_ref() {
+            return Ok(username.clone());
+        }
+        if !self.credentials.auth.identifies_user() {
+            return Err(CommandError::validation(
+                "Discogs collection access requires DISCOGS_TOKEN or OAuth credentials",
+            ));
+        }
+
+        let client = self.client;
+        let url = self.endpoint.url("oauth/identity");
+        let identity = send_request(self.credentials, &self.endpoint, self.rate_limiter, || {
+            client.get(&url)
+        })
+        .await
+        .map_err(LookupFailure::into_error)?
+        .json::<Value>()
+        .await
+        .map_err(|error| {
+            CommandError::network("discogs", format!("failed to parse identity: {error}"))
+        })?;
+
+        let username = identity
+            .get("username")
+            .and_then(|value| value.as_str())
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| CommandError::network("discogs", "identity did not include a username"))?
+            .to_string();
+        *self.username = Some(username.clone());
+        Ok(username)
+    }
+
+    /// Walks every page of a paginated list endpoint and returns the items
+    /// found under `key`.
+    async fn fetch_all(&mut self, url: &str, key: &str) -> Result<Vec<Value>, CommandError> {
+        let client = self.client;
+        let mut items = Vec::new();
+        let mut page = 1u32;
+        loop {
+            let response =
+                send_request(self.credentials, &self.endpoint, self.rate_limiter, || {
+                    client
+                        .get(url)
+                        .query(&[("page", page), ("per_page", LIST_PAGE_SIZE)])
+                })
+                .await
+                .map_err(LookupFailure::into_error)?;
+            if !response.status().is_success() {
+                return Err(CommandError::network(
+                    "discogs",
+                    format!("{url} returned status {}", response.status()),
+                ));
+            }
+
+            let body = response.json::<Value>().await.map_err(|error| {
+                CommandError::network("discogs", format!("failed to parse list page: {error}"))
+            })?;
+            if let Some(page_items) = body.get(key).and_then(|value| value.as_array()) {
+                items.extend(page_items.iter().cloned());
+            }
+
+            let pages = body
+                .pointer("/pagination/pages")
+                .and_then(|value| value.as_u64())
+                .unwrap_or(1);
+            if u64::from(page) >= pages {
+                return Ok(items);
+            }
+            page += 1;
+        }
+    }
+}
+
+async fn import_lists(
+    session: &mut AccountSession<'_>,
+    library: &Arc<Mutex<LibraryStore>>,
+) -> Result<DiscogsListSummary, CommandError> {
+    let username = session.username().await?;
+    let collection = session
+        .fetch_all(
+            &session
+                .endpoint
+                .url(&format!("users/{username}/collection/folders/0/releases")),
+            "releases",
+        )
+        .await?;
+    let wantlist = session
+        .fetch_all(
+            &session.endpoint.url(&format!("users/{username}/wants")),
+            "wants",
+        )
+        .await?;
+
+    let collection: Vec<DiscogsListEntry> = collection
+        .iter()
+        .filter_map(DiscogsListEntry::from_api_item)
+        .collect();
+    let wantlist: Vec<DiscogsListEntry> = wantlist
+        .iter()
+        .filter_map(DiscogsListEntry::from_api_item)
+        .collect();
+
+    let mut store = lock(library)?;
+    store.replace_discogs_list(DiscogsListKind::Collection, &collection)?;
+    store.replace_discogs_list(DiscogsListKind::Wantlist, &wantlist)?;
+
+    Ok(DiscogsListSummary {
+        username,
+        collection: collection.len() as u32,
+        wantlist: wantlist.len() as u32,
+    })
+}
+
+async fn add_to_list(
+    session: &mut AccountSession<'_>,
+    library: &Arc<Mutex<LibraryStore>>,
+    kind: DiscogsListKind,
+    folder_id: u64,
+    targets: &[DiscogsReleaseTarget],
+) -> Result<DiscogsListUpdate, CommandError> {
+    let username = session.username().await?;
+    let client = session.client;
+    let mut update = DiscogsListUpdate::default();
+
+    for target in targets {
+        let url = match kind {
+            DiscogsListKind::Wantlist => session
+                .endpoint
+                .url(&format!("users/{username}/wants/{}", target.release_id)),
+            DiscogsListKind::Collection => session.endpoint.url(&format!(
+                "users/{username}/collection/folders/{folder_id}/releases/{}",
+                target.release_id
+            )),
+        };
+
+        let response = send_request(
+            session.credentials,
+            &session.endpoint,
+            session.rate_limiter,
+            || match kind {
+                DiscogsListKind::Wantlist => client.put(&url),
+                DiscogsListKind::Collection => client.post(&url),
+            },
+        )
+        .await;
+
+        let body = match response {
+            Ok(response) if response.status().is_success() => {
+                response.json::<Value>().await.unwrap_or(Value::Null)
+            }
+            Ok(response) => {
+                update.failed.push(DiscogsListFailure {
+                    track_id: target.track_id.clone(),
+                    release_id: target.release_id.clone(),
+                    message: format!("Discogs returned status {}", response.status()),
+                });
+                continue;
+            }
+            Err(failure) => {
+                update.failed.push(DiscogsListFailure {
+                    track_id: target.track_id.clone(),
+                    release_id: target.release_id.clone(),
+                    message: failure.into_message(),
+                });
+                continue;
+            }
+        };
+
+        // The wantlist endpoint echoes the full want; the collection endpoint
+        // only returns the new instance id, so fall back to what we know.
+        let entry = DiscogsListEntry::from_api_item(&body)
+            .filter(|entry| entry.release_id == target.release_id)
+            .unwrap_or_else(|| DiscogsListEntry {
+                release_id: target.release_id.clone(),
+                folder_id: Some(folder_id as i64),
+                instance_id: body.get("instance_id").and_then(|value| value.as_i64()),
+                title: target.title.clone(),
+                artist: None,
+                formats: target.formats.clone(),
+                added_at: None,
+                raw_payload: body.clone(),
+            });
+
+        if let Ok(mut store) = library.lock() {
+            if let Err(error) = store.record_discogs_list_entry(kind, &entry) {
+                log::error!(
+                    "failed to persist list entry for {}: {error}",
+                    target.track_id
+                );
+            }
+        }
+        update.added.push(target.track_id.clone());
+    }
+
+    Ok(update)
+}
+
+fn emit_lists_updated(events: &dyn EventSink) {
+    if let Err(error) = events.emit(DISCOGS_LISTS_EVENT, json!({})) {
+        log::error!("failed to emit lists event: {error}");
+    }
+}
+
 enum LookupResult {
     Success { release: Value, confidence: f32 },
     Ambiguous { candidates: Vec<Value> },
@@ -158,6 +774,8 @@ enum LookupResult {
 enum LookupFailure {
     Message(String),
     Error(String),
+    /// Discogs rejected the credentials.
+    Unauthorized,
 }
 
 impl LookupFailure {
@@ -165,6 +783,16 @@ impl LookupFailure {
         match self {
             LookupFailure::Message(message) => message,
             LookupFailure::Error(error) => error,
+            LookupFailure::Unauthorized => "unauthorized Discogs request".to_string(),
+        }
+    }
+
+    /// Rejected credentials are the user's to fix; anything else is a
+    /// failed request.
+    fn into_error(self) -> CommandError {
+        match self {
+            LookupFailure::Unauthorized => CommandError::validation(self.into_message()),
+            failure => CommandError::network("discogs", failure.into_message()),
         }
     }
 }
@@ -182,29 +810,67 @@ struct SearchResult {
     result_type: Option<String>,
     resource_url: Option<String>,
     score: Option<f32>,
-    year: Option<u32>,
+    // Discogs sends the year as a string, but be lenient about numbers too.
+    year: Option<Value>,
     country: Option<String>,
     thumb: Option<String>,
 }
 
 fn build_search_term(payload: &SoundcloudTrackPayload) -> String {
-    let mut terms = Vec::new();
-    if let Some(artist) = payload.artist.as_ref() {
-        if !artist.trim().is_empty() {
-            terms.push(artist.trim().to_string());
-        }
-    }
-    if let Some(title) = payload.title.as_ref() {
-        if !title.trim().is_empty() {
-            terms.push(title.trim().to_string());
+    normalize_payload(payload).search_terms()
+}
+
+/// Sends a request built by `build`, retrying when Discogs answers with
+/// 429 and feeding every response's rate-limit headers into the limiter.
+async fn send_request(
+    credentials: &DiscogsCredentials,
+    endpoint: &Endpoint,
+    rate_limiter: &mut RateLimiter,
+    build: impl Fn() -> RequestBuilder,
+) -> Result<Response, LookupFailure> {
+    let mut attempts = 0usize;
+    loop {
+        attempts += 1;
+        rate_limiter.wait().await;
+        let response = credentials
+            .auth
+            .authorize(build())
+            .timeout(endpoint.timeout)
+            .send()
+            .await
+            .map_err(|error| LookupFailure::Error(format!("request failed: {error}")))?;
+        rate_limiter.observe(response.headers());
+
+        match response.status() {
+            StatusCode::TOO_MANY_REQUESTS => {
+                let retry_after = response
+                    .headers()
+                    .get("Retry-After")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .map(Duration::from_secs)
+                    .unwrap_or(RATE_LIMIT_WINDOW / 4);
+                if attempts >= MAX_ATTEMPTS {
+                    return Err(LookupFailure::Message(
+                        "rate limited by Discogs".to_string(),
+                    ));
+                }
+                sleep(retry_after).await;
+            }
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                return Err(LookupFailure::Unauthorized);
+            }
+            _ => return Ok(response),
         }
     }
-    terms.join(" ")
 }
 
 async fn perform_lookup(
     client: &Client,
+    credentials: &DiscogsCredentials,
+    endpoint: &Endpoint,
     rate_limiter: &mut RateLimiter,
+    settings: &MatchSettings,
     payload: &SoundcloudTrackPayload,
     query: &str,
 ) -> Result<LookupResult, LookupFailure> {
@@ -213,23 +879,22 @@ async fn perform_lookup(
         ("per_page", "5".to_string()),
     ];
 
-    if let Some(artist) = payload.artist.as_ref() {
-        params.push(("artist", artist.clone()));
+    let normalized = normalize_payload(payload);
+    if let Some(artist) = normalized.artist {
+        params.push(("artist", artist));
     }
-    if let Some(title) = payload.title.as_ref() {
-        params.push(("release_title", title.clone()));
+    if let Some(title) = normalized.title {
+        params.push(("release_title", title));
     }
     if !query.is_empty() {
         params.push(("q", query.to_string()));
     }
 
-    rate_limiter.wait().await;
-    let response = client
-        .get(SEARCH_URL)
-        .query(&params)
-        .send()
-        .await
-        .map_err(|error| LookupFailure::Error(error.to_string()))?;
+    let search_url = endpoint.url("database/search");
+    let response = send_request(credentials, endpoint, rate_limiter, || {
+        client.get(&search_url).query(&params)
+    })
+    .await?;
 
     if !response.status().is_success() {
         return Err(LookupFailure::Message(format!(
@@ -243,41 +908,38 @@ async fn perform_lookup(
         .await
         .map_err(|error| LookupFailure::Error(error.to_string()))?;
 
-    let mut results: Vec<SearchResult> = body
+    let track = TrackFeatures::from_payload(payload);
+    let mut results: Vec<(f32, SearchResult)> = body
         .results
         .into_iter()
         .filter(|result| {
             matches!(result.result_type.as_deref(), Some("release"))
                 && result.resource_url.is_some()
         })
+        .map(|result| {
+            let candidate = TrackFeatures::from_discogs_result(result.title.as_deref());
+            (settings.blend(result.score, &track, &candidate), result)
+        })
         .collect();
 
     if results.is_empty() {
         return Err(LookupFailure::Message("no releases found".to_string()));
     }
 
-    results.sort_by(|a, b| {
-        b.score
-            .partial_cmp(&a.score)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
-
-    let top_score = results
-        .first()
-        .and_then(|result| result.score)
-        .unwrap_or(0.0);
-    let second_score = results
-        .get(1)
-        .and_then(|result| result.score)
-        .unwrap_or(0.0);
-
-    if results.len() == 1 || (top_score >= 85.0 && top_score - second_score >= 10.0) {
-        let top = results.first().cloned().unwrap();
-        let release_url = top.resource_url.unwrap_or_else(|| {
-            top.id
-                .map(|id| format!("https://api.discogs.com/releases/{id}"))
-                .unwrap_or_default()
-        });
+    results.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    let top_score = results.first().map(|(score, _)| *score).unwrap_or(0.0);
+    let second_score = results.get(1).map(|(score, _)| *score).unwrap_or(0.0);
+
+    if settings.is_confident(results.len(), top_score, second_score) {
+        let (_, top) = results.first().cloned().unwrap();
+        // Prefer building the URL from the id so lookups stay on the
+        // configured endpoint even when it is a mirror.
+        let release_url = top
+            .id
+            .map(|id| endpoint.url(&format!("releases/{id}")))
+            .or(top.resource_url)
+            .unwrap_or_default();
 
         if release_url.is_empty() {
             return Err(LookupFailure::Message(
@@ -285,12 +947,17 @@ async fn perform_lookup(
             ));
         }
 
-        rate_limiter.wait().await;
-        let release = client
-            .get(release_url)
-            .send()
-            .await
-            .map_err(|error| LookupFailure::Error(error.to_string()))?
+        let response = send_request(credentials, endpoint, rate_limiter, || {
+            client.get(&release_url)
+        })
+        .await?;
+        if !response.status().is_success() {
+            return Err(LookupFailure::Message(format!(
+                "release returned status {}",
+                response.status()
+            )));
+        }
+        let release = response
             .json::<Value>()
             .await
             .map_err(|error| LookupFailure::Error(error.to_string()))?;
@@ -304,11 +971,12 @@ async fn perform_lookup(
     let candidates = results
         .into_iter()
         .take(5)
-        .map(|result| {
+        .map(|(score, result)| {
             json!({
                 "id": result.id,
                 "title": result.title,
-                "score": result.score,
+                "score": score,
+                "providerScore": result.score,
                 "year": result.year,
                 "country": result.country,
                 "resourceUrl": result.resource_url,
@@ -319,3 +987,214 @@ async fn perform_lookup(
 
     Ok(LookupResult::Ambiguous { candidates })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorCode;
+    use crate::library::LibraryStatusRow;
+    use crate::test_support::{
+        fixture, memory_library, payload, point_at, status_row, wait_for, RecordingSink,
+    };
+    use mockito::{Matcher, Server};
+    use reqwest::header::HeaderValue;
+
+    fn spawn_service(
+        server: &Server,
+        auth: DiscogsAuth,
+    ) -> (DiscogsService, Arc<Mutex<LibraryStore>>, Arc<RecordingSink>) {
+        let library = memory_library();
+        point_at(&library, DISCOGS_PROVIDER, &server.url());
+        let events = Arc::new(RecordingSink::default());
+        let credentials = DiscogsCredentials {
+            user_agent: "SoundCloudWrapperTests/0.0 (tests@example.com)".to_string(),
+            auth,
+        };
+        let service =
+            DiscogsService::spawn(events.clone(), Arc::clone(&library), credentials).unwrap();
+        (service, library, events)
+    }
+
+    async fn wait_for_match(
+        library: &Arc<Mutex<LibraryStore>>,
+        track_id: &str,
+    ) -> LibraryStatusRow {
+        wait_for(|| status_row(library, track_id).filter(|row| row.discogs_status.is_some())).await
+    }
+
+    #[tokio::test]
+    async fn confident_match_fetches_and_persists_release() {
+        let mut server = Server::new_async().await;
+        let search = server
+            .mock("GET", "/database/search")
+            .match_header("authorization", "Discogs token=secret")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("type".into(), "release".into()),
+                Matcher::UrlEncoded("artist".into(), "Bicep".into()),
+                Matcher::UrlEncoded("release_title".into(), "Glue".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_header("X-Discogs-Ratelimit", "60")
+            .with_header("X-Discogs-Ratelimit-Remaining", "59")
+            .with_body(fixture("discogs/search_single.json"))
+            .create_async()
+            .await;
+        let release = server
+            .mock("GET", "/releases/4321")
+            .with_header("content-type", "application/json")
+            .with_body(fixture("discogs/release_4321.json"))
+            .create_async()
+            .await;
+
+        let (service, library, events) =
+            spawn_service(&server, DiscogsAuth::Token("secret".to_string()));
+        service.queue_lookup(payload("sc-1", "Bicep", "Glue", None));
+
+        let row = wait_for_match(&library, "sc-1").await;
+        assert_eq!(row.discogs_status.as_deref(), Some("success"));
+        assert_eq!(row.discogs_release_id.as_deref(), Some("4321"));
+        assert!(row.discogs_confidence.is_some_and(|score| score >= 50.0));
+        assert!(events.events(DISCOGS_AMBIGUITY_EVENT).is_empty());
+        search.assert_async().await;
+        release.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn close_candidates_are_stored_and_announced() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("GET", "/database/search")
+            .match_query(Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(fixture("discogs/search_ambiguous.json"))
+            .create_async()
+            .await;
+        let release = server
+            .mock("GET", Matcher::Regex("^/releases/".into()))
+            .expect(0)
+            .create_async()
+            .await;
+
+        let (service, library, events) = spawn_service(&server, DiscogsAuth::Anonymous);
+        service.queue_lookup(payload("sc-2", "Bicep", "Glue", None));
+
+        let row = wait_for_match(&library, "sc-2").await;
+        assert_eq!(row.discogs_status.as_deref(), Some("ambiguous"));
+        assert_eq!(row.discogs_release_id, None);
+        assert_eq!(row.discogs_candidate_count, 2);
+
+        let announced = events.events(DISCOGS_AMBIGUITY_EVENT);
+        assert_eq!(announced.len(), 1);
+        assert_eq!(announced[0]["trackId"], "sc-2");
+        assert_eq!(announced[0]["candidates"].as_array().map(Vec::len), Some(2));
+        release.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn rate_limited_search_is_retried() {
+        let mut server = Server::new_async().await;
+        let limited = server
+            .mock("GET", "/database/search")
+            .match_query(Matcher::Any)
+            .with_status(429)
+            .with_header("Retry-After", "0")
+            .expect(1)
+            .create_async()
+            .await;
+        let search = server
+            .mock("GET", "/database/search")
+            .match_query(Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(fixture("discogs/search_single.json"))
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/releases/4321")
+            .with_header("content-type", "application/json")
+            .with_body(fixture("discogs/release_4321.json"))
+            .create_async()
+            .await;
+
+        let (service, library, _events) = spawn_service(&server, DiscogsAuth::Anonymous);
+        service.queue_lookup(payload("sc-3", "Bicep", "Glue", None));
+
+        let row = wait_for_match(&library, "sc-3").await;
+        assert_eq!(row.discogs_status.as_deref(), Some("success"));
+        limited.assert_async().await;
+        search.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn empty_search_records_failure() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("GET", "/database/search")
+            .match_query(Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(fixture("discogs/search_empty.json"))
+            .create_async()
+            .await;
+
+        let (service, library, _events) = spawn_service(&server, DiscogsAuth::Anonymous);
+        service.queue_lookup(payload("sc-4", "Nobody", "Nothing", None));
+
+        let row = wait_for_match(&library, "sc-4").await;
+        assert_eq!(row.discogs_status.as_deref(), Some("error"));
+        assert_eq!(row.discogs_message.as_deref(), Some("no releases found"));
+    }
+
+    #[tokio::test]
+    async fn list_errors_tell_credentials_from_request_failures() {
+        let mut server = Server::new_async().await;
+        let (service, _library, _events) = spawn_service(&server, DiscogsAuth::Anonymous);
+        let error = service.import_lists().await.unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Validation);
+
+        let refused = server
+            .mock("GET", "/oauth/identity")
+            .match_header("authorization", "Discogs token=revoked")
+            .with_status(401)
+            .create_async()
+            .await;
+        let (service, _library, _events) =
+            spawn_service(&server, DiscogsAuth::Token("revoked".to_string()));
+        let error = service.add_to_wantlist(Vec::new()).await.unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Validation);
+        refused.assert_async().await;
+
+        server
+            .mock("GET", "/oauth/identity")
+            .match_header("authorization", "Discogs token=secret")
+            .with_status(500)
+            .with_body("unavailable")
+            .create_async()
+            .await;
+        let (service, _library, _events) =
+            spawn_service(&server, DiscogsAuth::Token("secret".to_string()));
+        let error = service.import_lists().await.unwrap_err();
+        assert_eq!(error.code(), ErrorCode::Network);
+    }
+
+    #[test]
+    fn rate_limiter_backs_off_as_budget_runs_out() {
+        let mut limiter = RateLimiter::new(AUTHENTICATED_RATE_LIMIT);
+        assert_eq!(limiter.interval(), Duration::from_secs(1));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Discogs-Ratelimit", HeaderValue::from_static("60"));
+        headers.insert(
+            "X-Discogs-Ratelimit-Remaining",
+            HeaderValue::from_static("10"),
+        );
+        limiter.observe(&headers);
+        assert_eq!(limiter.interval(), Duration::from_secs(3));
+
+        headers.insert(
+            "X-Discogs-Ratelimit-Remaining",
+            HeaderValue::from_static("0"),
+        );
+        limiter.observe(&headers);
+        assert_eq!(limiter.interval(), RATE_LIMIT_WINDOW / 4);
+    }
+}
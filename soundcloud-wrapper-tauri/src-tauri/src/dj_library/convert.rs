@@ -0,0 +1,388 @@
+//! Converts cues between the conventions of the DJ programs: where each
+//! program puts the start of an MP3 file, which colors it can show, and
+//! which kinds of cues and how many hot cues it has.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use serde::Serialize;
+
+use super::{CueKind, DjCue, DjSoftware};
+
+/// Hot cue slots each program has.
+pub const HOT_CUE_SLOTS: u32 = 8;
+
+/// Colors Rekordbox offers for hot cues.
+const REKORDBOX_PALETTE: [u32; 16] = [
+    0x305AFF, 0x50B4FF, 0x00E0FF, 0x1FA392, 0x10B176, 0x28E214, 0xA5E116, 0xB4BE04, 0xC3AF04,
+    0xFFA000, 0xE0641B, 0xE62828, 0xFF127B, 0xDE44CF, 0xB432FF, 0xAA72FF,
+];
+
+/// Colors Serato stores for hot cues; it shows each a little differently.
+const SERATO_PALETTE: [u32; 18] = [
+    0xCC0000, 0xCC4400, 0xCC8800, 0xCCCC00, 0x88CC00, 0x44CC00, 0x00CC00, 0x00CC44, 0x00CC88,
+    0x00CCCC, 0x0088CC, 0x0044CC, 0x0000CC, 0x4400CC, 0x8800CC, 0xCC00CC, 0xCC0088, 0xCC0044,
+];
+
+/// Serato gives every saved loop this color.
+const SERATO_LOOP_COLOR: &str = "#27AAE1";
+
+/// How an MP3 file starts, which decides where the programs put position 0.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Mp3Layout {
+    /// Length of the Xing or Info frame at the start of VBR and LAME
+    /// encoded files, which holds no audio.
+    pub info_frame_ms: Option<f64>,
+}
+
+/// The layout of the file at `path`, or `None` when it is not an MP3 file.
+pub fn read_mp3_layout(path: &Path) -> io::Result<Option<Mp3Layout>> {
+    let is_mp3 = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("mp3"));
+    if !is_mp3 {
+        return Ok(None);
+    }
+    // The tag can hold artwork, so read up to the first frame only.
+    let mut file = File::open(path)?;
+    let mut header = [0u8; 10];
+    let read = file.read(&mut header)?;
+    let mut data = header[..read].to_vec();
+    if read == 10 && &header[..3] == b"ID3" {
+        let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+        let tag_size = super::serato::syncsafe(&header[6..10]) + footer;
+        io::copy(&mut (&mut file).take(tag_size as u64), &mut io::sink())?;
+        data.clear();
+    }
+    file.take(4096).read_to_end(&mut data)?;
+    Ok(Some(mp3_layout(&data)))
+}
+
+/// Reads the first frame of MPEG audio `data` and whether it is a Xing or
+/// Info frame.
+fn mp3_layout(data: &[u8]) -> Mp3Layout {
+    let Some(start) = data
+        .windows(2)
+        .position(|pair| pair[0] == 0xFF && pair[1] & 0xE0 == 0xE0)
+    else {
+        return Mp3Layout::default();
+    };
+    let Some(header) = data.get(start..start + 4) else {
+        return Mp3Layout::default();
+    };
+    let version = (header[1] >> 3) & 0b11;
+    let layer = (header[1] >> 1) & 0b11;
+    let rate_index = usize::from((header[2] >> 2) & 0b11);
+    let mono = header[3] >> 6 == 0b11;
+    // Layer III only; the other layers have no info frame.
+    if layer != 0b01 || rate_index == 3 || version == 0b01 {
+        return Mp3Layout::default();
+    }
+    let mpeg1 = version == 0b11;
+    let sample_rate = [44_100.0, 48_000.0, 32_000.0][rate_index]
+        / match version {
+            0b11 => 1.0,
+            0b10 => 2.0,
+            _ => 4.0,
+        };
+    let samples = if mpeg1 { 1152.0 } else { 576.0 };
+    let side_info = match (mpeg1, mono) {
+        (true, true) => 17,
+        (true, false) => 32,
+        (false, true) => 9,
+        (false, false) => 17,
+    };
+    let tag_offset = start + 4 + side_info;
+    let is_info = matches!(
+        data.get(tag_offset..tag_offset + 4),
+        Some(b"Xing") | Some(b"Info")
+    );
+    Mp3Layout {
+        info_frame_ms: is_info.then(|| samples / sample_rate * 1000.0),
+    }
+}
+
+/// Where `software` puts the start of a file laid out as `layout`, relative
+/// to its first audio frame. Rekordbox decodes the info frame as a frame of
+/// silence, so its positions in such files are one frame later than
+/// Traktor's and Serato's.
+fn origin_ms(software: DjSoftware, layout: Mp3Layout) -> f64 {
+    match software {
+        DjSoftware::Rekordbox => layout.info_frame_ms.unwrap_or_default(),
+        DjSoftware::Traktor | DjSoftware::Serato => 0.0,
+    }
+}
+
+/// How far positions move from `from` to `to`, in milliseconds.
+pub fn offset_ms(from: DjSoftware, to: DjSoftware, layout: Option<Mp3Layout>) -> f64 {
+    let layout = layout.unwrap_or_default();
+    origin_ms(to, layout) - origin_ms(from, layout)
+}
+
+/// Converts `cues` shown by `from` into what `to` would show for the same
+/// points of the file. Hot cues past the slots of `to` become memory cues,
+/// and cues `to` cannot store are left out.
+pub fn convert_cues(
+    cues: &[DjCue],
+    from: DjSoftware,
+    to: DjSoftware,
+    layout: Option<Mp3Layout>,
+) -> Vec<DjCue> {
+    let offset = offset_ms(from, to, layout);
+    let mut converted: Vec<DjCue> = cues
+        .iter()
+        .filter_map(|cue| {
+            let mut cue = cue.clone();
+            cue.position_ms = (cue.position_ms + offset).max(0.0);
+            cue.hot_cue = cue.hot_cue.filter(|slot| *slot < HOT_CUE_SLOTS);
+            let has_length = matches!(cue.length_ms, Some(length) if length > 0.0);
+            if cue.kind == CueKind::Loop && !has_length {
+                cue.kind = CueKind::Cue;
+                cue.length_ms = None;
+            }
+            match to {
+                // Serato only has hot cues and saved loops, both in slots.
+                DjSoftware::Serato => {
+                    cue.hot_cue?;
+                    if cue.kind == CueKind::Loop {
+                        cue.color = Some(SERATO_LOOP_COLOR.to_string());
+                    } else {
+                        cue.kind = CueKind::Cue;
+                        let color = cue.color.as_deref().and_then(parse_color);
+                        cue.color = Some(nearest_color(
+                            color.unwrap_or(SERATO_PALETTE[0]),
+                            &SERATO_PALETTE,
+                        ));
+                    }
+                }
+                DjSoftware::Rekordbox => {
+                    cue.color = cue
+                        .color
+                        .as_deref()
+                        .and_then(parse_color)
+                        .filter(|_| cue.hot_cue.is_some())
+                        .map(|color| nearest_color(color, &REKORDBOX_PALETTE));
+                }
+                // Traktor colors cues by their kind.
+                DjSoftware::Traktor => cue.color = None,
+            }
+            Some(cue)
+        })
+        .collect();
+    converted.sort_by(|a, b| a.position_ms.total_cmp(&b.position_ms));
+    converted
+}
+
+/// The entry of `palette` closest to `color`, as `#RRGGBB`.
+fn nearest_color(color: u32, palette: &[u32]) -> String {
+    let nearest = palette
+        .iter()
+        .copied()
+        .min_by_key(|candidate| color_distance(color, *candidate))
+        .unwrap_or(color);
+    format!("#{nearest:06X}")
+}
+
+/// Colors are `#RRGGBB`; Rekordbox databases also hold palette indexes,
+/// which are not converted.
+fn parse_color(color: &str) -> Option<u32> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
+
+fn color_distance(a: u32, b: u32) -> u32 {
+    [16, 8, 0]
+        .iter()
+        .map(|shift| {
+            let difference = ((a >> shift) & 0xFF).abs_diff((b >> shift) & 0xFF);
+            difference * difference
+        })
+        .sum()
+}
+
+/// Cues of a track converted for one program, with the data to load them.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CueExport {
+    pub software: DjSoftware,
+    /// The library the cues were taken from.
+    pub source: DjSoftware,
+    pub offset_ms: f64,
+    pub cues: Vec<DjCue>,
+    /// `CUE_V2` elements for Traktor, `POSITION_MARK` elements for
+    /// Rekordbox, or the base64 encoded `Serato Markers2` object.
+    pub data: String,
+}
+
+/// Converts the cues of the first of `sources` that has any, or of `from`,
+/// for `to`. Cues `to` already has are used as they are when no other
+/// library has cues.
+pub fn export_cues(
+    sources: &[(DjSoftware, Vec<DjCue>)],
+    to: DjSoftware,
+    from: Option<DjSoftware>,
+    layout: Option<Mp3Layout>,
+) -> Option<CueExport> {
+    let with_cues = |software: DjSoftware| {
+        sources
+            .iter()
+            .find(|(candidate, cues)| *candidate == software && !cues.is_empty())
+    };
+    let (source, cues) = match from {
+        Some(from) => with_cues(from)?,
+        None => DjSoftware::ALL
+            .into_iter()
+            .filter(|software| *software != to)
+            .chain([to])
+            .find_map(with_cues)?,
+    };
+    let cues = convert_cues(cues, *source, to, layout);
+    let data = match to {
+        DjSoftware::Rekordbox => crate::rekordbox::position_marks(&cues),
+        DjSoftware::Traktor => super::traktor::cue_elements(&cues),
+        DjSoftware::Serato => super::serato::encode_markers(&cues),
+    };
+    Some(CueExport {
+        software: to,
+        source: *source,
+        offset_ms: offset_ms(*source, to, layout),
+        cues,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(kind: CueKind, hot_cue: Option<u32>, position_ms: f64) -> DjCue {
+        DjCue {
+            kind,
+            hot_cue,
+            name: None,
+            color: None,
+            position_ms,
+            length_ms: None,
+        }
+    }
+
+    #[test]
+    fn reads_the_info_frame_of_mp3_files() {
+        // MPEG1 layer III, 128 kbit/s, 44.1 kHz, joint stereo.
+        let mut frame = vec![0xFF, 0xFB, 0x90, 0x44];
+        frame.extend([0; 32]);
+        frame.extend(b"Info");
+        let layout = mp3_layout(&frame);
+        let frame_ms = layout.info_frame_ms.unwrap();
+        assert!((frame_ms - 26.122).abs() < 0.001, "{frame_ms}");
+
+        frame[36..40].copy_from_slice(&[0; 4]);
+        assert_eq!(mp3_layout(&frame), Mp3Layout::default());
+    }
+
+    #[test]
+    fn moves_positions_by_the_info_frame_for_rekordbox() {
+        let layout = Some(Mp3Layout {
+            info_frame_ms: Some(26.0),
+        });
+        let cues = [cue(CueKind::Cue, Some(0), 1_000.0)];
+
+        let rekordbox = convert_cues(&cues, DjSoftware::Traktor, DjSoftware::Rekordbox, layout);
+        assert_eq!(rekordbox[0].position_ms, 1_026.0);
+        let traktor = convert_cues(
+            &rekordbox,
+            DjSoftware::Rekordbox,
+            DjSoftware::Traktor,
+            layout,
+        );
+        assert_eq!(traktor[0].position_ms, 1_000.0);
+
+        let serato = convert_cues(&cues, DjSoftware::Traktor, DjSoftware::Serato, layout);
+        assert_eq!(serato[0].position_ms, 1_000.0);
+        let wav = convert_cues(&cues, DjSoftware::Traktor, DjSoftware::Rekordbox, None);
+        assert_eq!(wav[0].position_ms, 1_000.0);
+    }
+
+    #[test]
+    fn maps_colors_to_the_palette_of_the_target() {
+        let mut red = cue(CueKind::Cue, Some(1), 0.0);
+        red.color = Some("#D01010".to_string());
+
+        let serato = convert_cues(
+            &[red.clone()],
+            DjSoftware::Rekordbox,
+            DjSoftware::Serato,
+            None,
+        );
+        assert_eq!(serato[0].color.as_deref(), Some("#CC0000"));
+        let rekordbox = convert_cues(
+            &[red.clone()],
+            DjSoftware::Serato,
+            DjSoftware::Rekordbox,
+            None,
+        );
+        assert_eq!(rekordbox[0].color.as_deref(), Some("#E62828"));
+        let traktor = convert_cues(&[red], DjSoftware::Serato, DjSoftware::Traktor, None);
+        assert_eq!(traktor[0].color, None);
+    }
+
+    #[test]
+    fn keeps_what_the_target_can_store() {
+        let mut looped = cue(CueKind::Loop, Some(0), 4_000.0);
+        looped.length_ms = Some(2_000.0);
+        let cues = [
+            cue(CueKind::Cue, None, 500.0),
+            cue(CueKind::FadeIn, Some(9), 1_000.0),
+            cue(CueKind::Load, Some(2), 2_000.0),
+            looped,
+            cue(CueKind::Loop, Some(3), 6_000.0),
+        ];
+
+        let serato = convert_cues(&cues, DjSoftware::Traktor, DjSoftware::Serato, None);
+        let serato: Vec<_> = serato
+            .iter()
+            .map(|cue| (cue.kind, cue.hot_cue, cue.position_ms))
+            .collect();
+        assert_eq!(
+            serato,
+            [
+                (CueKind::Cue, Some(2), 2_000.0),
+                (CueKind::Loop, Some(0), 4_000.0),
+                (CueKind::Cue, Some(3), 6_000.0),
+            ]
+        );
+
+        let rekordbox = convert_cues(&cues, DjSoftware::Traktor, DjSoftware::Rekordbox, None);
+        assert_eq!(rekordbox.len(), 5);
+        assert_eq!(rekordbox[1].kind, CueKind::FadeIn);
+        assert_eq!(rekordbox[1].hot_cue, None);
+    }
+
+    #[test]
+    fn exports_from_another_library_first() {
+        let sources = [
+            (
+                DjSoftware::Rekordbox,
+                vec![cue(CueKind::Cue, Some(0), 100.0)],
+            ),
+            (DjSoftware::Traktor, vec![cue(CueKind::Cue, Some(0), 200.0)]),
+        ];
+        let export = export_cues(&sources, DjSoftware::Rekordbox, None, None).unwrap();
+        assert_eq!(export.source, DjSoftware::Traktor);
+        assert_eq!(export.cues[0].position_ms, 200.0);
+
+        let export = export_cues(&sources[..1], DjSoftware::Rekordbox, None, None).unwrap();
+        assert_eq!(export.source, DjSoftware::Rekordbox);
+        assert_eq!(
+            export.data,
+            r#"<POSITION_MARK Name="" Type="0" Start="0.100" Num="0"/>"#
+        );
+        assert!(
+            export_cues(&sources, DjSoftware::Serato, Some(DjSoftware::Serato), None).is_none()
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! The libraries of DJ software, read into one model of tracks, cues and
+//! beatgrids. Each software has an importer behind [`DjLibraryImporter`];
+//! the library keeps what they read in per-software mapping tables, like the
+//! Rekordbox import does.
+
+mod convert;
+mod serato;
+mod traktor;
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::rekordbox::RekordboxCue;
+
+pub use convert::{
+    convert_cues, export_cues, offset_ms, read_mp3_layout, CueExport, Mp3Layout, HOT_CUE_SLOTS,
+};
+pub use serato::SeratoImporter;
+pub use traktor::TraktorImporter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DjSoftware {
+    Rekordbox,
+    Traktor,
+    Serato,
+}
+
+impl DjSoftware {
+    pub const ALL: [DjSoftware; 3] = [Self::Rekordbox, Self::Traktor, Self::Serato];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|software| software.as_str() == name)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Rekordbox => "rekordbox",
+            Self::Traktor => "traktor",
+            Self::Serato => "serato",
+        }
+    }
+
+    /// The table mapping the software's track IDs to library tracks.
+    pub fn mappings_table(self) -> &'static str {
+        match self {
+            Self::Rekordbox => "rekordbox_mappings",
+            Self::Traktor => "traktor_mappings",
+            Self::Serato => "serato_mappings",
+        }
+    }
+
+    /// The column of the mappings table holding the software's track ID.
+    pub fn id_column(self) -> &'static str {
+        match self {
+            Self::Rekordbox => "rekordbox_id",
+            Self::Traktor => "traktor_id",
+            Self::Serato => "serato_id",
+        }
+    }
+
+    /// The table keeping the entry as it was read, per library track.
+    pub fn sources_table(self) -> &'static str {
+        match self {
+            Self::Rekordbox => "rekordbox_sources",
+            Self::Traktor => "traktor_sources",
+            Self::Serato => "serato_sources",
+        }
+    }
+}
+
+impl fmt::Display for DjSoftware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CueKind {
+    Cue,
+    Loop,
+    FadeIn,
+    FadeOut,
+    /// Where the track starts when it is loaded.
+    Load,
+}
+
+/// A cue point or loop, at the position the software shows it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DjCue {
+    pub kind: CueKind,
+    /// Hot cue slot counted from 0; `None` for memory cues.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hot_cue: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// `#RRGGBB`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    pub position_ms: f64,
+    /// Length of a loop.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length_ms: Option<f64>,
+}
+
+impl From<&RekordboxCue> for DjCue {
+    fn from(cue: &RekordboxCue) -> Self {
+        // The `Type` of a Rekordbox XML export.
+        let kind = match cue.cue_type.as_deref() {
+            Some("1") => CueKind::FadeIn,
+            Some("2") => CueKind::FadeOut,
+            Some("3") => CueKind::Load,
+            Some("4") => CueKind::Loop,
+            _ => CueKind::Cue,
+        };
+        Self {
+            kind,
+            hot_cue: u32::try_from(cue.slot).ok(),
+            name: cue.name.clone().filter(|name| !name.is_empty()),
+            color: cue.color.clone(),
+            position_ms: cue.position_ms as f64,
+            length_ms: cue
+                .end_ms
+                .filter(|_| kind == CueKind::Loop)
+                .map(|end| (end - cue.position_ms) as f64),
+        }
+    }
+}
+
+/// A beatgrid anchor: from `position_ms` on, beats follow at `bpm` until
+/// the next marker.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BeatMarker {
+    pub position_ms: f64,
+    pub bpm: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DjTrack {
+    /// Identifies the entry within its library across imports.
+    pub external_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bpm: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<u64>,
+    #[serde(skip)]
+    pub fingerprint: Option<Vec<u32>>,
+    pub available: bool,
+    /// Paths of the playlists or crates holding the track, joined by `/`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub playlists: Vec<String>,
+    pub cues: Vec<DjCue>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub beatgrid: Vec<BeatMarker>,
+}
+
+impl DjTrack {
+    /// An entry for the file at `path`, with the checksum, duration and
+    /// fingerprint of the file when it can be read.
+    fn for_file(external_id: String, path: Option<PathBuf>) -> Self {
+        let metadata = crate::rekordbox::entry_metadata(path.as_deref(), &external_id);
+        Self {
+            external_id,
+            title: None,
+            artist: None,
+            album: None,
+            key: None,
+            bpm: None,
+            location: path.map(|path| path.to_string_lossy().into_owned()),
+            checksum: metadata.checksum,
+            duration_ms: metadata.duration_ms,
+            file_size: metadata.size,
+            fingerprint: metadata.fingerprint,
+            available: metadata.available,
+            playlists: Vec::new(),
+            cues: Vec::new(),
+            beatgrid: Vec::new(),
+        }
+    }
+}
+
+pub trait DjLibraryImporter: Sync {
+    fn software(&self) -> DjSoftware;
+
+    /// Reads every track of the library at `path`, which is the library
+    /// file or a folder holding it.
+    fn load_tracks(&self, path: &Path) -> Result<Vec<DjTrack>, DjLibraryError>;
+}
+
+/// The importer of `software`. Rekordbox libraries have their own sources
+/// with auto-refresh, see [`crate::rekordbox`], so there is none for it.
+pub fn importer(software: DjSoftware) -> Option<&'static dyn DjLibraryImporter> {
+    match software {
+        DjSoftware::Rekordbox => None,
+        DjSoftware::Traktor => Some(&TraktorImporter),
+        DjSoftware::Serato => Some(&SeratoImporter),
+    }
+}
+
+/// The library track ID of an entry. Traktor and Serato identify entries
+/// by their file path, so it is hashed into something shorter.
+pub fn track_id(software: DjSoftware, source_id: &str, external_id: &str) -> String {
+    let digest = Sha256::digest(external_id.as_bytes());
+    let hash: String = digest[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("{software}:{source_id}:{hash}")
+}
+
+#[derive(Debug)]
+pub enum DjLibraryError {
+    Io(io::Error),
+    Xml(quick_xml::DeError),
+    /// The library file is malformed.
+    Invalid(String),
+}
+
+impl fmt::Display for DjLibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DjLibraryError::Io(error) => write!(f, "filesystem error: {error}"),
+            DjLibraryError::Xml(error) => write!(f, "xml error: {error}"),
+            DjLibraryError::Invalid(message) => write!(f, "invalid library: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for DjLibraryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DjLibraryError::Io(error) => Some(error),
+            DjLibraryError::Xml(error) => Some(error),
+            DjLibraryError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for DjLibraryError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<quick_xml::DeError> for DjLibraryError {
+    fn from(value: quick_xml::DeError) -> Self {
+        Self::Xml(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn track_ids_are_namespaced_and_stable() {
+        let id = track_id(DjSoftware::Traktor, "default", "HD/:Music/:a.mp3");
+        assert_eq!(
+            id,
+            track_id(DjSoftware::Traktor, "default", "HD/:Music/:a.mp3")
+        );
+        assert!(id.starts_with("traktor:default:"), "{id}");
+        assert_eq!(id.len(), "traktor:default:".len() + 16);
+        assert_ne!(
+            id,
+            track_id(DjSoftware::Traktor, "laptop", "HD/:Music/:a.mp3")
+        );
+        assert_ne!(
+            id,
+            track_id(DjSoftware::Traktor, "default", "HD/:Music/:b.mp3")
+        );
+    }
+}
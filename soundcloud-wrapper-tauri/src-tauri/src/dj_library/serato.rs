@@ -0,0 +1,767 @@
+//! Importer for Serato libraries.
+//!
+//! Serato keeps its library in `_Serato_/database V2` and each crate in
+//! `_Serato_/Subcrates/<name>.crate`. Both are a list of tagged fields: a
+//! four character tag, a big endian `u32` length and the value, which for
+//! `o…` tags is itself a list of fields. Strings are UTF-16BE. Cues and
+//! beatgrids are not in the database but in the tags of each file; this
+//! reads them from the ID3 `GEOB` frames of MP3 files.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+
+use super::{BeatMarker, CueKind, DjCue, DjLibraryError, DjLibraryImporter, DjSoftware, DjTrack};
+
+const LIBRARY_DIR_NAME: &str = "_Serato_";
+const DATABASE_FILE_NAME: &str = "database V2";
+const CRATES_DIR_NAME: &str = "Subcrates";
+const CRATE_EXTENSION: &str = "crate";
+
+/// Crate names have their parent crates in front, separated by this.
+const CRATE_SEPARATOR: &str = "%%";
+
+const TRACK: &[u8; 4] = b"otrk";
+const CRATE_TRACK_PATH: &[u8; 4] = b"ptrk";
+const PATH: &[u8; 4] = b"pfil";
+const TITLE: &[u8; 4] = b"tsng";
+const ARTIST: &[u8; 4] = b"tart";
+const ALBUM: &[u8; 4] = b"talb";
+const KEY: &[u8; 4] = b"tkey";
+const BPM: &[u8; 4] = b"tbpm";
+
+const MARKERS_DESCRIPTION: &str = "Serato Markers2";
+const BEATGRID_DESCRIPTION: &str = "Serato BeatGrid";
+
+/// Serato wraps its base64 in lines, leaves out the padding and sometimes
+/// ends it with a stray character, so decoding is lenient.
+const MARKERS_BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new()
+        .with_decode_allow_trailing_bits(true)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+pub struct SeratoImporter;
+
+impl DjLibraryImporter for SeratoImporter {
+    fn software(&self) -> DjSoftware {
+        DjSoftware::Serato
+    }
+
+    fn load_tracks(&self, path: &Path) -> Result<Vec<DjTrack>, DjLibraryError> {
+        let library_dir = locate(path).ok_or_else(|| {
+            DjLibraryError::Invalid(format!(
+                "no {LIBRARY_DIR_NAME}/{DATABASE_FILE_NAME} at {}",
+                path.display()
+            ))
+        })?;
+        let database = fs::read(library_dir.join(DATABASE_FILE_NAME))?;
+        let crates = read_crates(&library_dir.join(CRATES_DIR_NAME))?;
+        let volume_root = library_dir.parent().unwrap_or(Path::new("/"));
+        read_database(&database, crates, &|file| {
+            let path = resolve(volume_root, file);
+            let markers = read_markers(&path);
+            (path, markers)
+        })
+    }
+}
+
+/// The `_Serato_` folder for `path`, which is its database file, the folder
+/// itself or the folder holding it.
+fn locate(path: &Path) -> Option<PathBuf> {
+    let candidates = [
+        path.parent().map(Path::to_path_buf),
+        Some(path.to_path_buf()),
+        Some(path.join(LIBRARY_DIR_NAME)),
+    ];
+    candidates
+        .into_iter()
+        .flatten()
+        .find(|dir| dir.join(DATABASE_FILE_NAME).is_file())
+}
+
+/// Paths in the database are relative to the root of the drive holding the
+/// `_Serato_` folder; for the system drive that is `/` rather than the
+/// folder the library is in.
+fn resolve(volume_root: &Path, file: &str) -> PathBuf {
+    let on_volume = volume_root.join(file);
+    let on_root = Path::new("/").join(file);
+    if on_volume.exists() {
+        return on_volume;
+    }
+    if on_root.exists() {
+        return on_root;
+    }
+    let volume_prefix = volume_root.to_string_lossy();
+    if file.starts_with(volume_prefix.trim_start_matches('/')) {
+        on_root
+    } else {
+        on_volume
+    }
+}
+
+fn read_database(
+    data: &[u8],
+    mut crates: HashMap<String, Vec<String>>,
+    read_file: &dyn Fn(&str) -> (PathBuf, Markers),
+) -> Result<Vec<DjTrack>, DjLibraryError> {
+    let mut tracks = Vec::new();
+    for (tag, value) in fields(data)? {
+        if tag != TRACK {
+            continue;
+        }
+        let mut file = None;
+        let mut title = None;
+        let mut artist = None;
+        let mut album = None;
+        let mut key = None;
+        let mut bpm = None;
+        for (tag, value) in fields(value)? {
+            match tag {
+                PATH => file = Some(utf16(value)),
+                TITLE => title = Some(utf16(value)),
+                ARTIST => artist = Some(utf16(value)),
+                ALBUM => album = Some(utf16(value)),
+                KEY => key = Some(utf16(value)),
+                BPM => bpm = utf16(value).trim().parse::<f64>().ok(),
+                _ => {}
+            }
+        }
+        let Some(file) = file.filter(|file| !file.is_empty()) else {
+            log::warn!("skipping serato entry without a path: {title:?}");
+            continue;
+        };
+
+        let (path, markers) = read_file(&file);
+        let mut track = DjTrack::for_file(file.clone(), Some(path));
+        track.title = title.filter(|value| !value.is_empty());
+        track.artist = artist.filter(|value| !value.is_empty());
+        track.album = album.filter(|value| !value.is_empty());
+        track.key = key.filter(|value| !value.is_empty());
+        track.bpm = bpm.filter(|bpm| *bpm > 0.0);
+        track.playlists = crates.remove(&file).unwrap_or_default();
+        track.cues = markers.cues;
+        track.beatgrid = markers.beatgrid;
+        tracks.push(track);
+    }
+    Ok(tracks)
+}
+
+/// The crates holding each file, by the file's database path.
+fn read_crates(dir: &Path) -> Result<HashMap<String, Vec<String>>, DjLibraryError> {
+    let mut crates: HashMap<String, Vec<String>> = HashMap::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(crates),
+        Err(error) => return Err(error.into()),
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == CRATE_EXTENSION))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let Some(stem) = path.file_stem() else {
+            continue;
+        };
+        let name = stem.to_string_lossy().replace(CRATE_SEPARATOR, "/");
+        let files = match fs::read(&path)
+            .map_err(DjLibraryError::from)
+            .and_then(|data| crate_files(&data))
+        {
+            Ok(files) => files,
+            Err(error) => {
+                log::warn!("failed to read serato crate {}: {error}", path.display());
+                continue;
+            }
+        };
+        for file in files {
+            let names = crates.entry(file).or_default();
+            if !names.contains(&name) {
+                names.push(name.clone());
+            }
+        }
+    }
+    Ok(crates)
+}
+
+fn crate_files(data: &[u8]) -> Result<Vec<String>, DjLibraryError> {
+    let mut files = Vec::new();
+    for (tag, value) in fields(data)? {
+        if tag != TRACK {
+            continue;
+        }
+        for (tag, value) in fields(value)? {
+            if tag == CRATE_TRACK_PATH {
+                files.push(utf16(value));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// A field's tag and value.
+type Field<'a> = (&'a [u8; 4], &'a [u8]);
+
+/// Splits `data` into its tagged fields.
+fn fields(data: &[u8]) -> Result<Vec<Field<'_>>, DjLibraryError> {
+    let mut fields = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let header = data
+            .get(offset..offset + 8)
+            .ok_or_else(|| DjLibraryError::Invalid(format!("truncated field at {offset}")))?;
+        let tag: &[u8; 4] = header[..4].try_into().expect("header is 8 bytes");
+        let length = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let start = offset + 8;
+        let value = start
+            .checked_add(length)
+            .and_then(|end| data.get(start..end))
+            .ok_or_else(|| {
+                DjLibraryError::Invalid(format!(
+                    "field {} at {offset} is past the end of the data",
+                    String::from_utf8_lossy(tag)
+                ))
+            })?;
+        fields.push((tag, value));
+        offset = start + length;
+    }
+    Ok(fields)
+}
+
+fn utf16(data: &[u8]) -> String {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+#[derive(Debug, Default)]
+struct Markers {
+    cues: Vec<DjCue>,
+    beatgrid: Vec<BeatMarker>,
+}
+
+/// The cues and beatgrid Serato wrote into the tags of the file at `path`.
+/// Files that cannot be read have none.
+fn read_markers(path: &Path) -> Markers {
+    let mut markers = Markers::default();
+    let is_mp3 = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("mp3"));
+    if !is_mp3 {
+        return markers;
+    }
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(error) => {
+            if error.kind() != io::ErrorKind::NotFound {
+                log::warn!("failed to read {}: {error}", path.display());
+            }
+            return markers;
+        }
+    };
+    for (description, data) in id3_objects(&data) {
+        let parsed = match description.as_str() {
+            MARKERS_DESCRIPTION => parse_markers(data).map(|cues| markers.cues = cues),
+            BEATGRID_DESCRIPTION => parse_beatgrid(data).map(|grid| markers.beatgrid = grid),
+            _ => Ok(()),
+        };
+        if let Err(error) = parsed {
+            log::warn!(
+                "failed to parse {description} of {}: {error}",
+                path.display()
+            );
+        }
+    }
+    markers
+}
+
+/// The descriptions and data of the `GEOB` frames of an ID3v2.3 or v2.4 tag.
+fn id3_objects(data: &[u8]) -> Vec<(String, &[u8])> {
+    let mut objects = Vec::new();
+    if data.len() < 10 || &data[..3] != b"ID3" {
+        return objects;
+    }
+    let version = data[3];
+    if version != 3 && version != 4 {
+        return objects;
+    }
+    let flags = data[5];
+    let tag_end = (10 + syncsafe(&data[6..10])).min(data.len());
+    let mut offset = 10;
+    if flags & 0x40 != 0 {
+        // The extended header; only its size matters.
+        let Some(size) = data.get(10..14) else {
+            return objects;
+        };
+        offset += if version == 4 {
+            syncsafe(size)
+        } else {
+            4 + u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize
+        };
+    }
+
+    while offset + 10 <= tag_end {
+        let header = &data[offset..offset + 10];
+        if header[0] == 0 {
+            break;
+        }
+        let size = if version == 4 {
+            syncsafe(&header[4..8])
+        } else {
+            u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize
+        };
+        let start = offset + 10;
+        let Some(body) = data.get(start..(start + size).min(tag_end)) else {
+            break;
+        };
+        if &header[..4] == b"GEOB" {
+            if let Some(object) = general_object(body) {
+                objects.push(object);
+            }
+        }
+        offset = start + size;
+    }
+    objects
+}
+
+pub(super) fn syncsafe(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .take(4)
+        .fold(0, |size, byte| (size << 7) | usize::from(byte & 0x7f))
+}
+
+/// The description and data of a `GEOB` frame: an encoding, a MIME type,
+/// a file name and a description, then the object.
+fn general_object(body: &[u8]) -> Option<(String, &[u8])> {
+    let (&encoding, rest) = body.split_first()?;
+    let wide = encoding == 1 || encoding == 2;
+    let (_, rest) = split_terminated(rest, false)?;
+    let (_, rest) = split_terminated(rest, wide)?;
+    let (description, rest) = split_terminated(rest, wide)?;
+    let description = if wide {
+        let units: Vec<u16> = description
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .filter(|unit| *unit != 0xfeff)
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        String::from_utf8_lossy(description).into_owned()
+    };
+    Some((description, rest))
+}
+
+/// Splits off a string ended by a NUL, which is two bytes for UTF-16.
+fn split_terminated(data: &[u8], wide: bool) -> Option<(&[u8], &[u8])> {
+    if wide {
+        let end = data.chunks_exact(2).position(|pair| pair == [0, 0])? * 2;
+        Some((&data[..end], &data[end + 2..]))
+    } else {
+        let end = data.iter().position(|byte| *byte == 0)?;
+        Some((&data[..end], &data[end + 1..]))
+    }
+}
+
+/// Reads the cues and loops of a `Serato Markers2` object: a version, then
+/// base64 of another version and entries of a name, a length and data.
+fn parse_markers(object: &[u8]) -> Result<Vec<DjCue>, DjLibraryError> {
+    let encoded = object
+        .strip_prefix(&[0x01, 0x01])
+        .ok_or_else(|| DjLibraryError::Invalid("unknown markers version".to_string()))?;
+    let mut encoded: Vec<u8> = encoded
+        .iter()
+        .copied()
+        .filter(|byte| !byte.is_ascii_whitespace() && *byte != 0)
+        .collect();
+    if encoded.len() % 4 == 1 {
+        encoded.pop();
+    }
+    let data = MARKERS_BASE64
+        .decode(&encoded)
+        .map_err(|error| DjLibraryError::Invalid(format!("markers are not base64: {error}")))?;
+    let mut data = data
+        .strip_prefix(&[0x01, 0x01])
+        .ok_or_else(|| DjLibraryError::Invalid("unknown markers version".to_string()))?;
+
+    let mut cues = Vec::new();
+    while let Some(name_end) = data.iter().position(|byte| *byte == 0) {
+        if name_end == 0 {
+            break;
+        }
+        let name = &data[..name_end];
+        let length = data
+            .get(name_end + 1..name_end + 5)
+            .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+            .ok_or_else(|| DjLibraryError::Invalid("truncated marker".to_string()))?;
+        let start = name_end + 5;
+        let entry = data
+            .get(start..start + length)
+            .ok_or_else(|| DjLibraryError::Invalid("truncated marker".to_string()))?;
+        match name {
+            b"CUE" if entry.len() >= 12 => cues.push(DjCue {
+                kind: CueKind::Cue,
+                hot_cue: Some(u32::from(entry[1])),
+                name: marker_name(&entry[12..]),
+                color: Some(color(&entry[7..10])),
+                position_ms: f64::from(be_u32(&entry[2..6])),
+                length_ms: None,
+            }),
+            b"LOOP" if entry.len() >= 20 => {
+                let start = be_u32(&entry[2..6]);
+                let end = be_u32(&entry[6..10]);
+                cues.push(DjCue {
+                    kind: CueKind::Loop,
+                    hot_cue: Some(u32::from(entry[1])),
+                    name: marker_name(&entry[20..]),
+                    color: Some(color(&entry[15..18])),
+                    position_ms: f64::from(start),
+                    length_ms: Some(f64::from(end.saturating_sub(start))),
+                });
+            }
+            _ => {}
+        }
+        data = &data[start + length..];
+    }
+    Ok(cues)
+}
+
+fn marker_name(data: &[u8]) -> Option<String> {
+    let end = data
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(data.len());
+    let name = String::from_utf8_lossy(&data[..end]);
+    (!name.is_empty()).then(|| name.into_owned())
+}
+
+fn color(rgb: &[u8]) -> String {
+    format!("#{:02X}{:02X}{:02X}", rgb[0], rgb[1], rgb[2])
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// The `Serato Markers2` object for `cues`, base64 encoded to pass it on as
+/// text. Only hot cues and loops with a slot are written.
+pub(super) fn encode_markers(cues: &[DjCue]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(write_markers(cues))
+}
+
+/// Writes `cues` the way [`parse_markers`] reads them.
+fn write_markers(cues: &[DjCue]) -> Vec<u8> {
+    let mut data = vec![0x01, 0x01];
+    for cue in cues {
+        let Some(slot) = cue.hot_cue.and_then(|slot| u8::try_from(slot).ok()) else {
+            continue;
+        };
+        let position = cue.position_ms.round() as u32;
+        let rgb = cue
+            .color
+            .as_deref()
+            .and_then(|color| color.strip_prefix('#'))
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .unwrap_or_default()
+            .to_be_bytes();
+        let name = cue.name.as_deref().unwrap_or_default().as_bytes();
+
+        let mut entry = vec![0, slot];
+        entry.extend(position.to_be_bytes());
+        let tag: &[u8] = if cue.kind == CueKind::Loop {
+            let end = (cue.position_ms + cue.length_ms.unwrap_or_default()).round() as u32;
+            entry.extend(end.to_be_bytes());
+            entry.extend([0xff; 4]);
+            entry.push(0);
+            entry.extend(&rgb[1..]);
+            // Then whether the loop is locked.
+            entry.extend([0, 0]);
+            b"LOOP"
+        } else {
+            entry.push(0);
+            entry.extend(&rgb[1..]);
+            entry.extend([0, 0]);
+            b"CUE"
+        };
+        entry.extend(name);
+        entry.push(0);
+
+        data.extend(tag);
+        data.push(0);
+        data.extend((entry.len() as u32).to_be_bytes());
+        data.extend(entry);
+    }
+    data.push(0);
+
+    let encoded = base64::engine::general_purpose::STANDARD_NO_PAD.encode(data);
+    let mut object = vec![0x01, 0x01];
+    for line in encoded.as_bytes().chunks(72) {
+        object.extend(line);
+        object.push(b'\n');
+    }
+    object.push(0);
+    object
+}
+
+/// Reads a `Serato BeatGrid` object: a version, a marker count and the
+/// markers. Each marker is a position in seconds and the number of beats up
+/// to the next marker; the last one has the tempo instead.
+fn parse_beatgrid(object: &[u8]) -> Result<Vec<BeatMarker>, DjLibraryError> {
+    let data = object
+        .strip_prefix(&[0x01, 0x00])
+        .ok_or_else(|| DjLibraryError::Invalid("unknown beatgrid version".to_string()))?;
+    let count = data
+        .get(..4)
+        .map(be_u32)
+        .ok_or_else(|| DjLibraryError::Invalid("truncated beatgrid".to_string()))?
+        as usize;
+    let markers = data
+        .get(4..)
+        .unwrap_or_default()
+        .chunks_exact(8)
+        .take(count)
+        .collect::<Vec<_>>();
+    if markers.len() != count {
+        return Err(DjLibraryError::Invalid("truncated beatgrid".to_string()));
+    }
+
+    let positions: Vec<f64> = markers
+        .iter()
+        .map(|marker| {
+            f64::from(f32::from_be_bytes([
+                marker[0], marker[1], marker[2], marker[3],
+            ]))
+        })
+        .collect();
+    let mut beatgrid = Vec::with_capacity(count);
+    for (index, marker) in markers.iter().enumerate() {
+        let value = &marker[4..8];
+        let bpm = match positions.get(index + 1) {
+            Some(next) if *next > positions[index] => {
+                f64::from(be_u32(value)) * 60.0 / (next - positions[index])
+            }
+            Some(_) => continue,
+            None => f64::from(f32::from_be_bytes([value[0], value[1], value[2], value[3]])),
+        };
+        beatgrid.push(BeatMarker {
+            position_ms: positions[index] * 1000.0,
+            bpm,
+        });
+    }
+    Ok(beatgrid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(tag: &[u8; 4], value: &[u8]) -> Vec<u8> {
+        let mut bytes = tag.to_vec();
+        bytes.extend((value.len() as u32).to_be_bytes());
+        bytes.extend(value);
+        bytes
+    }
+
+    fn text(value: &str) -> Vec<u8> {
+        value.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    fn markers_object(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut data = vec![0x01, 0x01];
+        for (name, entry) in entries {
+            data.extend(name.as_bytes());
+            data.push(0);
+            data.extend((entry.len() as u32).to_be_bytes());
+            data.extend(entry);
+        }
+        data.push(0);
+        let encoded = base64::engine::general_purpose::STANDARD_NO_PAD.encode(data);
+        let mut object = vec![0x01, 0x01];
+        for line in encoded.as_bytes().chunks(72) {
+            object.extend(line);
+            object.push(b'\n');
+        }
+        object.push(0);
+        object
+    }
+
+    #[test]
+    fn reads_tracks_and_crates_from_the_database() {
+        let mut track = field(PATH, &text("Users/dj/Music/a.mp3"));
+        track.extend(field(TITLE, &text("Eple")));
+        track.extend(field(ARTIST, &text("Röyksopp")));
+        track.extend(field(KEY, &text("Am")));
+        track.extend(field(BPM, &text("124.50")));
+        track.extend(field(b"bmis", &[0]));
+        let mut database = field(b"vrsn", &text("2.0/Serato Scratch LIVE Database"));
+        database.extend(field(TRACK, &track));
+        database.extend(field(TRACK, &field(TITLE, &text("No path"))));
+
+        let crate_data = field(
+            TRACK,
+            &field(CRATE_TRACK_PATH, &text("Users/dj/Music/a.mp3")),
+        );
+        let mut crates = HashMap::new();
+        for file in crate_files(&crate_data).unwrap() {
+            crates.insert(file, vec!["Sets/Warmup".to_string()]);
+        }
+
+        let tracks = read_database(&database, crates, &|file| {
+            (Path::new("/missing").join(file), Markers::default())
+        })
+        .unwrap();
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].external_id, "Users/dj/Music/a.mp3");
+        assert_eq!(tracks[0].title.as_deref(), Some("Eple"));
+        assert_eq!(tracks[0].artist.as_deref(), Some("Röyksopp"));
+        assert_eq!(tracks[0].key.as_deref(), Some("Am"));
+        assert_eq!(tracks[0].bpm, Some(124.5));
+        assert_eq!(tracks[0].playlists, ["Sets/Warmup"]);
+        assert!(!tracks[0].available);
+    }
+
+    #[test]
+    fn reads_cues_and_loops_from_markers() {
+        let mut cue = vec![0, 2];
+        cue.extend(1_500u32.to_be_bytes());
+        cue.extend([0, 0xcc, 0x00, 0x00, 0, 0]);
+        cue.extend(b"Drop\0");
+        let mut loop_entry = vec![0, 0];
+        loop_entry.extend(64_000u32.to_be_bytes());
+        loop_entry.extend(71_710u32.to_be_bytes());
+        loop_entry.extend([0xff; 4]);
+        loop_entry.extend([0, 0x27, 0xaa, 0xe1, 0, 0]);
+        loop_entry.extend(b"\0");
+        let object = markers_object(&[
+            ("COLOR", vec![0, 0xff, 0xff, 0xff]),
+            ("CUE", cue),
+            ("LOOP", loop_entry),
+        ]);
+
+        let cues = parse_markers(&object).unwrap();
+        assert_eq!(
+            cues,
+            [
+                DjCue {
+                    kind: CueKind::Cue,
+                    hot_cue: Some(2),
+                    name: Some("Drop".to_string()),
+                    color: Some("#CC0000".to_string()),
+                    position_ms: 1_500.0,
+                    length_ms: None,
+                },
+                DjCue {
+                    kind: CueKind::Loop,
+                    hot_cue: Some(0),
+                    name: None,
+                    color: Some("#27AAE1".to_string()),
+                    position_ms: 64_000.0,
+                    length_ms: Some(7_710.0),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn written_markers_read_back() {
+        let cues = [
+            DjCue {
+                kind: CueKind::Cue,
+                hot_cue: Some(1),
+                name: Some("Intro".to_string()),
+                color: Some("#CC8800".to_string()),
+                position_ms: 250.0,
+                length_ms: None,
+            },
+            DjCue {
+                kind: CueKind::Loop,
+                hot_cue: Some(0),
+                name: None,
+                color: Some("#27AAE1".to_string()),
+                position_ms: 32_000.0,
+                length_ms: Some(4_000.0),
+            },
+        ];
+        assert_eq!(parse_markers(&write_markers(&cues)).unwrap(), cues);
+    }
+
+    #[test]
+    fn reads_beatgrid_markers() {
+        let mut object = vec![0x01, 0x00];
+        object.extend(2u32.to_be_bytes());
+        object.extend(0.5f32.to_be_bytes());
+        object.extend(16u32.to_be_bytes());
+        object.extend(8.5f32.to_be_bytes());
+        object.extend(128.0f32.to_be_bytes());
+        object.push(0);
+
+        let beatgrid = parse_beatgrid(&object).unwrap();
+        assert_eq!(
+            beatgrid,
+            [
+                BeatMarker {
+                    position_ms: 500.0,
+                    bpm: 120.0
+                },
+                BeatMarker {
+                    position_ms: 8_500.0,
+                    bpm: 128.0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_objects_in_id3_tags() {
+        let mut frame_body = vec![0];
+        frame_body.extend(b"application/octet-stream\0\0");
+        frame_body.extend(MARKERS_DESCRIPTION.as_bytes());
+        frame_body.push(0);
+        frame_body.extend([0x01, 0x01]);
+        let mut frame = b"GEOB".to_vec();
+        frame.extend((frame_body.len() as u32).to_be_bytes());
+        frame.extend([0, 0]);
+        frame.extend(frame_body);
+
+        let mut data = b"ID3\x03\x00\x00".to_vec();
+        let size = frame.len() + 16;
+        data.extend([0, 0, (size >> 7) as u8, (size & 0x7f) as u8]);
+        data.extend(frame);
+        data.resize(10 + size, 0);
+        data.extend([0xff, 0xfb]);
+
+        let objects = id3_objects(&data);
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].0, MARKERS_DESCRIPTION);
+        assert_eq!(objects[0].1, [0x01, 0x01]);
+    }
+
+    #[test]
+    fn resolves_paths_against_the_drive_of_the_library() {
+        let root = std::env::temp_dir().join(format!("serato-resolve-{}", std::process::id()));
+        fs::create_dir_all(root.join("Music")).unwrap();
+        fs::write(root.join("Music/a.mp3"), b"").unwrap();
+
+        assert_eq!(resolve(&root, "Music/a.mp3"), root.join("Music/a.mp3"));
+        let missing = format!(
+            "{}/Music/b.mp3",
+            root.to_string_lossy().trim_start_matches('/')
+        );
+        assert_eq!(resolve(&root, &missing), Path::new("/").join(&missing));
+        assert_eq!(resolve(&root, "Music/b.mp3"), root.join("Music/b.mp3"));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
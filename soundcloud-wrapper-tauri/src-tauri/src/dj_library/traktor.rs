@@ -0,0 +1,471 @@
+//! Importer for `collection.nml`, the XML file Traktor keeps its collection,
+//! cues, beatgrids and playlists in.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use quick_xml::de::from_reader as from_xml_reader;
+use quick_xml::escape::escape;
+use serde::Deserialize;
+
+use super::{BeatMarker, CueKind, DjCue, DjLibraryError, DjLibraryImporter, DjSoftware, DjTrack};
+
+const COLLECTION_FILE_NAME: &str = "collection.nml";
+
+/// Traktor separates the folders of a `DIR` with this.
+const DIR_SEPARATOR: &str = "/:";
+
+/// The name Traktor gives cues the user did not name.
+const UNNAMED_CUE: &str = "n.n.";
+
+/// Keys by the `MUSICAL_KEY` value Traktor writes: the majors from C, then
+/// the minors from C.
+const MUSICAL_KEYS: [&str; 24] = [
+    "C", "Db", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B", "Cm", "Dbm", "Dm", "Ebm",
+    "Em", "Fm", "F#m", "Gm", "Abm", "Am", "Bbm", "Bm",
+];
+
+pub struct TraktorImporter;
+
+impl DjLibraryImporter for TraktorImporter {
+    fn software(&self) -> DjSoftware {
+        DjSoftware::Traktor
+    }
+
+    fn load_tracks(&self, path: &Path) -> Result<Vec<DjTrack>, DjLibraryError> {
+        let path = if path.is_dir() {
+            path.join(COLLECTION_FILE_NAME)
+        } else {
+            path.to_path_buf()
+        };
+        let nml: Nml = from_xml_reader(BufReader::new(File::open(&path)?))?;
+        Ok(read_collection(nml, &|location| location.resolve()))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Nml {
+    #[serde(rename = "COLLECTION")]
+    collection: Option<Collection>,
+    #[serde(rename = "PLAYLISTS")]
+    playlists: Option<Playlists>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Collection {
+    #[serde(rename = "ENTRY", default)]
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Entry {
+    #[serde(rename = "@TITLE")]
+    title: Option<String>,
+    #[serde(rename = "@ARTIST")]
+    artist: Option<String>,
+    #[serde(rename = "LOCATION")]
+    location: Option<Location>,
+    #[serde(rename = "ALBUM")]
+    album: Option<Album>,
+    #[serde(rename = "INFO")]
+    info: Option<Info>,
+    #[serde(rename = "TEMPO")]
+    tempo: Option<Tempo>,
+    #[serde(rename = "MUSICAL_KEY")]
+    musical_key: Option<MusicalKey>,
+    #[serde(rename = "CUE_V2", default)]
+    cues: Vec<Cue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Location {
+    #[serde(rename = "@VOLUME", default)]
+    volume: String,
+    #[serde(rename = "@DIR", default)]
+    dir: String,
+    #[serde(rename = "@FILE", default)]
+    file: String,
+}
+
+impl Location {
+    /// The key playlists refer to the entry by.
+    fn primary_key(&self) -> String {
+        format!("{}{}{}", self.volume, self.dir, self.file)
+    }
+
+    /// The file on this machine. Windows volumes are drive letters; on
+    /// macOS other volumes are mounted under `/Volumes`.
+    fn resolve(&self) -> PathBuf {
+        let mut path = if self.volume.ends_with(':') {
+            PathBuf::from(format!("{}/", self.volume))
+        } else {
+            let mounted = Path::new("/Volumes").join(&self.volume);
+            if !self.volume.is_empty() && mounted.is_dir() {
+                mounted
+            } else {
+                PathBuf::from("/")
+            }
+        };
+        path.extend(
+            self.dir
+                .split(DIR_SEPARATOR)
+                .filter(|part| !part.is_empty()),
+        );
+        path.push(&self.file);
+        path
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Album {
+    #[serde(rename = "@TITLE")]
+    title: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Info {
+    #[serde(rename = "@KEY")]
+    key: Option<String>,
+    #[serde(rename = "@PLAYTIME_FLOAT")]
+    playtime: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tempo {
+    #[serde(rename = "@BPM")]
+    bpm: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MusicalKey {
+    #[serde(rename = "@VALUE")]
+    value: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Cue {
+    #[serde(rename = "@NAME")]
+    name: Option<String>,
+    #[serde(rename = "@TYPE")]
+    kind: Option<u32>,
+    #[serde(rename = "@START")]
+    start: Option<f64>,
+    #[serde(rename = "@LEN")]
+    length: Option<f64>,
+    #[serde(rename = "@HOTCUE")]
+    hot_cue: Option<i32>,
+    #[serde(rename = "GRID")]
+    grid: Option<Grid>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Grid {
+    #[serde(rename = "@BPM")]
+    bpm: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Playlists {
+    #[serde(rename = "NODE")]
+    root: Option<Node>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Node {
+    #[serde(rename = "@NAME", default)]
+    name: String,
+    #[serde(rename = "SUBNODES")]
+    subnodes: Option<Subnodes>,
+    #[serde(rename = "PLAYLIST")]
+    playlist: Option<Playlist>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Subnodes {
+    #[serde(rename = "NODE", default)]
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Playlist {
+    #[serde(rename = "ENTRY", default)]
+    entries: Vec<PlaylistEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistEntry {
+    #[serde(rename = "PRIMARYKEY")]
+    primary_key: Option<PrimaryKey>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PrimaryKey {
+    #[serde(rename = "@KEY")]
+    key: Option<String>,
+}
+
+/// Cue types of `CUE_V2` entries.
+const CUE: u32 = 0;
+const FADE_IN: u32 = 1;
+const FADE_OUT: u32 = 2;
+const LOAD: u32 = 3;
+const GRID: u32 = 4;
+const LOOP: u32 = 5;
+
+fn read_collection(nml: Nml, resolve: &dyn Fn(&Location) -> PathBuf) -> Vec<DjTrack> {
+    let mut playlists = HashMap::new();
+    if let Some(root) = nml.playlists.and_then(|playlists| playlists.root) {
+        // The root node is `$ROOT`, which Traktor does not show.
+        for node in root
+            .subnodes
+            .map(|subnodes| subnodes.nodes)
+            .unwrap_or_default()
+        {
+            collect_playlists(node, "", &mut playlists);
+        }
+    }
+
+    let entries = nml
+        .collection
+        .map(|collection| collection.entries)
+        .unwrap_or_default();
+    let mut tracks = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let Some(location) = entry.location else {
+            log::warn!(
+                "skipping traktor entry without a location: {:?}",
+                entry.title
+            );
+            continue;
+        };
+        let primary_key = location.primary_key();
+        let mut track = DjTrack::for_file(primary_key.clone(), Some(resolve(&location)));
+
+        let bpm = entry.tempo.and_then(|tempo| tempo.bpm);
+        let mut cues = Vec::new();
+        let mut beatgrid = Vec::new();
+        for cue in entry.cues {
+            let position_ms = cue.start.unwrap_or_default();
+            let kind = match cue.kind.unwrap_or(CUE) {
+                GRID => {
+                    if let Some(bpm) = cue.grid.and_then(|grid| grid.bpm).or(bpm) {
+                        beatgrid.push(BeatMarker { position_ms, bpm });
+                    }
+                    continue;
+                }
+                FADE_IN => CueKind::FadeIn,
+                FADE_OUT => CueKind::FadeOut,
+                LOAD => CueKind::Load,
+                LOOP => CueKind::Loop,
+                _ => CueKind::Cue,
+            };
+            cues.push(DjCue {
+                kind,
+                hot_cue: cue.hot_cue.and_then(|slot| u32::try_from(slot).ok()),
+                name: cue
+                    .name
+                    .filter(|name| !name.is_empty() && name != UNNAMED_CUE),
+                color: None,
+                position_ms,
+                length_ms: cue.length.filter(|_| kind == CueKind::Loop),
+            });
+        }
+        beatgrid.sort_by(|a, b| a.position_ms.total_cmp(&b.position_ms));
+
+        track.title = entry.title;
+        track.artist = entry.artist;
+        track.album = entry.album.and_then(|album| album.title);
+        track.key = entry
+            .info
+            .as_ref()
+            .and_then(|info| info.key.clone())
+            .filter(|key| !key.is_empty())
+            .or_else(|| {
+                let value = entry.musical_key?.value?;
+                MUSICAL_KEYS.get(value).map(|key| key.to_string())
+            });
+        track.bpm = bpm;
+        if track.duration_ms.is_none() {
+            track.duration_ms = entry
+                .info
+                .and_then(|info| info.playtime)
+                .map(|seconds| (seconds * 1000.0) as u64);
+        }
+        track.playlists = playlists.remove(&primary_key).unwrap_or_default();
+        track.cues = cues;
+        track.beatgrid = beatgrid;
+        tracks.push(track);
+    }
+    tracks
+}
+
+/// `CUE_V2` elements for `cues`, to go into an `ENTRY` of a collection.
+pub(super) fn cue_elements(cues: &[DjCue]) -> String {
+    let elements: Vec<String> = cues
+        .iter()
+        .map(|cue| {
+            let kind = match cue.kind {
+                CueKind::Cue => CUE,
+                CueKind::FadeIn => FADE_IN,
+                CueKind::FadeOut => FADE_OUT,
+                CueKind::Load => LOAD,
+                CueKind::Loop => LOOP,
+            };
+            let hot_cue = cue.hot_cue.map_or(-1, i64::from);
+            format!(
+                r#"<CUE_V2 NAME="{}" DISPL_ORDER="0" TYPE="{kind}" START="{:.6}" LEN="{:.6}" REPEATS="-1" HOTCUE="{hot_cue}"></CUE_V2>"#,
+                escape(cue.name.as_deref().unwrap_or(UNNAMED_CUE)),
+                cue.position_ms,
+                cue.length_ms.unwrap_or_default(),
+            )
+        })
+        .collect();
+    elements.join("\n")
+}
+
+fn collect_playlists(node: Node, parent: &str, playlists: &mut HashMap<String, Vec<String>>) {
+    let path = if parent.is_empty() {
+        node.name
+    } else {
+        format!("{parent}/{}", node.name)
+    };
+    if let Some(playlist) = node.playlist {
+        for entry in playlist.entries {
+            let Some(key) = entry.primary_key.and_then(|key| key.key) else {
+                continue;
+            };
+            let names = playlists.entry(key).or_default();
+            if !names.contains(&path) {
+                names.push(path.clone());
+            }
+        }
+    }
+    for child in node
+        .subnodes
+        .map(|subnodes| subnodes.nodes)
+        .unwrap_or_default()
+    {
+        collect_playlists(child, &path, playlists);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLLECTION: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no" ?>
+<NML VERSION="19"><HEAD COMPANY="www.native-instruments.com" PROGRAM="Traktor"></HEAD>
+<COLLECTION ENTRIES="2">
+<ENTRY MODIFIED_DATE="2024/5/1" TITLE="Eple" ARTIST="Röyksopp">
+<LOCATION DIR="/:Music/:Royksopp/:" FILE="Eple.mp3" VOLUME="Macintosh HD" VOLUMEID="abc"></LOCATION>
+<ALBUM TRACK="2" TITLE="Melody A.M."></ALBUM>
+<INFO BITRATE="320000" PLAYTIME="241" PLAYTIME_FLOAT="240.500000"></INFO>
+<TEMPO BPM="124.500000" BPM_QUALITY="100.000000"></TEMPO>
+<MUSICAL_KEY VALUE="21"></MUSICAL_KEY>
+<CUE_V2 NAME="AutoGrid" DISPL_ORDER="0" TYPE="4" START="52.100000" LEN="0.000000" REPEATS="-1" HOTCUE="-1">
+<GRID BPM="124.500000"></GRID>
+</CUE_V2>
+<CUE_V2 NAME="n.n." DISPL_ORDER="0" TYPE="0" START="52.100000" LEN="0.000000" REPEATS="-1" HOTCUE="0"></CUE_V2>
+<CUE_V2 NAME="Break" DISPL_ORDER="0" TYPE="5" START="64000.000000" LEN="7710.000000" REPEATS="-1" HOTCUE="3"></CUE_V2>
+</ENTRY>
+<ENTRY TITLE="Untitled">
+<LOCATION DIR="/:Music/:" FILE="untitled.wav" VOLUME="C:"></LOCATION>
+<INFO KEY="8A"></INFO>
+</ENTRY>
+</COLLECTION>
+<PLAYLISTS><NODE TYPE="FOLDER" NAME="$ROOT"><SUBNODES COUNT="1">
+<NODE TYPE="FOLDER" NAME="Sets"><SUBNODES COUNT="1">
+<NODE TYPE="PLAYLIST" NAME="Warmup"><PLAYLIST ENTRIES="1" TYPE="LIST" UUID="1">
+<ENTRY><PRIMARYKEY TYPE="TRACK" KEY="Macintosh HD/:Music/:Royksopp/:Eple.mp3"></PRIMARYKEY></ENTRY>
+</PLAYLIST></NODE>
+</SUBNODES></NODE>
+</SUBNODES></NODE></PLAYLISTS>
+</NML>"#;
+
+    fn read(xml: &str) -> Vec<DjTrack> {
+        let nml: Nml = quick_xml::de::from_str(xml).unwrap();
+        read_collection(nml, &|location| {
+            Path::new("/missing").join(location.primary_key().replace(DIR_SEPARATOR, "/"))
+        })
+    }
+
+    #[test]
+    fn reads_entries_with_cues_beatgrid_and_playlists() {
+        let tracks = read(COLLECTION);
+        assert_eq!(tracks.len(), 2);
+
+        let eple = &tracks[0];
+        assert_eq!(eple.external_id, "Macintosh HD/:Music/:Royksopp/:Eple.mp3");
+        assert_eq!(eple.title.as_deref(), Some("Eple"));
+        assert_eq!(eple.artist.as_deref(), Some("Röyksopp"));
+        assert_eq!(eple.album.as_deref(), Some("Melody A.M."));
+        assert_eq!(eple.key.as_deref(), Some("Am"));
+        assert_eq!(eple.bpm, Some(124.5));
+        assert_eq!(eple.duration_ms, Some(240_500));
+        assert!(!eple.available);
+        assert_eq!(eple.playlists, ["Sets/Warmup"]);
+        assert_eq!(
+            eple.beatgrid,
+            [BeatMarker {
+                position_ms: 52.1,
+                bpm: 124.5
+            }]
+        );
+        assert_eq!(
+            eple.cues,
+            [
+                DjCue {
+                    kind: CueKind::Cue,
+                    hot_cue: Some(0),
+                    name: None,
+                    color: None,
+                    position_ms: 52.1,
+                    length_ms: None,
+                },
+                DjCue {
+                    kind: CueKind::Loop,
+                    hot_cue: Some(3),
+                    name: Some("Break".to_string()),
+                    color: None,
+                    position_ms: 64_000.0,
+                    length_ms: Some(7_710.0),
+                },
+            ]
+        );
+
+        let untitled = &tracks[1];
+        assert_eq!(untitled.key.as_deref(), Some("8A"));
+        assert!(untitled.cues.is_empty());
+        assert!(untitled.playlists.is_empty());
+    }
+
+    #[test]
+    fn written_cues_read_back() {
+        let tracks = read(COLLECTION);
+        let cues = &tracks[0].cues;
+        let xml = format!(
+            r#"<NML><COLLECTION><ENTRY><LOCATION DIR="/:" FILE="a.mp3" VOLUME="HD"></LOCATION>{}</ENTRY></COLLECTION></NML>"#,
+            cue_elements(cues)
+        );
+        assert_eq!(&read(&xml)[0].cues, cues);
+    }
+
+    #[test]
+    fn resolves_locations_per_volume() {
+        let windows = Location {
+            volume: "C:".to_string(),
+            dir: "/:Music/:".to_string(),
+            file: "a.wav".to_string(),
+        };
+        assert_eq!(windows.resolve(), PathBuf::from("C:/Music/a.wav"));
+
+        let unmounted = Location {
+            volume: "No Such Volume".to_string(),
+            dir: "/:home/:dj/:".to_string(),
+            file: "b.mp3".to_string(),
+        };
+        assert_eq!(unmounted.resolve(), PathBuf::from("/home/dj/b.mp3"));
+    }
+}
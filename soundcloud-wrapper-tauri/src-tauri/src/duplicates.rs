@@ -0,0 +1,387 @@
+//! Finds tracks that are likely the same recording: identical files, files
+//! with matching acoustic fingerprints, the same matched release with a
+//! matching title, or near-identical artist/title and duration.
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::Serialize;
+
+use crate::fingerprint;
+use crate::library::{DuplicateTrackRecord, LibraryError, LibraryStore};
+use crate::matching::{string_similarity, TrackFeatures};
+
+/// Title similarity two tracks on the same release need to be the same
+/// recording rather than two songs from one album.
+const RELEASE_TITLE_SIMILARITY: f32 = 0.85;
+/// Artist/title similarity needed when only the metadata agrees.
+const METADATA_SIMILARITY: f32 = 0.92;
+/// Without a duration on both sides the metadata has to agree almost exactly.
+const METADATA_SIMILARITY_WITHOUT_DURATION: f32 = 0.98;
+const DURATION_TOLERANCE_MS: i64 = 3_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DuplicateReason {
+    /// The local files have the same checksum.
+    Checksum,
+    /// The local files sound the same, even if they are encoded differently.
+    Fingerprint,
+    /// Matched to the same Discogs or MusicBrainz release with the same title.
+    Release,
+    /// Artist, title, version and duration agree.
+    Metadata,
+}
+
+impl DuplicateReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DuplicateReason::Checksum => "checksum",
+            DuplicateReason::Fingerprint => "fingerprint",
+            DuplicateReason::Release => "release",
+            DuplicateReason::Metadata => "metadata",
+        }
+    }
+}
+
+/// A cluster of tracks, with the one suggested to keep listed first.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    pub canonical_track_id: String,
+    pub reasons: Vec<DuplicateReason>,
+    pub tracks: Vec<DuplicateTrackRecord>,
+}
+
+pub fn find_duplicate_groups(store: &LibraryStore) -> Result<Vec<DuplicateGroup>, LibraryError> {
+    let tracks = store.list_duplicate_candidates()?;
+    Ok(group_duplicates(tracks))
+}
+
+fn group_duplicates(tracks: Vec<DuplicateTrackRecord>) -> Vec<DuplicateGroup> {
+    let mut clusters = Clusters::new(tracks.len());
+
+    let mut by_checksum: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut by_release: HashMap<(&str, &str), Vec<usize>> = HashMap::new();
+    let mut by_artist: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, track) in tracks.iter().enumerate() {
+        if let Some(checksum) = track.checksum.as_deref() {
+            by_checksum.entry(checksum).or_default().push(index);
+        }
+        if let Some(release_id) = track.discogs_release_id.as_deref() {
+            by_release
+                .entry(("discogs", release_id))
+                .or_default()
+                .push(index);
+        }
+        if let Some(release_id) = track.musicbrainz_release_id.as_deref() {
+            by_release
+                .entry(("musicbrainz", release_id))
+                .or_default()
+                .push(index);
+        }
+        if let Some(key) = artist_key(track) {
+            by_artist.entry(key).or_default().push(index);
+        }
+    }
+
+    for members in by_checksum.values() {
+        for pair in members.windows(2) {
+            clusters.union(pair[0], pair[1], DuplicateReason::Checksum);
+        }
+    }
+    // Fingerprints are only compared between files of about the same length.
+    let mut fingerprinted: Vec<(i64, usize)> = tracks
+        .iter()
+        .enumerate()
+        .filter(|(_, track)| track.fingerprint.is_some())
+        .filter_map(|(index, track)| {
+            track
+                .duration_ms
+                .filter(|value| *value > 0)
+                .map(|duration| (duration, index))
+        })
+        .collect();
+    fingerprinted.sort_unstable();
+    for (position, &(duration, left)) in fingerprinted.iter().enumerate() {
+        for &(other_duration, right) in &fingerprinted[position + 1..] {
+            if other_duration - duration > DURATION_TOLERANCE_MS {
+                break;
+            }
+            if let (Some(left_print), Some(right_print)) =
+                (&tracks[left].fingerprint, &tracks[right].fingerprint)
+            {
+                if fingerprint::same_recording(left_print, right_print) {
+                    clusters.union(left, right, DuplicateReason::Fingerprint);
+                }
+            }
+        }
+    }
+    for members in by_release.values() {
+        for_each_pair(members, |left, right| {
+            if same_recording_on_release(&tracks[left], &tracks[right]) {
+                clusters.union(left, right, DuplicateReason::Release);
+            }
+        });
+    }
+    for members in by_artist.values() {
+        for_each_pair(members, |left, right| {
+            if same_metadata(&tracks[left], &tracks[right]) {
+                clusters.union(left, right, DuplicateReason::Metadata);
+            }
+        });
+    }
+
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for index in 0..tracks.len() {
+        members.entry(clusters.find(index)).or_default().push(index);
+    }
+    let mut reasons: HashMap<usize, BTreeSet<DuplicateReason>> = HashMap::new();
+    for (index, reason) in std::mem::take(&mut clusters.edges) {
+        reasons
+            .entry(clusters.find(index))
+            .or_default()
+            .insert(reason);
+    }
+
+    let mut groups: Vec<DuplicateGroup> = members
+        .into_iter()
+        .filter(|(_, indexes)| indexes.len() > 1)
+        .map(|(root, indexes)| {
+            let mut group: Vec<DuplicateTrackRecord> = indexes
+                .into_iter()
+                .map(|index| tracks[index].clone())
+                .collect();
+            group.sort_by(|left, right| {
+                canonical_rank(right)
+                    .cmp(&canonical_rank(left))
+                    .then_with(|| left.track_id.cmp(&right.track_id))
+            });
+            DuplicateGroup {
+                canonical_track_id: group[0].track_id.clone(),
+                reasons: reasons
+                    .remove(&root)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect(),
+                tracks: group,
+            }
+        })
+        .collect();
+    groups.sort_by(|left, right| left.canonical_track_id.cmp(&right.canonical_track_id));
+    groups
+}
+
+/// Union-find over track indexes that remembers why tracks were joined.
+struct Clusters {
+    parent: Vec<usize>,
+    edges: Vec<(usize, DuplicateReason)>,
+}
+
+impl Clusters {
+    fn new(size: usize) -> Self {
+        Self {
+            parent: (0..size).collect(),
+            edges: Vec::new(),
+        }
+    }
+
+    fn find(&mut self, index: usize) -> usize {
+        let mut root = index;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut current = index;
+        while self.parent[current] != root {
+            let next = self.parent[current];
+            self.parent[current] = root;
+            current = next;
+        }
+        root
+    }
+
+    fn union(&mut self, left: usize, right: usize, reason: DuplicateReason) {
+        let left_root = self.find(left);
+        let right_root = self.find(right);
+        if left_root != right_root {
+            self.parent[right_root] = left_root;
+        }
+        self.edges.push((left, reason));
+    }
+}
+
+fn for_each_pair(members: &[usize], mut visit: impl FnMut(usize, usize)) {
+    for (position, &left) in members.iter().enumerate() {
+        for &right in &members[position + 1..] {
+            visit(left, right);
+        }
+    }
+}
+
+/// Tracks are only compared on metadata with others whose artist starts with
+/// the same word.
+fn artist_key(track: &DuplicateTrackRecord) -> Option<String> {
+    let artist = track.artist.as_deref()?.to_lowercase();
+    artist
+        .split(|c: char| !c.is_alphanumeric())
+        .find(|word| !word.is_empty())
+        .map(|word| word.to_string())
+}
+
+fn same_version(left: &DuplicateTrackRecord, right: &DuplicateTrackRecord) -> bool {
+    let version = |track: &DuplicateTrackRecord| {
+        track
+            .version
+            .as_deref()
+            .map(|value| value.trim().to_lowercase())
+            .filter(|value| !value.is_empty())
+    };
+    version(left) == version(right)
+}
+
+fn same_recording_on_release(left: &DuplicateTrackRecord, right: &DuplicateTrackRecord) -> bool {
+    match (&left.title, &right.title) {
+        (Some(left_title), Some(right_title)) => {
+            same_version(left, right)
+                && string_similarity(left_title, right_title) >= RELEASE_TITLE_SIMILARITY
+        }
+        _ => false,
+    }
+}
+
+fn same_metadata(left: &DuplicateTrackRecord, right: &DuplicateTrackRecord) -> bool {
+    if !same_version(left, right) {
+        return false;
+    }
+    let features = |track: &DuplicateTrackRecord| TrackFeatures {
+        artist: track.artist.clone(),
+        title: track.title.clone(),
+        duration_ms: None,
+    };
+    let similarity = match features(left).similarity(&features(right)) {
+        Some(similarity) => similarity,
+        None => return false,
+    };
+
+    match (
+        left.duration_ms.filter(|value| *value > 0),
+        right.duration_ms.filter(|value| *value > 0),
+    ) {
+        (Some(left_duration), Some(right_duration)) => {
+            similarity >= METADATA_SIMILARITY
+                && (left_duration - right_duration).abs() <= DURATION_TOLERANCE_MS
+        }
+        _ => similarity >= METADATA_SIMILARITY_WITHOUT_DURATION,
+    }
+}
+
+/// Prefers the track that carries the most: a SoundCloud source, an available
+/// local file, a Rekordbox entry, then successful matches.
+fn canonical_rank(track: &DuplicateTrackRecord) -> (bool, bool, bool, usize) {
+    let matches = usize::from(track.discogs_release_id.is_some())
+        + usize::from(track.musicbrainz_release_id.is_some());
+    (
+        track.soundcloud_id.is_some(),
+        track.asset_available,
+        track.in_rekordbox,
+        matches,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(track_id: &str, artist: &str, title: &str) -> DuplicateTrackRecord {
+        DuplicateTrackRecord {
+            track_id: track_id.to_string(),
+            title: Some(title.to_string()),
+            artist: Some(artist.to_string()),
+            version: None,
+            duration_ms: Some(240_000),
+            checksum: None,
+            location: None,
+            asset_available: false,
+            discogs_release_id: None,
+            musicbrainz_release_id: None,
+            soundcloud_id: None,
+            in_rekordbox: false,
+            fingerprint: None,
+        }
+    }
+
+    #[test]
+    fn clusters_join_transitively() {
+        let mut clusters = Clusters::new(4);
+        clusters.union(0, 1, DuplicateReason::Checksum);
+        clusters.union(2, 1, DuplicateReason::Metadata);
+        assert_eq!(clusters.find(0), clusters.find(2));
+        assert_ne!(clusters.find(0), clusters.find(3));
+        assert_eq!(clusters.edges.len(), 2);
+    }
+
+    #[test]
+    fn chained_matches_form_one_group_with_every_reason() {
+        let mut file = track("rb-1", "Bicep", "Glue");
+        file.checksum = Some("abc".to_string());
+        let mut copy = track("rb-2", "Bicep Live", "Untitled");
+        copy.checksum = Some("abc".to_string());
+        let upload = track("sc-1", "Bicep", "Glue");
+
+        let groups = group_duplicates(vec![file, copy, upload]);
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].tracks.len(), 3);
+        assert_eq!(
+            groups[0].reasons,
+            vec![DuplicateReason::Checksum, DuplicateReason::Metadata]
+        );
+    }
+
+    #[test]
+    fn release_needs_the_same_title() {
+        let mut glue = track("sc-1", "Bicep", "Glue");
+        glue.discogs_release_id = Some("100".to_string());
+        let mut glue_again = track("sc-2", "BICEP", "Glue");
+        glue_again.discogs_release_id = Some("100".to_string());
+        glue_again.duration_ms = Some(300_000);
+        let mut other = track("sc-3", "Bicep", "Atlas");
+        other.discogs_release_id = Some("100".to_string());
+
+        let groups = group_duplicates(vec![glue, glue_again, other]);
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].reasons, vec![DuplicateReason::Release]);
+        let ids: Vec<&str> = groups[0]
+            .tracks
+            .iter()
+            .map(|track| track.track_id.as_str())
+            .collect();
+        assert_eq!(ids, ["sc-1", "sc-2"]);
+    }
+
+    #[test]
+    fn metadata_needs_the_same_version_and_duration() {
+        let mut remix = track("sc-2", "Bicep", "Glue");
+        remix.version = Some("Remix".to_string());
+        let mut longer = track("sc-3", "Bicep", "Glue");
+        longer.duration_ms = Some(300_000);
+
+        let groups = group_duplicates(vec![track("sc-1", "Bicep", "Glue"), remix, longer]);
+
+        assert!(groups.is_empty());
+    }
+
+    #[test]
+    fn canonical_track_prefers_the_soundcloud_upload() {
+        let mut file = track("a-local", "Bicep", "Glue");
+        file.asset_available = true;
+        file.in_rekordbox = true;
+        let mut upload = track("z-upload", "Bicep", "Glue");
+        upload.soundcloud_id = Some("1".to_string());
+
+        let groups = group_duplicates(vec![file, upload]);
+
+        assert_eq!(groups[0].canonical_track_id, "z-upload");
+        assert_eq!(groups[0].tracks[0].track_id, "z-upload");
+    }
+}
@@ -0,0 +1,206 @@
+use std::env;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use url::{Host, Url};
+
+use crate::matching::{DISCOGS_PROVIDER, MUSICBRAINZ_PROVIDER};
+
+const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
+/// AcoustID has an endpoint but no match settings, as its results are scored
+/// by the service itself.
+pub const ACOUSTID_PROVIDER: &str = "acoustid";
+pub const ENDPOINT_PROVIDERS: [&str; 3] =
+    [DISCOGS_PROVIDER, MUSICBRAINZ_PROVIDER, ACOUSTID_PROVIDER];
+
+/// Where providers can find out about the app; sent as the contact in user
+/// agents unless one is configured.
+pub const PROJECT_URL: &str = "https://github.com/gordo-labs/desktop-soundcloud";
+
+/// Application name and version advertised in the user agent sent to
+/// metadata providers.
+#[derive(Debug, Clone)]
+pub struct ClientIdentity {
+    pub name: String,
+    pub version: String,
+}
+
+impl ClientIdentity {
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+        }
+    }
+
+    pub fn from_app(app: &AppHandle) -> Self {
+        let package_info = app.package_info();
+        Self::new(package_info.name.clone(), package_info.version.to_string())
+    }
+
+    /// `name/version (+PROJECT_URL)`, for providers without a configurable
+    /// contact.
+    pub fn user_agent(&self) -> String {
+        format!("{}/{} (+{PROJECT_URL})", self.name, self.version)
+    }
+}
+
+/// Overrides for where and how fast a metadata provider is queried.
+///
+/// Fields left unset fall back to the `<PROVIDER>_BASE_URL`,
+/// `<PROVIDER>_MIN_INTERVAL_MS` and `<PROVIDER>_TIMEOUT_MS` environment
+/// variables, then to the provider's public API and its own rate limiting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointSettings {
+    pub provider: String,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Minimum delay between two requests to the provider.
+    #[serde(default)]
+    pub min_interval_ms: Option<u64>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// The effective endpoint configuration a worker uses for one job.
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    pub base_url: Url,
+    /// `None` leaves pacing to the provider's default policy.
+    pub min_interval: Option<Duration>,
+    pub timeout: Duration,
+}
+
+impl EndpointSettings {
+    pub fn unset(provider: &str) -> Self {
+        Self {
+            provider: provider.to_string(),
+            base_url: None,
+            min_interval_ms: None,
+            timeout_ms: None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !ENDPOINT_PROVIDERS.contains(&self.provider.as_str()) {
+            return Err(format!("unknown endpoint provider '{}'", self.provider));
+        }
+        if let Some(base_url) = self.base_url.as_deref() {
+            parse_base_url(base_url)?;
+        }
+        if self.timeout_ms == Some(0) {
+            return Err("timeoutMs must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn resolve(&self) -> Endpoint {
+        let prefix = self.provider.to_uppercase();
+
+        let base_url = self
+            .base_url
+            .clone()
+            .or_else(|| env_value(&format!("{prefix}_BASE_URL")))
+            .and_then(|value| match parse_base_url(&value) {
+                Ok(url) => Some(url),
+                Err(error) => {
+                    log::warn!("ignoring {} base URL: {error}", self.provider);
+                    None
+                }
+            })
+            .unwrap_or_else(|| default_base_url(&self.provider));
+
+        let min_interval = self
+            .min_interval_ms
+            .or_else(|| env_number(&format!("{prefix}_MIN_INTERVAL_MS")))
+            .map(Duration::from_millis);
+
+        let timeout = self
+            .timeout_ms
+            .or_else(|| env_number(&format!("{prefix}_TIMEOUT_MS")))
+            .filter(|value| *value > 0)
+            .unwrap_or(DEFAULT_TIMEOUT_MS);
+
+        Endpoint {
+            base_url,
+            min_interval,
+            timeout: Duration::from_millis(timeout),
+        }
+    }
+}
+
+impl Endpoint {
+    /// Appends `path` to the base URL, keeping any path prefix of the base
+    /// (such as `/ws/2` for MusicBrainz).
+    pub fn url(&self, path: &str) -> String {
+        format!(
+            "{}/{}",
+            self.base_url.as_str().trim_end_matches('/'),
+            path.trim_start_matches('/')
+        )
+    }
+
+    pub fn host(&self) -> &str {
+        self.base_url.host_str().unwrap_or_default()
+    }
+
+    /// Whether the base URL points at this machine or the local network,
+    /// where a self-hosted server sets its own limits.
+    pub fn is_private_host(&self) -> bool {
+        match self.base_url.host() {
+            Some(Host::Domain(domain)) => {
+                domain == "localhost"
+                    || domain.ends_with(".localhost")
+                    || domain.ends_with(".local")
+            }
+            Some(Host::Ipv4(address)) => {
+                address.is_loopback() || address.is_private() || address.is_link_local()
+            }
+            // Loopback and unique local (fc00::/7) addresses.
+            Some(Host::Ipv6(address)) => {
+                address.is_loopback() || address.segments()[0] & 0xfe00 == 0xfc00
+            }
+            None => false,
+        }
+    }
+}
+
+fn default_base_url(provider: &str) -> Url {
+    let url = match provider {
+        DISCOGS_PROVIDER => "https://api.discogs.com",
+        MUSICBRAINZ_PROVIDER => "https://musicbrainz.org/ws/2",
+        ACOUSTID_PROVIDER => "https://api.acoustid.org/v2",
+        _ => "http://localhost",
+    };
+    Url::parse(url).expect("default base URL is valid")
+}
+
+fn parse_base_url(value: &str) -> Result<Url, String> {
+    let url = Url::parse(value.trim()).map_err(|error| format!("invalid base URL: {error}"))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err("base URL must be an http(s) URL with a host".to_string());
+    }
+    Ok(url)
+}
+
+/// Reads an environment variable, treating blank values as unset.
+pub(crate) fn env_value(name: &str) -> Option<String> {
+    env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn env_number(name: &str) -> Option<u64> {
+    let value = env_value(name)?;
+    match value.parse() {
+        Ok(number) => Some(number),
+        Err(_) => {
+            log::warn!("ignoring {name}: '{value}' is not a number");
+            None
+        }
+    }
+}
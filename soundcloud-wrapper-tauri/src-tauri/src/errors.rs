@@ -0,0 +1,398 @@
+//! Errors returned by the Tauri commands. Each error serializes to an object
+//! with a stable `code`, a human readable `message` and the context fields
+//! of its kind, so the frontend can react to the kind of failure instead of
+//! parsing strings.
+
+use std::fmt;
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::dj_library::DjLibraryError;
+use crate::library::LibraryError;
+use crate::rekordbox::RekordboxError;
+use crate::settings::SettingsError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorCode {
+    NotFound,
+    Validation,
+    Database,
+    Io,
+    Rekordbox,
+    DjLibrary,
+    LockPoisoned,
+    Network,
+    Internal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandError {
+    /// The `entity` with `id`, e.g. a track or its local file, does not exist.
+    NotFound { entity: String, id: String },
+    /// An argument was rejected; `field` names it when there is one.
+    Validation {
+        field: Option<String>,
+        message: String,
+    },
+    /// The library database failed.
+    Database { message: String },
+    /// A file or directory could not be read or written.
+    Io {
+        path: Option<String>,
+        message: String,
+    },
+    /// A Rekordbox library or export could not be read.
+    Rekordbox {
+        path: Option<String>,
+        message: String,
+    },
+    /// A Traktor or Serato library could not be read.
+    DjLibrary {
+        path: Option<String>,
+        message: String,
+    },
+    /// A lock was poisoned by a panic while it was held.
+    LockPoisoned { resource: String },
+    /// A request to `service` failed.
+    Network { service: String, message: String },
+    /// A background task or worker failed.
+    Internal { message: String },
+}
+
+impl CommandError {
+    pub fn not_found(entity: &str, id: &str) -> Self {
+        Self::NotFound {
+            entity: entity.to_string(),
+            id: id.to_string(),
+        }
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        Self::Validation {
+            field: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_field(field: &str, message: impl Into<String>) -> Self {
+        Self::Validation {
+            field: Some(field.to_string()),
+            message: message.into(),
+        }
+    }
+
+    pub fn io(path: impl Into<String>, error: impl fmt::Display) -> Self {
+        Self::Io {
+            path: Some(path.into()),
+            message: error.to_string(),
+        }
+    }
+
+    pub fn lock_poisoned(resource: &str) -> Self {
+        Self::LockPoisoned {
+            resource: resource.to_string(),
+        }
+    }
+
+    pub fn network(service: &str, error: impl fmt::Display) -> Self {
+        Self::Network {
+            service: service.to_string(),
+            message: error.to_string(),
+        }
+    }
+
+    pub fn internal(error: impl fmt::Display) -> Self {
+        Self::Internal {
+            message: error.to_string(),
+        }
+    }
+
+    /// Attaches `path` to a library or filesystem error that was raised
+    /// without one.
+    pub fn with_path(self, path: impl Into<String>) -> Self {
+        match self {
+            Self::Rekordbox {
+                path: None,
+                message,
+            } => Self::Rekordbox {
+                path: Some(path.into()),
+                message,
+            },
+            Self::DjLibrary {
+                path: None,
+                message,
+            } => Self::DjLibrary {
+                path: Some(path.into()),
+                message,
+            },
+            Self::Io {
+                path: None,
+                message,
+            } => Self::Io {
+                path: Some(path.into()),
+                message,
+            },
+            other => other,
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::NotFound { .. } => ErrorCode::NotFound,
+            Self::Validation { .. } => ErrorCode::Validation,
+            Self::Database { .. } => ErrorCode::Database,
+            Self::Io { .. } => ErrorCode::Io,
+            Self::Rekordbox { .. } => ErrorCode::Rekordbox,
+            Self::DjLibrary { .. } => ErrorCode::DjLibrary,
+            Self::LockPoisoned { .. } => ErrorCode::LockPoisoned,
+            Self::Network { .. } => ErrorCode::Network,
+            Self::Internal { .. } => ErrorCode::Internal,
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound { entity, id } => write!(f, "{entity} '{id}' not found"),
+            Self::Validation { message, .. }
+            | Self::Database { message }
+            | Self::Internal { message } => write!(f, "{message}"),
+            Self::Io {
+                path: Some(path),
+                message,
+            }
+            | Self::Rekordbox {
+                path: Some(path),
+                message,
+            }
+            | Self::DjLibrary {
+                path: Some(path),
+                message,
+            } => write!(f, "{path}: {message}"),
+            Self::Io {
+                path: None,
+                message,
+            }
+            | Self::Rekordbox {
+                path: None,
+                message,
+            }
+            | Self::DjLibrary {
+                path: None,
+                message,
+            } => {
+                write!(f, "{message}")
+            }
+            Self::LockPoisoned { resource } => write!(f, "{resource} lock poisoned"),
+            Self::Network { service, message } => write!(f, "{service} request failed: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl Serialize for CommandError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("CommandError", 4)?;
+        state.serialize_field("code", &self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        match self {
+            Self::NotFound { entity, id } => {
+                state.serialize_field("entity", entity)?;
+                state.serialize_field("id", id)?;
+            }
+            Self::Validation { field, .. } => state.serialize_field("field", field)?,
+            Self::Io { path, .. } | Self::Rekordbox { path, .. } | Self::DjLibrary { path, .. } => {
+                state.serialize_field("path", path)?
+            }
+            Self::LockPoisoned { resource } => state.serialize_field("resource", resource)?,
+            Self::Network { service, .. } => state.serialize_field("service", service)?,
+            Self::Database { .. } | Self::Internal { .. } => {}
+        }
+        state.end()
+    }
+}
+
+impl From<LibraryError> for CommandError {
+    fn from(value: LibraryError) -> Self {
+        match value {
+            LibraryError::AppDataDirUnavailable => Self::Io {
+                path: None,
+                message: value.to_string(),
+            },
+            LibraryError::LockPoisoned => Self::lock_poisoned("library store"),
+            LibraryError::Io(error) => Self::Io {
+                path: None,
+                message: error.to_string(),
+            },
+            LibraryError::Database(error) => Self::Database {
+                message: error.to_string(),
+            },
+            // Only stored payloads are deserialized, so a failure means the
+            // database holds data it cannot read back.
+            LibraryError::Serialization(error) => Self::Database {
+                message: format!("stored data is invalid: {error}"),
+            },
+        }
+    }
+}
+
+impl From<RekordboxError> for CommandError {
+    fn from(value: RekordboxError) -> Self {
+        Self::Rekordbox {
+            path: None,
+            message: value.to_string(),
+        }
+    }
+}
+
+impl From<DjLibraryError> for CommandError {
+    fn from(value: DjLibraryError) -> Self {
+        Self::DjLibrary {
+            path: None,
+            message: value.to_string(),
+        }
+    }
+}
+
+impl From<SettingsError> for CommandError {
+    fn from(value: SettingsError) -> Self {
+        match value {
+            SettingsError::Invalid(invalid) => Self::invalid_field(invalid.field, invalid.message),
+            SettingsError::Io { path, error } => Self::io(path.display().to_string(), error),
+            SettingsError::LockPoisoned => Self::lock_poisoned("settings"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serializes_code_message_and_context() {
+        let error = CommandError::not_found("track", "sc-1");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": "notFound",
+                "message": "track 'sc-1' not found",
+                "entity": "track",
+                "id": "sc-1",
+            })
+        );
+
+        let error = CommandError::invalid_field("url", "unsupported scheme 'ftp'");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": "validation",
+                "message": "unsupported scheme 'ftp'",
+                "field": "url",
+            })
+        );
+
+        let error = CommandError::lock_poisoned("library store");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": "lockPoisoned",
+                "message": "library store lock poisoned",
+                "resource": "library store",
+            })
+        );
+
+        let error = CommandError::network("discogs", "timed out");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": "network",
+                "message": "discogs request failed: timed out",
+                "service": "discogs",
+            })
+        );
+    }
+
+    #[test]
+    fn every_code_serializes_in_camel_case() {
+        let codes = [
+            (ErrorCode::NotFound, "notFound"),
+            (ErrorCode::Validation, "validation"),
+            (ErrorCode::Database, "database"),
+            (ErrorCode::Io, "io"),
+            (ErrorCode::Rekordbox, "rekordbox"),
+            (ErrorCode::DjLibrary, "djLibrary"),
+            (ErrorCode::LockPoisoned, "lockPoisoned"),
+            (ErrorCode::Network, "network"),
+            (ErrorCode::Internal, "internal"),
+        ];
+        for (code, expected) in codes {
+            assert_eq!(serde_json::to_value(code).unwrap(), json!(expected));
+        }
+    }
+
+    #[test]
+    fn library_errors_map_to_database_and_io() {
+        let error =
+            CommandError::from(LibraryError::Database(rusqlite::Error::QueryReturnedNoRows));
+        assert_eq!(error.code(), ErrorCode::Database);
+
+        let error = CommandError::from(LibraryError::Io(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "denied",
+        )));
+        assert_eq!(
+            error,
+            CommandError::Io {
+                path: None,
+                message: "denied".to_string(),
+            }
+        );
+
+        let error = CommandError::from(LibraryError::AppDataDirUnavailable);
+        assert_eq!(error.code(), ErrorCode::Io);
+
+        let error = CommandError::from(LibraryError::LockPoisoned);
+        assert_eq!(error, CommandError::lock_poisoned("library store"));
+
+        let invalid = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        let error = CommandError::from(LibraryError::Serialization(invalid));
+        assert_eq!(error.code(), ErrorCode::Database);
+    }
+
+    #[test]
+    fn rekordbox_errors_keep_the_path_they_are_given() {
+        let missing = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+        let error = CommandError::from(RekordboxError::Io(missing)).with_path("/music/master.db");
+        assert_eq!(error.code(), ErrorCode::Rekordbox);
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({
+                "code": "rekordbox",
+                "message": "/music/master.db: filesystem error: no such file",
+                "path": "/music/master.db",
+            })
+        );
+    }
+
+    #[test]
+    fn with_path_leaves_errors_that_have_one() {
+        let error = CommandError::io("/a.mp3", "denied").with_path("/b.mp3");
+        assert_eq!(error, CommandError::io("/a.mp3", "denied"));
+        let error = CommandError::not_found("track", "sc-1").with_path("/b.mp3");
+        assert_eq!(error, CommandError::not_found("track", "sc-1"));
+    }
+
+    #[test]
+    fn internal_errors_carry_only_a_message() {
+        let error = CommandError::internal("worker stopped");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            json!({ "code": "internal", "message": "worker stopped" })
+        );
+    }
+}
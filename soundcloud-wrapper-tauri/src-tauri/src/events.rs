@@ -0,0 +1,19 @@
+use serde_json::Value;
+use tauri::{AppHandle, Emitter};
+
+/// Progress of the background jobs. Payloads carry the job's `id`, `label`,
+/// `state`, `completed` and `total` counts and an optional `message`.
+pub const JOB_PROGRESS_EVENT: &str = "app://jobs/progress";
+
+/// Destination for notifications raised by the background services. The
+/// desktop app forwards them to the webview; tests and headless callers can
+/// record or drop them instead.
+pub trait EventSink: Send + Sync {
+    fn emit(&self, event: &str, payload: Value) -> Result<(), String>;
+}
+
+impl EventSink for AppHandle {
+    fn emit(&self, event: &str, payload: Value) -> Result<(), String> {
+        Emitter::emit(self, event, payload).map_err(|error| error.to_string())
+    }
+}
@@ -0,0 +1,329 @@
+//! Chromaprint fingerprints of local files. They are computed while a file is
+//! decoded for its duration, compared to find the same recording in different
+//! encodings, and encoded the way AcoustID expects them.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rusty_chromaprint::{Configuration, Fingerprinter};
+use serde::Serialize;
+use symphonia::core::audio::{AudioBufferRef, SampleBuffer};
+
+use crate::errors::CommandError;
+use crate::library::{lock, LibraryStore};
+use crate::rekordbox::compute_fingerprint;
+
+/// Like `fpcalc`, only the first two minutes are fingerprinted, which is what
+/// AcoustID indexes.
+const MAX_FINGERPRINT_SECONDS: u64 = 120;
+/// Chromaprint's id for the `test2` preset, the one AcoustID uses.
+const ALGORITHM_ID: u8 = 1;
+/// Bit deltas up to this value are stored in the 3-bit stream; larger ones
+/// spill into the 5-bit stream.
+const MAX_NORMAL_DELTA: u8 = 7;
+/// Share of differing bits up to which two fingerprints are the same
+/// recording. Unrelated audio sits around one half.
+const MAX_BIT_ERROR_RATE: f32 = 0.2;
+/// Encoder padding shifts the start of a file by a fraction of a second, so
+/// fingerprints are compared at a few offsets.
+const MAX_OFFSET: usize = 3;
+/// Roughly ten seconds of audio; shorter overlaps are not compared.
+const MIN_OVERLAP: usize = 80;
+
+/// Counts reported back after fingerprinting the library's files.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FingerprintSummary {
+    pub fingerprinted: u32,
+    pub failed: u32,
+}
+
+/// Fingerprints available local assets that do not have a fingerprint yet,
+/// such as files imported before fingerprints were computed. New imports
+/// and scans fingerprint files as they decode them.
+pub fn fingerprint_local_assets(
+    library: &Arc<Mutex<LibraryStore>>,
+) -> Result<FingerprintSummary, CommandError> {
+    let assets = lock(library)?.list_unfingerprinted_assets()?;
+
+    let mut summary = FingerprintSummary::default();
+    for asset in assets {
+        let fingerprint = match compute_fingerprint(Path::new(&asset.location)) {
+            Ok(Some(fingerprint)) => fingerprint,
+            Ok(None) => {
+                log::warn!("no audio to fingerprint in {}", asset.location);
+                summary.failed += 1;
+                continue;
+            }
+            Err(error) => {
+                log::warn!("failed to decode {}: {error}", asset.location);
+                summary.failed += 1;
+                continue;
+            }
+        };
+        let stored = lock(library)?.record_asset_fingerprint(
+            &asset.track_id,
+            &asset.location,
+            &fingerprint,
+        )?;
+        if stored {
+            summary.fingerprinted += 1;
+        }
+    }
+    Ok(summary)
+}
+
+/// Feeds decoded packets into a Chromaprint fingerprinter until the first
+/// two minutes have been consumed.
+pub(crate) struct FingerprintBuilder {
+    printer: Fingerprinter,
+    remaining_frames: u64,
+    buffer: Option<(usize, SampleBuffer<i16>)>,
+}
+
+impl FingerprintBuilder {
+    pub(crate) fn start(sample_rate: u32, channels: u32) -> Option<Self> {
+        let mut printer = Fingerprinter::new(&Configuration::preset_test2());
+        printer.start(sample_rate, channels).ok()?;
+        Some(Self {
+            printer,
+            remaining_frames: u64::from(sample_rate) * MAX_FINGERPRINT_SECONDS,
+            buffer: None,
+        })
+    }
+
+    pub(crate) fn consume(&mut self, decoded: AudioBufferRef<'_>) {
+        if self.remaining_frames == 0 {
+            return;
+        }
+        let spec = *decoded.spec();
+        let capacity = decoded.capacity();
+        let frames = (decoded.frames() as u64).min(self.remaining_frames);
+
+        if !matches!(&self.buffer, Some((size, _)) if *size >= capacity) {
+            self.buffer = Some((capacity, SampleBuffer::new(capacity as u64, spec)));
+        }
+        let Some((_, buffer)) = &mut self.buffer else {
+            return;
+        };
+        buffer.copy_interleaved_ref(decoded);
+        let samples = frames as usize * spec.channels.count();
+        self.printer.consume(&buffer.samples()[..samples]);
+        self.remaining_frames -= frames;
+    }
+
+    pub(crate) fn finish(mut self) -> Option<Vec<u32>> {
+        self.printer.finish();
+        let fingerprint = self.printer.fingerprint().to_vec();
+        (!fingerprint.is_empty()).then_some(fingerprint)
+    }
+}
+
+/// Stores a fingerprint as little-endian 32-bit words.
+pub(crate) fn to_blob(fingerprint: &[u32]) -> Vec<u8> {
+    fingerprint
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+pub(crate) fn from_blob(blob: &[u8]) -> Vec<u32> {
+    blob.chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+/// Whether two fingerprints come from the same recording.
+pub(crate) fn same_recording(left: &[u32], right: &[u32]) -> bool {
+    similarity(left, right).is_some_and(|similarity| similarity >= 1.0 - MAX_BIT_ERROR_RATE)
+}
+
+/// Share of matching bits at the best of a few small offsets, or `None` when
+/// the fingerprints are too short to compare.
+pub(crate) fn similarity(left: &[u32], right: &[u32]) -> Option<f32> {
+    let mut best: Option<f32> = None;
+    for offset in 0..=MAX_OFFSET {
+        for (shifted, fixed) in [(left, right), (right, left)] {
+            let Some(shifted) = shifted.get(offset..) else {
+                continue;
+            };
+            let overlap = shifted.len().min(fixed.len());
+            if overlap < MIN_OVERLAP {
+                continue;
+            }
+            let errors: u32 = shifted
+                .iter()
+                .zip(fixed)
+                .map(|(a, b)| (a ^ b).count_ones())
+                .sum();
+            let similarity = 1.0 - errors as f32 / (overlap * 32) as f32;
+            best = Some(best.map_or(similarity, |current| current.max(similarity)));
+        }
+    }
+    best
+}
+
+/// Compresses a fingerprint the way Chromaprint does before handing it to
+/// AcoustID: each item is XORed with the previous one, the positions of the
+/// set bits are stored as deltas in a 3-bit stream with a 5-bit overflow
+/// stream, and the result is base64 encoded without padding.
+pub(crate) fn encode(fingerprint: &[u32]) -> String {
+    let mut normal = Vec::new();
+    let mut exceptional = Vec::new();
+    let mut previous = 0u32;
+    for (index, &item) in fingerprint.iter().enumerate() {
+        let mut bits = if index == 0 { item } else { item ^ previous };
+        previous = item;
+
+        let mut bit = 1u8;
+        let mut last_bit = 0u8;
+        while bits != 0 {
+            if bits & 1 != 0 {
+                let delta = bit - last_bit;
+                if delta >= MAX_NORMAL_DELTA {
+                    normal.push(MAX_NORMAL_DELTA);
+                    exceptional.push(delta - MAX_NORMAL_DELTA);
+                } else {
+                    normal.push(delta);
+                }
+                last_bit = bit;
+            }
+            bits >>= 1;
+            bit += 1;
+        }
+        normal.push(0);
+    }
+
+    let length = fingerprint.len() as u32;
+    let mut output = vec![
+        ALGORITHM_ID,
+        (length >> 16) as u8,
+        (length >> 8) as u8,
+        length as u8,
+    ];
+    pack(&mut output, &normal, 3);
+    pack(&mut output, &exceptional, 5);
+    URL_SAFE_NO_PAD.encode(output)
+}
+
+/// Packs `width`-bit values into bytes, least significant bits first.
+fn pack(output: &mut Vec<u8>, values: &[u8], width: u32) {
+    let mut accumulator = 0u32;
+    let mut filled = 0u32;
+    for &value in values {
+        accumulator |= u32::from(value) << filled;
+        filled += width;
+        while filled >= 8 {
+            output.push(accumulator as u8);
+            accumulator >>= 8;
+            filled -= 8;
+        }
+    }
+    if filled > 0 {
+        output.push(accumulator as u8);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::LocalAssetRecord;
+    use crate::test_support::memory_library;
+
+    /// A reproducible fingerprint of `length` pseudo-random items.
+    fn fingerprint(seed: u32, length: usize) -> Vec<u32> {
+        let mut state = seed;
+        (0..length)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                state
+            })
+            .collect()
+    }
+
+    /// Flips the lowest `bits` bits of every item.
+    fn degrade(fingerprint: &[u32], bits: u32) -> Vec<u32> {
+        let mask = (1u32 << bits) - 1;
+        fingerprint.iter().map(|item| item ^ mask).collect()
+    }
+
+    #[test]
+    fn encoding_matches_chromaprint() {
+        // Vectors from Chromaprint's compressor tests, with the header byte
+        // set to the `test2` algorithm AcoustID expects.
+        let bytes = |fingerprint: &[u32]| URL_SAFE_NO_PAD.decode(encode(fingerprint)).unwrap();
+        assert_eq!(bytes(&[1]), [1, 0, 0, 1, 1]);
+        assert_eq!(bytes(&[7]), [1, 0, 0, 1, 73, 0]);
+        assert_eq!(bytes(&[1 << 6]), [1, 0, 0, 1, 7, 0]);
+        assert_eq!(bytes(&[1 << 8]), [1, 0, 0, 1, 7, 2]);
+        assert_eq!(bytes(&[1, 0]), [1, 0, 0, 2, 65, 0]);
+        assert_eq!(bytes(&[1, 1]), [1, 0, 0, 2, 1, 0]);
+        assert_eq!(encode(&[1, 0]), "AQAAAkEA");
+    }
+
+    #[test]
+    fn blob_round_trips_through_the_store() {
+        let fingerprint = fingerprint(1, 200);
+        assert_eq!(from_blob(&to_blob(&fingerprint)), fingerprint);
+
+        let library = memory_library();
+        let mut store = library.lock().unwrap();
+        store
+            .record_local_asset(&LocalAssetRecord {
+                track_id: "sc-1".to_string(),
+                location: "/music/glue.wav".to_string(),
+                checksum: None,
+                available: true,
+                duration_ms: Some(20_000),
+                file_size: None,
+                rekordbox_cues: None,
+            })
+            .unwrap();
+        assert!(store
+            .record_asset_fingerprint("sc-1", "/music/glue.wav", &fingerprint)
+            .unwrap());
+        let targets = store.list_acoustid_targets(&[]).unwrap();
+        assert_eq!(targets[0].fingerprint, fingerprint);
+    }
+
+    #[test]
+    fn fingerprints_match_themselves_at_a_small_offset() {
+        let fingerprint = fingerprint(1, 200);
+        assert_eq!(similarity(&fingerprint, &fingerprint), Some(1.0));
+        assert_eq!(
+            similarity(&fingerprint[MAX_OFFSET..], &fingerprint),
+            Some(1.0)
+        );
+        assert_eq!(similarity(&fingerprint, &fingerprint[2..]), Some(1.0));
+        assert!(same_recording(&fingerprint, &fingerprint[1..]));
+    }
+
+    #[test]
+    fn few_differing_bits_are_the_same_recording() {
+        let fingerprint = fingerprint(1, 200);
+        // 4 of 32 bits differ, below the error rate allowed.
+        let close = degrade(&fingerprint, 4);
+        assert_eq!(similarity(&fingerprint, &close), Some(0.875));
+        assert!(same_recording(&fingerprint, &close));
+        // 8 of 32 bits is above it.
+        assert!(!same_recording(&fingerprint, &degrade(&fingerprint, 8)));
+    }
+
+    #[test]
+    fn unrelated_fingerprints_differ() {
+        let left = fingerprint(1, 200);
+        let right = fingerprint(2, 200);
+        let similarity = similarity(&left, &right).unwrap();
+        assert!(similarity < 0.6, "{similarity}");
+        assert!(!same_recording(&left, &right));
+    }
+
+    #[test]
+    fn short_fingerprints_are_not_compared() {
+        let short = fingerprint(1, MIN_OVERLAP - 1);
+        assert_eq!(similarity(&short, &short), None);
+        assert!(!same_recording(&short, &short));
+    }
+}
@@ -0,0 +1,533 @@
+//! Integrity checks of local files. Each file is decoded in full; decode
+//! errors, truncation and empty files are recorded with the stream's codec
+//! and format. The spectrum is searched for the low-pass edge lossy
+//! encoders leave behind, which gives away files converted up from a
+//! low-bitrate source.
+
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use serde::Serialize;
+use symphonia::core::audio::{AudioBufferRef, SampleBuffer, SignalSpec};
+
+use crate::background::{JobKind, JobQueue};
+use crate::errors::CommandError;
+use crate::events::EventSink;
+use crate::library::{
+    lock, AssetHealthRecord, AssetHealthStatus, LibraryStore, LocalAssetLocation,
+};
+use crate::rekordbox::inspect_audio;
+
+const JOB_ID: &str = "asset-integrity";
+const JOB_LABEL: &str = "Comprobación de integridad de archivos";
+
+/// Files ending more than this share of their declared length early, plus
+/// a tenth of a second, count as truncated.
+const TRUNCATION_TOLERANCE: f64 = 0.01;
+
+const SPECTRUM_SIZE: usize = 4_096;
+/// The spectrum is averaged over bands this wide before the cutoff is
+/// searched, so single bins of noise do not count.
+const BAND_HZ: f64 = 250.0;
+/// The level of the cutoff search is taken relative to this band.
+const REFERENCE_LOW_HZ: f64 = 1_000.0;
+const REFERENCE_HIGH_HZ: f64 = 4_000.0;
+/// The cutoff is the top of the highest band within this many dB of the
+/// reference band. Real recordings keep some air well above it; the
+/// low-pass of a lossy encoder drops far below it.
+const CUTOFF_RANGE_DB: f64 = 75.0;
+/// Files quieter than this in the reference band, or shorter than the given
+/// number of spectrum windows, are not judged.
+const MIN_REFERENCE_DB: f64 = -100.0;
+const MIN_SPECTRUM_WINDOWS: usize = 20;
+
+/// Lowest cutoff expected from a file of each kind. 128 kbps MP3 and AAC
+/// are low-passed at about 16 kHz, higher bitrates at 19 kHz or above.
+const LOSSLESS_MIN_CUTOFF_HZ: f64 = 19_000.0;
+const HIGH_BITRATE_MIN_CUTOFF_HZ: f64 = 18_000.0;
+const MEDIUM_BITRATE_MIN_CUTOFF_HZ: f64 = 17_000.0;
+const HIGH_BITRATE_KBPS: u32 = 256;
+const MEDIUM_BITRATE_KBPS: u32 = 192;
+
+/// What a full decode of a file found.
+#[derive(Debug, Default)]
+struct Inspection {
+    codec: Option<String>,
+    lossless: bool,
+    sample_rate: Option<u32>,
+    bit_depth: Option<u32>,
+    channels: Option<u32>,
+    bitrate_kbps: Option<u32>,
+    duration_ms: Option<u64>,
+    file_size: Option<u64>,
+    decode_errors: u32,
+    truncated: bool,
+    zero_length: bool,
+    spectral_cutoff_hz: Option<f32>,
+    suspected_upsample: bool,
+    /// Human readable findings, worst first.
+    issues: Vec<String>,
+    /// Set when the file could not be read to the end.
+    fatal: bool,
+}
+
+/// Averages the power spectrum of the downmixed audio over the whole file.
+pub(crate) struct SpectrumBuilder {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    pending: Vec<f32>,
+    power: Vec<f64>,
+    windows: usize,
+    rate: u32,
+    buffer: Option<(usize, SampleBuffer<f32>)>,
+}
+
+impl SpectrumBuilder {
+    pub(crate) fn start(spec: &SignalSpec) -> Option<Self> {
+        if spec.rate == 0 || spec.channels.count() == 0 {
+            return None;
+        }
+        let window = (0..SPECTRUM_SIZE)
+            .map(|index| {
+                let phase = std::f32::consts::TAU * index as f32 / SPECTRUM_SIZE as f32;
+                0.5 - 0.5 * phase.cos()
+            })
+            .collect();
+        Some(Self {
+            fft: FftPlanner::new().plan_fft_forward(SPECTRUM_SIZE),
+            window,
+            pending: Vec::with_capacity(SPECTRUM_SIZE),
+            power: vec![0.0; SPECTRUM_SIZE / 2],
+            windows: 0,
+            rate: spec.rate,
+            buffer: None,
+        })
+    }
+
+    pub(crate) fn consume(&mut self, decoded: AudioBufferRef<'_>) {
+        let spec = *decoded.spec();
+        let channels = spec.channels.count();
+        let frames = decoded.frames();
+        if channels == 0 || frames == 0 {
+            return;
+        }
+        let capacity = decoded.capacity();
+        let (size, mut buffer) = match self.buffer.take() {
+            Some((size, buffer)) if size >= capacity => (size, buffer),
+            _ => (capacity, SampleBuffer::new(capacity as u64, spec)),
+        };
+        buffer.copy_interleaved_ref(decoded);
+        for frame in buffer.samples()[..frames * channels].chunks_exact(channels) {
+            self.pending
+                .push(frame.iter().sum::<f32>() / channels as f32);
+            if self.pending.len() == SPECTRUM_SIZE {
+                self.transform();
+            }
+        }
+        self.buffer = Some((size, buffer));
+    }
+
+    fn transform(&mut self) {
+        let mut bins: Vec<Complex<f32>> = self
+            .pending
+            .drain(..)
+            .zip(&self.window)
+            .map(|(sample, weight)| Complex::new(sample * weight, 0.0))
+            .collect();
+        self.fft.process(&mut bins);
+        for (power, bin) in self.power.iter_mut().zip(&bins) {
+            *power += f64::from(bin.norm_sqr());
+        }
+        self.windows += 1;
+    }
+
+    /// Top of the highest band that still carries audio, or `None` when the
+    /// file is too short or too quiet to tell.
+    pub(crate) fn finish(self) -> Option<f32> {
+        self.cutoff_hz().map(|cutoff| cutoff as f32)
+    }
+
+    fn cutoff_hz(&self) -> Option<f64> {
+        if self.windows < MIN_SPECTRUM_WINDOWS {
+            return None;
+        }
+        let bin_hz = f64::from(self.rate) / SPECTRUM_SIZE as f64;
+        let bins_per_band = ((BAND_HZ / bin_hz).round() as usize).max(1);
+        // Skip the DC bin, then level each band in dB.
+        let bands: Vec<(f64, f64)> = self.power[1..]
+            .chunks(bins_per_band)
+            .enumerate()
+            .map(|(index, band)| {
+                let top_hz = (1 + (index + 1) * bins_per_band) as f64 * bin_hz;
+                let mean = band.iter().sum::<f64>() / (band.len() * self.windows) as f64;
+                (top_hz, 10.0 * mean.max(1e-30).log10())
+            })
+            .collect();
+
+        let reference: Vec<f64> = bands
+            .iter()
+            .filter(|(top_hz, _)| *top_hz > REFERENCE_LOW_HZ && *top_hz <= REFERENCE_HIGH_HZ)
+            .map(|(_, level)| *level)
+            .collect();
+        if reference.is_empty() {
+            return None;
+        }
+        let reference = reference.iter().sum::<f64>() / reference.len() as f64;
+        if reference < MIN_REFERENCE_DB {
+            return None;
+        }
+        bands
+            .iter()
+            .rev()
+            .find(|(_, level)| *level >= reference - CUTOFF_RANGE_DB)
+            .map(|(top_hz, _)| top_hz.min(f64::from(self.rate) / 2.0))
+    }
+}
+
+/// Lowest believable cutoff for the file, or `None` when lower bitrates make
+/// an early cutoff normal.
+fn expected_cutoff_hz(inspection: &Inspection) -> Option<f64> {
+    let floor = if inspection.lossless {
+        LOSSLESS_MIN_CUTOFF_HZ
+    } else {
+        match inspection.bitrate_kbps? {
+            kbps if kbps >= HIGH_BITRATE_KBPS => HIGH_BITRATE_MIN_CUTOFF_HZ,
+            kbps if kbps >= MEDIUM_BITRATE_KBPS => MEDIUM_BITRATE_MIN_CUTOFF_HZ,
+            _ => return None,
+        }
+    };
+    // Low sample rates cannot reach the usual floor at all.
+    let nyquist = f64::from(inspection.sample_rate?) / 2.0;
+    Some(floor.min(nyquist * 0.9))
+}
+
+fn is_lossless(codec: &str) -> bool {
+    codec.starts_with("pcm_") || matches!(codec, "flac" | "alac" | "wavpack")
+}
+
+fn format_time(ms: u64) -> String {
+    let seconds = ms / 1000;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Decodes `path` to the end, skipping packets that fail to decode, and
+/// reports everything that looks wrong with it.
+fn inspect_file(path: &Path) -> Inspection {
+    let mut inspection = Inspection::default();
+    let size = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(error) => {
+            inspection.fatal = true;
+            inspection.issues.push(format!("cannot read file: {error}"));
+            return inspection;
+        }
+    };
+    inspection.file_size = Some(size);
+    if size == 0 {
+        inspection.zero_length = true;
+        inspection.issues.push("file is empty".to_string());
+        return inspection;
+    }
+
+    if let Err(error) = decode(path, &mut inspection) {
+        inspection.fatal = true;
+        inspection.issues.insert(0, error);
+    }
+
+    if let Some(duration_ms) = inspection.duration_ms.filter(|duration| *duration > 0) {
+        let kbps = size as f64 * 8.0 / duration_ms as f64;
+        inspection.bitrate_kbps = Some(kbps.round() as u32);
+    }
+    if let (Some(cutoff), Some(expected)) = (
+        inspection.spectral_cutoff_hz,
+        expected_cutoff_hz(&inspection),
+    ) {
+        if f64::from(cutoff) < expected {
+            inspection.suspected_upsample = true;
+            inspection.issues.push(format!(
+                "audio stops at {:.1} kHz, which suggests a low-bitrate source",
+                cutoff / 1000.0
+            ));
+        }
+    }
+    inspection
+}
+
+/// Copies what the decoder found into `inspection` and judges the stream.
+fn decode(path: &Path, inspection: &mut Inspection) -> Result<(), String> {
+    let stream = inspect_audio(path).map_err(|error| error.to_string())?;
+    inspection.codec = stream.codec;
+    inspection.lossless = inspection.codec.as_deref().is_some_and(is_lossless);
+    inspection.sample_rate = stream.sample_rate;
+    inspection.bit_depth = stream.bit_depth;
+    inspection.channels = stream.channels;
+    inspection.decode_errors = stream.decode_errors;
+
+    let frames = stream.frames;
+    let rate = inspection.sample_rate.filter(|rate| *rate > 0);
+    if let Some(rate) = rate {
+        inspection.duration_ms = Some(frames * 1000 / u64::from(rate));
+    }
+    if let Some(error) = stream.read_error {
+        inspection
+            .issues
+            .push(format!("reading stopped early: {error}"));
+    }
+    if inspection.decode_errors > 0 {
+        inspection.issues.push(format!(
+            "{} packets failed to decode, first: {}",
+            inspection.decode_errors,
+            stream.first_decode_error.unwrap_or_default()
+        ));
+    }
+    if frames == 0 {
+        inspection.zero_length = true;
+        inspection.issues.push("no audio frames".to_string());
+        return Ok(());
+    }
+    if let (Some(expected), Some(rate)) = (stream.declared_frames, rate) {
+        let tolerance = (expected as f64 * TRUNCATION_TOLERANCE) as u64 + u64::from(rate) / 10;
+        if frames + tolerance < expected {
+            inspection.truncated = true;
+            inspection.issues.push(format!(
+                "audio ends at {} of {}",
+                format_time(frames * 1000 / u64::from(rate)),
+                format_time(expected * 1000 / u64::from(rate))
+            ));
+        }
+    }
+    inspection.spectral_cutoff_hz = stream.spectral_cutoff_hz;
+    Ok(())
+}
+
+/// Checks one local asset and turns the findings into a stored record.
+fn check_asset(target: LocalAssetLocation) -> AssetHealthRecord {
+    let inspection = inspect_file(Path::new(&target.location));
+    let status = if inspection.fatal
+        || inspection.zero_length
+        || inspection.truncated
+        || inspection.decode_errors > 0
+    {
+        AssetHealthStatus::Damaged
+    } else if inspection.suspected_upsample {
+        AssetHealthStatus::Suspect
+    } else {
+        AssetHealthStatus::Healthy
+    };
+    AssetHealthRecord {
+        track_id: target.track_id,
+        location: target.location,
+        checksum: target.checksum,
+        status,
+        codec: inspection.codec,
+        sample_rate: inspection.sample_rate,
+        bit_depth: inspection.bit_depth,
+        channels: inspection.channels,
+        bitrate_kbps: inspection.bitrate_kbps,
+        duration_ms: inspection.duration_ms,
+        file_size: inspection.file_size,
+        decode_errors: inspection.decode_errors,
+        truncated: inspection.truncated,
+        zero_length: inspection.zero_length,
+        spectral_cutoff_hz: inspection.spectral_cutoff_hz,
+        suspected_upsample: inspection.suspected_upsample,
+        message: (!inspection.issues.is_empty()).then(|| inspection.issues.join("; ")),
+        checked_at: None,
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegritySummary {
+    pub checked: u32,
+    pub healthy: u32,
+    pub suspect: u32,
+    pub damaged: u32,
+}
+
+/// Checks `track_ids`, or every available local file that was not checked
+/// as it is now, and stores the results. `progress` is called with the
+/// number of finished and total tracks after each one.
+pub fn check_tracks(
+    library: &Arc<Mutex<LibraryStore>>,
+    track_ids: &[String],
+    mut progress: impl FnMut(u32, u32),
+) -> Result<IntegritySummary, CommandError> {
+    let targets = lock(library)?.list_integrity_targets(track_ids)?;
+    let total = targets.len() as u32;
+    progress(0, total);
+
+    let mut summary = IntegritySummary::default();
+    for (index, target) in targets.into_iter().enumerate() {
+        let record = check_asset(target);
+        summary.checked += 1;
+        match record.status {
+            AssetHealthStatus::Healthy => summary.healthy += 1,
+            AssetHealthStatus::Suspect => summary.suspect += 1,
+            AssetHealthStatus::Damaged => {
+                summary.damaged += 1;
+                log::warn!(
+                    "{} is damaged: {}",
+                    record.location,
+                    record.message.as_deref().unwrap_or_default()
+                );
+            }
+        }
+        lock(library)?.record_asset_health(&record)?;
+        progress(index as u32 + 1, total);
+    }
+    Ok(summary)
+}
+
+/// Runs integrity checks one at a time in the background and reports their
+/// progress as job events.
+#[derive(Clone)]
+pub struct IntegrityService {
+    jobs: JobQueue<IntegritySummary>,
+}
+
+impl IntegrityService {
+    pub fn new(events: Arc<dyn EventSink>, library: Arc<Mutex<LibraryStore>>) -> Self {
+        let kind = JobKind {
+            id: JOB_ID,
+            label: JOB_LABEL,
+            name: "integrity check",
+            run: |library, track_ids, progress| check_tracks(library, track_ids, progress),
+            describe: |summary| {
+                format!(
+                    "{} correctos, {} sospechosos, {} dañados",
+                    summary.healthy, summary.suspect, summary.damaged
+                )
+            },
+        };
+        Self {
+            jobs: JobQueue::start(kind, events, library),
+        }
+    }
+
+    /// Queues a check of `track_ids`, or of every pending file when empty,
+    /// without waiting for it.
+    pub fn queue_check(&self, track_ids: Vec<String>) {
+        self.jobs.queue(track_ids);
+    }
+
+    /// Runs a check through the worker queue and resolves once its results
+    /// have been stored.
+    pub async fn check(&self, track_ids: Vec<String>) -> Result<IntegritySummary, CommandError> {
+        self.jobs.run(track_ids).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::path::PathBuf;
+
+    const RATE: u32 = 44_100;
+
+    /// Writes three seconds of 32-bit float mono audio to a WAV file.
+    fn write_float_wav(name: &str, sample: impl Fn(f64) -> f32) -> PathBuf {
+        let path = env::temp_dir().join(format!("integrity-{name}-{}.wav", std::process::id()));
+        let samples: Vec<f32> = (0..RATE * 3)
+            .map(|index| sample(f64::from(index) / f64::from(RATE)))
+            .collect();
+        let data_len = (samples.len() * 4) as u32;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&3u16.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&RATE.to_le_bytes());
+        bytes.extend_from_slice(&(RATE * 4).to_le_bytes());
+        bytes.extend_from_slice(&4u16.to_le_bytes());
+        bytes.extend_from_slice(&32u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    /// Equal sines every 100 Hz up to `top_hz`, with scattered phases.
+    fn partials(top_hz: u32) -> impl Fn(f64) -> f32 {
+        let count = top_hz / 100;
+        move |time| {
+            let sum: f64 = (1..=count)
+                .map(|index| {
+                    let frequency = f64::from(index * 100);
+                    let phase = f64::from(index * index) * 0.7;
+                    (std::f64::consts::TAU * frequency * time + phase).sin()
+                })
+                .sum();
+            (sum / f64::from(count)) as f32
+        }
+    }
+
+    fn check(path: &Path) -> AssetHealthRecord {
+        check_asset(LocalAssetLocation {
+            track_id: "sc-1".to_string(),
+            location: path.to_string_lossy().into_owned(),
+            checksum: None,
+            duration_ms: None,
+            file_size: None,
+            available: true,
+        })
+    }
+
+    #[test]
+    fn full_band_lossless_audio_is_healthy() {
+        let path = write_float_wav("full-band", partials(21_500));
+        let record = check(&path);
+        assert_eq!(
+            record.status,
+            AssetHealthStatus::Healthy,
+            "{:?}",
+            record.message
+        );
+        assert_eq!(record.codec.as_deref(), Some("pcm_f32le"));
+        assert!(record.spectral_cutoff_hz.unwrap() >= 21_000.0);
+        assert!(!record.suspected_upsample);
+        assert_eq!(record.duration_ms, Some(3_000));
+    }
+
+    #[test]
+    fn band_limited_lossless_audio_is_suspect() {
+        // What a 128 kbps MP3 converted to a lossless format looks like.
+        let path = write_float_wav("band-limited", partials(16_000));
+        let record = check(&path);
+        assert_eq!(record.status, AssetHealthStatus::Suspect);
+        assert!(record.suspected_upsample);
+        let cutoff = record.spectral_cutoff_hz.unwrap();
+        assert!((15_500.0..=16_600.0).contains(&cutoff), "{cutoff}");
+        assert!(record.message.unwrap().contains("low-bitrate source"));
+    }
+
+    #[test]
+    fn silence_is_not_judged_by_its_spectrum() {
+        let path = write_float_wav("silence", |_| 0.0);
+        let record = check(&path);
+        assert_eq!(record.status, AssetHealthStatus::Healthy);
+        assert_eq!(record.spectral_cutoff_hz, None);
+    }
+
+    #[test]
+    fn empty_and_missing_files_are_damaged() {
+        let empty = env::temp_dir().join(format!("integrity-empty-{}.wav", std::process::id()));
+        fs::write(&empty, b"").unwrap();
+        let record = check(&empty);
+        assert_eq!(record.status, AssetHealthStatus::Damaged);
+        assert!(record.zero_length);
+
+        let record = check(Path::new("/missing/track.wav"));
+        assert_eq!(record.status, AssetHealthStatus::Damaged);
+        assert!(record.message.unwrap().starts_with("cannot read file"));
+    }
+}
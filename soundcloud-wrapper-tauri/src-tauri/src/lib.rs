@@ -1,27 +1,73 @@
-mod discogs;
-mod library;
+pub mod acoustid;
+pub mod analysis;
+mod background;
+pub mod diagnostics;
+pub mod discogs;
+pub mod dj_library;
+pub mod duplicates;
+pub mod endpoints;
+pub mod errors;
+pub mod events;
+pub mod fingerprint;
+pub mod integrity;
+pub mod library;
+pub mod logging;
+pub mod loudness;
+pub mod matching;
 mod media;
-mod musicbrainz;
-mod rekordbox;
-
+pub mod musicbrainz;
+pub mod normalize;
+pub mod rekordbox;
+pub mod relocate;
+pub mod scanner;
+pub mod settings;
+pub mod stores;
+pub mod tags;
+// Public so the tests of the `soundcloud-library` binary can use it too.
+#[doc(hidden)]
+pub mod test_support;
+pub mod waveform;
+
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
-
-use discogs::DiscogsService;
+use std::time::{Duration, SystemTime};
+
+use acoustid::{AcoustidClient, AcoustidSummary};
+use analysis::AnalysisService;
+use diagnostics::{DiagnosticsExport, JobMonitor};
+use discogs::{DiscogsListSummary, DiscogsListUpdate, DiscogsService};
+use dj_library::{CueExport, DjLibraryImporter, DjSoftware};
+use duplicates::DuplicateGroup;
+use endpoints::{ClientIdentity, EndpointSettings};
+use errors::CommandError;
+use events::EventSink;
+use fingerprint::FingerprintSummary;
+use integrity::IntegrityService;
 use library::{
-    DiscogsCandidateRecord, LibraryStatusPage, LibraryStore, LocalAssetRecord,
-    MusicbrainzCandidateRecord, SoundcloudLookupRecord, SoundcloudSourceRecord, StatusFilter,
-    TrackRecord,
+    AmbiguousLocalFile, AssetHealthRecord, AssetRelocation, DiscogsCandidateRecord, DjLibrarySync,
+    DjLibrarySyncRecord, LibraryStatusPage, LibraryStore, LocalAssetRecord,
+    MusicbrainzCandidateRecord, PurchaseQueueRow, RekordboxSync, RekordboxSyncRecord,
+    SoundcloudLookupRecord, SoundcloudSourceRecord, StatusFilter, StoreLinkRecord, TrackRecord,
+    WatchedFolder,
 };
+use matching::MatchSettings;
 use media::{MediaCache, MediaIntegration, MediaUpdate, MediaUpdatePayload, ThemeChangePayload};
 use musicbrainz::MusicbrainzService;
-use rekordbox::{load_tracks, supports_auto_refresh};
+use rekordbox::{load_tracks_as, RekordboxFormat};
+use relocate::RelocationReport;
+use scanner::ScanSummary;
 use serde::Deserialize;
 use serde_json::{self, Value};
+use settings::{
+    AppSettings, RekordboxSource, SettingsError, SettingsStore, ShortcutSettings,
+    SETTINGS_CHANGED_EVENT,
+};
+use stores::StoreLinkService;
+use tags::{TagWriteReport, TagWriter};
 use tauri::async_runtime::{self, JoinHandle};
 use tauri::menu::MenuBuilder;
 use tauri::tray::{MouseButton, TrayIcon, TrayIconBuilder, TrayIconEvent};
@@ -29,9 +75,10 @@ use tauri::{AppHandle, Manager, WindowEvent};
 use tauri::PhysicalSize;
 use tauri::{Emitter, Listener};
 use tokio::time::sleep;
-use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
 use tauri_plugin_notification::NotificationExt;
 use tauri_plugin_shell::ShellExt;
+use waveform::{Waveform, WaveformCache};
 
 const MAIN_WINDOW_LABEL: &str = "main";
 const MEDIA_TOGGLE_EVENT: &str = "media://toggle";
@@ -54,6 +101,16 @@ struct AppState {
     library: Arc<Mutex<LibraryStore>>,
     discogs: DiscogsService,
     musicbrainz: MusicbrainzService,
+    stores: StoreLinkService,
+    tags: TagWriter,
+    acoustid: AcoustidClient,
+    analysis: AnalysisService,
+    integrity: IntegrityService,
+    waveforms: Arc<WaveformCache>,
+    jobs: Arc<JobMonitor>,
+    log_dir: PathBuf,
+    diagnostics_dir: PathBuf,
+    settings: SettingsStore,
     rekordbox: Mutex<RekordboxState>,
 }
 
@@ -62,19 +119,21 @@ struct MediaManager {
     cache: MediaCache,
 }
 
+/// The watchers of the auto-refreshed Rekordbox sources, by source ID.
 #[derive(Default)]
 struct RekordboxState {
-    watcher: Option<RekordboxWatcher>,
+    watchers: HashMap<String, RekordboxWatcher>,
 }
 
 struct RekordboxWatcher {
-    path: PathBuf,
+    path: String,
     handle: JoinHandle<()>,
 }
 
+/// A SoundCloud track as handed to the metadata lookup workers.
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub(crate) struct SoundcloudTrackPayload {
+pub struct SoundcloudTrackPayload {
     track_id: String,
     soundcloud_id: String,
     #[serde(default)]
@@ -125,12 +184,39 @@ struct SoundcloudPlaylistPayload {
 }
 
 impl AppState {
-    fn new(app: &AppHandle) -> Result<Self, library::LibraryError> {
+    fn new(app: &AppHandle) -> Result<Self, CommandError> {
         let library = LibraryStore::initialize(app)?;
 
         let library = Arc::new(Mutex::new(library));
-        let discogs = DiscogsService::new(app, Arc::clone(&library));
-        let musicbrainz = MusicbrainzService::new(app, Arc::clone(&library));
+        let jobs = Arc::new(JobMonitor::new(Arc::new(app.clone())));
+        let events: Arc<dyn EventSink> = jobs.clone();
+        let identity = ClientIdentity::from_app(app);
+        let config_dir = app
+            .path()
+            .app_config_dir()
+            .map_err(|_| library::LibraryError::AppDataDirUnavailable)?;
+        let settings = SettingsStore::load(&config_dir);
+        let discogs = DiscogsService::new(Arc::clone(&events), Arc::clone(&library), &identity)?;
+        let musicbrainz = MusicbrainzService::new(
+            Arc::clone(&events),
+            Arc::clone(&library),
+            &identity,
+            &settings.get().musicbrainz,
+        )?;
+        let analysis = AnalysisService::new(Arc::clone(&events), Arc::clone(&library));
+        let integrity = IntegrityService::new(Arc::clone(&events), Arc::clone(&library));
+        let data_dir = app
+            .path()
+            .app_data_dir()
+            .map_err(|_| library::LibraryError::AppDataDirUnavailable)?;
+        let stores = StoreLinkService::new(
+            events,
+            Arc::clone(&library),
+            stores::default_providers(),
+            &identity,
+        )?;
+        let tags = TagWriter::new(Arc::clone(&library), &identity)?;
+        let acoustid = AcoustidClient::new(Arc::clone(&library), &identity)?;
 
         Ok(Self {
             media: Mutex::new(MediaManager {
@@ -140,82 +226,87 @@ impl AppState {
             library,
             discogs,
             musicbrainz,
+            stores,
+            tags,
+            acoustid,
+            analysis,
+            integrity,
+            waveforms: Arc::new(WaveformCache::new(data_dir.join(waveform::CACHE_DIR_NAME))),
+            jobs,
+            log_dir: data_dir.join(logging::LOG_DIR_NAME),
+            diagnostics_dir: data_dir.join(diagnostics::EXPORT_DIR_NAME),
+            settings,
             rekordbox: Mutex::new(RekordboxState::default()),
         })
     }
 }
 
 impl RekordboxState {
-    fn configure(&mut self, path: PathBuf, store: Arc<Mutex<LibraryStore>>) {
-        if let Some(existing) = self.watcher.as_ref() {
-            if existing.path == path {
-                return;
+    /// Starts a watcher for every auto-refreshed source and stops the ones
+    /// whose source was removed, moved or switched to manual imports.
+    fn apply(&mut self, settings: &settings::RekordboxSettings, store: &Arc<Mutex<LibraryStore>>) {
+        self.watchers.retain(|id, watcher| {
+            settings
+                .source(id)
+                .is_some_and(|source| source.auto_refresh && source.path == watcher.path)
+        });
+        for source in settings.sources.iter().filter(|source| source.auto_refresh) {
+            if !self.watchers.contains_key(&source.id) {
+                let watcher = RekordboxWatcher::spawn(source.clone(), Arc::clone(store));
+                self.watchers.insert(source.id.clone(), watcher);
             }
         }
-        self.watcher = Some(RekordboxWatcher::spawn(path, store));
-    }
-
-    fn disable(&mut self) {
-        self.watcher = None;
     }
 }
 
 impl RekordboxWatcher {
-    fn spawn(path: PathBuf, store: Arc<Mutex<LibraryStore>>) -> Self {
-        let watch_path = path.clone();
+    fn spawn(source: RekordboxSource, store: Arc<Mutex<LibraryStore>>) -> Self {
+        let path = source.path.clone();
         let handle = async_runtime::spawn(async move {
-            let mut last_modified = fs::metadata(&watch_path)
-                .and_then(|meta| meta.modified())
-                .ok();
+            // Starting from the last synced version picks up changes made
+            // while the app was closed.
+            let mut last_modified = match store.lock() {
+                Ok(guard) => guard
+                    .rekordbox_sync(&source.id)
+                    .ok()
+                    .flatten()
+                    .and_then(|sync| sync.modified_at),
+                Err(_) => None,
+            };
 
             loop {
                 sleep(Duration::from_secs(30)).await;
 
-                let metadata = match fs::metadata(&watch_path) {
-                    Ok(metadata) => metadata,
-                    Err(error) => {
-                        eprintln!("failed to read rekordbox database metadata: {error}");
-                        continue;
-                    }
+                let Some(modified) = rekordbox::modified_at(Path::new(&source.path)) else {
+                    log::warn!(
+                        "failed to read modification time of rekordbox source {}",
+                        source.id
+                    );
+                    continue;
                 };
-
-                let modified = match metadata.modified() {
-                    Ok(modified) => modified,
-                    Err(error) => {
-                        eprintln!("failed to read rekordbox database modification time: {error}");
-                        continue;
+                if last_modified == Some(modified) {
+                    continue;
+                }
+                last_modified = Some(modified);
+
+                let refresh_store = Arc::clone(&store);
+                let refresh_source = source.clone();
+                match async_runtime::spawn_blocking(move || {
+                    sync_rekordbox_source(&refresh_store, &refresh_source)
+                })
+                .await
+                {
+                    Ok(Ok(count)) => {
+                        log::info!(
+                            "refreshed {count} tracks from rekordbox source {}",
+                            source.id
+                        );
                     }
-                };
-
-                let changed = last_modified
-                    .map(|previous| modified > previous)
-                    .unwrap_or(true);
-
-                if changed {
-                    last_modified = Some(modified);
-                    let import_path = watch_path.clone();
-                    match async_runtime::spawn_blocking(move || load_tracks(&import_path)).await {
-                        Ok(Ok(tracks)) => {
-                            let mut guard = match store.lock() {
-                                Ok(guard) => guard,
-                                Err(_) => {
-                                    eprintln!(
-                                        "failed to acquire library store lock during rekordbox refresh"
-                                    );
-                                    continue;
-                                }
-                            };
-
-                            if let Err(error) = guard.sync_rekordbox_tracks(&tracks) {
-                                eprintln!("failed to persist rekordbox refresh: {error}");
-                            }
-                        }
-                        Ok(Err(error)) => {
-                            eprintln!("failed to refresh rekordbox library: {error}");
-                        }
-                        Err(error) => {
-                            eprintln!("failed to join rekordbox refresh task: {error}");
-                        }
+                    Ok(Err(error)) => {
+                        log::error!("failed to refresh rekordbox source {}: {error}", source.id);
+                    }
+                    Err(error) => {
+                        log::error!("failed to join rekordbox refresh task: {error}");
                     }
                 }
             }
@@ -225,6 +316,37 @@ impl RekordboxWatcher {
     }
 }
 
+/// Reads the library file of `source` and replaces the tracks of its
+/// namespace with it. Returns the number of tracks read.
+fn sync_rekordbox_source(
+    store: &Mutex<LibraryStore>,
+    source: &RekordboxSource,
+) -> Result<usize, CommandError> {
+    let path = Path::new(&source.path);
+    let modified_at = rekordbox::modified_at(path);
+    let tracks = load_tracks_as(path, source.format)
+        .map_err(|error| CommandError::from(error).with_path(&source.path))?;
+    let sync = RekordboxSync {
+        source_id: source.id.clone(),
+        path: source.path.clone(),
+        modified_at,
+    };
+    library::lock(store)?.sync_rekordbox_tracks(&sync, &tracks)?;
+    Ok(tracks.len())
+}
+
+fn sync_dj_library(
+    store: &Mutex<LibraryStore>,
+    importer: &dyn DjLibraryImporter,
+    sync: &DjLibrarySync,
+) -> Result<usize, CommandError> {
+    let tracks = importer
+        .load_tracks(Path::new(&sync.path))
+        .map_err(|error| CommandError::from(error).with_path(&sync.path))?;
+    library::lock(store)?.sync_dj_tracks(sync, &tracks)?;
+    Ok(tracks.len())
+}
+
 impl Drop for RekordboxWatcher {
     fn drop(&mut self) {
         self.handle.abort();
@@ -238,52 +360,135 @@ struct WindowState {
 
 struct TrayState(TrayIcon);
 
+fn library(state: &AppState) -> Result<std::sync::MutexGuard<'_, LibraryStore>, CommandError> {
+    Ok(library::lock(&state.library)?)
+}
+
 #[tauri::command]
-fn open_external(app: AppHandle, url: String) -> Result<(), String> {
-    let parsed = url::Url::parse(&url).map_err(|error| format!("invalid URL: {error}"))?;
+fn open_external(app: AppHandle, url: String) -> Result<(), CommandError> {
+    let parsed = url::Url::parse(&url)
+        .map_err(|error| CommandError::invalid_field("url", format!("invalid URL: {error}")))?;
     if !parsed.username().is_empty() || parsed.password().is_some() {
-        return Err("URLs with embedded credentials are not allowed".into());
+        return Err(CommandError::invalid_field(
+            "url",
+            "URLs with embedded credentials are not allowed",
+        ));
     }
     match parsed.scheme() {
         "http" => {
             if parsed.host_str() != Some("localhost") {
-                return Err("http scheme is only allowed for the local development server".into());
+                return Err(CommandError::invalid_field(
+                    "url",
+                    "http scheme is only allowed for the local development server",
+                ));
             }
             let target = parsed.into_string();
-            app.shell()
-                .open(target, None)
-                .map_err(|error| format!("failed to open URL externally: {error}"))
+            app.shell().open(target, None).map_err(|error| {
+                CommandError::internal(format!("failed to open URL externally: {error}"))
+            })
         }
         "https" => {
             let target = parsed.into_string();
-            app.shell()
-                .open(target, None)
-                .map_err(|error| format!("failed to open URL externally: {error}"))
+            app.shell().open(target, None).map_err(|error| {
+                CommandError::internal(format!("failed to open URL externally: {error}"))
+            })
         }
-        scheme => Err(format!("unsupported scheme '{scheme}'")),
+        scheme => Err(CommandError::invalid_field(
+            "url",
+            format!("unsupported scheme '{scheme}'"),
+        )),
     }
 }
 
 #[tauri::command]
-fn refresh_soundcloud_likes(app: AppHandle) -> Result<(), String> {
+fn refresh_soundcloud_likes(app: AppHandle) -> Result<(), CommandError> {
     app.emit_to(MAIN_WINDOW_LABEL, LIBRARY_REFRESH_LIKES_EVENT, ())
-        .map_err(|error| format!("failed to request SoundCloud likes refresh: {error}"))
+        .map_err(|error| {
+            CommandError::internal(format!(
+                "failed to request SoundCloud likes refresh: {error}"
+            ))
+        })
 }
 
 #[tauri::command]
-fn retry_discogs_lookup(state: tauri::State<AppState>, track_id: String) -> Result<(), String> {
+fn retry_discogs_lookup(
+    state: tauri::State<AppState>,
+    track_id: String,
+) -> Result<(), CommandError> {
     let payload = resolve_lookup_payload(&state, &track_id)?;
     state.discogs.queue_lookup(payload);
     Ok(())
 }
 
 #[tauri::command]
-fn retry_musicbrainz_lookup(state: tauri::State<AppState>, track_id: String) -> Result<(), String> {
+fn retry_musicbrainz_lookup(
+    state: tauri::State<AppState>,
+    track_id: String,
+) -> Result<(), CommandError> {
     let payload = resolve_lookup_payload(&state, &track_id)?;
     state.musicbrainz.queue_lookup(payload);
     Ok(())
 }
 
+#[tauri::command]
+fn resolve_store_links(
+    state: tauri::State<AppState>,
+    track_ids: Option<Vec<String>>,
+) -> Result<u32, CommandError> {
+    let track_ids = match track_ids {
+        Some(track_ids) => track_ids,
+        None => {
+            let store = library(&state)?;
+            store.list_missing_assets()?
+        }
+    };
+
+    let mut queued = 0;
+    for track_id in track_ids {
+        let payload = resolve_lookup_payload(&state, &track_id)?;
+        state.stores.queue_lookup(payload);
+        queued += 1;
+    }
+    Ok(queued)
+}
+
+#[tauri::command]
+async fn import_discogs_lists(
+    state: tauri::State<'_, AppState>,
+) -> Result<DiscogsListSummary, CommandError> {
+    state.discogs.import_lists().await
+}
+
+#[tauri::command]
+async fn add_to_discogs_wantlist(
+    state: tauri::State<'_, AppState>,
+    track_ids: Option<Vec<String>>,
+) -> Result<DiscogsListUpdate, CommandError> {
+    let targets = {
+        let store = library(&state)?;
+        match track_ids {
+            Some(track_ids) => store.list_discogs_release_targets(&track_ids),
+            None => store.list_pending_wantlist_targets(),
+        }?
+    };
+
+    state.discogs.add_to_wantlist(targets).await
+}
+
+#[tauri::command]
+async fn add_to_discogs_collection(
+    state: tauri::State<'_, AppState>,
+    track_ids: Vec<String>,
+    folder_id: Option<u64>,
+) -> Result<DiscogsListUpdate, CommandError> {
+    let targets = {
+        let store = library(&state)?;
+        store.list_discogs_release_targets(&track_ids)?
+    };
+
+    state.discogs.add_to_collection(targets, folder_id).await
+}
+
 #[tauri::command]
 fn confirm_musicbrainz_match(
     state: tauri::State<AppState>,
@@ -291,7 +496,7 @@ fn confirm_musicbrainz_match(
     release: Value,
     confidence: Option<f32>,
     query: Option<String>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let query_value = query.unwrap_or_default();
     let resolved_confidence = confidence
         .or_else(|| {
@@ -302,91 +507,117 @@ fn confirm_musicbrainz_match(
         })
         .unwrap_or(100.0);
 
-    let mut store = state
-        .library
-        .lock()
-        .map_err(|_| "library store lock poisoned".to_string())?;
+    let mut store = library(&state)?;
     store
         .record_musicbrainz_success(&track_id, &query_value, &release, resolved_confidence)
-        .map_err(|error| error.to_string())
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
-fn upsert_track(state: tauri::State<AppState>, record: TrackRecord) -> Result<(), String> {
-    let mut store = state
-        .library
-        .lock()
-        .map_err(|_| "library store lock poisoned".to_string())?;
-    store
-        .upsert_track(&record)
-        .map_err(|error| error.to_string())
+fn get_match_settings(state: tauri::State<AppState>) -> Result<Vec<MatchSettings>, CommandError> {
+    let store = library(&state)?;
+    store.list_match_settings().map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn update_match_settings(
+    state: tauri::State<AppState>,
+    settings: MatchSettings,
+) -> Result<MatchSettings, CommandError> {
+    settings.validate().map_err(CommandError::validation)?;
+    let store = library(&state)?;
+    store.save_match_settings(&settings)?;
+    Ok(settings)
+}
+
+#[tauri::command]
+fn get_endpoint_settings(
+    state: tauri::State<AppState>,
+) -> Result<Vec<EndpointSettings>, CommandError> {
+    let store = library(&state)?;
+    store.list_endpoint_settings().map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn update_endpoint_settings(
+    state: tauri::State<AppState>,
+    settings: EndpointSettings,
+) -> Result<EndpointSettings, CommandError> {
+    settings.validate().map_err(CommandError::validation)?;
+    let store = library(&state)?;
+    store.save_endpoint_settings(&settings)?;
+    Ok(settings)
+}
+
+#[tauri::command]
+fn upsert_track(state: tauri::State<AppState>, record: TrackRecord) -> Result<(), CommandError> {
+    let mut store = library(&state)?;
+    store.upsert_track(&record).map_err(CommandError::from)
 }
 
 fn resolve_lookup_payload(
     state: &AppState,
     track_id: &str,
-) -> Result<SoundcloudTrackPayload, String> {
+) -> Result<SoundcloudTrackPayload, CommandError> {
     let snapshot = {
-        let store = state
-            .library
-            .lock()
-            .map_err(|_| "library store lock poisoned".to_string())?;
-        store
-            .load_soundcloud_lookup(track_id)
-            .map_err(|error| error.to_string())?
+        let store = library(state)?;
+        store.load_soundcloud_lookup(track_id)?
     };
 
     match snapshot {
-        Some(record) => Ok(build_lookup_payload(record)),
-        None => Err(format!("track '{track_id}' not found in library")),
-    }
-}
-
-fn build_lookup_payload(record: SoundcloudLookupRecord) -> SoundcloudTrackPayload {
-    let SoundcloudLookupRecord {
-        track_id,
-        title,
-        artist,
-        soundcloud_id,
-        permalink_url,
-        raw_payload,
-    } = record;
-
-    let raw_value = raw_payload.unwrap_or(Value::Null);
-    let tags = extract_lookup_tags(&raw_value);
-    let artwork_url = extract_first_string(&raw_value, &["artwork_url", "artworkUrl"]);
-    let duration_ms = raw_value
-        .get("duration")
-        .and_then(|value| value.as_i64())
-        .or_else(|| {
-            raw_value
-                .get("full_duration")
-                .and_then(|value| value.as_i64())
-        });
-    let liked_at = extract_first_string(&raw_value, &["liked_at", "likedAt"]);
-    let playlist_id = extract_first_string(&raw_value, &["playlist_id", "playlistId"]);
-    let playlist_position = raw_value
-        .get("playlist_position")
-        .or_else(|| raw_value.get("playlistPosition"))
-        .and_then(|value| value.as_i64());
-    let source = extract_first_string(&raw_value, &["source"]);
-
-    let resolved_soundcloud_id = soundcloud_id.unwrap_or_else(|| track_id.clone());
-
-    SoundcloudTrackPayload {
-        track_id,
-        soundcloud_id: resolved_soundcloud_id,
-        title,
-        artist,
-        tags,
-        permalink_url,
-        artwork_url,
-        duration_ms,
-        liked_at,
-        playlist_id,
-        playlist_position,
-        source,
-        raw: raw_value,
+        Some(record) => Ok(SoundcloudTrackPayload::from_lookup(record)),
+        None => Err(CommandError::not_found("track", track_id)),
+    }
+}
+
+impl SoundcloudTrackPayload {
+    /// Rebuilds a lookup payload from what the library stored for a track.
+    pub fn from_lookup(record: SoundcloudLookupRecord) -> Self {
+        let SoundcloudLookupRecord {
+            track_id,
+            title,
+            artist,
+            soundcloud_id,
+            permalink_url,
+            raw_payload,
+        } = record;
+
+        let raw_value = raw_payload.unwrap_or(Value::Null);
+        let tags = extract_lookup_tags(&raw_value);
+        let artwork_url = extract_first_string(&raw_value, &["artwork_url", "artworkUrl"]);
+        let duration_ms = raw_value
+            .get("duration")
+            .and_then(|value| value.as_i64())
+            .or_else(|| {
+                raw_value
+                    .get("full_duration")
+                    .and_then(|value| value.as_i64())
+            });
+        let liked_at = extract_first_string(&raw_value, &["liked_at", "likedAt"]);
+        let playlist_id = extract_first_string(&raw_value, &["playlist_id", "playlistId"]);
+        let playlist_position = raw_value
+            .get("playlist_position")
+            .or_else(|| raw_value.get("playlistPosition"))
+            .and_then(|value| value.as_i64());
+        let source = extract_first_string(&raw_value, &["source"]);
+
+        let resolved_soundcloud_id = soundcloud_id.unwrap_or_else(|| track_id.clone());
+
+        Self {
+            track_id,
+            soundcloud_id: resolved_soundcloud_id,
+            title,
+            artist,
+            tags,
+            permalink_url,
+            artwork_url,
+            duration_ms,
+            liked_at,
+            playlist_id,
+            playlist_position,
+            source,
+            raw: raw_value,
+        }
     }
 }
 
@@ -427,161 +658,677 @@ fn extract_first_string(raw: &Value, keys: &[&str]) -> Option<String> {
 fn link_soundcloud_source(
     state: tauri::State<AppState>,
     record: SoundcloudSourceRecord,
-) -> Result<(), String> {
-    let store = state
-        .library
-        .lock()
-        .map_err(|_| "library store lock poisoned".to_string())?;
+) -> Result<(), CommandError> {
+    let store = library(&state)?;
     store
         .link_soundcloud_source(&record)
-        .map_err(|error| error.to_string())
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
 fn record_local_asset(
     state: tauri::State<AppState>,
     record: LocalAssetRecord,
-) -> Result<(), String> {
-    let store = state
-        .library
-        .lock()
-        .map_err(|_| "library store lock poisoned".to_string())?;
+) -> Result<(), CommandError> {
+    let store = library(&state)?;
     store
         .record_local_asset(&record)
-        .map_err(|error| error.to_string())
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
-fn list_missing_assets(state: tauri::State<AppState>) -> Result<Vec<String>, String> {
-    let store = state
-        .library
-        .lock()
-        .map_err(|_| "library store lock poisoned".to_string())?;
-    store
-        .list_missing_assets()
-        .map_err(|error| error.to_string())
+fn list_missing_assets(state: tauri::State<AppState>) -> Result<Vec<String>, CommandError> {
+    let store = library(&state)?;
+    store.list_missing_assets().map_err(CommandError::from)
 }
 
 #[tauri::command]
 fn list_library_status(
     state: tauri::State<AppState>,
     filter: Option<StatusFilter>,
-) -> Result<LibraryStatusPage, String> {
+) -> Result<LibraryStatusPage, CommandError> {
     let filter = filter.unwrap_or_default();
-    let store = state
-        .library
-        .lock()
-        .map_err(|_| "library store lock poisoned".to_string())?;
+    let store = library(&state)?;
     store
         .list_library_status(&filter)
-        .map_err(|error| error.to_string())
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
 fn list_discogs_candidates(
     state: tauri::State<AppState>,
     track_id: String,
-) -> Result<Vec<DiscogsCandidateRecord>, String> {
-    let store = state
-        .library
-        .lock()
-        .map_err(|_| "library store lock poisoned".to_string())?;
+) -> Result<Vec<DiscogsCandidateRecord>, CommandError> {
+    let store = library(&state)?;
     store
         .list_discogs_candidates(&track_id)
-        .map_err(|error| error.to_string())
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
 fn list_musicbrainz_candidates(
     state: tauri::State<AppState>,
     track_id: String,
-) -> Result<Vec<MusicbrainzCandidateRecord>, String> {
-    let store = state
-        .library
-        .lock()
-        .map_err(|_| "library store lock poisoned".to_string())?;
+) -> Result<Vec<MusicbrainzCandidateRecord>, CommandError> {
+    let store = library(&state)?;
     store
         .list_musicbrainz_candidates(&track_id)
-        .map_err(|error| error.to_string())
+        .map_err(CommandError::from)
 }
 
+#[tauri::command]
+fn list_store_links(
+    state: tauri::State<AppState>,
+    track_id: String,
+) -> Result<Vec<StoreLinkRecord>, CommandError> {
+    let store = library(&state)?;
+    store
+        .list_store_links(&track_id)
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn list_purchase_queue(
+    state: tauri::State<AppState>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<PurchaseQueueRow>, CommandError> {
+    let store = library(&state)?;
+    store
+        .list_purchase_queue(limit, offset)
+        .map_err(CommandError::from)
+}
+
+/// Imports a Rekordbox library into the source `source_id`, or into the
+/// source that already reads `db_path`. Without either, a new source is
+/// added. The source is saved in the settings, so it is watched again after
+/// a restart, and returned.
 #[tauri::command]
 async fn import_rekordbox_library(
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
     db_path: String,
-) -> Result<(), String> {
-    let source_path = PathBuf::from(db_path);
-    let import_path = source_path.clone();
-    let tracks = async_runtime::spawn_blocking(move || load_tracks(&import_path))
+    source_id: Option<String>,
+    label: Option<String>,
+    auto_refresh: Option<bool>,
+) -> Result<RekordboxSource, CommandError> {
+    let db_path = db_path.trim().to_string();
+    let format = RekordboxFormat::detect(Path::new(&db_path));
+    let current = state.settings.get();
+    let existing = match source_id.as_deref() {
+        Some(id) => current.rekordbox.source(id),
+        None => current.rekordbox.source_for_path(&db_path),
+    };
+    let source = match existing {
+        Some(existing) => RekordboxSource {
+            id: existing.id.clone(),
+            label: label.or_else(|| existing.label.clone()),
+            path: db_path.clone(),
+            format,
+            auto_refresh: auto_refresh
+                .unwrap_or(existing.auto_refresh && format.supports_auto_refresh()),
+        },
+        None => RekordboxSource {
+            id: source_id
+                .unwrap_or_else(|| current.rekordbox.new_source_id(label.as_deref(), &db_path)),
+            label,
+            path: db_path.clone(),
+            format,
+            auto_refresh: auto_refresh.unwrap_or(format.supports_auto_refresh()),
+        },
+    };
+
+    // Rejects an invalid source before spending time on the import.
+    let mut candidate = current.clone();
+    put_rekordbox_source(&mut candidate.rekordbox, source);
+    let candidate = candidate.normalized();
+    candidate.validate().map_err(SettingsError::Invalid)?;
+    let source = candidate
+        .rekordbox
+        .source_for_path(&db_path)
+        .cloned()
+        .ok_or_else(|| CommandError::internal("imported rekordbox source was not kept"))?;
+
+    let library = Arc::clone(&state.library);
+    let import_source = source.clone();
+    async_runtime::spawn_blocking(move || sync_rekordbox_source(&library, &import_source))
         .await
-        .map_err(|error| format!("failed to join rekordbox import task: {error}"))?
-        .map_err(|error| error.to_string())?;
+        .map_err(|error| {
+            CommandError::internal(format!("failed to join rekordbox import task: {error}"))
+        })??;
+
+    if current.rekordbox.source(&source.id) != Some(&source) {
+        let settings = state
+            .settings
+            .modify(|settings| put_rekordbox_source(&mut settings.rekordbox, source.clone()))?;
+        app.emit(SETTINGS_CHANGED_EVENT, &settings)
+            .map_err(CommandError::internal)?;
+    }
+
+    Ok(source)
+}
 
+/// Replaces the source with the ID of `source`, or adds it.
+fn put_rekordbox_source(settings: &mut settings::RekordboxSettings, source: RekordboxSource) {
+    match settings
+        .sources
+        .iter_mut()
+        .find(|existing| existing.id == source.id)
     {
-        let mut library = state
-            .library
-            .lock()
-            .map_err(|_| "library store lock poisoned".to_string())?;
-        library
-            .sync_rekordbox_tracks(&tracks)
-            .map_err(|error| error.to_string())?;
+        Some(existing) => *existing = source,
+        None => settings.sources.push(source),
     }
+}
 
-    let mut rekordbox_state = state
-        .rekordbox
-        .lock()
-        .map_err(|_| "rekordbox state lock poisoned".to_string())?;
+/// When each Rekordbox source was last imported, and how many tracks it had.
+#[tauri::command]
+fn list_rekordbox_syncs(
+    state: tauri::State<AppState>,
+) -> Result<Vec<RekordboxSyncRecord>, CommandError> {
+    let store = library(&state)?;
+    store.list_rekordbox_syncs().map_err(CommandError::from)
+}
 
-    if supports_auto_refresh(&source_path) {
-        rekordbox_state.configure(source_path, state.library.clone());
-    } else {
-        rekordbox_state.disable();
+/// Forgets the Rekordbox source `source_id` and removes the tracks only it
+/// provided.
+#[tauri::command]
+fn remove_rekordbox_source(
+    app: AppHandle,
+    state: tauri::State<AppState>,
+    source_id: String,
+) -> Result<(), CommandError> {
+    let configured = state.settings.get().rekordbox.source(&source_id).is_some();
+    {
+        let mut store = library(&state)?;
+        if !configured && store.rekordbox_sync(&source_id)?.is_none() {
+            return Err(CommandError::not_found("rekordbox source", &source_id));
+        }
+        store.remove_rekordbox_source(&source_id)?;
     }
 
+    if configured {
+        let settings = state.settings.modify(|settings| {
+            settings
+                .rekordbox
+                .sources
+                .retain(|source| source.id != source_id)
+        })?;
+        app.emit(SETTINGS_CHANGED_EVENT, &settings)
+            .map_err(CommandError::internal)?;
+    }
     Ok(())
 }
 
-fn register_media_shortcuts(app: &AppHandle) -> Result<(), tauri_plugin_global_shortcut::Error> {
-    let shortcut_manager = app.global_shortcut();
+/// Imports a Traktor or Serato library into the source `source_id` (the
+/// default source if not given) and returns how many tracks it has.
+#[tauri::command]
+async fn import_dj_library(
+    state: tauri::State<'_, AppState>,
+    software: DjSoftware,
+    path: String,
+    source_id: Option<String>,
+) -> Result<usize, CommandError> {
+    let importer = dj_library::importer(software).ok_or_else(|| {
+        CommandError::invalid_field(
+            "software",
+            format!("{software} libraries are imported as rekordbox sources"),
+        )
+    })?;
+    let source_id = source_id.unwrap_or_else(|| rekordbox::DEFAULT_SOURCE_ID.to_string());
+    settings::validate_source_id(&source_id)
+        .map_err(|message| CommandError::invalid_field("sourceId", message))?;
+    let path = path.trim().to_string();
+
+    let sync = DjLibrarySync {
+        software,
+        source_id,
+        modified_at: rekordbox::modified_at(Path::new(&path)),
+        path,
+    };
+    let library = Arc::clone(&state.library);
+    async_runtime::spawn_blocking(move || sync_dj_library(&library, importer, &sync))
+        .await
+        .map_err(|error| {
+            CommandError::internal(format!("failed to join {software} import task: {error}"))
+        })?
+}
 
-    shortcut_manager.on_shortcuts(
-        ["CmdOrCtrl+Alt+P", "MediaPlayPause"],
-        |app, _shortcut, event| {
-            if matches!(event.state, ShortcutState::Pressed) {
-                emit_media_event(app, MEDIA_TOGGLE_EVENT);
-            }
-        },
-    )?;
+/// When each Traktor and Serato source was last imported, and how many
+/// tracks it had.
+#[tauri::command]
+fn list_dj_library_syncs(
+    state: tauri::State<AppState>,
+) -> Result<Vec<DjLibrarySyncRecord>, CommandError> {
+    let store = library(&state)?;
+    store.list_dj_library_syncs().map_err(CommandError::from)
+}
 
-    shortcut_manager.on_shortcuts(
-        ["CmdOrCtrl+Alt+N", "MediaNextTrack"],
-        |app, _shortcut, event| {
-            if matches!(event.state, ShortcutState::Pressed) {
-                emit_media_event(app, MEDIA_NEXT_EVENT);
-            }
-        },
-    )?;
+/// Forgets a Traktor or Serato source and removes the tracks only it
+/// provided.
+#[tauri::command]
+fn remove_dj_library_source(
+    state: tauri::State<AppState>,
+    software: DjSoftware,
+    source_id: String,
+) -> Result<(), CommandError> {
+    let mut store = library(&state)?;
+    let known = store
+        .list_dj_library_syncs()?
+        .iter()
+        .any(|sync| sync.software == software && sync.source_id == source_id);
+    if !known {
+        return Err(CommandError::not_found(
+            &format!("{software} source"),
+            &source_id,
+        ));
+    }
+    store
+        .remove_dj_library_source(software, &source_id)
+        .map_err(CommandError::from)
+}
 
-    shortcut_manager.on_shortcuts(
-        ["CmdOrCtrl+Alt+B", "MediaPreviousTrack"],
-        |app, _shortcut, event| {
-            if matches!(event.state, ShortcutState::Pressed) {
-                emit_media_event(app, MEDIA_PREVIOUS_EVENT);
-            }
-        },
-    )?;
+/// Converts the cues of `track_id` for `software`, taken from the library
+/// `from` or, without it, from another library that has cues for the track.
+#[tauri::command]
+fn export_cues(
+    state: tauri::State<AppState>,
+    track_id: String,
+    software: DjSoftware,
+    from: Option<DjSoftware>,
+) -> Result<CueExport, CommandError> {
+    let track = library(&state)?
+        .load_track_cues(&track_id)?
+        .ok_or_else(|| CommandError::not_found("track", &track_id))?;
+    let layout = track.location.as_deref().and_then(|location| {
+        dj_library::read_mp3_layout(Path::new(location)).unwrap_or_else(|error| {
+            log::warn!("failed to read the layout of {location}: {error}");
+            None
+        })
+    });
+    dj_library::export_cues(&track.sources, software, from, layout)
+        .ok_or_else(|| CommandError::not_found("cues", &track_id))
+}
+
+#[tauri::command]
+fn list_watched_folders(state: tauri::State<AppState>) -> Result<Vec<WatchedFolder>, CommandError> {
+    let store = library(&state)?;
+    store.list_watched_folders().map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn add_watched_folder(
+    state: tauri::State<AppState>,
+    path: String,
+) -> Result<Vec<WatchedFolder>, CommandError> {
+    let folder = fs::canonicalize(&path).map_err(|error| CommandError::io(&path, error))?;
+    if !folder.is_dir() {
+        return Err(CommandError::invalid_field(
+            "path",
+            format!("{path} is not a directory"),
+        ));
+    }
+
+    let store = library(&state)?;
+    store.add_watched_folder(&folder.to_string_lossy())?;
+    store.list_watched_folders().map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn remove_watched_folder(
+    state: tauri::State<AppState>,
+    path: String,
+) -> Result<Vec<WatchedFolder>, CommandError> {
+    let mut store = library(&state)?;
+    store.remove_watched_folder(&path)?;
+    store.list_watched_folders().map_err(CommandError::from)
+}
+
+#[tauri::command]
+async fn scan_watched_folders(
+    state: tauri::State<'_, AppState>,
+) -> Result<ScanSummary, CommandError> {
+    let library = Arc::clone(&state.library);
+    let summary = async_runtime::spawn_blocking(move || scanner::scan_watched_folders(&library))
+        .await
+        .map_err(|error| {
+            CommandError::internal(format!("failed to join folder scan task: {error}"))
+        })??;
+    // Newly linked files get their BPM and key and an integrity check in
+    // the background.
+    if summary.linked > 0 {
+        state.analysis.queue_analysis(Vec::new());
+        state.integrity.queue_check(Vec::new());
+    }
+    Ok(summary)
+}
+
+#[tauri::command]
+fn list_ambiguous_local_files(
+    state: tauri::State<AppState>,
+) -> Result<Vec<AmbiguousLocalFile>, CommandError> {
+    let store = library(&state)?;
+    store
+        .list_ambiguous_local_files()
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn confirm_local_link(
+    state: tauri::State<AppState>,
+    location: String,
+    track_id: String,
+) -> Result<(), CommandError> {
+    let mut store = library(&state)?;
+    let linked = store.confirm_local_link(&location, &track_id)?;
+    if linked {
+        Ok(())
+    } else {
+        Err(CommandError::not_found("scanned file", &location))
+    }
+}
+
+#[tauri::command]
+async fn find_relocations(
+    state: tauri::State<'_, AppState>,
+    roots: Option<Vec<String>>,
+) -> Result<RelocationReport, CommandError> {
+    let library = Arc::clone(&state.library);
+    let roots = roots.unwrap_or_default();
+    let report =
+        async_runtime::spawn_blocking(move || relocate::find_relocations(&library, &roots))
+            .await
+            .map_err(|error| {
+                CommandError::internal(format!("failed to join relocation task: {error}"))
+            })??;
+    Ok(report)
+}
+
+#[tauri::command]
+fn apply_relocations(
+    state: tauri::State<AppState>,
+    relocations: Vec<AssetRelocation>,
+) -> Result<Vec<AssetRelocation>, CommandError> {
+    Ok(relocate::apply_relocations(&state.library, &relocations)?)
+}
+
+#[tauri::command]
+fn list_duplicate_groups(
+    state: tauri::State<AppState>,
+) -> Result<Vec<DuplicateGroup>, CommandError> {
+    let store = library(&state)?;
+    duplicates::find_duplicate_groups(&store).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn merge_duplicate_tracks(
+    state: tauri::State<AppState>,
+    canonical_track_id: String,
+    track_ids: Vec<String>,
+) -> Result<u32, CommandError> {
+    let mut store = library(&state)?;
+    let exists = store.load_soundcloud_lookup(&canonical_track_id)?.is_some();
+    if !exists {
+        return Err(CommandError::not_found("track", &canonical_track_id));
+    }
+    store
+        .merge_tracks(&canonical_track_id, &track_ids)
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+async fn write_tags(
+    state: tauri::State<'_, AppState>,
+    track_id: String,
+    dry_run: Option<bool>,
+) -> Result<TagWriteReport, CommandError> {
+    state.tags.write(&track_id, dry_run.unwrap_or(false)).await
+}
+
+#[tauri::command]
+async fn write_replaygain_tags(
+    state: tauri::State<'_, AppState>,
+    track_id: String,
+    dry_run: Option<bool>,
+) -> Result<TagWriteReport, CommandError> {
+    state
+        .tags
+        .write_replaygain(&track_id, dry_run.unwrap_or(false))
+        .await
+}
+
+#[tauri::command]
+fn restore_tags(
+    state: tauri::State<AppState>,
+    track_id: String,
+) -> Result<TagWriteReport, CommandError> {
+    state.tags.restore(&track_id)
+}
+
+#[tauri::command]
+async fn fingerprint_local_assets(
+    state: tauri::State<'_, AppState>,
+) -> Result<FingerprintSummary, CommandError> {
+    let library = Arc::clone(&state.library);
+    let summary =
+        async_runtime::spawn_blocking(move || fingerprint::fingerprint_local_assets(&library))
+            .await
+            .map_err(|error| {
+                CommandError::internal(format!("failed to join fingerprint task: {error}"))
+            })??;
+    Ok(summary)
+}
+
+#[tauri::command]
+async fn lookup_acoustid(
+    state: tauri::State<'_, AppState>,
+    track_ids: Option<Vec<String>>,
+) -> Result<AcoustidSummary, CommandError> {
+    state.acoustid.lookup(&track_ids.unwrap_or_default()).await
+}
+
+/// Returns the waveform overview of the track's local file with
+/// `resolution` columns, decoding the file only when it is not cached yet.
+#[tauri::command]
+async fn get_waveform(
+    state: tauri::State<'_, AppState>,
+    track_id: String,
+    resolution: Option<u32>,
+) -> Result<Waveform, CommandError> {
+    let library = Arc::clone(&state.library);
+    let waveforms = Arc::clone(&state.waveforms);
+    async_runtime::spawn_blocking(move || waveforms.waveform(&library, &track_id, resolution))
+        .await
+        .map_err(|error| CommandError::internal(format!("failed to join waveform task: {error}")))?
+}
 
-    shortcut_manager.on_shortcuts(["MediaPlay"], |app, _shortcut, event| {
-        if matches!(event.state, ShortcutState::Pressed) {
-            emit_media_event(app, MEDIA_PLAY_EVENT);
+/// Queues a BPM and key analysis of `track_ids`, or of every local file not
+/// analyzed yet. Progress is reported on the job progress event.
+#[tauri::command]
+fn analyze_audio(state: tauri::State<AppState>, track_ids: Option<Vec<String>>) {
+    state.analysis.queue_analysis(track_ids.unwrap_or_default());
+}
+
+/// Queues an integrity check of `track_ids`, or of every local file not
+/// checked as it is now. Progress is reported on the job progress event.
+#[tauri::command]
+fn check_asset_integrity(state: tauri::State<AppState>, track_ids: Option<Vec<String>>) {
+    state.integrity.queue_check(track_ids.unwrap_or_default());
+}
+
+#[tauri::command]
+fn list_unhealthy_assets(
+    state: tauri::State<AppState>,
+) -> Result<Vec<AssetHealthRecord>, CommandError> {
+    let store = library(&state)?;
+    store.list_unhealthy_assets().map_err(CommandError::from)
+}
+
+/// Zips the recent logs and a report on the library, settings and queues
+/// into `path`, or into the diagnostics directory of the app data when no
+/// path is given.
+#[tauri::command]
+async fn export_diagnostics(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: Option<String>,
+) -> Result<DiagnosticsExport, CommandError> {
+    let report = {
+        let store = library(&state)?;
+        diagnostics::collect_report(
+            &store,
+            &ClientIdentity::from_app(&app),
+            state.settings.get(),
+            state.jobs.snapshot(),
+        )?
+    };
+    let destination = match path {
+        Some(path) => PathBuf::from(path),
+        None => state
+            .diagnostics_dir
+            .join(diagnostics::bundle_file_name(SystemTime::now())),
+    };
+    let log_dir = state.log_dir.clone();
+    async_runtime::spawn_blocking(move || {
+        diagnostics::write_bundle(&report, &log_dir, &destination)
+    })
+    .await
+    .map_err(|error| CommandError::internal(format!("failed to join diagnostics task: {error}")))?
+}
+
+#[tauri::command]
+fn get_settings(state: tauri::State<AppState>) -> AppSettings {
+    state.settings.get()
+}
+
+/// Saves `settings` and announces them on the settings changed event, which
+/// reconfigures the running services.
+#[tauri::command]
+fn update_settings(
+    app: AppHandle,
+    state: tauri::State<AppState>,
+    settings: AppSettings,
+) -> Result<AppSettings, CommandError> {
+    let settings = settings.normalized();
+    for (field, shortcut) in settings.shortcuts.entries() {
+        if !shortcut.is_empty() {
+            shortcut.parse::<Shortcut>().map_err(|error| {
+                CommandError::invalid_field(field, format!("invalid shortcut: {error}"))
+            })?;
         }
-    })?;
+    }
+    let current = state.settings.get();
+    for source in &settings.rekordbox.sources {
+        let moved = current
+            .rekordbox
+            .source(&source.id)
+            .is_none_or(|existing| existing.path != source.path);
+        if moved && !Path::new(&source.path).exists() {
+            return Err(CommandError::invalid_field(
+                "rekordbox.sources",
+                format!("{} does not exist", source.path),
+            ));
+        }
+    }
+
+    let settings = state.settings.update(settings)?;
+    app.emit(SETTINGS_CHANGED_EVENT, &settings)
+        .map_err(CommandError::internal)?;
+    Ok(settings)
+}
+
+/// Reconfigures the services from the saved settings. Runs at startup and
+/// on every settings changed event.
+fn apply_settings(app: &AppHandle) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let settings = state.settings.get();
+    state.musicbrainz.reconfigure(&settings.musicbrainz);
+    if let Err(error) = register_media_shortcuts(app, &settings.shortcuts) {
+        log::warn!("failed to register global shortcuts: {error}, skipping the media key hook");
+    }
+    match state.rekordbox.lock() {
+        Ok(mut rekordbox) => rekordbox.apply(&settings.rekordbox, &state.library),
+        Err(_) => log::error!("failed to acquire rekordbox state lock to apply settings"),
+    }
+    align_webviews(app);
+}
+
+/// Width of the sidebar the background webviews are placed next to.
+fn sidebar_width(app: &AppHandle) -> i32 {
+    let width = app
+        .try_state::<AppState>()
+        .map(|state| state.settings.get().window.sidebar_width)
+        .unwrap_or(settings::DEFAULT_SIDEBAR_WIDTH);
+    width as i32
+}
 
-    shortcut_manager.on_shortcuts(["MediaPause"], |app, _shortcut, event| {
-        if matches!(event.state, ShortcutState::Pressed) {
-            emit_media_event(app, MEDIA_PAUSE_EVENT);
+/// Places the background webviews next to the sidebar of the main window.
+fn align_webviews(app: &AppHandle) {
+    let Some(main) = app.get_webview_window("main") else {
+        return;
+    };
+    let sidebar_width = sidebar_width(app);
+    if let (Ok(pos), Ok(size)) = (main.outer_position(), main.outer_size()) {
+        let x = pos.x + sidebar_width;
+        let y = pos.y;
+        let w = if size.width > sidebar_width as u32 {
+            size.width - sidebar_width as u32
+        } else {
+            600
+        };
+        let h = size.height;
+        for label in ["soundcloud-bg", "bandcamp-bg"] {
+            if let Some(webview) = app.get_webview_window(label) {
+                let _ = webview.set_position(tauri::PhysicalPosition { x, y });
+                let _ = webview.set_size(PhysicalSize {
+                    width: w,
+                    height: h,
+                });
+            }
         }
-    })?;
+    }
+}
+
+/// Replaces the registered shortcuts with the configured ones. The media
+/// keys are always registered.
+fn register_media_shortcuts(
+    app: &AppHandle,
+    shortcuts: &ShortcutSettings,
+) -> Result<(), tauri_plugin_global_shortcut::Error> {
+    let shortcut_manager = app.global_shortcut();
+    shortcut_manager.unregister_all()?;
+
+    let bindings = [
+        (
+            shortcuts.play_pause.as_str(),
+            "MediaPlayPause",
+            MEDIA_TOGGLE_EVENT,
+        ),
+        (
+            shortcuts.next_track.as_str(),
+            "MediaNextTrack",
+            MEDIA_NEXT_EVENT,
+        ),
+        (
+            shortcuts.previous_track.as_str(),
+            "MediaPreviousTrack",
+            MEDIA_PREVIOUS_EVENT,
+        ),
+        ("", "MediaPlay", MEDIA_PLAY_EVENT),
+        ("", "MediaPause", MEDIA_PAUSE_EVENT),
+    ];
+    for (shortcut, media_key, media_event) in bindings {
+        let keys: Vec<&str> = [shortcut, media_key]
+            .into_iter()
+            .filter(|key| !key.is_empty())
+            .collect();
+        shortcut_manager.on_shortcuts(keys, move |app, _shortcut, event| {
+            if matches!(event.state, ShortcutState::Pressed) {
+                emit_media_event(app, media_event);
+            }
+        })?;
+    }
 
     Ok(())
 }
@@ -604,20 +1351,69 @@ pub fn run() {
             retry_musicbrainz_lookup,
             list_discogs_candidates,
             list_musicbrainz_candidates,
+            resolve_store_links,
+            list_store_links,
+            list_purchase_queue,
+            import_discogs_lists,
+            add_to_discogs_wantlist,
+            add_to_discogs_collection,
             confirm_musicbrainz_match,
+            get_match_settings,
+            update_match_settings,
+            get_endpoint_settings,
+            update_endpoint_settings,
             upsert_track,
             link_soundcloud_source,
             record_local_asset,
             list_missing_assets,
             list_library_status,
-            import_rekordbox_library
+            import_rekordbox_library,
+            list_rekordbox_syncs,
+            remove_rekordbox_source,
+            import_dj_library,
+            list_dj_library_syncs,
+            remove_dj_library_source,
+            export_cues,
+            list_watched_folders,
+            add_watched_folder,
+            remove_watched_folder,
+            scan_watched_folders,
+            list_ambiguous_local_files,
+            confirm_local_link,
+            find_relocations,
+            apply_relocations,
+            list_duplicate_groups,
+            merge_duplicate_tracks,
+            write_tags,
+            write_replaygain_tags,
+            restore_tags,
+            fingerprint_local_assets,
+            lookup_acoustid,
+            analyze_audio,
+            check_asset_integrity,
+            list_unhealthy_assets,
+            get_waveform,
+            export_diagnostics,
+            get_settings,
+            update_settings
         ])
         .setup(|app| {
-            if let Err(error) = register_media_shortcuts(&app.handle()) {
-                eprintln!(
-                    "[soundcloud-wrapper] Failed to register global shortcuts: {error}. Skipping media key hook."
-                );
+            let log_dir = app
+                .path()
+                .app_data_dir()
+                .ok()
+                .map(|dir| dir.join(logging::LOG_DIR_NAME));
+            if let Err(error) = logging::init(log_dir.as_deref()) {
+                eprintln!("failed to initialize logging: {error}");
             }
+            let package_info = app.package_info();
+            log::info!(
+                "starting {} {} on {} {}",
+                package_info.name,
+                package_info.version,
+                std::env::consts::OS,
+                std::env::consts::ARCH
+            );
             // Spawn background webviews to prime integrations and collect likes/playlists
             // SoundCloud background window
             let sc_window = tauri::WebviewWindowBuilder::new(
@@ -650,7 +1446,7 @@ pub fn run() {
             app.listen_global("ui://mode", move |event| {
                 let mode = event.payload().unwrap_or("");
                 let main = app_handle.get_webview_window("main");
-                let sidebar_width: i32 = 320; // pixels (match CSS grid sidebar)
+                let sidebar_width = sidebar_width(&app_handle); // pixels (match CSS grid sidebar)
                 if let Some(main_win) = &main {
                     if let (Ok(outer_pos), Ok(outer_size)) = (main_win.outer_position(), main_win.outer_size()) {
                         let new_x = outer_pos.x + sidebar_width;
@@ -684,33 +1480,26 @@ pub fn run() {
             // Keep background webviews aligned when the main window moves or resizes
             let app_handle2 = app.handle();
             if let Some(main) = app.get_webview_window("main") {
-                let align = move || {
-                    let sidebar_width: i32 = 320;
-                    if let (Ok(pos), Ok(size)) = (main.outer_position(), main.outer_size()) {
-                        let x = pos.x + sidebar_width;
-                        let y = pos.y;
-                        let w = if size.width > sidebar_width as u32 { size.width - sidebar_width as u32 } else { 600 };
-                        let h = size.height;
-                        if let Some(sc) = app_handle2.get_webview_window("soundcloud-bg") {
-                            let _ = sc.set_position(tauri::PhysicalPosition { x, y });
-                            let _ = sc.set_size(PhysicalSize { width: w, height: h });
-                        }
-                        if let Some(bc) = app_handle2.get_webview_window("bandcamp-bg") {
-                            let _ = bc.set_position(tauri::PhysicalPosition { x, y });
-                            let _ = bc.set_size(PhysicalSize { width: w, height: h });
-                        }
-                    }
-                };
                 let _ = main.on_window_event(move |e| match e {
                     WindowEvent::Resized { .. } | WindowEvent::Moved { .. } => {
-                        align();
+                        align_webviews(&app_handle2);
                     }
                     _ => {}
                 });
             }
             let app_state = AppState::new(&app.handle())
                 .map_err(|error| -> Box<dyn Error> { Box::new(error) })?;
+            // Drop the overviews of files that were relocated or removed
+            // while the app was closed.
+            let waveforms = Arc::clone(&app_state.waveforms);
+            let library = Arc::clone(&app_state.library);
+            async_runtime::spawn_blocking(move || match waveforms.prune(&library) {
+                Ok(0) => {}
+                Ok(pruned) => log::info!("deleted {pruned} stale waveform overviews"),
+                Err(error) => log::warn!("failed to prune the waveform cache: {error}"),
+            });
             app.manage(app_state);
+            apply_settings(&app.handle());
             app.manage(WindowState::default());
             let tray =
                 setup_tray(&app.handle()).map_err(|error| -> Box<dyn Error> { Box::new(error) })?;
@@ -727,6 +1516,11 @@ pub fn run() {
                 }
             });
 
+            let settings_handle = handle.clone();
+            handle.listen(SETTINGS_CHANGED_EVENT, move |_event| {
+                apply_settings(&settings_handle);
+            });
+
             let theme_handle = handle.clone();
             handle.listen(THEME_CHANGE_EVENT, move |event| {
                 if let Ok(payload) = serde_json::from_str::<ThemeChangePayload>(event.payload()) {
@@ -741,8 +1535,8 @@ pub fn run() {
                         let mut store = match state.library.lock() {
                             Ok(store) => store,
                             Err(_) => {
-                                eprintln!(
-                                    "[soundcloud-wrapper] failed to acquire library store lock for like update"
+                                log::error!(
+                                    "failed to acquire library store lock for like update"
                                 );
                                 return;
                             }
@@ -766,15 +1560,15 @@ pub fn run() {
                             raw_payload: payload.raw.clone(),
                         };
 
-                        if let Err(error) =
-                            store.sync_soundcloud_track(&track_record, &source_record)
-                        {
-                            eprintln!(
-                                "[soundcloud-wrapper] failed to persist SoundCloud like update: {error}"
-                            );
-                        } else {
-                            state.musicbrainz.queue_lookup(payload.clone());
-                            state.discogs.queue_lookup(payload);
+                        match store.sync_soundcloud_track(&track_record, &source_record) {
+                            Ok(true) => {
+                                state.musicbrainz.queue_lookup(payload.clone());
+                                state.discogs.queue_lookup(payload);
+                            }
+                            Ok(false) => {}
+                            Err(error) => log::error!(
+                                "failed to persist SoundCloud like update: {error}"
+                            ),
                         }
                     }
                 }
@@ -787,8 +1581,8 @@ pub fn run() {
                         let mut store = match state.library.lock() {
                             Ok(store) => store,
                             Err(_) => {
-                                eprintln!(
-                                    "[soundcloud-wrapper] failed to acquire library store lock for playlist update"
+                                log::error!(
+                                    "failed to acquire library store lock for playlist update"
                                 );
                                 return;
                             }
@@ -813,15 +1607,15 @@ pub fn run() {
                                 raw_payload: track.raw.clone(),
                             };
 
-                            if let Err(error) =
-                                store.sync_soundcloud_track(&track_record, &source_record)
-                            {
-                                eprintln!(
-                                    "[soundcloud-wrapper] failed to persist SoundCloud playlist update: {error}"
-                                );
-                            } else {
-                                state.musicbrainz.queue_lookup(track.clone());
-                                state.discogs.queue_lookup(track);
+                            match store.sync_soundcloud_track(&track_record, &source_record) {
+                                Ok(true) => {
+                                    state.musicbrainz.queue_lookup(track.clone());
+                                    state.discogs.queue_lookup(track);
+                                }
+                                Ok(false) => {}
+                                Err(error) => log::error!(
+                                    "failed to persist SoundCloud playlist update: {error}"
+                                ),
                             }
                         }
                     }
@@ -832,7 +1626,7 @@ pub fn run() {
         })
         .on_page_load(|window, _payload| {
             if let Err(error) = window.eval(include_str!("scripts/inject.js")) {
-                eprintln!("failed to inject media bridge script: {error}");
+                log::error!("failed to inject media bridge script: {error}");
             }
         })
         .on_window_event(|window, event| {
@@ -915,7 +1709,7 @@ fn handle_theme_change(app: &AppHandle, payload: ThemeChangePayload) {
         .body(body)
         .show()
     {
-        eprintln!("failed to show theme change notification: {error}");
+        log::warn!("failed to show theme change notification: {error}");
     }
 }
 
@@ -931,10 +1725,10 @@ fn toggle_main_window(app: &AppHandle) {
 fn show_main_window(app: &AppHandle) {
     if let Some(window) = app.get_webview_window(MAIN_WINDOW_LABEL) {
         if let Err(error) = window.show() {
-            eprintln!("failed to show window: {error}");
+            log::warn!("failed to show window: {error}");
         }
         if let Err(error) = window.set_focus() {
-            eprintln!("failed to focus window: {error}");
+            log::warn!("failed to focus window: {error}");
         }
     }
     app.state::<WindowState>()
@@ -945,7 +1739,7 @@ fn show_main_window(app: &AppHandle) {
 fn hide_main_window(app: &AppHandle) {
     if let Some(window) = app.get_webview_window(MAIN_WINDOW_LABEL) {
         if let Err(error) = window.hide() {
-            eprintln!("failed to hide window: {error}");
+            log::warn!("failed to hide window: {error}");
         }
     }
     app.state::<WindowState>()
@@ -957,11 +1751,11 @@ fn go_home(app: &AppHandle) {
     show_main_window(app);
     if let Some(window) = app.get_webview_window(MAIN_WINDOW_LABEL) {
         if let Err(error) = window.emit(TRAY_HOME_EVENT, Value::Null) {
-            eprintln!("failed to emit home event: {error}");
+            log::error!("failed to emit home event: {error}");
             if let Err(eval_error) =
                 window.eval("window.location.href = 'https://soundcloud.com/';")
             {
-                eprintln!("failed to navigate home: {eval_error}");
+                log::warn!("failed to navigate home: {eval_error}");
             }
         }
     }
@@ -2,18 +2,44 @@ use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
 use std::fs;
-use std::path::PathBuf;
-
-use crate::rekordbox::RekordboxTrack;
-use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::analysis;
+use crate::dj_library::{self, DjCue, DjSoftware, DjTrack};
+use crate::endpoints::{EndpointSettings, ENDPOINT_PROVIDERS};
+use crate::fingerprint;
+use crate::matching::{MatchSettings, DISCOGS_PROVIDER, MUSICBRAINZ_PROVIDER, PROVIDERS};
+use crate::normalize::{normalize_track, NormalizedTrack};
+use crate::rekordbox::{self, RekordboxCue, RekordboxTrack};
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json::{json, Value};
 use tauri::{AppHandle, Manager};
 
+/// File name of the library database inside the application data directory.
+pub const DATABASE_FILE_NAME: &str = "library.sqlite3";
+
+/// Version of the schema the migrations produce, stored in the database's
+/// `user_version`. Bump it whenever a migration is added.
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// Tables whose rows move through a status, and the column holding it.
+const STATUS_COLUMNS: [(&str, &str); 7] = [
+    ("discogs_matches", "status"),
+    ("musicbrainz_matches", "status"),
+    ("store_matches", "status"),
+    ("acoustid_matches", "status"),
+    ("audio_analysis", "status"),
+    ("asset_health", "status"),
+    ("local_files", "link_status"),
+];
+
 #[derive(Debug)]
 pub enum LibraryError {
     AppDataDirUnavailable,
+    LockPoisoned,
     Io(std::io::Error),
     Database(rusqlite::Error),
     Serialization(serde_json::Error),
@@ -25,6 +51,7 @@ impl fmt::Display for LibraryError {
             LibraryError::AppDataDirUnavailable => {
                 write!(f, "unable to resolve application data directory")
             }
+            LibraryError::LockPoisoned => write!(f, "library store lock poisoned"),
             LibraryError::Io(error) => write!(f, "filesystem error: {error}"),
             LibraryError::Database(error) => write!(f, "database error: {error}"),
             LibraryError::Serialization(error) => write!(f, "serialization error: {error}"),
@@ -35,7 +62,7 @@ impl fmt::Display for LibraryError {
 impl Error for LibraryError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
-            LibraryError::AppDataDirUnavailable => None,
+            LibraryError::AppDataDirUnavailable | LibraryError::LockPoisoned => None,
             LibraryError::Io(error) => Some(error),
             LibraryError::Database(error) => Some(error),
             LibraryError::Serialization(error) => Some(error),
@@ -43,6 +70,11 @@ impl Error for LibraryError {
     }
 }
 
+/// Locks the shared store, reporting a poisoned lock as [`LibraryError::LockPoisoned`].
+pub fn lock(library: &Mutex<LibraryStore>) -> Result<MutexGuard<'_, LibraryStore>, LibraryError> {
+    library.lock().map_err(|_| LibraryError::LockPoisoned)
+}
+
 impl From<std::io::Error> for LibraryError {
     fn from(value: std::io::Error) -> Self {
         Self::Io(value)
@@ -164,6 +196,161 @@ pub struct MusicbrainzCandidateRecord {
     pub raw_payload: Value,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub enum StoreMatchStatus {
+    Candidates,
+    LinkOnly,
+    Error,
+}
+
+impl StoreMatchStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            StoreMatchStatus::Candidates => "candidates",
+            StoreMatchStatus::LinkOnly => "link_only",
+            StoreMatchStatus::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StoreMatchRecord {
+    pub track_id: String,
+    pub store: String,
+    pub status: StoreMatchStatus,
+    pub query: Option<String>,
+    pub search_url: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreCandidateRecord {
+    pub match_id: String,
+    pub store: String,
+    pub product_id: Option<String>,
+    pub score: Option<f32>,
+    pub price: Option<f64>,
+    pub currency: Option<String>,
+    pub format: Option<String>,
+    pub url: Option<String>,
+    pub raw_payload: Value,
+}
+
+/// Stored search link and candidate matches for one track in one store.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreLinkRecord {
+    pub track_id: String,
+    pub store: String,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub checked_at: String,
+    pub candidates: Vec<StoreCandidateRecord>,
+}
+
+/// A track without an available local asset together with the best purchase
+/// option found in one store, as returned by [`LibraryStore::list_purchase_queue`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseQueueRow {
+    pub track_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    pub store: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_url: Option<String>,
+    pub candidate_count: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub best_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub best_score: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub best_price: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub best_currency: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub best_format: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscogsListKind {
+    Collection,
+    Wantlist,
+}
+
+impl DiscogsListKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DiscogsListKind::Collection => "collection",
+            DiscogsListKind::Wantlist => "wantlist",
+        }
+    }
+}
+
+/// A release in the user's Discogs collection or wantlist.
+#[derive(Debug, Clone)]
+pub struct DiscogsListEntry {
+    pub release_id: String,
+    pub folder_id: Option<i64>,
+    pub instance_id: Option<i64>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub formats: Vec<String>,
+    pub added_at: Option<String>,
+    pub raw_payload: Value,
+}
+
+impl DiscogsListEntry {
+    /// Parses an item from the `/collection/folders/{id}/releases` or `/wants`
+    /// endpoints. Both share the `basic_information` shape.
+    pub fn from_api_item(item: &Value) -> Option<Self> {
+        let basic = item.get("basic_information").unwrap_or(item);
+        let release_id = extract_release_id(item).or_else(|| extract_release_id(basic))?;
+        let artist = basic
+            .get("artists")
+            .and_then(|value| value.as_array())
+            .map(|artists| {
+                artists
+                    .iter()
+                    .filter_map(|artist| artist.get("name").and_then(|value| value.as_str()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .filter(|value| !value.is_empty());
+
+        Some(Self {
+            release_id,
+            folder_id: item.get("folder_id").and_then(|value| value.as_i64()),
+            instance_id: item.get("instance_id").and_then(|value| value.as_i64()),
+            title: json_string(basic, "title"),
+            artist,
+            formats: format_names(basic),
+            added_at: json_string(item, "date_added"),
+            raw_payload: item.clone(),
+        })
+    }
+}
+
+/// A track with a confirmed Discogs release that can be pushed to the user's
+/// collection or wantlist.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscogsReleaseTarget {
+    pub track_id: String,
+    pub release_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub formats: Vec<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SoundcloudSourceRecord {
     pub track_id: String,
@@ -198,109 +385,574 @@ pub struct LocalAssetRecord {
     #[serde(default)]
     pub duration_ms: Option<i64>,
     #[serde(default)]
+    pub file_size: Option<i64>,
+    #[serde(default)]
     pub rekordbox_cues: Option<Value>,
 }
 
-/// Describes a single row returned by [`LibraryStore::list_library_status`].
+/// The Rekordbox library a set of tracks is imported from. Each source
+/// keeps its own mappings, so importing one does not remove the tracks of
+/// another.
+#[derive(Debug, Clone)]
+pub struct RekordboxSync {
+    pub source_id: String,
+    pub path: String,
+    /// Modification time of the library file, in milliseconds since the
+    /// Unix epoch.
+    pub modified_at: Option<i64>,
+}
+
+/// The last import of a Rekordbox source.
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct LibraryStatusRow {
-    pub track_id: String,
+pub struct RekordboxSyncRecord {
+    pub source_id: String,
+    pub path: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub title: Option<String>,
+    pub modified_at: Option<i64>,
+    pub track_count: u64,
+    pub synced_at: String,
+}
+
+/// The Traktor or Serato library a set of tracks is imported from. Like
+/// Rekordbox sources, each keeps its own mappings.
+#[derive(Debug, Clone)]
+pub struct DjLibrarySync {
+    pub software: DjSoftware,
+    pub source_id: String,
+    pub path: String,
+    /// Modification time of the library file, in milliseconds since the
+    /// Unix epoch.
+    pub modified_at: Option<i64>,
+}
+
+/// The last import of a Traktor or Serato source.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DjLibrarySyncRecord {
+    pub software: DjSoftware,
+    pub source_id: String,
+    pub path: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub artist: Option<String>,
+    pub modified_at: Option<i64>,
+    pub track_count: u64,
+    pub synced_at: String,
+}
+
+/// A music folder the scanner walks recursively for audio files.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchedFolder {
+    pub path: String,
+    pub added_at: String,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_scanned_at: Option<String>,
+}
+
+/// An audio file found in a watched folder, with the tags and metadata read
+/// from it. `size` and `modified_at` let later scans skip unchanged files.
+#[derive(Debug, Clone)]
+pub struct ScannedFileRecord {
+    pub location: String,
+    pub folder: String,
+    pub size: i64,
+    pub modified_at: i64,
+    pub checksum: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
     pub album: Option<String>,
-    pub liked: bool,
-    pub matched: bool,
-    pub has_local_file: bool,
-    pub local_available: bool,
-    pub in_rekordbox: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub discogs_status: Option<String>,
+    pub fingerprint: Option<Vec<u32>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalLinkStatus {
+    Linked,
+    Confirmed,
+    Ambiguous,
+    Unmatched,
+}
+
+impl LocalLinkStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LocalLinkStatus::Linked => "linked",
+            LocalLinkStatus::Confirmed => "confirmed",
+            LocalLinkStatus::Ambiguous => "ambiguous",
+            LocalLinkStatus::Unmatched => "unmatched",
+        }
+    }
+
+    fn from_str(value: &str) -> Self {
+        match value {
+            "linked" => LocalLinkStatus::Linked,
+            "confirmed" => LocalLinkStatus::Confirmed,
+            "ambiguous" => LocalLinkStatus::Ambiguous,
+            _ => LocalLinkStatus::Unmatched,
+        }
+    }
+}
+
+/// How the scanner matched a file against the liked tracks.
+#[derive(Debug, Clone)]
+pub struct LocalLinkOutcome {
+    pub status: LocalLinkStatus,
+    pub track_id: Option<String>,
+    pub confidence: Option<f32>,
+    /// Scored tracks the user can pick from when the link is ambiguous.
+    pub candidates: Vec<(String, f32)>,
+}
+
+/// A liked track without an available local file, as seen by the scanner.
+#[derive(Debug, Clone)]
+pub struct LocalLinkTarget {
+    pub track_id: String,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub duration_ms: Option<i64>,
+}
+
+/// A scanned file the scanner could not link on its own.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmbiguousLocalFile {
+    pub location: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub discogs_release_id: Option<String>,
+    pub title: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub discogs_confidence: Option<f32>,
+    pub artist: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub discogs_checked_at: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub candidates: Vec<LocalFileCandidate>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalFileCandidate {
+    pub track_id: String,
+    pub score: f32,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub discogs_message: Option<String>,
+    pub title: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub discogs_query: Option<String>,
-    pub discogs_candidate_count: u32,
+    pub artist: Option<String>,
+}
+
+/// A local asset as the relocation pass sees it.
+#[derive(Debug, Clone)]
+pub struct LocalAssetLocation {
+    pub track_id: String,
+    pub location: String,
+    pub checksum: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub file_size: Option<i64>,
+    pub available: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RelocationMatch {
+    /// The file contents are identical.
+    Checksum,
+    /// No checksum was recorded; the size and duration agree.
+    SizeAndDuration,
+}
+
+/// A proposed or applied move of a local asset to a new path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetRelocation {
+    pub track_id: String,
+    pub old_location: String,
+    pub new_location: String,
+    pub matched_by: RelocationMatch,
+}
+
+/// What the duplicate finder compares for each track.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateTrackRecord {
+    pub track_id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub musicbrainz_status: Option<String>,
+    pub title: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub musicbrainz_release_id: Option<String>,
+    pub artist: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub musicbrainz_confidence: Option<f32>,
+    pub version: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub musicbrainz_checked_at: Option<String>,
+    pub duration_ms: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub musicbrainz_message: Option<String>,
+    pub checksum: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub musicbrainz_query: Option<String>,
-    pub musicbrainz_candidate_count: u32,
+    pub location: Option<String>,
+    pub asset_available: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub soundcloud_permalink_url: Option<String>,
+    pub discogs_release_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub soundcloud_liked_at: Option<String>,
+    pub musicbrainz_release_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub local_location: Option<String>,
+    pub soundcloud_id: Option<String>,
+    pub in_rekordbox: bool,
+    #[serde(skip)]
+    pub fingerprint: Option<Vec<u32>>,
 }
 
-/// A paginated response produced by [`LibraryStore::list_library_status`].
-#[derive(Debug, Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct LibraryStatusPage {
-    pub rows: Vec<LibraryStatusRow>,
-    pub total: u32,
-    pub limit: u32,
-    pub offset: u32,
+/// A track's local file and the matched releases the tag writer reads from.
+#[derive(Debug, Clone)]
+pub struct TagSourceRecord {
+    pub track_id: String,
+    pub location: String,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub version: Option<String>,
+    pub discogs_release: Option<Value>,
+    pub musicbrainz_release: Option<Value>,
+    /// MusicBrainz recording found through AcoustID.
+    pub recording_id: Option<String>,
+    /// ReplayGain values measured on the file at `location`.
+    pub replaygain_gain: Option<f64>,
+    pub replaygain_peak: Option<f64>,
 }
 
-/// Filtering and pagination options for [`LibraryStore::list_library_status`].
-///
-/// * `missing_assets_only` &mdash; return tracks that do not have an available
-///   local asset entry. This includes tracks that have never been downloaded or
-///   where the asset is marked as unavailable.
-/// * `unresolved_discogs_only` &mdash; return tracks where the Discogs
-///   integration has not produced a successful match.
-/// * `liked_only` &mdash; limit results to tracks that have a SoundCloud payload
-///   containing a `likedAt` timestamp.
-/// * `rekordbox_only` &mdash; limit results to tracks that currently have a
-///   Rekordbox source entry.
-/// * `limit` / `offset` &mdash; standard pagination controls applied to the
-///   ordered result set. The backend enforces sensible defaults to avoid
-///   fetching excessively large pages.
-#[derive(Debug, Clone, Deserialize, Default)]
-#[serde(default, rename_all = "camelCase")]
-pub struct StatusFilter {
-    pub missing_assets_only: bool,
-    pub unresolved_discogs_only: bool,
-    pub liked_only: bool,
-    pub rekordbox_only: bool,
-    pub limit: Option<u32>,
-    pub offset: Option<u32>,
+/// The tags a file had before the tag writer first changed them. `fields`
+/// maps each overwritten field to its original value, `None` when it was
+/// not set. `artwork_added` records that the writer added the front cover.
+#[derive(Debug, Clone)]
+pub struct TagBackup {
+    pub track_id: String,
+    pub location: String,
+    pub fields: HashMap<String, Option<String>>,
+    pub artwork_added: bool,
 }
 
-pub struct LibraryStore {
-    connection: Connection,
+#[derive(Debug, Clone, Copy)]
+pub enum AcoustidMatchStatus {
+    Success,
+    NoMatch,
+    Error,
 }
 
-impl LibraryStore {
-    pub fn initialize(app: &AppHandle) -> Result<Self, LibraryError> {
-        let mut database_path = resolve_database_path(app)?;
-        fs::create_dir_all(&database_path)?;
-        database_path.push("library.sqlite3");
-
-        let connection = Connection::open(database_path)?;
-        let mut store = Self { connection };
-        store.apply_migrations()?;
-        store.enable_foreign_keys()?;
-        Ok(store)
+impl AcoustidMatchStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AcoustidMatchStatus::Success => "success",
+            AcoustidMatchStatus::NoMatch => "no_match",
+            AcoustidMatchStatus::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AcoustidMatchRecord {
+    pub track_id: String,
+    pub status: AcoustidMatchStatus,
+    pub acoustid_id: Option<String>,
+    pub recording_id: Option<String>,
+    pub score: Option<f32>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum AudioAnalysisStatus {
+    Success,
+    Error,
+}
+
+impl AudioAnalysisStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AudioAnalysisStatus::Success => "success",
+            AudioAnalysisStatus::Error => "error",
+        }
+    }
+}
+
+/// BPM, key and loudness measured for the local file of a track. `location`
+/// is the file that was analyzed, so a relinked asset is analyzed again.
+#[derive(Debug, Clone)]
+pub struct AudioAnalysisRecord {
+    pub track_id: String,
+    pub location: String,
+    pub status: AudioAnalysisStatus,
+    pub bpm: Option<f32>,
+    pub musical_key: Option<String>,
+    pub camelot: Option<String>,
+    pub key_confidence: Option<f32>,
+    pub integrated_lufs: Option<f64>,
+    pub loudness_range: Option<f64>,
+    pub true_peak_dbtp: Option<f64>,
+    /// ReplayGain 2.0 track gain in dB.
+    pub replaygain_gain: Option<f64>,
+    /// ReplayGain track peak as a linear amplitude.
+    pub replaygain_peak: Option<f64>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AssetHealthStatus {
+    Healthy,
+    /// Decodes cleanly but looks converted up from a low-bitrate source.
+    Suspect,
+    /// Empty, truncated or failing to decode.
+    Damaged,
+}
+
+impl AssetHealthStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AssetHealthStatus::Healthy => "healthy",
+            AssetHealthStatus::Suspect => "suspect",
+            AssetHealthStatus::Damaged => "damaged",
+        }
+    }
+
+    fn from_str(value: &str) -> Self {
+        match value {
+            "healthy" => AssetHealthStatus::Healthy,
+            "suspect" => AssetHealthStatus::Suspect,
+            _ => AssetHealthStatus::Damaged,
+        }
+    }
+}
+
+/// Outcome of the integrity check of a track's local file. `location` and
+/// `checksum` identify the file that was checked, so a relinked or rewritten
+/// file is checked again.
+/// Number of rows in a library table.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableRowCount {
+    pub table: String,
+    pub rows: u64,
+}
+
+/// Number of rows of `table` in each `status`, e.g. how many Discogs
+/// lookups failed or how many scanned files wait for confirmation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusCount {
+    pub table: String,
+    pub status: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetHealthRecord {
+    pub track_id: String,
+    pub location: String,
+    #[serde(skip)]
+    pub checksum: Option<String>,
+    pub status: AssetHealthStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codec: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bit_depth: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channels: Option<u32>,
+    /// Average bitrate over the whole file, tags included.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bitrate_kbps: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<u64>,
+    pub decode_errors: u32,
+    pub truncated: bool,
+    pub zero_length: bool,
+    /// Highest frequency that still carries audio, in Hz.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spectral_cutoff_hz: Option<f32>,
+    pub suspected_upsample: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Set on records read back from the library.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checked_at: Option<String>,
+}
+
+/// The local file of a track and its Rekordbox cues, for the waveform view.
+#[derive(Debug, Clone)]
+pub struct WaveformSource {
+    pub location: String,
+    pub cues: Vec<RekordboxCue>,
+}
+
+/// The local file of a track and the cues each DJ library has for it, for
+/// converting them between the libraries.
+#[derive(Debug, Clone)]
+pub struct TrackCues {
+    pub location: Option<String>,
+    pub sources: Vec<(DjSoftware, Vec<DjCue>)>,
+}
+
+/// A fingerprinted local asset to look up on AcoustID.
+#[derive(Debug, Clone)]
+pub struct FingerprintTarget {
+    pub track_id: String,
+    pub duration_ms: Option<i64>,
+    pub fingerprint: Vec<u32>,
+}
+
+/// Describes a single row returned by [`LibraryStore::list_library_status`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryStatusRow {
+    pub track_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    pub liked: bool,
+    pub matched: bool,
+    pub has_local_file: bool,
+    pub local_available: bool,
+    pub in_rekordbox: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discogs_status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discogs_release_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discogs_confidence: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discogs_checked_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discogs_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discogs_query: Option<String>,
+    pub discogs_candidate_count: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub musicbrainz_status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub musicbrainz_release_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub musicbrainz_confidence: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub musicbrainz_checked_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub musicbrainz_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub musicbrainz_query: Option<String>,
+    pub musicbrainz_candidate_count: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soundcloud_permalink_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soundcloud_liked_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normalized_artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normalized_title: Option<String>,
+    pub discogs_owned: bool,
+    pub discogs_owned_vinyl: bool,
+    pub discogs_wanted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bpm: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub musical_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camelot: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integrated_lufs: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loudness_range: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub true_peak_dbtp: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replaygain_gain: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health_status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health_message: Option<String>,
+}
+
+/// A paginated response produced by [`LibraryStore::list_library_status`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryStatusPage {
+    pub rows: Vec<LibraryStatusRow>,
+    pub total: u32,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+/// Filtering and pagination options for [`LibraryStore::list_library_status`].
+///
+/// * `missing_assets_only` &mdash; return tracks that do not have an available
+///   local asset entry. This includes tracks that have never been downloaded or
+///   where the asset is marked as unavailable.
+/// * `unresolved_discogs_only` &mdash; return tracks where the Discogs
+///   integration has not produced a successful match.
+/// * `liked_only` &mdash; limit results to tracks that have a SoundCloud payload
+///   containing a `likedAt` timestamp.
+/// * `rekordbox_only` &mdash; limit results to tracks that currently have a
+///   Rekordbox source entry.
+/// * `harmonic_key` &mdash; limit results to analyzed tracks whose key mixes
+///   harmonically with the given Camelot code (e.g. `8A`): the same key, its
+///   neighbours on the wheel and its relative major or minor. A value that is
+///   not a Camelot code matches no track.
+/// * `min_bpm` / `max_bpm` &mdash; limit results to analyzed tracks within the
+///   given tempo range, inclusive.
+/// * `unhealthy_only` &mdash; limit results to tracks whose local file failed
+///   its last integrity check, either damaged or suspected to be upsampled.
+/// * `limit` / `offset` &mdash; standard pagination controls applied to the
+///   ordered result set. The backend enforces sensible defaults to avoid
+///   fetching excessively large pages.
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct StatusFilter {
+    pub missing_assets_only: bool,
+    pub unresolved_discogs_only: bool,
+    pub liked_only: bool,
+    pub rekordbox_only: bool,
+    pub harmonic_key: Option<String>,
+    pub min_bpm: Option<f32>,
+    pub max_bpm: Option<f32>,
+    pub unhealthy_only: bool,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+pub struct LibraryStore {
+    connection: Connection,
+}
+
+impl LibraryStore {
+    pub fn initialize(app: &AppHandle) -> Result<Self, LibraryError> {
+        let mut database_path = resolve_database_path(app)?;
+        database_path.push(DATABASE_FILE_NAME);
+        Self::open(database_path)
+    }
+
+    /// Opens the library database at `path`, creating it and its parent
+    /// directory when missing, and brings the schema up to date.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, LibraryError> {
+        let path = path.as_ref();
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Opens an empty, fully migrated library that lives only as long as the
+    /// returned store.
+    pub fn open_in_memory() -> Result<Self, LibraryError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self, LibraryError> {
+        let mut store = Self { connection };
+        store.apply_migrations()?;
+        store.enable_foreign_keys()?;
+        Ok(store)
     }
 
     fn enable_foreign_keys(&self) -> Result<(), LibraryError> {
@@ -353,12 +1005,64 @@ impl LibraryStore {
             );
 
             CREATE TABLE IF NOT EXISTS rekordbox_mappings (
-                rekordbox_id TEXT PRIMARY KEY,
+                source_id TEXT NOT NULL DEFAULT 'default',
+                rekordbox_id TEXT NOT NULL,
                 track_id TEXT NOT NULL,
                 updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+                PRIMARY KEY (source_id, rekordbox_id),
                 FOREIGN KEY(track_id) REFERENCES tracks(id) ON DELETE CASCADE
             );
 
+            CREATE TABLE IF NOT EXISTS rekordbox_syncs (
+                source_id TEXT PRIMARY KEY,
+                path TEXT NOT NULL,
+                modified_at INTEGER,
+                track_count INTEGER NOT NULL,
+                synced_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE IF NOT EXISTS traktor_sources (
+                track_id TEXT PRIMARY KEY,
+                raw_payload TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+                FOREIGN KEY(track_id) REFERENCES tracks(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS traktor_mappings (
+                source_id TEXT NOT NULL,
+                traktor_id TEXT NOT NULL,
+                track_id TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+                PRIMARY KEY (source_id, traktor_id),
+                FOREIGN KEY(track_id) REFERENCES tracks(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS serato_sources (
+                track_id TEXT PRIMARY KEY,
+                raw_payload TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+                FOREIGN KEY(track_id) REFERENCES tracks(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS serato_mappings (
+                source_id TEXT NOT NULL,
+                serato_id TEXT NOT NULL,
+                track_id TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+                PRIMARY KEY (source_id, serato_id),
+                FOREIGN KEY(track_id) REFERENCES tracks(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS dj_library_syncs (
+                software TEXT NOT NULL,
+                source_id TEXT NOT NULL,
+                path TEXT NOT NULL,
+                modified_at INTEGER,
+                track_count INTEGER NOT NULL,
+                synced_at TEXT NOT NULL DEFAULT (datetime('now')),
+                PRIMARY KEY (software, source_id)
+            );
+
             CREATE TABLE IF NOT EXISTS discogs_matches (
                 track_id TEXT PRIMARY KEY,
                 release_id TEXT,
@@ -406,37 +1110,204 @@ impl LibraryStore {
             CREATE INDEX IF NOT EXISTS musicbrainz_matches_status_idx ON musicbrainz_matches(status);
             CREATE INDEX IF NOT EXISTS musicbrainz_candidates_match_idx ON musicbrainz_candidates(match_id);
             CREATE INDEX IF NOT EXISTS musicbrainz_candidates_release_idx ON musicbrainz_candidates(release_id);
-            "#,
-        )?;
 
-        if let Err(error) = self.connection.execute(
-            "ALTER TABLE local_assets ADD COLUMN duration_ms INTEGER;",
-            [],
-        ) {
-            if !is_duplicate_column_error(&error) {
-                return Err(error.into());
-            }
-        }
+            CREATE TABLE IF NOT EXISTS store_matches (
+                track_id TEXT NOT NULL,
+                store TEXT NOT NULL,
+                status TEXT NOT NULL,
+                query TEXT,
+                search_url TEXT,
+                message TEXT,
+                checked_at TEXT NOT NULL DEFAULT (datetime('now')),
+                PRIMARY KEY (track_id, store),
+                FOREIGN KEY(track_id) REFERENCES tracks(id) ON DELETE CASCADE
+            );
 
-        if let Err(error) = self
-            .connection
-            .execute("ALTER TABLE tracks ADD COLUMN discogs_release_id TEXT;", [])
-        {
-            if !is_duplicate_column_error(&error) {
-                return Err(error.into());
-            }
-        }
+            CREATE TABLE IF NOT EXISTS store_candidates (
+                match_id TEXT NOT NULL,
+                store TEXT NOT NULL,
+                product_id TEXT,
+                score REAL,
+                price REAL,
+                currency TEXT,
+                format TEXT,
+                url TEXT,
+                raw_payload TEXT NOT NULL,
+                FOREIGN KEY(match_id, store) REFERENCES store_matches(track_id, store) ON DELETE CASCADE
+            );
 
-        if let Err(error) = self
-            .connection
-            .execute("ALTER TABLE tracks ADD COLUMN discogs_confidence REAL;", [])
-        {
-            if !is_duplicate_column_error(&error) {
-                return Err(error.into());
-            }
-        }
+            CREATE INDEX IF NOT EXISTS store_matches_status_idx ON store_matches(status);
+            CREATE INDEX IF NOT EXISTS store_candidates_match_idx ON store_candidates(match_id, store);
 
-        if let Err(error) = self.connection.execute(
+            CREATE TABLE IF NOT EXISTS match_settings (
+                provider TEXT PRIMARY KEY,
+                settings TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE IF NOT EXISTS endpoint_settings (
+                provider TEXT PRIMARY KEY,
+                settings TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE IF NOT EXISTS discogs_user_releases (
+                release_id TEXT NOT NULL,
+                list TEXT NOT NULL,
+                folder_id INTEGER,
+                instance_id INTEGER NOT NULL DEFAULT 0,
+                title TEXT,
+                artist TEXT,
+                formats TEXT NOT NULL DEFAULT '[]',
+                added_at TEXT,
+                raw_payload TEXT NOT NULL,
+                synced_at TEXT NOT NULL DEFAULT (datetime('now')),
+                PRIMARY KEY (release_id, list, instance_id)
+            );
+
+            CREATE INDEX IF NOT EXISTS discogs_user_releases_list_idx ON discogs_user_releases(list);
+
+            CREATE TABLE IF NOT EXISTS watched_folders (
+                path TEXT PRIMARY KEY,
+                added_at TEXT NOT NULL DEFAULT (datetime('now')),
+                last_scanned_at TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS local_files (
+                location TEXT PRIMARY KEY,
+                folder TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                modified_at INTEGER NOT NULL,
+                checksum TEXT,
+                duration_ms INTEGER,
+                title TEXT,
+                artist TEXT,
+                album TEXT,
+                track_id TEXT,
+                link_status TEXT NOT NULL,
+                confidence REAL,
+                scanned_at TEXT NOT NULL DEFAULT (datetime('now')),
+                FOREIGN KEY(track_id) REFERENCES tracks(id) ON DELETE SET NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS local_files_folder_idx ON local_files(folder);
+            CREATE INDEX IF NOT EXISTS local_files_checksum_idx ON local_files(checksum);
+
+            CREATE TABLE IF NOT EXISTS asset_relocations (
+                old_location TEXT PRIMARY KEY,
+                new_location TEXT NOT NULL,
+                track_id TEXT NOT NULL,
+                relocated_at TEXT NOT NULL DEFAULT (datetime('now')),
+                FOREIGN KEY(track_id) REFERENCES tracks(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS local_file_candidates (
+                location TEXT NOT NULL,
+                track_id TEXT NOT NULL,
+                score REAL NOT NULL,
+                PRIMARY KEY (location, track_id),
+                FOREIGN KEY(location) REFERENCES local_files(location) ON DELETE CASCADE,
+                FOREIGN KEY(track_id) REFERENCES tracks(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS track_aliases (
+                alias_id TEXT PRIMARY KEY,
+                track_id TEXT NOT NULL,
+                merged_at TEXT NOT NULL DEFAULT (datetime('now')),
+                FOREIGN KEY(track_id) REFERENCES tracks(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS track_aliases_track_idx ON track_aliases(track_id);
+
+            CREATE TABLE IF NOT EXISTS tag_backups (
+                track_id TEXT PRIMARY KEY,
+                location TEXT NOT NULL,
+                fields TEXT NOT NULL,
+                artwork_added INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                FOREIGN KEY(track_id) REFERENCES tracks(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS acoustid_matches (
+                track_id TEXT PRIMARY KEY,
+                status TEXT NOT NULL,
+                acoustid_id TEXT,
+                recording_id TEXT,
+                score REAL,
+                message TEXT,
+                checked_at TEXT NOT NULL DEFAULT (datetime('now')),
+                FOREIGN KEY(track_id) REFERENCES tracks(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS audio_analysis (
+                track_id TEXT PRIMARY KEY,
+                location TEXT NOT NULL,
+                status TEXT NOT NULL,
+                bpm REAL,
+                musical_key TEXT,
+                camelot TEXT,
+                key_confidence REAL,
+                message TEXT,
+                analyzed_at TEXT NOT NULL DEFAULT (datetime('now')),
+                FOREIGN KEY(track_id) REFERENCES tracks(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS audio_analysis_camelot_idx ON audio_analysis(camelot);
+
+            CREATE TABLE IF NOT EXISTS asset_health (
+                track_id TEXT PRIMARY KEY,
+                location TEXT NOT NULL,
+                checksum TEXT,
+                status TEXT NOT NULL,
+                codec TEXT,
+                sample_rate INTEGER,
+                bit_depth INTEGER,
+                channels INTEGER,
+                bitrate_kbps INTEGER,
+                duration_ms INTEGER,
+                file_size INTEGER,
+                decode_errors INTEGER NOT NULL DEFAULT 0,
+                truncated INTEGER NOT NULL DEFAULT 0,
+                zero_length INTEGER NOT NULL DEFAULT 0,
+                spectral_cutoff_hz REAL,
+                suspected_upsample INTEGER NOT NULL DEFAULT 0,
+                message TEXT,
+                checked_at TEXT NOT NULL DEFAULT (datetime('now')),
+                FOREIGN KEY(track_id) REFERENCES tracks(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS asset_health_status_idx ON asset_health(status);
+            "#,
+        )?;
+
+        if let Err(error) = self.connection.execute(
+            "ALTER TABLE local_assets ADD COLUMN duration_ms INTEGER;",
+            [],
+        ) {
+            if !is_duplicate_column_error(&error) {
+                return Err(error.into());
+            }
+        }
+
+        if let Err(error) = self
+            .connection
+            .execute("ALTER TABLE tracks ADD COLUMN discogs_release_id TEXT;", [])
+        {
+            if !is_duplicate_column_error(&error) {
+                return Err(error.into());
+            }
+        }
+
+        if let Err(error) = self
+            .connection
+            .execute("ALTER TABLE tracks ADD COLUMN discogs_confidence REAL;", [])
+        {
+            if !is_duplicate_column_error(&error) {
+                return Err(error.into());
+            }
+        }
+
+        if let Err(error) = self.connection.execute(
             "ALTER TABLE tracks ADD COLUMN musicbrainz_payload TEXT;",
             [],
         ) {
@@ -463,8 +1334,37 @@ impl LibraryStore {
             }
         }
 
+        for statement in [
+            "ALTER TABLE tracks ADD COLUMN normalized_artist TEXT;",
+            "ALTER TABLE tracks ADD COLUMN normalized_title TEXT;",
+            "ALTER TABLE tracks ADD COLUMN featured_artists TEXT;",
+            "ALTER TABLE tracks ADD COLUMN remixers TEXT;",
+            "ALTER TABLE tracks ADD COLUMN version TEXT;",
+            "ALTER TABLE local_assets ADD COLUMN file_size INTEGER;",
+            "ALTER TABLE local_assets ADD COLUMN fingerprint BLOB;",
+            "ALTER TABLE local_files ADD COLUMN fingerprint BLOB;",
+            "ALTER TABLE audio_analysis ADD COLUMN integrated_lufs REAL;",
+            "ALTER TABLE audio_analysis ADD COLUMN loudness_range REAL;",
+            "ALTER TABLE audio_analysis ADD COLUMN true_peak_dbtp REAL;",
+            "ALTER TABLE audio_analysis ADD COLUMN replaygain_gain REAL;",
+            "ALTER TABLE audio_analysis ADD COLUMN replaygain_peak REAL;",
+            "ALTER TABLE audio_analysis ADD COLUMN analysis_version INTEGER NOT NULL DEFAULT 1;",
+        ] {
+            if let Err(error) = self.connection.execute(statement, []) {
+                if !is_duplicate_column_error(&error) {
+                    return Err(error.into());
+                }
+            }
+        }
+
         self.migrate_discogs_payloads()?;
         self.migrate_musicbrainz_payloads()?;
+        self.migrate_rekordbox_mappings()?;
+        self.migrate_normalized_metadata()?;
+        self.migrate_discogs_user_releases()?;
+        self.migrate_local_asset_sizes()?;
+        self.connection
+            .execute_batch(&format!("PRAGMA user_version = {SCHEMA_VERSION};"))?;
         Ok(())
     }
 
@@ -554,14 +1454,44 @@ impl LibraryStore {
         Ok(())
     }
 
+    /// Stores a SoundCloud track and its source. Returns `false` without
+    /// touching the library when the track was merged into another one.
     pub fn sync_soundcloud_track(
         &self,
         track: &TrackRecord,
         source: &SoundcloudSourceRecord,
-    ) -> Result<(), LibraryError> {
+    ) -> Result<bool, LibraryError> {
+        if self.resolve_track_alias(&track.track_id)?.is_some() {
+            return Ok(false);
+        }
         self.upsert_track(track)?;
         self.link_soundcloud_source(source)?;
-        Ok(())
+        let normalized = normalize_track(track.artist.as_deref(), track.title.as_deref());
+        self.record_normalized_metadata(&track.track_id, &normalized)?;
+        Ok(true)
+    }
+
+    /// Returns the track `track_id` was merged into, if any.
+    pub fn resolve_track_alias(&self, track_id: &str) -> Result<Option<String>, LibraryError> {
+        let canonical = self
+            .connection
+            .query_row(
+                "SELECT track_id FROM track_aliases WHERE alias_id = :alias_id;",
+                rusqlite::named_params! { ":alias_id": track_id },
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        Ok(canonical)
+    }
+
+    /// Stores the artist/title parsed from a raw SoundCloud upload next to the
+    /// raw `title`/`artist` columns.
+    pub fn record_normalized_metadata(
+        &self,
+        track_id: &str,
+        normalized: &NormalizedTrack,
+    ) -> Result<(), LibraryError> {
+        write_normalized_metadata(&self.connection, track_id, normalized)
     }
 
     pub fn record_discogs_match(
@@ -654,6 +1584,298 @@ impl LibraryStore {
         Ok(result)
     }
 
+    pub fn list_store_links(&self, track_id: &str) -> Result<Vec<StoreLinkRecord>, LibraryError> {
+        let mut candidate_statement = self.connection.prepare(
+            r#"
+            SELECT match_id, store, product_id, score, price, currency, format, url, raw_payload
+            FROM store_candidates
+            WHERE match_id = :match_id
+            ORDER BY score DESC;
+            "#,
+        )?;
+
+        let mut candidate_rows =
+            candidate_statement.query(rusqlite::named_params! { ":match_id": track_id })?;
+        let mut candidates: HashMap<String, Vec<StoreCandidateRecord>> = HashMap::new();
+
+        while let Some(row) = candidate_rows.next()? {
+            let store: String = row.get(1)?;
+            let score: Option<f64> = row.get(3)?;
+            let raw_payload: String = row.get(8)?;
+            let raw_payload: Value = serde_json::from_str(&raw_payload)?;
+
+            candidates
+                .entry(store.clone())
+                .or_default()
+                .push(StoreCandidateRecord {
+                    match_id: row.get(0)?,
+                    store,
+                    product_id: row.get(2)?,
+                    score: score.map(|value| value as f32),
+                    price: row.get(4)?,
+                    currency: row.get(5)?,
+                    format: row.get(6)?,
+                    url: row.get(7)?,
+                    raw_payload,
+                });
+        }
+
+        let mut statement = self.connection.prepare(
+            r#"
+            SELECT track_id, store, status, query, search_url, message, checked_at
+            FROM store_matches
+            WHERE track_id = :track_id
+            ORDER BY store ASC;
+            "#,
+        )?;
+
+        let mut rows = statement.query(rusqlite::named_params! { ":track_id": track_id })?;
+        let mut result = Vec::new();
+
+        while let Some(row) = rows.next()? {
+            let store: String = row.get(1)?;
+            result.push(StoreLinkRecord {
+                track_id: row.get(0)?,
+                candidates: candidates.remove(&store).unwrap_or_default(),
+                store,
+                status: row.get(2)?,
+                query: row.get(3)?,
+                search_url: row.get(4)?,
+                message: row.get(5)?,
+                checked_at: row.get(6)?,
+            });
+        }
+
+        Ok(result)
+    }
+
+    /// Lists store links for tracks that still lack an available local asset,
+    /// best-scoring purchase options first.
+    pub fn list_purchase_queue(
+        &self,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Vec<PurchaseQueueRow>, LibraryError> {
+        const DEFAULT_LIMIT: u32 = 100;
+        const MAX_LIMIT: u32 = 500;
+
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT) as i64;
+        let offset = offset.unwrap_or(0) as i64;
+
+        let mut statement = self.connection.prepare(
+            r#"
+            SELECT
+                t.id,
+                t.title,
+                t.artist,
+                sm.store,
+                sm.search_url,
+                (SELECT COUNT(*) FROM store_candidates sc
+                    WHERE sc.match_id = sm.track_id AND sc.store = sm.store) AS candidate_count,
+                best.url,
+                best.score,
+                best.price,
+                best.currency,
+                best.format
+            FROM store_matches sm
+            JOIN tracks t ON t.id = sm.track_id
+            LEFT JOIN local_assets la ON la.track_id = t.id
+            LEFT JOIN store_candidates best ON best.rowid = (
+                SELECT sc.rowid FROM store_candidates sc
+                WHERE sc.match_id = sm.track_id AND sc.store = sm.store
+                ORDER BY sc.score DESC
+                LIMIT 1
+            )
+            WHERE (la.track_id IS NULL OR la.available = 0)
+              AND sm.status != 'error'
+            ORDER BY best.score IS NULL, best.score DESC, t.id ASC, sm.store ASC
+            LIMIT :limit OFFSET :offset;
+            "#,
+        )?;
+
+        let mut rows = statement.query(rusqlite::named_params! {
+            ":limit": limit,
+            ":offset": offset,
+        })?;
+        let mut result = Vec::new();
+
+        while let Some(row) = rows.next()? {
+            let candidate_count: i64 = row.get(5)?;
+            let best_score: Option<f64> = row.get(7)?;
+            result.push(PurchaseQueueRow {
+                track_id: row.get(0)?,
+                title: row.get(1)?,
+                artist: row.get(2)?,
+                store: row.get(3)?,
+                search_url: row.get(4)?,
+                candidate_count: candidate_count.max(0) as u32,
+                best_url: row.get(6)?,
+                best_score: best_score.map(|value| value as f32),
+                best_price: row.get(8)?,
+                best_currency: row.get(9)?,
+                best_format: row.get(10)?,
+            });
+        }
+
+        Ok(result)
+    }
+
+    /// Returns the stored match settings for `provider`, falling back to the
+    /// built-in defaults when none have been saved.
+    pub fn load_match_settings(&self, provider: &str) -> Result<MatchSettings, LibraryError> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT settings FROM match_settings WHERE provider = :provider;")?;
+        let mut rows = statement.query(rusqlite::named_params! { ":provider": provider })?;
+
+        match rows.next()? {
+            Some(row) => {
+                let payload: String = row.get(0)?;
+                Ok(serde_json::from_str(&payload)?)
+            }
+            None => Ok(MatchSettings::defaults_for(provider)),
+        }
+    }
+
+    pub fn list_match_settings(&self) -> Result<Vec<MatchSettings>, LibraryError> {
+        PROVIDERS
+            .iter()
+            .map(|provider| self.load_match_settings(provider))
+            .collect()
+    }
+
+    pub fn save_match_settings(&self, settings: &MatchSettings) -> Result<(), LibraryError> {
+        let payload = serde_json::to_string(settings)?;
+        self.connection.execute(
+            r#"
+            INSERT INTO match_settings (provider, settings)
+            VALUES (:provider, :settings)
+            ON CONFLICT(provider) DO UPDATE SET
+                settings = excluded.settings,
+                updated_at = datetime('now');
+            "#,
+            rusqlite::named_params! {
+                ":provider": &settings.provider,
+                ":settings": payload,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Returns the stored endpoint overrides for `provider`, with every field
+    /// unset when none have been saved.
+    pub fn load_endpoint_settings(&self, provider: &str) -> Result<EndpointSettings, LibraryError> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT settings FROM endpoint_settings WHERE provider = :provider;")?;
+        let mut rows = statement.query(rusqlite::named_params! { ":provider": provider })?;
+
+        match rows.next()? {
+            Some(row) => {
+                let payload: String = row.get(0)?;
+                Ok(serde_json::from_str(&payload)?)
+            }
+            None => Ok(EndpointSettings::unset(provider)),
+        }
+    }
+
+    pub fn list_endpoint_settings(&self) -> Result<Vec<EndpointSettings>, LibraryError> {
+        ENDPOINT_PROVIDERS
+            .iter()
+            .map(|provider| self.load_endpoint_settings(provider))
+            .collect()
+    }
+
+    pub fn save_endpoint_settings(&self, settings: &EndpointSettings) -> Result<(), LibraryError> {
+        let payload = serde_json::to_string(settings)?;
+        self.connection.execute(
+            r#"
+            INSERT INTO endpoint_settings (provider, settings)
+            VALUES (:provider, :settings)
+            ON CONFLICT(provider) DO UPDATE SET
+                settings = excluded.settings,
+                updated_at = datetime('now');
+            "#,
+            rusqlite::named_params! {
+                ":provider": &settings.provider,
+                ":settings": payload,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Replaces the stored copy of the user's Discogs collection or wantlist
+    /// with a freshly imported one.
+    pub fn replace_discogs_list(
+        &mut self,
+        kind: DiscogsListKind,
+        entries: &[DiscogsListEntry],
+    ) -> Result<(), LibraryError> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "DELETE FROM discogs_user_releases WHERE list = :list;",
+            rusqlite::named_params! { ":list": kind.as_str() },
+        )?;
+        for entry in entries {
+            Self::persist_discogs_list_entry(&transaction, kind, entry)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    pub fn record_discogs_list_entry(
+        &mut self,
+        kind: DiscogsListKind,
+        entry: &DiscogsListEntry,
+    ) -> Result<(), LibraryError> {
+        let transaction = self.connection.transaction()?;
+        Self::persist_discogs_list_entry(&transaction, kind, entry)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Resolves the confirmed Discogs release for each of `track_ids`. Tracks
+    /// without a successful match are skipped.
+    pub fn list_discogs_release_targets(
+        &self,
+        track_ids: &[String],
+    ) -> Result<Vec<DiscogsReleaseTarget>, LibraryError> {
+        let mut statement = self.connection.prepare(&format!(
+            "{DISCOGS_TARGET_SELECT} AND dm.track_id = :track_id;"
+        ))?;
+
+        let mut result = Vec::new();
+        for track_id in track_ids {
+            let mut rows = statement.query(rusqlite::named_params! { ":track_id": track_id })?;
+            if let Some(row) = rows.next()? {
+                result.push(read_discogs_target(row)?);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Lists liked tracks whose confirmed Discogs release is neither in the
+    /// user's collection nor on their wantlist yet.
+    pub fn list_pending_wantlist_targets(&self) -> Result<Vec<DiscogsReleaseTarget>, LibraryError> {
+        let mut statement = self.connection.prepare(&format!(
+            r#"
+            {DISCOGS_TARGET_SELECT}
+              AND json_extract(ss.raw_payload, '$.likedAt') IS NOT NULL
+              AND NOT EXISTS (
+                  SELECT 1 FROM discogs_user_releases ur WHERE ur.release_id = dm.release_id
+              )
+            ORDER BY dm.track_id ASC;
+            "#
+        ))?;
+
+        let mut rows = statement.query([])?;
+        let mut result = Vec::new();
+        while let Some(row) = rows.next()? {
+            result.push(read_discogs_target(row)?);
+        }
+        Ok(result)
+    }
+
     pub fn load_soundcloud_lookup(
         &self,
         track_id: &str,
@@ -764,16 +1986,56 @@ impl LibraryStore {
         Ok(())
     }
 
-    fn persist_musicbrainz_match(
+    fn persist_discogs_list_entry(
         transaction: &rusqlite::Transaction<'_>,
-        record: &MusicbrainzMatchRecord,
-        candidates: &[MusicbrainzCandidateRecord],
+        kind: DiscogsListKind,
+        entry: &DiscogsListEntry,
     ) -> Result<(), LibraryError> {
-        transaction.execute(
-            "INSERT OR IGNORE INTO tracks (id) VALUES (:track_id);",
-            rusqlite::named_params! { ":track_id": &record.track_id },
-        )?;
-
+        let formats = serde_json::to_string(&entry.formats)?;
+        let raw_payload = serde_json::to_string(&entry.raw_payload)?;
+        transaction.execute(
+            r#"
+            INSERT INTO discogs_user_releases (
+                release_id, list, folder_id, instance_id, title, artist, formats, added_at, raw_payload
+            )
+            VALUES (
+                :release_id, :list, :folder_id, :instance_id, :title, :artist, :formats, :added_at, :raw_payload
+            )
+            ON CONFLICT(release_id, list, instance_id) DO UPDATE SET
+                folder_id = excluded.folder_id,
+                title = excluded.title,
+                artist = excluded.artist,
+                formats = excluded.formats,
+                added_at = excluded.added_at,
+                raw_payload = excluded.raw_payload,
+                synced_at = datetime('now');
+            "#,
+            rusqlite::named_params! {
+                ":release_id": &entry.release_id,
+                ":list": kind.as_str(),
+                ":folder_id": entry.folder_id,
+                // Wants have no instance; 0 keeps them unique per release.
+                ":instance_id": entry.instance_id.unwrap_or(0),
+                ":title": entry.title.as_ref(),
+                ":artist": entry.artist.as_ref(),
+                ":formats": formats,
+                ":added_at": entry.added_at.as_ref(),
+                ":raw_payload": raw_payload,
+            },
+        )?;
+        Ok(())
+    }
+
+    fn persist_musicbrainz_match(
+        transaction: &rusqlite::Transaction<'_>,
+        record: &MusicbrainzMatchRecord,
+        candidates: &[MusicbrainzCandidateRecord],
+    ) -> Result<(), LibraryError> {
+        transaction.execute(
+            "INSERT OR IGNORE INTO tracks (id) VALUES (:track_id);",
+            rusqlite::named_params! { ":track_id": &record.track_id },
+        )?;
+
         transaction.execute(
             r#"
             INSERT INTO musicbrainz_matches (track_id, release_id, confidence, status, query, message, checked_at)
@@ -836,6 +2098,146 @@ impl LibraryStore {
         Ok(())
     }
 
+    fn persist_store_match(
+        transaction: &rusqlite::Transaction<'_>,
+        record: &StoreMatchRecord,
+        candidates: &[StoreCandidateRecord],
+    ) -> Result<(), LibraryError> {
+        transaction.execute(
+            "INSERT OR IGNORE INTO tracks (id) VALUES (:track_id);",
+            rusqlite::named_params! { ":track_id": &record.track_id },
+        )?;
+
+        transaction.execute(
+            r#"
+            INSERT INTO store_matches (track_id, store, status, query, search_url, message, checked_at)
+            VALUES (:track_id, :store, :status, :query, :search_url, :message, datetime('now'))
+            ON CONFLICT(track_id, store) DO UPDATE SET
+                status = excluded.status,
+                query = excluded.query,
+                search_url = excluded.search_url,
+                message = excluded.message,
+                checked_at = excluded.checked_at;
+            "#,
+            rusqlite::named_params! {
+                ":track_id": &record.track_id,
+                ":store": &record.store,
+                ":status": record.status.as_str(),
+                ":query": record.query.as_ref(),
+                ":search_url": record.search_url.as_ref(),
+                ":message": record.message.as_ref(),
+            },
+        )?;
+
+        transaction.execute(
+            "DELETE FROM store_candidates WHERE match_id = :match_id AND store = :store;",
+            rusqlite::named_params! {
+                ":match_id": &record.track_id,
+                ":store": &record.store,
+            },
+        )?;
+
+        for candidate in candidates {
+            if candidate.match_id != record.track_id || candidate.store != record.store {
+                continue;
+            }
+
+            let raw_payload = serde_json::to_string(&candidate.raw_payload)?;
+            transaction.execute(
+                r#"
+                INSERT INTO store_candidates (match_id, store, product_id, score, price, currency, format, url, raw_payload)
+                VALUES (:match_id, :store, :product_id, :score, :price, :currency, :format, :url, :raw_payload);
+                "#,
+                rusqlite::named_params! {
+                    ":match_id": &record.track_id,
+                    ":store": &record.store,
+                    ":product_id": candidate.product_id.as_ref(),
+                    ":score": candidate.score.map(|value| value as f64),
+                    ":price": candidate.price,
+                    ":currency": candidate.currency.as_ref(),
+                    ":format": candidate.format.as_ref(),
+                    ":url": candidate.url.as_ref(),
+                    ":raw_payload": raw_payload,
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn record_store_match(
+        &mut self,
+        record: &StoreMatchRecord,
+        candidates: &[StoreCandidateRecord],
+    ) -> Result<(), LibraryError> {
+        let transaction = self.connection.transaction()?;
+        Self::persist_store_match(&transaction, record, candidates)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    pub fn record_store_links(
+        &mut self,
+        track_id: &str,
+        store: &str,
+        query: &str,
+        search_url: Option<&str>,
+        candidates: &[Value],
+    ) -> Result<(), LibraryError> {
+        let candidate_records = candidates
+            .iter()
+            .map(|candidate| StoreCandidateRecord {
+                match_id: track_id.to_string(),
+                store: store.to_string(),
+                product_id: extract_release_id(candidate),
+                score: candidate
+                    .get("score")
+                    .and_then(|value| value.as_f64())
+                    .map(|value| value as f32),
+                price: candidate.get("price").and_then(|value| value.as_f64()),
+                currency: json_string(candidate, "currency"),
+                format: json_string(candidate, "format"),
+                url: json_string(candidate, "url"),
+                raw_payload: candidate.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let record = StoreMatchRecord {
+            track_id: track_id.to_string(),
+            store: store.to_string(),
+            status: if candidate_records.is_empty() {
+                StoreMatchStatus::LinkOnly
+            } else {
+                StoreMatchStatus::Candidates
+            },
+            query: Some(query.to_string()),
+            search_url: search_url.map(|value| value.to_string()),
+            message: None,
+        };
+
+        self.record_store_match(&record, &candidate_records)
+    }
+
+    pub fn record_store_failure(
+        &mut self,
+        track_id: &str,
+        store: &str,
+        query: &str,
+        search_url: Option<&str>,
+        reason: &str,
+    ) -> Result<(), LibraryError> {
+        let record = StoreMatchRecord {
+            track_id: track_id.to_string(),
+            store: store.to_string(),
+            status: StoreMatchStatus::Error,
+            query: Some(query.to_string()),
+            search_url: search_url.map(|value| value.to_string()),
+            message: Some(reason.to_string()),
+        };
+
+        self.record_store_match(&record, &[])
+    }
+
     pub fn record_discogs_success(
         &mut self,
         track_id: &str,
@@ -974,89 +2376,1539 @@ impl LibraryStore {
             })
             .collect::<Vec<_>>();
 
-        let record = MusicbrainzMatchRecord {
-            track_id: track_id.to_string(),
-            release_id: None,
-            confidence: None,
-            status: MusicbrainzMatchStatus::Ambiguous,
-            query: Some(query.to_string()),
-            message: None,
-            checked_at: None,
+        let record = MusicbrainzMatchRecord {
+            track_id: track_id.to_string(),
+            release_id: None,
+            confidence: None,
+            status: MusicbrainzMatchStatus::Ambiguous,
+            query: Some(query.to_string()),
+            message: None,
+            checked_at: None,
+        };
+
+        self.record_musicbrainz_match(&record, &candidate_records)
+    }
+
+    pub fn record_musicbrainz_failure(
+        &mut self,
+        track_id: &str,
+        query: &str,
+        reason: &str,
+    ) -> Result<(), LibraryError> {
+        let record = MusicbrainzMatchRecord {
+            track_id: track_id.to_string(),
+            release_id: None,
+            confidence: None,
+            status: MusicbrainzMatchStatus::Error,
+            query: Some(query.to_string()),
+            message: Some(reason.to_string()),
+            checked_at: None,
+        };
+
+        self.record_musicbrainz_match(&record, &[])
+    }
+
+    pub fn record_local_asset(&self, record: &LocalAssetRecord) -> Result<(), LibraryError> {
+        self.ensure_track(&record.track_id)?;
+        self.connection.execute(
+            r#"
+            INSERT INTO local_assets (track_id, location, checksum, available, duration_ms, file_size)
+            VALUES (:track_id, :location, :checksum, :available, :duration_ms, :file_size)
+            ON CONFLICT(track_id) DO UPDATE SET
+                location = excluded.location,
+                checksum = excluded.checksum,
+                available = excluded.available,
+                duration_ms = excluded.duration_ms,
+                file_size = excluded.file_size,
+                fingerprint = CASE
+                    WHEN local_assets.location = excluded.location
+                        AND local_assets.checksum IS excluded.checksum
+                    THEN local_assets.fingerprint
+                    ELSE NULL
+                END,
+                recorded_at = datetime('now');
+            "#,
+            rusqlite::named_params! {
+                ":track_id": record.track_id,
+                ":location": record.location,
+                ":checksum": record.checksum,
+                ":available": i64::from(record.available),
+                ":duration_ms": record.duration_ms,
+                ":file_size": record.file_size,
+            },
+        )?;
+
+        if let Some(cues) = &record.rekordbox_cues {
+            let payload = serde_json::to_string(cues)?;
+            self.connection.execute(
+                r#"
+                INSERT INTO rekordbox_sources (track_id, raw_payload)
+                VALUES (:track_id, :raw_payload)
+                ON CONFLICT(track_id) DO UPDATE SET
+                    raw_payload = excluded.raw_payload,
+                    updated_at = datetime('now');
+                "#,
+                rusqlite::named_params! {
+                    ":track_id": record.track_id,
+                    ":raw_payload": payload,
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn list_watched_folders(&self) -> Result<Vec<WatchedFolder>, LibraryError> {
+        let mut statement = self.connection.prepare(
+            "SELECT path, added_at, last_scanned_at FROM watched_folders ORDER BY path ASC;",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(WatchedFolder {
+                path: row.get(0)?,
+                added_at: row.get(1)?,
+                last_scanned_at: row.get(2)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    pub fn add_watched_folder(&self, path: &str) -> Result<(), LibraryError> {
+        self.connection.execute(
+            "INSERT OR IGNORE INTO watched_folders (path) VALUES (:path);",
+            rusqlite::named_params! { ":path": path },
+        )?;
+        Ok(())
+    }
+
+    /// Stops watching `path` and forgets the files scanned from it. Local assets
+    /// that were already linked stay in place.
+    pub fn remove_watched_folder(&mut self, path: &str) -> Result<(), LibraryError> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "DELETE FROM local_files WHERE folder = :path;",
+            rusqlite::named_params! { ":path": path },
+        )?;
+        transaction.execute(
+            "DELETE FROM watched_folders WHERE path = :path;",
+            rusqlite::named_params! { ":path": path },
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    pub fn mark_folder_scanned(&self, path: &str) -> Result<(), LibraryError> {
+        self.connection.execute(
+            "UPDATE watched_folders SET last_scanned_at = datetime('now') WHERE path = :path;",
+            rusqlite::named_params! { ":path": path },
+        )?;
+        Ok(())
+    }
+
+    /// Files previously scanned from `folder` along with their link status.
+    pub fn list_scanned_files(
+        &self,
+        folder: &str,
+    ) -> Result<Vec<(ScannedFileRecord, LocalLinkStatus)>, LibraryError> {
+        let mut statement = self.connection.prepare(
+            r#"
+            SELECT location, folder, size, modified_at, checksum, duration_ms, title, artist, album,
+                   link_status, fingerprint
+            FROM local_files
+            WHERE folder = :folder;
+            "#,
+        )?;
+        let rows = statement.query_map(rusqlite::named_params! { ":folder": folder }, |row| {
+            let status: String = row.get(9)?;
+            Ok((
+                ScannedFileRecord {
+                    location: row.get(0)?,
+                    folder: row.get(1)?,
+                    size: row.get(2)?,
+                    modified_at: row.get(3)?,
+                    checksum: row.get(4)?,
+                    duration_ms: row.get(5)?,
+                    title: row.get(6)?,
+                    artist: row.get(7)?,
+                    album: row.get(8)?,
+                    fingerprint: row
+                        .get::<_, Option<Vec<u8>>>(10)?
+                        .map(|blob| fingerprint::from_blob(&blob)),
+                },
+                LocalLinkStatus::from_str(&status),
+            ))
+        })?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    /// Liked SoundCloud tracks that do not have an available local file yet.
+    pub fn list_local_link_targets(&self) -> Result<Vec<LocalLinkTarget>, LibraryError> {
+        let mut statement = self.connection.prepare(
+            r#"
+            SELECT
+                t.id,
+                COALESCE(t.normalized_artist, t.artist),
+                COALESCE(t.normalized_title, t.title),
+                COALESCE(
+                    json_extract(ss.raw_payload, '$.duration'),
+                    json_extract(ss.raw_payload, '$.full_duration')
+                )
+            FROM tracks t
+            JOIN soundcloud_sources ss ON ss.track_id = t.id
+            LEFT JOIN local_assets la ON la.track_id = t.id
+            WHERE json_extract(ss.raw_payload, '$.likedAt') IS NOT NULL
+              AND (la.track_id IS NULL OR la.available = 0)
+            ORDER BY t.id ASC;
+            "#,
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(LocalLinkTarget {
+                track_id: row.get(0)?,
+                artist: row.get(1)?,
+                title: row.get(2)?,
+                duration_ms: row.get(3)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    /// Stores a scanned file and the scanner's verdict. An automatic link only
+    /// fills in a local asset when the track has no available one, so files
+    /// from Rekordbox or the frontend are never replaced.
+    pub fn record_scanned_file(
+        &mut self,
+        file: &ScannedFileRecord,
+        outcome: &LocalLinkOutcome,
+    ) -> Result<(), LibraryError> {
+        let fingerprint = file.fingerprint.as_deref().map(fingerprint::to_blob);
+        let transaction = self.connection.transaction()?;
+
+        transaction.execute(
+            r#"
+            INSERT INTO local_files (
+                location, folder, size, modified_at, checksum, duration_ms,
+                title, artist, album, track_id, link_status, confidence, fingerprint
+            )
+            VALUES (
+                :location, :folder, :size, :modified_at, :checksum, :duration_ms,
+                :title, :artist, :album, :track_id, :link_status, :confidence, :fingerprint
+            )
+            ON CONFLICT(location) DO UPDATE SET
+                folder = excluded.folder,
+                size = excluded.size,
+                modified_at = excluded.modified_at,
+                checksum = excluded.checksum,
+                duration_ms = excluded.duration_ms,
+                title = excluded.title,
+                artist = excluded.artist,
+                album = excluded.album,
+                track_id = excluded.track_id,
+                link_status = excluded.link_status,
+                confidence = excluded.confidence,
+                fingerprint = excluded.fingerprint,
+                scanned_at = datetime('now');
+            "#,
+            rusqlite::named_params! {
+                ":location": &file.location,
+                ":folder": &file.folder,
+                ":size": file.size,
+                ":modified_at": file.modified_at,
+                ":checksum": file.checksum.as_ref(),
+                ":duration_ms": file.duration_ms,
+                ":title": file.title.as_ref(),
+                ":artist": file.artist.as_ref(),
+                ":album": file.album.as_ref(),
+                ":track_id": outcome.track_id.as_ref(),
+                ":link_status": outcome.status.as_str(),
+                ":confidence": outcome.confidence.map(|value| value as f64),
+                ":fingerprint": &fingerprint,
+            },
+        )?;
+
+        transaction.execute(
+            "DELETE FROM local_file_candidates WHERE location = :location;",
+            rusqlite::named_params! { ":location": &file.location },
+        )?;
+        for (track_id, score) in &outcome.candidates {
+            transaction.execute(
+                r#"
+                INSERT INTO local_file_candidates (location, track_id, score)
+                VALUES (:location, :track_id, :score);
+                "#,
+                rusqlite::named_params! {
+                    ":location": &file.location,
+                    ":track_id": track_id,
+                    ":score": *score as f64,
+                },
+            )?;
+        }
+
+        if let (LocalLinkStatus::Linked, Some(track_id)) = (outcome.status, &outcome.track_id) {
+            transaction.execute(
+                r#"
+                INSERT INTO local_assets (
+                    track_id, location, checksum, available, duration_ms, file_size, fingerprint
+                )
+                VALUES (:track_id, :location, :checksum, 1, :duration_ms, :file_size, :fingerprint)
+                ON CONFLICT(track_id) DO UPDATE SET
+                    location = excluded.location,
+                    checksum = excluded.checksum,
+                    available = 1,
+                    duration_ms = excluded.duration_ms,
+                    file_size = excluded.file_size,
+                    fingerprint = excluded.fingerprint,
+                    recorded_at = datetime('now')
+                WHERE local_assets.available = 0;
+                "#,
+                rusqlite::named_params! {
+                    ":track_id": track_id,
+                    ":location": &file.location,
+                    ":checksum": file.checksum.as_ref(),
+                    ":duration_ms": file.duration_ms,
+                    ":file_size": file.size,
+                    ":fingerprint": &fingerprint,
+                },
+            )?;
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    pub fn list_ambiguous_local_files(&self) -> Result<Vec<AmbiguousLocalFile>, LibraryError> {
+        let mut statement = self.connection.prepare(
+            r#"
+            SELECT lf.location, lf.title, lf.artist, lf.duration_ms, c.track_id, c.score, t.title, t.artist
+            FROM local_files lf
+            JOIN local_file_candidates c ON c.location = lf.location
+            JOIN tracks t ON t.id = c.track_id
+            WHERE lf.link_status = 'ambiguous'
+            ORDER BY lf.location ASC, c.score DESC;
+            "#,
+        )?;
+
+        let mut rows = statement.query([])?;
+        let mut result: Vec<AmbiguousLocalFile> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let location: String = row.get(0)?;
+            let candidate = LocalFileCandidate {
+                track_id: row.get(4)?,
+                score: row.get::<_, f64>(5)? as f32,
+                title: row.get(6)?,
+                artist: row.get(7)?,
+            };
+            match result.last_mut() {
+                Some(file) if file.location == location => file.candidates.push(candidate),
+                _ => result.push(AmbiguousLocalFile {
+                    location,
+                    title: row.get(1)?,
+                    artist: row.get(2)?,
+                    duration_ms: row.get(3)?,
+                    candidates: vec![candidate],
+                }),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Links a scanned file to the track the user picked, replacing any local
+    /// asset the track already had. Returns `false` when `location` was never
+    /// scanned.
+    pub fn confirm_local_link(
+        &mut self,
+        location: &str,
+        track_id: &str,
+    ) -> Result<bool, LibraryError> {
+        let transaction = self.connection.transaction()?;
+
+        let updated = transaction.execute(
+            r#"
+            UPDATE local_files
+            SET track_id = :track_id, link_status = 'confirmed', confidence = 100.0
+            WHERE location = :location;
+            "#,
+            rusqlite::named_params! { ":location": location, ":track_id": track_id },
+        )?;
+        if updated == 0 {
+            return Ok(false);
+        }
+
+        transaction.execute(
+            "DELETE FROM local_file_candidates WHERE location = :location;",
+            rusqlite::named_params! { ":location": location },
+        )?;
+        transaction.execute(
+            r#"
+            INSERT INTO local_assets (
+                track_id, location, checksum, available, duration_ms, file_size, fingerprint
+            )
+            SELECT :track_id, location, checksum, 1, duration_ms, size, fingerprint
+            FROM local_files
+            WHERE location = :location
+            ON CONFLICT(track_id) DO UPDATE SET
+                location = excluded.location,
+                checksum = excluded.checksum,
+                available = 1,
+                duration_ms = excluded.duration_ms,
+                file_size = excluded.file_size,
+                fingerprint = excluded.fingerprint,
+                recorded_at = datetime('now');
+            "#,
+            rusqlite::named_params! { ":location": location, ":track_id": track_id },
+        )?;
+
+        transaction.commit()?;
+        Ok(true)
+    }
+
+    pub fn list_local_asset_locations(&self) -> Result<Vec<LocalAssetLocation>, LibraryError> {
+        let mut statement = self.connection.prepare(
+            r#"
+            SELECT track_id, location, checksum, duration_ms, file_size, available
+            FROM local_assets
+            ORDER BY track_id ASC;
+            "#,
+        )?;
+        let rows = statement.query_map([], read_local_asset_location)?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    pub fn set_local_asset_available(
+        &self,
+        track_id: &str,
+        available: bool,
+    ) -> Result<(), LibraryError> {
+        self.connection.execute(
+            "UPDATE local_assets SET available = :available WHERE track_id = :track_id;",
+            rusqlite::named_params! {
+                ":track_id": track_id,
+                ":available": i64::from(available),
+            },
+        )?;
+        Ok(())
+    }
+
+    pub fn find_scanned_file(
+        &self,
+        location: &str,
+    ) -> Result<Option<ScannedFileRecord>, LibraryError> {
+        let record = self
+            .connection
+            .query_row(
+                r#"
+                SELECT location, folder, size, modified_at, checksum, duration_ms, title, artist, album,
+                       fingerprint
+                FROM local_files
+                WHERE location = :location;
+                "#,
+                rusqlite::named_params! { ":location": location },
+                |row| {
+                    Ok(ScannedFileRecord {
+                        location: row.get(0)?,
+                        folder: row.get(1)?,
+                        size: row.get(2)?,
+                        modified_at: row.get(3)?,
+                        checksum: row.get(4)?,
+                        duration_ms: row.get(5)?,
+                        title: row.get(6)?,
+                        artist: row.get(7)?,
+                        album: row.get(8)?,
+                        fingerprint: row
+                            .get::<_, Option<Vec<u8>>>(9)?
+                            .map(|blob| fingerprint::from_blob(&blob)),
+                    })
+                },
+            )
+            .optional()?;
+        Ok(record)
+    }
+
+    /// Points each asset at its new path and remembers the move, so a later
+    /// Rekordbox refresh does not restore the stale path. Relocations whose
+    /// asset no longer sits at `old_location` are skipped; the applied ones
+    /// are returned.
+    pub fn relocate_local_assets(
+        &mut self,
+        relocations: &[AssetRelocation],
+    ) -> Result<Vec<AssetRelocation>, LibraryError> {
+        let transaction = self.connection.transaction()?;
+        let mut applied = Vec::new();
+
+        for relocation in relocations {
+            let updated = transaction.execute(
+                r#"
+                UPDATE local_assets
+                SET location = :new_location, available = 1, recorded_at = datetime('now')
+                WHERE track_id = :track_id AND location = :old_location;
+                "#,
+                rusqlite::named_params! {
+                    ":track_id": &relocation.track_id,
+                    ":old_location": &relocation.old_location,
+                    ":new_location": &relocation.new_location,
+                },
+            )?;
+            if updated == 0 {
+                continue;
+            }
+
+            transaction.execute(
+                r#"
+                INSERT INTO asset_relocations (old_location, new_location, track_id)
+                VALUES (:old_location, :new_location, :track_id)
+                ON CONFLICT(old_location) DO UPDATE SET
+                    new_location = excluded.new_location,
+                    track_id = excluded.track_id,
+                    relocated_at = datetime('now');
+                "#,
+                rusqlite::named_params! {
+                    ":track_id": &relocation.track_id,
+                    ":old_location": &relocation.old_location,
+                    ":new_location": &relocation.new_location,
+                },
+            )?;
+            transaction.execute(
+                "DELETE FROM local_files WHERE location = :location;",
+                rusqlite::named_params! { ":location": &relocation.old_location },
+            )?;
+            applied.push(relocation.clone());
+        }
+
+        transaction.commit()?;
+        Ok(applied)
+    }
+
+    /// Every track with the fields the duplicate finder compares. Matches only
+    /// count once they succeeded.
+    pub fn list_duplicate_candidates(&self) -> Result<Vec<DuplicateTrackRecord>, LibraryError> {
+        let mut statement = self.connection.prepare(
+            r#"
+            SELECT
+                t.id,
+                COALESCE(t.normalized_title, t.title),
+                COALESCE(t.normalized_artist, t.artist),
+                t.version,
+                COALESCE(
+                    la.duration_ms,
+                    json_extract(ss.raw_payload, '$.duration'),
+                    json_extract(ss.raw_payload, '$.full_duration')
+                ),
+                la.checksum,
+                la.location,
+                COALESCE(la.available, 0),
+                CASE WHEN dm.status = 'success' THEN dm.release_id END,
+                CASE WHEN mm.status = 'success' THEN mm.release_id END,
+                ss.soundcloud_id,
+                EXISTS (SELECT 1 FROM rekordbox_mappings rm WHERE rm.track_id = t.id),
+                la.fingerprint
+            FROM tracks t
+            LEFT JOIN local_assets la ON la.track_id = t.id
+            LEFT JOIN soundcloud_sources ss ON ss.track_id = t.id
+            LEFT JOIN discogs_matches dm ON dm.track_id = t.id
+            LEFT JOIN musicbrainz_matches mm ON mm.track_id = t.id
+            ORDER BY t.id ASC;
+            "#,
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(DuplicateTrackRecord {
+                track_id: row.get(0)?,
+                title: row.get(1)?,
+                artist: row.get(2)?,
+                version: row.get(3)?,
+                duration_ms: row.get(4)?,
+                checksum: row.get(5)?,
+                location: row.get(6)?,
+                asset_available: row.get::<_, i64>(7)? != 0,
+                discogs_release_id: row.get(8)?,
+                musicbrainz_release_id: row.get(9)?,
+                soundcloud_id: row.get(10)?,
+                in_rekordbox: row.get::<_, i64>(11)? != 0,
+                fingerprint: row
+                    .get::<_, Option<Vec<u8>>>(12)?
+                    .map(|blob| fingerprint::from_blob(&blob)),
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    /// Folds each of `duplicates` into `canonical` and deletes it. Sources,
+    /// matches and assets the canonical track lacks are moved over; a
+    /// successful match replaces an unsuccessful one and an available asset
+    /// replaces a missing one. Rekordbox mappings and scanned files follow
+    /// the merge, and the removed ids are remembered so later SoundCloud
+    /// syncs do not bring them back. Returns how many tracks were merged.
+    pub fn merge_tracks(
+        &mut self,
+        canonical: &str,
+        duplicates: &[String],
+    ) -> Result<u32, LibraryError> {
+        let transaction = self.connection.transaction()?;
+        let mut merged = 0;
+
+        for duplicate in duplicates {
+            if duplicate == canonical {
+                continue;
+            }
+            let exists = transaction
+                .query_row(
+                    "SELECT 1 FROM tracks WHERE id = :track_id;",
+                    rusqlite::named_params! { ":track_id": duplicate },
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            if !exists {
+                continue;
+            }
+
+            Self::merge_track(&transaction, canonical, duplicate)?;
+            merged += 1;
+        }
+
+        transaction.commit()?;
+        Ok(merged)
+    }
+
+    fn merge_track(
+        transaction: &rusqlite::Transaction<'_>,
+        canonical: &str,
+        duplicate: &str,
+    ) -> Result<(), LibraryError> {
+        let pair = rusqlite::named_params! {
+            ":canonical": canonical,
+            ":duplicate": duplicate,
+        };
+
+        transaction.execute(
+            r#"
+            UPDATE tracks SET
+                title = COALESCE(title, (SELECT title FROM tracks WHERE id = :duplicate)),
+                artist = COALESCE(artist, (SELECT artist FROM tracks WHERE id = :duplicate)),
+                album = COALESCE(album, (SELECT album FROM tracks WHERE id = :duplicate)),
+                normalized_title = COALESCE(normalized_title, (SELECT normalized_title FROM tracks WHERE id = :duplicate)),
+                normalized_artist = COALESCE(normalized_artist, (SELECT normalized_artist FROM tracks WHERE id = :duplicate)),
+                featured_artists = COALESCE(featured_artists, (SELECT featured_artists FROM tracks WHERE id = :duplicate)),
+                remixers = COALESCE(remixers, (SELECT remixers FROM tracks WHERE id = :duplicate)),
+                version = COALESCE(version, (SELECT version FROM tracks WHERE id = :duplicate)),
+                updated_at = datetime('now')
+            WHERE id = :canonical;
+            "#,
+            pair,
+        )?;
+
+        for table in [
+            "soundcloud_sources",
+            "rekordbox_sources",
+            "traktor_sources",
+            "serato_sources",
+        ] {
+            transaction.execute(
+                &format!(
+                    r#"
+                    UPDATE {table} SET track_id = :canonical
+                    WHERE track_id = :duplicate
+                      AND NOT EXISTS (SELECT 1 FROM {table} WHERE track_id = :canonical);
+                    "#
+                ),
+                pair,
+            )?;
+        }
+
+        transaction.execute(
+            r#"
+            DELETE FROM local_assets
+            WHERE track_id = :canonical
+              AND available = 0
+              AND EXISTS (SELECT 1 FROM local_assets WHERE track_id = :duplicate AND available = 1);
+            "#,
+            pair,
+        )?;
+        transaction.execute(
+            r#"
+            UPDATE local_assets SET track_id = :canonical
+            WHERE track_id = :duplicate
+              AND NOT EXISTS (SELECT 1 FROM local_assets WHERE track_id = :canonical);
+            "#,
+            pair,
+        )?;
+
+        for (table, candidates, prefix) in [
+            ("discogs_matches", "discogs_candidates", "discogs"),
+            (
+                "musicbrainz_matches",
+                "musicbrainz_candidates",
+                "musicbrainz",
+            ),
+        ] {
+            let status = |track_id: &str| {
+                transaction
+                    .query_row(
+                        &format!("SELECT status FROM {table} WHERE track_id = :track_id;"),
+                        rusqlite::named_params! { ":track_id": track_id },
+                        |row| row.get::<_, String>(0),
+                    )
+                    .optional()
+            };
+            let replace = match (status(canonical)?, status(duplicate)?) {
+                (_, None) => false,
+                (None, Some(_)) => true,
+                (Some(existing), Some(incoming)) => existing != "success" && incoming == "success",
+            };
+            if !replace {
+                continue;
+            }
+
+            transaction.execute(
+                &format!("DELETE FROM {table} WHERE track_id = :canonical;"),
+                rusqlite::named_params! { ":canonical": canonical },
+            )?;
+            transaction.execute(
+                &format!(
+                    r#"
+                    INSERT INTO {table} (track_id, release_id, confidence, status, query, message, checked_at)
+                    SELECT :canonical, release_id, confidence, status, query, message, checked_at
+                    FROM {table}
+                    WHERE track_id = :duplicate;
+                    "#
+                ),
+                pair,
+            )?;
+            transaction.execute(
+                &format!(
+                    "UPDATE {candidates} SET match_id = :canonical WHERE match_id = :duplicate;"
+                ),
+                pair,
+            )?;
+            transaction.execute(
+                &format!(
+                    r#"
+                    UPDATE tracks SET
+                        {prefix}_payload = (SELECT {prefix}_payload FROM tracks WHERE id = :duplicate),
+                        {prefix}_release_id = (SELECT {prefix}_release_id FROM tracks WHERE id = :duplicate),
+                        {prefix}_confidence = (SELECT {prefix}_confidence FROM tracks WHERE id = :duplicate)
+                    WHERE id = :canonical;
+                    "#
+                ),
+                pair,
+            )?;
+        }
+
+        let stores = {
+            let mut statement = transaction.prepare(
+                r#"
+                SELECT store FROM store_matches d
+                WHERE d.track_id = :duplicate
+                  AND NOT EXISTS (
+                      SELECT 1 FROM store_matches c
+                      WHERE c.track_id = :canonical AND c.store = d.store
+                  );
+                "#,
+            )?;
+            let rows = statement.query_map(pair, |row| row.get::<_, String>(0))?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+        for store in stores {
+            let params = rusqlite::named_params! {
+                ":canonical": canonical,
+                ":duplicate": duplicate,
+                ":store": &store,
+            };
+            transaction.execute(
+                r#"
+                INSERT INTO store_matches (track_id, store, status, query, search_url, message, checked_at)
+                SELECT :canonical, store, status, query, search_url, message, checked_at
+                FROM store_matches
+                WHERE track_id = :duplicate AND store = :store;
+                "#,
+                params,
+            )?;
+            transaction.execute(
+                r#"
+                UPDATE store_candidates SET match_id = :canonical
+                WHERE match_id = :duplicate AND store = :store;
+                "#,
+                params,
+            )?;
+        }
+
+        for statement in [
+            "UPDATE rekordbox_mappings SET track_id = :canonical, updated_at = datetime('now') WHERE track_id = :duplicate;",
+            "UPDATE traktor_mappings SET track_id = :canonical, updated_at = datetime('now') WHERE track_id = :duplicate;",
+            "UPDATE serato_mappings SET track_id = :canonical, updated_at = datetime('now') WHERE track_id = :duplicate;",
+            "UPDATE local_files SET track_id = :canonical WHERE track_id = :duplicate;",
+            "UPDATE OR IGNORE local_file_candidates SET track_id = :canonical WHERE track_id = :duplicate;",
+            "UPDATE asset_relocations SET track_id = :canonical WHERE track_id = :duplicate;",
+            "UPDATE track_aliases SET track_id = :canonical WHERE track_id = :duplicate;",
+            "UPDATE OR IGNORE tag_backups SET track_id = :canonical WHERE track_id = :duplicate;",
+            "UPDATE OR IGNORE acoustid_matches SET track_id = :canonical WHERE track_id = :duplicate;",
+            "UPDATE OR IGNORE audio_analysis SET track_id = :canonical WHERE track_id = :duplicate;",
+            "UPDATE OR IGNORE asset_health SET track_id = :canonical WHERE track_id = :duplicate;",
+        ] {
+            transaction.execute(statement, pair)?;
+        }
+
+        transaction.execute(
+            r#"
+            INSERT INTO track_aliases (alias_id, track_id)
+            VALUES (:duplicate, :canonical)
+            ON CONFLICT(alias_id) DO UPDATE SET
+                track_id = excluded.track_id,
+                merged_at = datetime('now');
+            "#,
+            pair,
+        )?;
+        transaction.execute(
+            "DELETE FROM tracks WHERE id = :duplicate;",
+            rusqlite::named_params! { ":duplicate": duplicate },
+        )?;
+
+        Ok(())
+    }
+
+    /// Loads the available local file of `track_id` with its successful
+    /// Discogs and MusicBrainz matches. Returns `None` when the track has no
+    /// available local file.
+    pub fn load_tag_source(&self, track_id: &str) -> Result<Option<TagSourceRecord>, LibraryError> {
+        let row = self
+            .connection
+            .query_row(
+                r#"
+                SELECT
+                    t.id,
+                    la.location,
+                    COALESCE(t.normalized_artist, t.artist),
+                    COALESCE(t.normalized_title, t.title),
+                    t.version,
+                    (
+                        SELECT dc.raw_payload
+                        FROM discogs_matches dm
+                        JOIN discogs_candidates dc
+                            ON dc.match_id = dm.track_id AND dc.release_id = dm.release_id
+                        WHERE dm.track_id = t.id AND dm.status = 'success'
+                        LIMIT 1
+                    ),
+                    (
+                        SELECT mc.raw_payload
+                        FROM musicbrainz_matches mm
+                        JOIN musicbrainz_candidates mc
+                            ON mc.match_id = mm.track_id AND mc.release_id = mm.release_id
+                        WHERE mm.track_id = t.id AND mm.status = 'success'
+                        LIMIT 1
+                    ),
+                    (
+                        SELECT am.recording_id
+                        FROM acoustid_matches am
+                        WHERE am.track_id = t.id AND am.status = 'success'
+                    ),
+                    aa.replaygain_gain,
+                    aa.replaygain_peak
+                FROM tracks t
+                JOIN local_assets la ON la.track_id = t.id
+                LEFT JOIN audio_analysis aa
+                    ON aa.track_id = t.id AND aa.location = la.location AND aa.status = 'success'
+                WHERE t.id = :track_id AND la.available = 1;
+                "#,
+                rusqlite::named_params! { ":track_id": track_id },
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                        row.get::<_, Option<String>>(4)?,
+                        row.get::<_, Option<String>>(5)?,
+                        row.get::<_, Option<String>>(6)?,
+                        row.get::<_, Option<String>>(7)?,
+                        row.get::<_, Option<f64>>(8)?,
+                        row.get::<_, Option<f64>>(9)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        let Some((
+            track_id,
+            location,
+            artist,
+            title,
+            version,
+            discogs,
+            musicbrainz,
+            recording_id,
+            replaygain_gain,
+            replaygain_peak,
+        )) = row
+        else {
+            return Ok(None);
+        };
+        Ok(Some(TagSourceRecord {
+            track_id,
+            location,
+            artist,
+            title,
+            version,
+            discogs_release: discogs.as_deref().map(serde_json::from_str).transpose()?,
+            musicbrainz_release: musicbrainz
+                .as_deref()
+                .map(serde_json::from_str)
+                .transpose()?,
+            recording_id,
+            replaygain_gain,
+            replaygain_peak,
+        }))
+    }
+
+    pub fn load_tag_backup(&self, track_id: &str) -> Result<Option<TagBackup>, LibraryError> {
+        let row = self
+            .connection
+            .query_row(
+                r#"
+                SELECT track_id, location, fields, artwork_added
+                FROM tag_backups
+                WHERE track_id = :track_id;
+                "#,
+                rusqlite::named_params! { ":track_id": track_id },
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, i64>(3)? != 0,
+                    ))
+                },
+            )
+            .optional()?;
+
+        let Some((track_id, location, fields, artwork_added)) = row else {
+            return Ok(None);
+        };
+        Ok(Some(TagBackup {
+            track_id,
+            location,
+            fields: serde_json::from_str(&fields)?,
+            artwork_added,
+        }))
+    }
+
+    pub fn save_tag_backup(&self, backup: &TagBackup) -> Result<(), LibraryError> {
+        let fields = serde_json::to_string(&backup.fields)?;
+        self.connection.execute(
+            r#"
+            INSERT INTO tag_backups (track_id, location, fields, artwork_added)
+            VALUES (:track_id, :location, :fields, :artwork_added)
+            ON CONFLICT(track_id) DO UPDATE SET
+                location = excluded.location,
+                fields = excluded.fields,
+                artwork_added = excluded.artwork_added;
+            "#,
+            rusqlite::named_params! {
+                ":track_id": &backup.track_id,
+                ":location": &backup.location,
+                ":fields": fields,
+                ":artwork_added": if backup.artwork_added { 1 } else { 0 },
+            },
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_tag_backup(&self, track_id: &str) -> Result<(), LibraryError> {
+        self.connection.execute(
+            "DELETE FROM tag_backups WHERE track_id = :track_id;",
+            rusqlite::named_params! { ":track_id": track_id },
+        )?;
+        Ok(())
+    }
+
+    /// Stores the checksum and size of a file the app rewrote itself, so
+    /// relocation and later scans still recognise it.
+    pub fn record_rewritten_file(
+        &mut self,
+        track_id: &str,
+        location: &str,
+        checksum: &str,
+        size: i64,
+        modified_at: i64,
+    ) -> Result<(), LibraryError> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            r#"
+            UPDATE local_assets
+            SET checksum = :checksum, file_size = :size, recorded_at = datetime('now')
+            WHERE track_id = :track_id AND location = :location;
+            "#,
+            rusqlite::named_params! {
+                ":track_id": track_id,
+                ":location": location,
+                ":checksum": checksum,
+                ":size": size,
+            },
+        )?;
+        transaction.execute(
+            r#"
+            UPDATE local_files
+            SET checksum = :checksum, size = :size, modified_at = :modified_at
+            WHERE location = :location;
+            "#,
+            rusqlite::named_params! {
+                ":location": location,
+                ":checksum": checksum,
+                ":size": size,
+                ":modified_at": modified_at,
+            },
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Available local assets that have not been fingerprinted yet, such as
+    /// files imported before fingerprints were computed.
+    pub fn list_unfingerprinted_assets(&self) -> Result<Vec<LocalAssetLocation>, LibraryError> {
+        let mut statement = self.connection.prepare(
+            r#"
+            SELECT track_id, location, checksum, duration_ms, file_size, available
+            FROM local_assets
+            WHERE available = 1 AND fingerprint IS NULL
+            ORDER BY track_id ASC;
+            "#,
+        )?;
+        let rows = statement.query_map([], read_local_asset_location)?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
+    /// Stores the fingerprint of the asset and of the scanned file at
+    /// `location`. Nothing is stored when the asset moved in the meantime.
+    pub fn record_asset_fingerprint(
+        &mut self,
+        track_id: &str,
+        location: &str,
+        fingerprint: &[u32],
+    ) -> Result<bool, LibraryError> {
+        let blob = fingerprint::to_blob(fingerprint);
+        let transaction = self.connection.transaction()?;
+        let updated = transaction.execute(
+            r#"
+            UPDATE local_assets SET fingerprint = :fingerprint
+            WHERE track_id = :track_id AND location = :location;
+            "#,
+            rusqlite::named_params! {
+                ":track_id": track_id,
+                ":location": location,
+                ":fingerprint": &blob,
+            },
+        )?;
+        transaction.execute(
+            "UPDATE local_files SET fingerprint = :fingerprint WHERE location = :location;",
+            rusqlite::named_params! { ":location": location, ":fingerprint": &blob },
+        )?;
+        transaction.commit()?;
+        Ok(updated > 0)
+    }
+
+    /// Fingerprinted tracks to look up on AcoustID. Without `track_ids`,
+    /// only tracks that were never looked up or whose lookup failed are
+    /// returned.
+    pub fn list_acoustid_targets(
+        &self,
+        track_ids: &[String],
+    ) -> Result<Vec<FingerprintTarget>, LibraryError> {
+        let read = |row: &rusqlite::Row<'_>| {
+            Ok(FingerprintTarget {
+                track_id: row.get(0)?,
+                duration_ms: row.get(1)?,
+                fingerprint: fingerprint::from_blob(&row.get::<_, Vec<u8>>(2)?),
+            })
+        };
+
+        let mut result = Vec::new();
+        if track_ids.is_empty() {
+            let mut statement = self.connection.prepare(
+                r#"
+                SELECT la.track_id, la.duration_ms, la.fingerprint
+                FROM local_assets la
+                LEFT JOIN acoustid_matches am ON am.track_id = la.track_id
+                WHERE la.fingerprint IS NOT NULL
+                  AND (am.track_id IS NULL OR am.status = 'error')
+                ORDER BY la.track_id ASC;
+                "#,
+            )?;
+            for row in statement.query_map([], read)? {
+                result.push(row?);
+            }
+            return Ok(result);
+        }
+
+        let mut statement = self.connection.prepare(
+            r#"
+            SELECT track_id, duration_ms, fingerprint
+            FROM local_assets
+            WHERE track_id = :track_id AND fingerprint IS NOT NULL;
+            "#,
+        )?;
+        for track_id in track_ids {
+            let target = statement
+                .query_row(rusqlite::named_params! { ":track_id": track_id }, read)
+                .optional()?;
+            result.extend(target);
+        }
+        Ok(result)
+    }
+
+    pub fn record_acoustid_match(&self, record: &AcoustidMatchRecord) -> Result<(), LibraryError> {
+        self.connection.execute(
+            r#"
+            INSERT INTO acoustid_matches (track_id, status, acoustid_id, recording_id, score, message)
+            VALUES (:track_id, :status, :acoustid_id, :recording_id, :score, :message)
+            ON CONFLICT(track_id) DO UPDATE SET
+                status = excluded.status,
+                acoustid_id = excluded.acoustid_id,
+                recording_id = excluded.recording_id,
+                score = excluded.score,
+                message = excluded.message,
+                checked_at = datetime('now');
+            "#,
+            rusqlite::named_params! {
+                ":track_id": &record.track_id,
+                ":status": record.status.as_str(),
+                ":acoustid_id": record.acoustid_id.as_ref(),
+                ":recording_id": record.recording_id.as_ref(),
+                ":score": record.score.map(|value| value as f64),
+                ":message": record.message.as_ref(),
+            },
+        )?;
+        Ok(())
+    }
+
+    pub fn load_waveform_source(
+        &self,
+        track_id: &str,
+    ) -> Result<Option<WaveformSource>, LibraryError> {
+        let row = self
+            .connection
+            .query_row(
+                r#"
+                SELECT la.location, rb.raw_payload
+                FROM local_assets la
+                LEFT JOIN rekordbox_sources rb ON rb.track_id = la.track_id
+                WHERE la.track_id = :track_id AND la.available = 1;
+                "#,
+                rusqlite::named_params! { ":track_id": track_id },
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+            )
+            .optional()?;
+        let Some((location, payload)) = row else {
+            return Ok(None);
         };
 
-        self.record_musicbrainz_match(&record, &candidate_records)
+        // Imports store the cues inside the track payload; the frontend may
+        // store the bare list.
+        let payload: Value = payload
+            .as_deref()
+            .map(serde_json::from_str)
+            .transpose()?
+            .unwrap_or(Value::Null);
+        let cues = match payload.get("cues").unwrap_or(&payload) {
+            Value::Array(cues) => cues
+                .iter()
+                .filter_map(|cue| serde_json::from_value(cue.clone()).ok())
+                .collect(),
+            _ => Vec::new(),
+        };
+        Ok(Some(WaveformSource { location, cues }))
     }
 
-    pub fn record_musicbrainz_failure(
-        &mut self,
-        track_id: &str,
-        query: &str,
-        reason: &str,
-    ) -> Result<(), LibraryError> {
-        let record = MusicbrainzMatchRecord {
-            track_id: track_id.to_string(),
-            release_id: None,
-            confidence: None,
-            status: MusicbrainzMatchStatus::Error,
-            query: Some(query.to_string()),
-            message: Some(reason.to_string()),
-            checked_at: None,
+    /// The cues imported for `track_id` from each DJ library, or `None` when
+    /// the track does not exist.
+    pub fn load_track_cues(&self, track_id: &str) -> Result<Option<TrackCues>, LibraryError> {
+        let location = self
+            .connection
+            .query_row(
+                r#"
+                SELECT la.location
+                FROM tracks t
+                LEFT JOIN local_assets la ON la.track_id = t.id
+                WHERE t.id = :track_id;
+                "#,
+                rusqlite::named_params! { ":track_id": track_id },
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?;
+        let Some(location) = location else {
+            return Ok(None);
         };
 
-        self.record_musicbrainz_match(&record, &[])
+        let mut sources = Vec::new();
+        for software in DjSoftware::ALL {
+            let payload: Option<String> = self
+                .connection
+                .query_row(
+                    &format!(
+                        "SELECT raw_payload FROM {} WHERE track_id = :track_id;",
+                        software.sources_table()
+                    ),
+                    rusqlite::named_params! { ":track_id": track_id },
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let Some(payload) = payload else {
+                continue;
+            };
+            let payload: Value = serde_json::from_str(&payload)?;
+            let cues = match payload.get("cues").unwrap_or(&payload) {
+                Value::Array(cues) => cues.as_slice(),
+                _ => &[],
+            };
+            let cues = cues
+                .iter()
+                .filter_map(|cue| match software {
+                    DjSoftware::Rekordbox => serde_json::from_value::<RekordboxCue>(cue.clone())
+                        .ok()
+                        .map(|cue| DjCue::from(&cue)),
+                    DjSoftware::Traktor | DjSoftware::Serato => {
+                        serde_json::from_value(cue.clone()).ok()
+                    }
+                })
+                .collect();
+            sources.push((software, cues));
+        }
+        Ok(Some(TrackCues { location, sources }))
     }
 
-    pub fn record_local_asset(&self, record: &LocalAssetRecord) -> Result<(), LibraryError> {
-        self.ensure_track(&record.track_id)?;
+    /// Local assets to analyze. Without `track_ids`, only available assets
+    /// that were never analyzed at their current location, or were analyzed
+    /// before the analysis measured everything it does now, are returned.
+    pub fn list_analysis_targets(
+        &self,
+        track_ids: &[String],
+    ) -> Result<Vec<LocalAssetLocation>, LibraryError> {
+        self.list_asset_targets(
+            track_ids,
+            r#"
+            SELECT la.track_id, la.location, la.checksum, la.duration_ms, la.file_size, la.available
+            FROM local_assets la
+            LEFT JOIN audio_analysis aa ON aa.track_id = la.track_id
+            WHERE la.available = 1
+              AND (
+                aa.track_id IS NULL
+                OR aa.location != la.location
+                OR (aa.status = 'success' AND aa.analysis_version < :version)
+              )
+            ORDER BY la.track_id ASC;
+            "#,
+            rusqlite::named_params! { ":version": analysis::ANALYSIS_VERSION },
+        )
+    }
+
+    pub fn record_audio_analysis(&self, record: &AudioAnalysisRecord) -> Result<(), LibraryError> {
+        self.connection.execute(
+            r#"
+            INSERT INTO audio_analysis (
+                track_id, location, status, bpm, musical_key, camelot, key_confidence,
+                integrated_lufs, loudness_range, true_peak_dbtp, replaygain_gain, replaygain_peak,
+                message, analysis_version
+            )
+            VALUES (
+                :track_id, :location, :status, :bpm, :musical_key, :camelot, :key_confidence,
+                :integrated_lufs, :loudness_range, :true_peak_dbtp, :replaygain_gain,
+                :replaygain_peak, :message, :version
+            )
+            ON CONFLICT(track_id) DO UPDATE SET
+                location = excluded.location,
+                status = excluded.status,
+                bpm = excluded.bpm,
+                musical_key = excluded.musical_key,
+                camelot = excluded.camelot,
+                key_confidence = excluded.key_confidence,
+                integrated_lufs = excluded.integrated_lufs,
+                loudness_range = excluded.loudness_range,
+                true_peak_dbtp = excluded.true_peak_dbtp,
+                replaygain_gain = excluded.replaygain_gain,
+                replaygain_peak = excluded.replaygain_peak,
+                message = excluded.message,
+                analysis_version = excluded.analysis_version,
+                analyzed_at = datetime('now');
+            "#,
+            rusqlite::named_params! {
+                ":track_id": &record.track_id,
+                ":location": &record.location,
+                ":status": record.status.as_str(),
+                ":bpm": record.bpm.map(|value| value as f64),
+                ":musical_key": record.musical_key.as_ref(),
+                ":camelot": record.camelot.as_ref(),
+                ":key_confidence": record.key_confidence.map(|value| value as f64),
+                ":integrated_lufs": record.integrated_lufs,
+                ":loudness_range": record.loudness_range,
+                ":true_peak_dbtp": record.true_peak_dbtp,
+                ":replaygain_gain": record.replaygain_gain,
+                ":replaygain_peak": record.replaygain_peak,
+                ":message": record.message.as_ref(),
+                ":version": analysis::ANALYSIS_VERSION,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Local assets to check for damage. Without `track_ids`, only available
+    /// assets that were never checked as they are now, at their current
+    /// location and with their current checksum, are returned.
+    pub fn list_integrity_targets(
+        &self,
+        track_ids: &[String],
+    ) -> Result<Vec<LocalAssetLocation>, LibraryError> {
+        self.list_asset_targets(
+            track_ids,
+            r#"
+            SELECT la.track_id, la.location, la.checksum, la.duration_ms, la.file_size, la.available
+            FROM local_assets la
+            LEFT JOIN asset_health ah ON ah.track_id = la.track_id
+            WHERE la.available = 1
+              AND (
+                ah.track_id IS NULL
+                OR ah.location != la.location
+                OR ah.checksum IS NOT la.checksum
+              )
+            ORDER BY la.track_id ASC;
+            "#,
+            [],
+        )
+    }
+
+    /// Available local assets for a background job: those of `track_ids`,
+    /// or every asset `pending` selects when no tracks are given.
+    fn list_asset_targets(
+        &self,
+        track_ids: &[String],
+        pending: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<LocalAssetLocation>, LibraryError> {
+        let mut result = Vec::new();
+        if track_ids.is_empty() {
+            let mut statement = self.connection.prepare(pending)?;
+            for row in statement.query_map(params, read_local_asset_location)? {
+                result.push(row?);
+            }
+            return Ok(result);
+        }
+
+        let mut statement = self.connection.prepare(
+            r#"
+            SELECT track_id, location, checksum, duration_ms, file_size, available
+            FROM local_assets
+            WHERE track_id = :track_id AND available = 1;
+            "#,
+        )?;
+        for track_id in track_ids {
+            let target = statement
+                .query_row(
+                    rusqlite::named_params! { ":track_id": track_id },
+                    read_local_asset_location,
+                )
+                .optional()?;
+            result.extend(target);
+        }
+        Ok(result)
+    }
+
+    pub fn record_asset_health(&self, record: &AssetHealthRecord) -> Result<(), LibraryError> {
         self.connection.execute(
             r#"
-            INSERT INTO local_assets (track_id, location, checksum, available, duration_ms)
-            VALUES (:track_id, :location, :checksum, :available, :duration_ms)
+            INSERT INTO asset_health (
+                track_id, location, checksum, status, codec, sample_rate, bit_depth, channels,
+                bitrate_kbps, duration_ms, file_size, decode_errors, truncated, zero_length,
+                spectral_cutoff_hz, suspected_upsample, message
+            )
+            VALUES (
+                :track_id, :location, :checksum, :status, :codec, :sample_rate, :bit_depth,
+                :channels, :bitrate_kbps, :duration_ms, :file_size, :decode_errors, :truncated,
+                :zero_length, :spectral_cutoff_hz, :suspected_upsample, :message
+            )
             ON CONFLICT(track_id) DO UPDATE SET
                 location = excluded.location,
                 checksum = excluded.checksum,
-                available = excluded.available,
+                status = excluded.status,
+                codec = excluded.codec,
+                sample_rate = excluded.sample_rate,
+                bit_depth = excluded.bit_depth,
+                channels = excluded.channels,
+                bitrate_kbps = excluded.bitrate_kbps,
                 duration_ms = excluded.duration_ms,
-                recorded_at = datetime('now');
+                file_size = excluded.file_size,
+                decode_errors = excluded.decode_errors,
+                truncated = excluded.truncated,
+                zero_length = excluded.zero_length,
+                spectral_cutoff_hz = excluded.spectral_cutoff_hz,
+                suspected_upsample = excluded.suspected_upsample,
+                message = excluded.message,
+                checked_at = datetime('now');
             "#,
             rusqlite::named_params! {
-                ":track_id": record.track_id,
-                ":location": record.location,
-                ":checksum": record.checksum,
-                ":available": i64::from(record.available),
-                ":duration_ms": record.duration_ms,
+                ":track_id": &record.track_id,
+                ":location": &record.location,
+                ":checksum": record.checksum.as_ref(),
+                ":status": record.status.as_str(),
+                ":codec": record.codec.as_ref(),
+                ":sample_rate": record.sample_rate,
+                ":bit_depth": record.bit_depth,
+                ":channels": record.channels,
+                ":bitrate_kbps": record.bitrate_kbps,
+                ":duration_ms": record.duration_ms.map(|value| value as i64),
+                ":file_size": record.file_size.map(|value| value as i64),
+                ":decode_errors": record.decode_errors,
+                ":truncated": record.truncated as i64,
+                ":zero_length": record.zero_length as i64,
+                ":spectral_cutoff_hz": record.spectral_cutoff_hz.map(|value| value as f64),
+                ":suspected_upsample": record.suspected_upsample as i64,
+                ":message": record.message.as_ref(),
             },
         )?;
+        Ok(())
+    }
 
-        if let Some(cues) = &record.rekordbox_cues {
-            let payload = serde_json::to_string(cues)?;
-            self.connection.execute(
+    /// The schema version the database was last migrated to.
+    pub fn schema_version(&self) -> Result<u32, LibraryError> {
+        Ok(self
+            .connection
+            .query_row("PRAGMA user_version;", [], |row| row.get(0))?)
+    }
+
+    pub fn table_row_counts(&self) -> Result<Vec<TableRowCount>, LibraryError> {
+        let tables = {
+            let mut statement = self.connection.prepare(
                 r#"
-                INSERT INTO rekordbox_sources (track_id, raw_payload)
-                VALUES (:track_id, :raw_payload)
-                ON CONFLICT(track_id) DO UPDATE SET
-                    raw_payload = excluded.raw_payload,
-                    updated_at = datetime('now');
+                SELECT name FROM sqlite_master
+                WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+                ORDER BY name ASC;
                 "#,
-                rusqlite::named_params! {
-                    ":track_id": record.track_id,
-                    ":raw_payload": payload,
-                },
             )?;
+            let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+
+        let mut result = Vec::with_capacity(tables.len());
+        for table in tables {
+            let rows: i64 = self.connection.query_row(
+                &format!("SELECT COUNT(*) FROM \"{table}\";"),
+                [],
+                |row| row.get(0),
+            )?;
+            result.push(TableRowCount {
+                table,
+                rows: rows as u64,
+            });
         }
+        Ok(result)
+    }
 
-        Ok(())
+    /// Counts the rows of every table that tracks a lookup, analysis or
+    /// check by their status.
+    pub fn status_counts(&self) -> Result<Vec<StatusCount>, LibraryError> {
+        let mut result = Vec::new();
+        for (table, column) in STATUS_COLUMNS {
+            let mut statement = self.connection.prepare(&format!(
+                "SELECT {column}, COUNT(*) FROM {table} GROUP BY {column} ORDER BY {column} ASC;"
+            ))?;
+            let rows = statement.query_map([], |row| {
+                Ok(StatusCount {
+                    table: table.to_string(),
+                    status: row.get(0)?,
+                    count: row.get::<_, i64>(1)? as u64,
+                })
+            })?;
+            for row in rows {
+                result.push(row?);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Local files whose last integrity check found them damaged or suspect,
+    /// damaged ones first.
+    pub fn list_unhealthy_assets(&self) -> Result<Vec<AssetHealthRecord>, LibraryError> {
+        let mut statement = self.connection.prepare(
+            r#"
+            SELECT track_id, location, checksum, status, codec, sample_rate, bit_depth, channels,
+                   bitrate_kbps, duration_ms, file_size, decode_errors, truncated, zero_length,
+                   spectral_cutoff_hz, suspected_upsample, message, checked_at
+            FROM asset_health
+            WHERE status IN ('damaged', 'suspect')
+            ORDER BY CASE status WHEN 'damaged' THEN 0 ELSE 1 END, track_id ASC;
+            "#,
+        )?;
+
+        let rows = statement.query_map([], |row| {
+            Ok(AssetHealthRecord {
+                track_id: row.get(0)?,
+                location: row.get(1)?,
+                checksum: row.get(2)?,
+                status: AssetHealthStatus::from_str(&row.get::<_, String>(3)?),
+                codec: row.get(4)?,
+                sample_rate: row.get(5)?,
+                bit_depth: row.get(6)?,
+                channels: row.get(7)?,
+                bitrate_kbps: row.get(8)?,
+                duration_ms: row.get::<_, Option<i64>>(9)?.map(|value| value as u64),
+                file_size: row.get::<_, Option<i64>>(10)?.map(|value| value as u64),
+                decode_errors: row.get(11)?,
+                truncated: row.get::<_, i64>(12)? != 0,
+                zero_length: row.get::<_, i64>(13)? != 0,
+                spectral_cutoff_hz: row.get::<_, Option<f64>>(14)?.map(|value| value as f32),
+                suspected_upsample: row.get::<_, i64>(15)? != 0,
+                message: row.get(16)?,
+                checked_at: row.get(17)?,
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
     }
 
-    pub fn sync_rekordbox_tracks(&mut self, tracks: &[RekordboxTrack]) -> Result<(), LibraryError> {
+    /// Replaces the tracks of `sync.source_id` with `tracks`. Tracks of the
+    /// source that are not in `tracks` any more are removed.
+    pub fn sync_rekordbox_tracks(
+        &mut self,
+        sync: &RekordboxSync,
+        tracks: &[RekordboxTrack],
+    ) -> Result<(), LibraryError> {
         let transaction = self.connection.transaction()?;
 
         {
-            let mut existing_statement =
-                transaction.prepare("SELECT rekordbox_id, track_id FROM rekordbox_mappings")?;
-            let existing_rows = existing_statement.query_map([], |row| {
-                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
-            })?;
+            let mut existing_statement = transaction.prepare(
+                "SELECT rekordbox_id, track_id FROM rekordbox_mappings WHERE source_id = :source_id",
+            )?;
+            let existing_rows = existing_statement.query_map(
+                rusqlite::named_params! { ":source_id": &sync.source_id },
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )?;
 
             let mut existing_map: HashMap<String, String> = HashMap::new();
             for row in existing_rows {
@@ -1069,7 +3921,7 @@ impl LibraryStore {
                 let track_id = existing_map
                     .get(&track.rekordbox_id)
                     .cloned()
-                    .unwrap_or_else(|| format!("rekordbox:{}", track.rekordbox_id));
+                    .unwrap_or_else(|| rekordbox::track_id(&sync.source_id, &track.rekordbox_id));
                 existing_map.insert(track.rekordbox_id.clone(), track_id.clone());
                 stale_map.remove(&track.rekordbox_id);
 
@@ -1093,33 +3945,61 @@ impl LibraryStore {
 
                 transaction.execute(
                     r#"
-                    INSERT INTO rekordbox_mappings (rekordbox_id, track_id)
-                    VALUES (:rekordbox_id, :track_id)
-                    ON CONFLICT(rekordbox_id) DO UPDATE SET
+                    INSERT INTO rekordbox_mappings (source_id, rekordbox_id, track_id)
+                    VALUES (:source_id, :rekordbox_id, :track_id)
+                    ON CONFLICT(source_id, rekordbox_id) DO UPDATE SET
                         track_id = excluded.track_id,
                         updated_at = datetime('now');
                     "#,
                     rusqlite::named_params! {
+                        ":source_id": &sync.source_id,
                         ":rekordbox_id": &track.rekordbox_id,
                         ":track_id": &track_id,
                     },
                 )?;
 
-                if let Some(location) = track.location.clone().or_else(|| {
+                let location = track.location.clone().or_else(|| {
                     track
                         .normalized_path
                         .as_ref()
                         .map(|path| path.to_string_lossy().into_owned())
-                }) {
+                });
+                // Files relocated outside Rekordbox keep their new path until
+                // Rekordbox itself finds the file again.
+                let relocated = match (&location, track.available) {
+                    (Some(location), false) => transaction
+                        .query_row(
+                            "SELECT 1 FROM asset_relocations WHERE old_location = :location;",
+                            rusqlite::named_params! { ":location": location },
+                            |_| Ok(()),
+                        )
+                        .optional()?
+                        .is_some(),
+                    _ => false,
+                };
+                if let Some(location) = location.filter(|_| !relocated) {
                     transaction.execute(
                         r#"
-                        INSERT INTO local_assets (track_id, location, checksum, available, duration_ms)
-                        VALUES (:track_id, :location, :checksum, :available, :duration_ms)
+                        INSERT INTO local_assets (
+                            track_id, location, checksum, available, duration_ms, file_size, fingerprint
+                        )
+                        VALUES (
+                            :track_id, :location, :checksum, :available, :duration_ms, :file_size,
+                            :fingerprint
+                        )
                         ON CONFLICT(track_id) DO UPDATE SET
                             location = excluded.location,
                             checksum = excluded.checksum,
                             available = excluded.available,
                             duration_ms = excluded.duration_ms,
+                            file_size = excluded.file_size,
+                            fingerprint = COALESCE(
+                                excluded.fingerprint,
+                                CASE
+                                    WHEN local_assets.location = excluded.location
+                                    THEN local_assets.fingerprint
+                                END
+                            ),
                             recorded_at = datetime('now');
                         "#,
                         rusqlite::named_params! {
@@ -1128,52 +4008,385 @@ impl LibraryStore {
                             ":checksum": track.checksum.as_ref(),
                             ":available": if track.available { 1 } else { 0 },
                             ":duration_ms": track.duration_ms.map(|value| value as i64),
+                            ":file_size": track.file_size.map(|value| value as i64),
+                            ":fingerprint": track.fingerprint.as_deref().map(fingerprint::to_blob),
                         },
                     )?;
                 }
 
                 let raw_payload = serde_json::to_string(&json!({
+                    "source_id": sync.source_id,
                     "rekordbox_id": track.rekordbox_id,
                     "track_reference": track.track_reference,
                     "track_id": track_id,
                     "title": track.title,
                     "artist": track.artist,
                     "album": track.album,
+                    "key": track.key,
+                    "bpm": track.bpm,
                     "location": track.location,
                     "normalized_path": track.normalized_path,
                     "checksum": track.checksum,
                     "duration_ms": track.duration_ms,
                     "available": track.available,
+                    "playlists": track.playlists,
                     "cues": track.cues,
                 }))?;
 
                 transaction.execute(
-                    r#"
-                    INSERT INTO rekordbox_sources (track_id, raw_payload)
-                    VALUES (:track_id, :raw_payload)
-                    ON CONFLICT(track_id) DO UPDATE SET
-                        raw_payload = excluded.raw_payload,
-                        updated_at = datetime('now');
-                    "#,
+                    r#"
+                    INSERT INTO rekordbox_sources (track_id, raw_payload)
+                    VALUES (:track_id, :raw_payload)
+                    ON CONFLICT(track_id) DO UPDATE SET
+                        raw_payload = excluded.raw_payload,
+                        updated_at = datetime('now');
+                    "#,
+                    rusqlite::named_params! {
+                        ":track_id": &track_id,
+                        ":raw_payload": raw_payload,
+                    },
+                )?;
+            }
+
+            for (rekordbox_id, track_id) in stale_map {
+                remove_dj_mapping(
+                    &transaction,
+                    DjSoftware::Rekordbox,
+                    &sync.source_id,
+                    &rekordbox_id,
+                    &track_id,
+                )?;
+            }
+        }
+
+        transaction.execute(
+            r#"
+            INSERT INTO rekordbox_syncs (source_id, path, modified_at, track_count)
+            VALUES (:source_id, :path, :modified_at, :track_count)
+            ON CONFLICT(source_id) DO UPDATE SET
+                path = excluded.path,
+                modified_at = excluded.modified_at,
+                track_count = excluded.track_count,
+                synced_at = datetime('now');
+            "#,
+            rusqlite::named_params! {
+                ":source_id": &sync.source_id,
+                ":path": &sync.path,
+                ":modified_at": sync.modified_at,
+                ":track_count": tracks.len() as i64,
+            },
+        )?;
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Removes every track imported from `source_id` that no other source
+    /// or SoundCloud refers to, and forgets the source.
+    pub fn remove_rekordbox_source(&mut self, source_id: &str) -> Result<(), LibraryError> {
+        let transaction = self.connection.transaction()?;
+
+        {
+            let mut statement = transaction.prepare(
+                "SELECT rekordbox_id, track_id FROM rekordbox_mappings WHERE source_id = :source_id",
+            )?;
+            let mappings = statement
+                .query_map(rusqlite::named_params! { ":source_id": source_id }, |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            for (rekordbox_id, track_id) in mappings {
+                remove_dj_mapping(
+                    &transaction,
+                    DjSoftware::Rekordbox,
+                    source_id,
+                    &rekordbox_id,
+                    &track_id,
+                )?;
+            }
+        }
+        transaction.execute(
+            "DELETE FROM rekordbox_syncs WHERE source_id = :source_id;",
+            rusqlite::named_params! { ":source_id": source_id },
+        )?;
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    pub fn rekordbox_sync(
+        &self,
+        source_id: &str,
+    ) -> Result<Option<RekordboxSyncRecord>, LibraryError> {
+        Ok(self
+            .connection
+            .query_row(
+                r#"
+                SELECT source_id, path, modified_at, track_count, synced_at
+                FROM rekordbox_syncs
+                WHERE source_id = :source_id;
+                "#,
+                rusqlite::named_params! { ":source_id": source_id },
+                read_rekordbox_sync,
+            )
+            .optional()?)
+    }
+
+    pub fn list_rekordbox_syncs(&self) -> Result<Vec<RekordboxSyncRecord>, LibraryError> {
+        let mut statement = self.connection.prepare(
+            r#"
+            SELECT source_id, path, modified_at, track_count, synced_at
+            FROM rekordbox_syncs
+            ORDER BY source_id ASC;
+            "#,
+        )?;
+        let rows = statement.query_map([], read_rekordbox_sync)?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Replaces the tracks of a Traktor or Serato source with `tracks`.
+    /// Entries whose file is already in the library, from another source,
+    /// are mapped to that track instead of adding a second one.
+    pub fn sync_dj_tracks(
+        &mut self,
+        sync: &DjLibrarySync,
+        tracks: &[DjTrack],
+    ) -> Result<(), LibraryError> {
+        let software = sync.software;
+        let mappings = software.mappings_table();
+        let id_column = software.id_column();
+        let transaction = self.connection.transaction()?;
+
+        {
+            let mut existing_statement = transaction.prepare(&format!(
+                "SELECT {id_column}, track_id FROM {mappings} WHERE source_id = :source_id"
+            ))?;
+            let existing_rows = existing_statement.query_map(
+                rusqlite::named_params! { ":source_id": &sync.source_id },
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )?;
+
+            let mut existing_map: HashMap<String, String> = HashMap::new();
+            for row in existing_rows {
+                let (external_id, track_id) = row?;
+                existing_map.insert(external_id, track_id);
+            }
+            let mut stale_map = existing_map.clone();
+
+            for track in tracks {
+                let own_id = dj_library::track_id(software, &sync.source_id, &track.external_id);
+                let track_id = match existing_map.get(&track.external_id) {
+                    Some(track_id) => track_id.clone(),
+                    None => match &track.location {
+                        Some(location) => transaction
+                            .query_row(
+                                "SELECT track_id FROM local_assets WHERE location = :location LIMIT 1;",
+                                rusqlite::named_params! { ":location": location },
+                                |row| row.get::<_, String>(0),
+                            )
+                            .optional()?
+                            .unwrap_or_else(|| own_id.clone()),
+                        None => own_id.clone(),
+                    },
+                };
+                existing_map.insert(track.external_id.clone(), track_id.clone());
+                stale_map.remove(&track.external_id);
+
+                // Tracks linked to another source keep the metadata and file
+                // that source gave them.
+                if track_id == own_id {
+                    transaction.execute(
+                        r#"
+                        INSERT INTO tracks (id, title, artist, album)
+                        VALUES (:id, :title, :artist, :album)
+                        ON CONFLICT(id) DO UPDATE SET
+                            title = excluded.title,
+                            artist = excluded.artist,
+                            album = excluded.album,
+                            updated_at = datetime('now');
+                        "#,
+                        rusqlite::named_params! {
+                            ":id": &track_id,
+                            ":title": track.title.as_ref(),
+                            ":artist": track.artist.as_ref(),
+                            ":album": track.album.as_ref(),
+                        },
+                    )?;
+                }
+
+                transaction.execute(
+                    &format!(
+                        r#"
+                        INSERT INTO {mappings} (source_id, {id_column}, track_id)
+                        VALUES (:source_id, :external_id, :track_id)
+                        ON CONFLICT(source_id, {id_column}) DO UPDATE SET
+                            track_id = excluded.track_id,
+                            updated_at = datetime('now');
+                        "#
+                    ),
+                    rusqlite::named_params! {
+                        ":source_id": &sync.source_id,
+                        ":external_id": &track.external_id,
+                        ":track_id": &track_id,
+                    },
+                )?;
+
+                // As for Rekordbox, files relocated in the library keep their
+                // new path until the DJ software finds the file again.
+                let relocated = match (&track.location, track.available) {
+                    (Some(location), false) => transaction
+                        .query_row(
+                            "SELECT 1 FROM asset_relocations WHERE old_location = :location;",
+                            rusqlite::named_params! { ":location": location },
+                            |_| Ok(()),
+                        )
+                        .optional()?
+                        .is_some(),
+                    _ => false,
+                };
+                if let Some(location) = track
+                    .location
+                    .as_ref()
+                    .filter(|_| track_id == own_id && !relocated)
+                {
+                    transaction.execute(
+                        r#"
+                        INSERT INTO local_assets (
+                            track_id, location, checksum, available, duration_ms, file_size, fingerprint
+                        )
+                        VALUES (
+                            :track_id, :location, :checksum, :available, :duration_ms, :file_size,
+                            :fingerprint
+                        )
+                        ON CONFLICT(track_id) DO UPDATE SET
+                            location = excluded.location,
+                            checksum = excluded.checksum,
+                            available = excluded.available,
+                            duration_ms = excluded.duration_ms,
+                            file_size = excluded.file_size,
+                            fingerprint = COALESCE(
+                                excluded.fingerprint,
+                                CASE
+                                    WHEN local_assets.location = excluded.location
+                                    THEN local_assets.fingerprint
+                                END
+                            ),
+                            recorded_at = datetime('now');
+                        "#,
+                        rusqlite::named_params! {
+                            ":track_id": &track_id,
+                            ":location": location,
+                            ":checksum": track.checksum.as_ref(),
+                            ":available": if track.available { 1 } else { 0 },
+                            ":duration_ms": track.duration_ms.map(|value| value as i64),
+                            ":file_size": track.file_size.map(|value| value as i64),
+                            ":fingerprint": track.fingerprint.as_deref().map(fingerprint::to_blob),
+                        },
+                    )?;
+                }
+
+                let mut raw_payload = serde_json::to_value(track)?;
+                if let Some(payload) = raw_payload.as_object_mut() {
+                    payload.insert("source_id".to_string(), json!(sync.source_id));
+                    payload.insert("track_id".to_string(), json!(track_id));
+                }
+                transaction.execute(
+                    &format!(
+                        r#"
+                        INSERT INTO {} (track_id, raw_payload)
+                        VALUES (:track_id, :raw_payload)
+                        ON CONFLICT(track_id) DO UPDATE SET
+                            raw_payload = excluded.raw_payload,
+                            updated_at = datetime('now');
+                        "#,
+                        software.sources_table()
+                    ),
                     rusqlite::named_params! {
                         ":track_id": &track_id,
-                        ":raw_payload": raw_payload,
+                        ":raw_payload": serde_json::to_string(&raw_payload)?,
                     },
                 )?;
             }
 
-            for (_rekordbox_id, track_id) in stale_map {
-                transaction.execute(
-                    "DELETE FROM tracks WHERE id = :track_id;",
-                    rusqlite::named_params! { ":track_id": track_id },
+            for (external_id, track_id) in stale_map {
+                remove_dj_mapping(
+                    &transaction,
+                    software,
+                    &sync.source_id,
+                    &external_id,
+                    &track_id,
                 )?;
             }
         }
 
+        transaction.execute(
+            r#"
+            INSERT INTO dj_library_syncs (software, source_id, path, modified_at, track_count)
+            VALUES (:software, :source_id, :path, :modified_at, :track_count)
+            ON CONFLICT(software, source_id) DO UPDATE SET
+                path = excluded.path,
+                modified_at = excluded.modified_at,
+                track_count = excluded.track_count,
+                synced_at = datetime('now');
+            "#,
+            rusqlite::named_params! {
+                ":software": software.as_str(),
+                ":source_id": &sync.source_id,
+                ":path": &sync.path,
+                ":modified_at": sync.modified_at,
+                ":track_count": tracks.len() as i64,
+            },
+        )?;
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Removes every track imported from a Traktor or Serato source that
+    /// nothing else refers to, and forgets the source.
+    pub fn remove_dj_library_source(
+        &mut self,
+        software: DjSoftware,
+        source_id: &str,
+    ) -> Result<(), LibraryError> {
+        let transaction = self.connection.transaction()?;
+
+        {
+            let mut statement = transaction.prepare(&format!(
+                "SELECT {}, track_id FROM {} WHERE source_id = :source_id",
+                software.id_column(),
+                software.mappings_table()
+            ))?;
+            let mappings = statement
+                .query_map(rusqlite::named_params! { ":source_id": source_id }, |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            for (external_id, track_id) in mappings {
+                remove_dj_mapping(&transaction, software, source_id, &external_id, &track_id)?;
+            }
+        }
+        transaction.execute(
+            "DELETE FROM dj_library_syncs WHERE software = :software AND source_id = :source_id;",
+            rusqlite::named_params! { ":software": software.as_str(), ":source_id": source_id },
+        )?;
+
         transaction.commit()?;
         Ok(())
     }
 
+    pub fn list_dj_library_syncs(&self) -> Result<Vec<DjLibrarySyncRecord>, LibraryError> {
+        let mut statement = self.connection.prepare(
+            r#"
+            SELECT software, source_id, path, modified_at, track_count, synced_at
+            FROM dj_library_syncs
+            ORDER BY software ASC, source_id ASC;
+            "#,
+        )?;
+        let rows = statement.query_map([], read_dj_library_sync)?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
     pub fn list_missing_assets(&self) -> Result<Vec<String>, LibraryError> {
         let mut statement = self.connection.prepare(
             r#"
@@ -1193,6 +4406,41 @@ impl LibraryStore {
         Ok(result)
     }
 
+    /// Tracks that have never been looked up on `provider`, plus, when
+    /// `include_unresolved` is set, those whose last lookup did not succeed.
+    pub fn list_lookup_candidates(
+        &self,
+        provider: &str,
+        include_unresolved: bool,
+    ) -> Result<Vec<String>, LibraryError> {
+        let table = match provider {
+            DISCOGS_PROVIDER => "discogs_matches",
+            MUSICBRAINZ_PROVIDER => "musicbrainz_matches",
+            _ => return Ok(Vec::new()),
+        };
+        let unresolved = if include_unresolved {
+            " OR m.status != 'success' OR m.release_id IS NULL"
+        } else {
+            ""
+        };
+        let mut statement = self.connection.prepare(&format!(
+            r#"
+            SELECT t.id
+            FROM tracks t
+            LEFT JOIN {table} m ON m.track_id = t.id
+            WHERE m.track_id IS NULL{unresolved}
+            ORDER BY t.id ASC;
+            "#
+        ))?;
+
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+        let mut result = Vec::new();
+        for row in rows {
+            result.push(row?);
+        }
+        Ok(result)
+    }
+
     pub fn list_library_status(
         &self,
         filter: &StatusFilter,
@@ -1206,19 +4454,44 @@ impl LibraryStore {
 
         let liked_predicate = "json_extract(ss.raw_payload, '$.likedAt') IS NOT NULL";
 
-        let mut conditions: Vec<&'static str> = Vec::new();
+        let mut conditions: Vec<String> = Vec::new();
+        let mut bindings: Vec<(&str, rusqlite::types::Value)> = Vec::new();
         if filter.missing_assets_only {
-            conditions.push("(la.track_id IS NULL OR la.available = 0)");
+            conditions.push("(la.track_id IS NULL OR la.available = 0)".to_string());
         }
         if filter.unresolved_discogs_only {
-            conditions
-                .push("(dm.track_id IS NULL OR dm.status != 'success' OR dm.release_id IS NULL)");
+            conditions.push(
+                "(dm.track_id IS NULL OR dm.status != 'success' OR dm.release_id IS NULL)"
+                    .to_string(),
+            );
         }
         if filter.liked_only {
-            conditions.push(liked_predicate);
+            conditions.push(liked_predicate.to_string());
         }
         if filter.rekordbox_only {
-            conditions.push("rb.track_id IS NOT NULL");
+            conditions.push("rb.track_id IS NOT NULL".to_string());
+        }
+        if let Some(code) = &filter.harmonic_key {
+            match analysis::compatible_camelot_keys(code) {
+                Some(codes) => {
+                    conditions.push(
+                        "aa.camelot IN (SELECT value FROM json_each(:camelot_codes))".to_string(),
+                    );
+                    bindings.push((":camelot_codes", json!(codes).to_string().into()));
+                }
+                None => conditions.push("0".to_string()),
+            }
+        }
+        if let Some(min_bpm) = filter.min_bpm.filter(|value| value.is_finite()) {
+            conditions.push("aa.bpm >= :min_bpm".to_string());
+            bindings.push((":min_bpm", f64::from(min_bpm).into()));
+        }
+        if let Some(max_bpm) = filter.max_bpm.filter(|value| value.is_finite()) {
+            conditions.push("aa.bpm <= :max_bpm".to_string());
+            bindings.push((":max_bpm", f64::from(max_bpm).into()));
+        }
+        if filter.unhealthy_only {
+            conditions.push("ah.status IN ('damaged', 'suspect')".to_string());
         }
 
         let where_clause = if conditions.is_empty() {
@@ -1234,6 +4507,8 @@ impl LibraryStore {
             LEFT JOIN musicbrainz_matches mb ON mb.track_id = t.id
             LEFT JOIN local_assets la ON la.track_id = t.id
             LEFT JOIN rekordbox_sources rb ON rb.track_id = t.id
+            LEFT JOIN audio_analysis aa ON aa.track_id = t.id AND aa.status = 'success'
+            LEFT JOIN asset_health ah ON ah.track_id = t.id
             LEFT JOIN (
                 SELECT match_id, COUNT(*) AS candidate_count
                 FROM discogs_candidates
@@ -1246,9 +4521,14 @@ impl LibraryStore {
             ) mbc ON mbc.match_id = t.id
         "#;
 
+        let mut params: Vec<(&str, &dyn rusqlite::ToSql)> = bindings
+            .iter()
+            .map(|(name, value)| (*name, value as &dyn rusqlite::ToSql))
+            .collect();
+
         let count_query = format!("SELECT COUNT(*) {from_clause} {where_clause};");
         let mut count_statement = self.connection.prepare(&count_query)?;
-        let total: i64 = count_statement.query_row([], |row| row.get(0))?;
+        let total: i64 = count_statement.query_row(params.as_slice(), |row| row.get(0))?;
 
         let select_query = format!(
             r#"
@@ -1278,7 +4558,32 @@ impl LibraryStore {
                 COALESCE(mbc.candidate_count, 0) AS musicbrainz_candidate_count,
                 ss.permalink_url,
                 json_extract(ss.raw_payload, '$.likedAt') AS liked_at,
-                la.location
+                la.location,
+                t.normalized_artist,
+                t.normalized_title,
+                CASE WHEN EXISTS (
+                    SELECT 1 FROM discogs_user_releases ur
+                    WHERE ur.release_id = dm.release_id AND ur.list = 'collection'
+                ) THEN 1 ELSE 0 END AS discogs_owned,
+                CASE WHEN EXISTS (
+                    SELECT 1 FROM discogs_user_releases ur, json_each(ur.formats)
+                    WHERE ur.release_id = dm.release_id
+                      AND ur.list = 'collection'
+                      AND json_each.value = 'Vinyl'
+                ) THEN 1 ELSE 0 END AS discogs_owned_vinyl,
+                CASE WHEN EXISTS (
+                    SELECT 1 FROM discogs_user_releases ur
+                    WHERE ur.release_id = dm.release_id AND ur.list = 'wantlist'
+                ) THEN 1 ELSE 0 END AS discogs_wanted,
+                aa.bpm,
+                aa.musical_key,
+                aa.camelot,
+                aa.integrated_lufs,
+                aa.loudness_range,
+                aa.true_peak_dbtp,
+                aa.replaygain_gain,
+                ah.status,
+                ah.message
             {from_clause}
             {where_clause}
             ORDER BY t.updated_at DESC, t.id ASC
@@ -1286,11 +4591,10 @@ impl LibraryStore {
             "#
         );
 
+        params.push((":limit", &limit));
+        params.push((":offset", &offset_value));
         let mut statement = self.connection.prepare(&select_query)?;
-        let mut rows = statement.query(rusqlite::named_params! {
-            ":limit": limit,
-            ":offset": offset_value,
-        })?;
+        let mut rows = statement.query(params.as_slice())?;
 
         let mut result_rows = Vec::new();
         while let Some(row) = rows.next()? {
@@ -1334,6 +4638,20 @@ impl LibraryStore {
                 soundcloud_permalink_url: row.get(23)?,
                 soundcloud_liked_at: row.get(24)?,
                 local_location: row.get(25)?,
+                normalized_artist: row.get(26)?,
+                normalized_title: row.get(27)?,
+                discogs_owned: row.get::<_, i64>(28)? != 0,
+                discogs_owned_vinyl: row.get::<_, i64>(29)? != 0,
+                discogs_wanted: row.get::<_, i64>(30)? != 0,
+                bpm: row.get::<_, Option<f64>>(31)?.map(|value| value as f32),
+                musical_key: row.get(32)?,
+                camelot: row.get(33)?,
+                integrated_lufs: row.get::<_, Option<f64>>(34)?.map(|value| value as f32),
+                loudness_range: row.get::<_, Option<f64>>(35)?.map(|value| value as f32),
+                true_peak_dbtp: row.get::<_, Option<f64>>(36)?.map(|value| value as f32),
+                replaygain_gain: row.get::<_, Option<f64>>(37)?.map(|value| value as f32),
+                health_status: row.get(38)?,
+                health_message: row.get(39)?,
             });
         }
 
@@ -1347,6 +4665,154 @@ impl LibraryStore {
         })
     }
 
+    /// Moves the mappings of schema 1, keyed by the Rekordbox ID alone, into
+    /// the default source.
+    fn migrate_rekordbox_mappings(&mut self) -> Result<(), LibraryError> {
+        let namespaced: bool = self.connection.query_row(
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info('rekordbox_mappings') WHERE name = 'source_id');",
+            [],
+            |row| row.get(0),
+        )?;
+        if namespaced {
+            return Ok(());
+        }
+
+        let transaction = self.connection.transaction()?;
+        transaction.execute_batch(
+            r#"
+            CREATE TABLE rekordbox_mappings_v2 (
+                source_id TEXT NOT NULL DEFAULT 'default',
+                rekordbox_id TEXT NOT NULL,
+                track_id TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+                PRIMARY KEY (source_id, rekordbox_id),
+                FOREIGN KEY(track_id) REFERENCES tracks(id) ON DELETE CASCADE
+            );
+            INSERT INTO rekordbox_mappings_v2 (source_id, rekordbox_id, track_id, updated_at)
+            SELECT 'default', rekordbox_id, track_id, updated_at FROM rekordbox_mappings;
+            DROP TABLE rekordbox_mappings;
+            ALTER TABLE rekordbox_mappings_v2 RENAME TO rekordbox_mappings;
+            "#,
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Adds the instance id to the key of schema 2's collection and wantlist
+    /// table, which kept a single copy of each release.
+    fn migrate_discogs_user_releases(&mut self) -> Result<(), LibraryError> {
+        let keyed: bool = self.connection.query_row(
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info('discogs_user_releases') WHERE name = 'instance_id' AND pk > 0);",
+            [],
+            |row| row.get(0),
+        )?;
+        if keyed {
+            return Ok(());
+        }
+
+        let transaction = self.connection.transaction()?;
+        transaction.execute_batch(
+            r#"
+            CREATE TABLE discogs_user_releases_v3 (
+                release_id TEXT NOT NULL,
+                list TEXT NOT NULL,
+                folder_id INTEGER,
+                instance_id INTEGER NOT NULL DEFAULT 0,
+                title TEXT,
+                artist TEXT,
+                formats TEXT NOT NULL DEFAULT '[]',
+                added_at TEXT,
+                raw_payload TEXT NOT NULL,
+                synced_at TEXT NOT NULL DEFAULT (datetime('now')),
+                PRIMARY KEY (release_id, list, instance_id)
+            );
+            INSERT INTO discogs_user_releases_v3 (
+                release_id, list, folder_id, instance_id, title, artist, formats, added_at, raw_payload, synced_at
+            )
+            SELECT release_id, list, folder_id, COALESCE(instance_id, 0), title, artist, formats, added_at, raw_payload, synced_at
+            FROM discogs_user_releases;
+            DROP TABLE discogs_user_releases;
+            ALTER TABLE discogs_user_releases_v3 RENAME TO discogs_user_releases;
+            CREATE INDEX IF NOT EXISTS discogs_user_releases_list_idx ON discogs_user_releases(list);
+            "#,
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Normalizes the SoundCloud tracks stored before the normalized columns
+    /// existed. `featured_artists` is always written as a JSON array, so a
+    /// `NULL` marks a track that was never normalized.
+    fn migrate_normalized_metadata(&mut self) -> Result<(), LibraryError> {
+        let pending = {
+            let mut statement = self.connection.prepare(
+                r#"
+                SELECT t.id, t.artist, t.title
+                FROM tracks t
+                JOIN soundcloud_sources s ON s.track_id = t.id
+                WHERE t.featured_artists IS NULL;
+                "#,
+            )?;
+            let rows = statement.query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let transaction = self.connection.transaction()?;
+        for (track_id, artist, title) in &pending {
+            let normalized = normalize_track(artist.as_deref(), title.as_deref());
+            write_normalized_metadata(&transaction, track_id, &normalized)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Records the size of local files linked before sizes were stored, so
+    /// relocation can narrow its candidates by size instead of hashing every
+    /// file with the same extension. Files already missing keep no size.
+    fn migrate_local_asset_sizes(&mut self) -> Result<(), LibraryError> {
+        if self.schema_version()? >= 4 {
+            return Ok(());
+        }
+
+        let pending = {
+            let mut statement = self
+                .connection
+                .prepare("SELECT track_id, location FROM local_assets WHERE file_size IS NULL;")?;
+            let rows = statement.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+
+        let transaction = self.connection.transaction()?;
+        for (track_id, location) in &pending {
+            let Ok(metadata) = fs::metadata(location) else {
+                continue;
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+            transaction.execute(
+                "UPDATE local_assets SET file_size = :size WHERE track_id = :track_id;",
+                rusqlite::named_params! {
+                    ":track_id": track_id,
+                    ":size": metadata.len() as i64,
+                },
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
     fn migrate_discogs_payloads(&mut self) -> Result<(), LibraryError> {
         let mut transaction = self.connection.transaction()?;
 
@@ -1636,6 +5102,568 @@ fn extract_release_id(value: &Value) -> Option<String> {
     }
 }
 
+/// Selects confirmed Discogs matches together with the stored release payload;
+/// callers append further `AND` conditions.
+const DISCOGS_TARGET_SELECT: &str = r#"
+    SELECT dm.track_id, dm.release_id, t.title, dc.raw_payload
+    FROM discogs_matches dm
+    JOIN tracks t ON t.id = dm.track_id
+    LEFT JOIN soundcloud_sources ss ON ss.track_id = dm.track_id
+    LEFT JOIN discogs_candidates dc
+        ON dc.match_id = dm.track_id AND dc.release_id = dm.release_id
+    WHERE dm.status = 'success' AND dm.release_id IS NOT NULL
+"#;
+
+fn read_discogs_target(row: &rusqlite::Row<'_>) -> Result<DiscogsReleaseTarget, LibraryError> {
+    let release: Option<String> = row.get(3)?;
+    let formats = release
+        .as_deref()
+        .and_then(|payload| serde_json::from_str::<Value>(payload).ok())
+        .map(|payload| format_names(&payload))
+        .unwrap_or_default();
+
+    Ok(DiscogsReleaseTarget {
+        track_id: row.get(0)?,
+        release_id: row.get(1)?,
+        title: row.get(2)?,
+        formats,
+    })
+}
+
+/// Collects `formats[].name` from a Discogs release or `basic_information`.
+fn format_names(value: &Value) -> Vec<String> {
+    let mut names = Vec::new();
+    for format in value
+        .get("formats")
+        .and_then(|value| value.as_array())
+        .into_iter()
+        .flatten()
+    {
+        if let Some(name) = json_string(format, "name") {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+fn json_string(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(|value| value.as_str())
+        .map(|value| value.to_string())
+}
+
+/// Drops one mapping of `source_id`. A track that was merged with a
+/// SoundCloud track or is mapped from another library entry outlives the
+/// entry that disappeared.
+fn remove_dj_mapping(
+    transaction: &rusqlite::Transaction<'_>,
+    software: DjSoftware,
+    source_id: &str,
+    external_id: &str,
+    track_id: &str,
+) -> Result<(), LibraryError> {
+    let mappings = software.mappings_table();
+    let id_column = software.id_column();
+    transaction.execute(
+        &format!(
+            "DELETE FROM {mappings} WHERE source_id = :source_id AND {id_column} = :external_id;"
+        ),
+        rusqlite::named_params! { ":source_id": source_id, ":external_id": external_id },
+    )?;
+    let mapped_elsewhere: String = DjSoftware::ALL
+        .iter()
+        .map(|software| {
+            format!(
+                "\n          AND NOT EXISTS (SELECT 1 FROM {} WHERE track_id = :track_id)",
+                software.mappings_table()
+            )
+        })
+        .collect();
+    transaction.execute(
+        &format!(
+            r#"
+            DELETE FROM tracks
+            WHERE id = :track_id{mapped_elsewhere}
+              AND NOT EXISTS (SELECT 1 FROM soundcloud_sources WHERE track_id = :track_id);
+            "#
+        ),
+        rusqlite::named_params! { ":track_id": track_id },
+    )?;
+    transaction.execute(
+        &format!(
+            r#"
+            DELETE FROM {sources}
+            WHERE track_id = :track_id
+              AND NOT EXISTS (SELECT 1 FROM {mappings} WHERE track_id = :track_id);
+            "#,
+            sources = software.sources_table(),
+        ),
+        rusqlite::named_params! { ":track_id": track_id },
+    )?;
+    Ok(())
+}
+
+fn read_local_asset_location(row: &rusqlite::Row<'_>) -> rusqlite::Result<LocalAssetLocation> {
+    Ok(LocalAssetLocation {
+        track_id: row.get(0)?,
+        location: row.get(1)?,
+        checksum: row.get(2)?,
+        duration_ms: row.get(3)?,
+        file_size: row.get(4)?,
+        available: row.get::<_, i64>(5)? != 0,
+    })
+}
+
+fn read_dj_library_sync(row: &rusqlite::Row<'_>) -> rusqlite::Result<DjLibrarySyncRecord> {
+    let software: String = row.get(0)?;
+    let software = DjSoftware::parse(&software).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            0,
+            rusqlite::types::Type::Text,
+            format!("unknown DJ software {software}").into(),
+        )
+    })?;
+    Ok(DjLibrarySyncRecord {
+        software,
+        source_id: row.get(1)?,
+        path: row.get(2)?,
+        modified_at: row.get(3)?,
+        track_count: row.get::<_, i64>(4)? as u64,
+        synced_at: row.get(5)?,
+    })
+}
+
+fn write_normalized_metadata(
+    connection: &Connection,
+    track_id: &str,
+    normalized: &NormalizedTrack,
+) -> Result<(), LibraryError> {
+    let featured_artists = serde_json::to_string(&normalized.featured_artists)?;
+    let remixers = serde_json::to_string(&normalized.remixers)?;
+
+    connection.execute(
+        r#"
+        UPDATE tracks
+        SET normalized_artist = :artist,
+            normalized_title = :title,
+            featured_artists = :featured_artists,
+            remixers = :remixers,
+            version = :version
+        WHERE id = :track_id;
+        "#,
+        rusqlite::named_params! {
+            ":track_id": track_id,
+            ":artist": normalized.artist.as_ref(),
+            ":title": normalized.title.as_ref(),
+            ":featured_artists": featured_artists,
+            ":remixers": remixers,
+            ":version": normalized.version.as_ref(),
+        },
+    )?;
+
+    Ok(())
+}
+
+fn read_rekordbox_sync(row: &rusqlite::Row<'_>) -> rusqlite::Result<RekordboxSyncRecord> {
+    Ok(RekordboxSyncRecord {
+        source_id: row.get(0)?,
+        path: row.get(1)?,
+        modified_at: row.get(2)?,
+        track_count: row.get::<_, i64>(3)? as u64,
+        synced_at: row.get(4)?,
+    })
+}
+
 fn is_duplicate_column_error(error: &rusqlite::Error) -> bool {
     matches!(error, rusqlite::Error::SqliteFailure(_, _))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rekordbox::DEFAULT_SOURCE_ID;
+
+    fn list_entry(release_id: &str, instance_id: Option<i64>) -> DiscogsListEntry {
+        DiscogsListEntry {
+            release_id: release_id.to_string(),
+            folder_id: instance_id.map(|_| 1),
+            instance_id,
+            title: Some("Glue".to_string()),
+            artist: Some("Bicep".to_string()),
+            formats: vec!["Vinyl".to_string()],
+            added_at: None,
+            raw_payload: json!({ "id": release_id }),
+        }
+    }
+
+    fn count_user_releases(store: &LibraryStore, list: &str) -> i64 {
+        store
+            .connection
+            .query_row(
+                "SELECT COUNT(*) FROM discogs_user_releases WHERE list = ?1;",
+                params![list],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn collection_keeps_every_copy_of_a_release() {
+        let mut store = LibraryStore::open_in_memory().unwrap();
+        store
+            .replace_discogs_list(
+                DiscogsListKind::Collection,
+                &[list_entry("4321", Some(1)), list_entry("4321", Some(2))],
+            )
+            .unwrap();
+        store
+            .record_discogs_list_entry(DiscogsListKind::Collection, &list_entry("4321", Some(2)))
+            .unwrap();
+        assert_eq!(count_user_releases(&store, "collection"), 2);
+
+        for _ in 0..2 {
+            store
+                .record_discogs_list_entry(DiscogsListKind::Wantlist, &list_entry("4321", None))
+                .unwrap();
+        }
+        assert_eq!(count_user_releases(&store, "wantlist"), 1);
+
+        store
+            .record_discogs_success("sc-1", "Bicep Glue", &json!({ "id": 4321 }), 90.0)
+            .unwrap();
+        let rows = store
+            .list_library_status(&StatusFilter::default())
+            .unwrap()
+            .rows;
+        assert_eq!(rows.len(), 1);
+        assert!(rows[0].discogs_owned && rows[0].discogs_owned_vinyl && rows[0].discogs_wanted);
+    }
+
+    #[test]
+    fn migration_moves_rekordbox_mappings_into_the_default_source() {
+        let mut store = LibraryStore::open_in_memory().unwrap();
+        store
+            .connection
+            .execute_batch(
+                r#"
+                DROP TABLE rekordbox_mappings;
+                CREATE TABLE rekordbox_mappings (
+                    rekordbox_id TEXT PRIMARY KEY,
+                    track_id TEXT NOT NULL,
+                    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    FOREIGN KEY(track_id) REFERENCES tracks(id) ON DELETE CASCADE
+                );
+                INSERT INTO tracks (id) VALUES ('sc-1'), ('rb-2');
+                INSERT INTO rekordbox_mappings (rekordbox_id, track_id, updated_at)
+                VALUES ('7', 'sc-1', '2024-01-01 00:00:00'), ('8', 'rb-2', '2024-01-02 00:00:00');
+                "#,
+            )
+            .unwrap();
+
+        store.migrate_rekordbox_mappings().unwrap();
+
+        let rows: Vec<(String, String, String, String)> = {
+            let mut statement = store
+                .connection
+                .prepare(
+                    "SELECT source_id, rekordbox_id, track_id, updated_at FROM rekordbox_mappings ORDER BY rekordbox_id;",
+                )
+                .unwrap();
+            statement
+                .query_map([], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                })
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+        let row = |rekordbox_id: &str, track_id: &str, updated_at: &str| {
+            (
+                DEFAULT_SOURCE_ID.to_string(),
+                rekordbox_id.to_string(),
+                track_id.to_string(),
+                updated_at.to_string(),
+            )
+        };
+        assert_eq!(
+            rows,
+            vec![
+                row("7", "sc-1", "2024-01-01 00:00:00"),
+                row("8", "rb-2", "2024-01-02 00:00:00"),
+            ]
+        );
+
+        // Running it again leaves the namespaced table alone.
+        store.migrate_rekordbox_mappings().unwrap();
+        let count: i64 = store
+            .connection
+            .query_row("SELECT COUNT(*) FROM rekordbox_mappings;", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn migration_adds_the_instance_to_the_user_release_key() {
+        let mut store = LibraryStore::open_in_memory().unwrap();
+        store
+            .connection
+            .execute_batch(
+                r#"
+                DROP TABLE discogs_user_releases;
+                CREATE TABLE discogs_user_releases (
+                    release_id TEXT NOT NULL,
+                    list TEXT NOT NULL,
+                    folder_id INTEGER,
+                    instance_id INTEGER,
+                    title TEXT,
+                    artist TEXT,
+                    formats TEXT NOT NULL DEFAULT '[]',
+                    added_at TEXT,
+                    raw_payload TEXT NOT NULL,
+                    synced_at TEXT NOT NULL DEFAULT (datetime('now')),
+                    PRIMARY KEY (release_id, list)
+                );
+                INSERT INTO discogs_user_releases (release_id, list, instance_id, raw_payload)
+                VALUES ('4321', 'collection', 1, '{}'), ('4321', 'wantlist', NULL, '{}');
+                "#,
+            )
+            .unwrap();
+
+        store.migrate_discogs_user_releases().unwrap();
+
+        assert_eq!(count_user_releases(&store, "wantlist"), 1);
+        store
+            .record_discogs_list_entry(DiscogsListKind::Collection, &list_entry("4321", Some(2)))
+            .unwrap();
+        assert_eq!(count_user_releases(&store, "collection"), 2);
+    }
+
+    #[test]
+    fn migration_normalizes_tracks_stored_before_the_columns() {
+        let mut store = LibraryStore::open_in_memory().unwrap();
+        store
+            .connection
+            .execute_batch(
+                r#"
+                INSERT INTO tracks (id, title, artist)
+                VALUES ('sc-1', 'Bonobo - Kerala (Original Mix)', 'Ninja Tune'),
+                       ('rb-1', 'Kerala', 'Bonobo');
+                INSERT INTO soundcloud_sources (track_id, soundcloud_id, raw_payload)
+                VALUES ('sc-1', '1', '{}');
+                "#,
+            )
+            .unwrap();
+
+        store.migrate_normalized_metadata().unwrap();
+
+        let normalized = |id: &str| {
+            store
+                .connection
+                .query_row(
+                    "SELECT normalized_artist, normalized_title, featured_artists FROM tracks WHERE id = ?1;",
+                    params![id],
+                    |row| {
+                        Ok((
+                            row.get::<_, Option<String>>(0)?,
+                            row.get::<_, Option<String>>(1)?,
+                            row.get::<_, Option<String>>(2)?,
+                        ))
+                    },
+                )
+                .unwrap()
+        };
+        assert_eq!(
+            normalized("sc-1"),
+            (
+                Some("Bonobo".to_string()),
+                Some("Kerala".to_string()),
+                Some("[]".to_string())
+            )
+        );
+        // Only SoundCloud uploads are normalized, as on a fresh library.
+        assert_eq!(normalized("rb-1"), (None, None, None));
+    }
+
+    fn sync_upload(store: &LibraryStore, track_id: &str) -> bool {
+        let track: TrackRecord = serde_json::from_value(json!({
+            "track_id": track_id,
+            "artist": "Bicep",
+            "title": "Glue",
+        }))
+        .unwrap();
+        let source = SoundcloudSourceRecord {
+            track_id: track_id.to_string(),
+            soundcloud_id: track_id.to_string(),
+            permalink_url: None,
+            raw_payload: json!({ "id": track_id }),
+        };
+        store.sync_soundcloud_track(&track, &source).unwrap()
+    }
+
+    #[test]
+    fn merged_tracks_move_to_the_canonical_track_and_stay_merged() {
+        let mut store = LibraryStore::open_in_memory().unwrap();
+        assert!(sync_upload(&store, "sc-1"));
+        assert!(sync_upload(&store, "sc-2"));
+        store
+            .record_local_asset(&LocalAssetRecord {
+                track_id: "sc-2".to_string(),
+                location: "/music/glue.mp3".to_string(),
+                checksum: None,
+                available: true,
+                duration_ms: None,
+                file_size: None,
+                rekordbox_cues: None,
+            })
+            .unwrap();
+        store
+            .connection
+            .execute_batch(
+                r#"
+                INSERT INTO rekordbox_sources (track_id, raw_payload) VALUES ('sc-2', '{}');
+                INSERT INTO rekordbox_mappings (rekordbox_id, track_id) VALUES ('7', 'sc-2');
+                INSERT INTO track_aliases (alias_id, track_id) VALUES ('sc-0', 'sc-2');
+                "#,
+            )
+            .unwrap();
+
+        assert_eq!(
+            store.merge_tracks("sc-1", &["sc-2".to_string()]).unwrap(),
+            1
+        );
+
+        let owner = |sql: &str| {
+            store
+                .connection
+                .query_row(sql, [], |row| row.get::<_, String>(0))
+                .unwrap()
+        };
+        assert_eq!(owner("SELECT track_id FROM rekordbox_mappings;"), "sc-1");
+        assert_eq!(owner("SELECT track_id FROM rekordbox_sources;"), "sc-1");
+        assert_eq!(owner("SELECT track_id FROM local_assets;"), "sc-1");
+        assert_eq!(owner("SELECT group_concat(id) FROM tracks;"), "sc-1");
+        assert_eq!(
+            store.resolve_track_alias("sc-2").unwrap().as_deref(),
+            Some("sc-1")
+        );
+        assert_eq!(
+            store.resolve_track_alias("sc-0").unwrap().as_deref(),
+            Some("sc-1")
+        );
+
+        // Syncing the merged upload again does not bring the duplicate back.
+        assert!(!sync_upload(&store, "sc-2"));
+        assert_eq!(owner("SELECT group_concat(id) FROM tracks;"), "sc-1");
+    }
+
+    fn record_analysis(store: &LibraryStore, track_id: &str, bpm: f32, camelot: &str) {
+        store
+            .record_audio_analysis(&AudioAnalysisRecord {
+                track_id: track_id.to_string(),
+                location: format!("/music/{track_id}.wav"),
+                status: AudioAnalysisStatus::Success,
+                bpm: Some(bpm),
+                musical_key: None,
+                camelot: Some(camelot.to_string()),
+                key_confidence: None,
+                integrated_lufs: None,
+                loudness_range: None,
+                true_peak_dbtp: None,
+                replaygain_gain: None,
+                replaygain_peak: None,
+                message: None,
+            })
+            .unwrap();
+    }
+
+    fn filtered_ids(store: &LibraryStore, filter: StatusFilter) -> (u32, Vec<String>) {
+        let page = store.list_library_status(&filter).unwrap();
+        let mut ids: Vec<String> = page.rows.into_iter().map(|row| row.track_id).collect();
+        ids.sort();
+        (page.total, ids)
+    }
+
+    #[test]
+    fn status_filters_bind_the_key_and_tempo() {
+        let store = LibraryStore::open_in_memory().unwrap();
+        store
+            .connection
+            .execute_batch("INSERT INTO tracks (id) VALUES ('a'), ('b'), ('c'), ('d');")
+            .unwrap();
+        record_analysis(&store, "a", 124.0, "8A");
+        record_analysis(&store, "b", 128.0, "9A");
+        record_analysis(&store, "c", 126.0, "3B");
+        record_analysis(&store, "d", 140.0, "8B");
+
+        let (total, ids) = filtered_ids(
+            &store,
+            StatusFilter {
+                harmonic_key: Some("8a".to_string()),
+                ..StatusFilter::default()
+            },
+        );
+        assert_eq!(total, 3);
+        assert_eq!(ids, ["a", "b", "d"]);
+
+        let (_, ids) = filtered_ids(
+            &store,
+            StatusFilter {
+                harmonic_key: Some("8A".to_string()),
+                min_bpm: Some(125.0),
+                max_bpm: Some(130.0),
+                ..StatusFilter::default()
+            },
+        );
+        assert_eq!(ids, ["b"]);
+
+        let (total, ids) = filtered_ids(
+            &store,
+            StatusFilter {
+                harmonic_key: Some("8A') OR 1=1 --".to_string()),
+                ..StatusFilter::default()
+            },
+        );
+        assert_eq!(total, 0);
+        assert!(ids.is_empty());
+    }
+
+    #[test]
+    fn migration_records_the_size_of_existing_local_files() {
+        let path = std::env::temp_dir().join(format!("asset-size-{}.mp3", std::process::id()));
+        fs::write(&path, [0u8; 1234]).unwrap();
+        let mut store = LibraryStore::open_in_memory().unwrap();
+        store
+            .connection
+            .execute_batch(&format!(
+                r#"
+                INSERT INTO tracks (id) VALUES ('sc-1'), ('sc-2');
+                INSERT INTO local_assets (track_id, location)
+                VALUES ('sc-1', '{}'), ('sc-2', '/missing/track.mp3');
+                PRAGMA user_version = 3;
+                "#,
+                path.display()
+            ))
+            .unwrap();
+
+        store.migrate_local_asset_sizes().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let size = |id: &str| {
+            store
+                .connection
+                .query_row(
+                    "SELECT file_size FROM local_assets WHERE track_id = ?1;",
+                    params![id],
+                    |row| row.get::<_, Option<i64>>(0),
+                )
+                .unwrap()
+        };
+        assert_eq!(size("sc-1"), Some(1234));
+        assert_eq!(size("sc-2"), None);
+    }
+}
@@ -0,0 +1,349 @@
+//! Leveled logging for the desktop app and the library CLI. Records go to
+//! stderr and, in the app, to a log file in the app data directory that is
+//! rotated once it grows past [`MAX_LOG_BYTES`].
+
+use std::cmp::Reverse;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+pub const LOG_DIR_NAME: &str = "logs";
+/// Overrides the levels, e.g. `debug` or `info,discogs=debug,reqwest=info`.
+pub const FILTER_ENV: &str = "SOUNDCLOUD_LOG";
+
+const LOG_FILE_NAME: &str = "app.log";
+const MAX_LOG_BYTES: u64 = 2 * 1024 * 1024;
+/// The current file and the rotated `app.1.log` to `app.4.log`.
+const KEPT_LOG_FILES: usize = 5;
+const CRATE_TARGET: &str = "soundcloud_wrapper_tauri_lib";
+/// Dependencies that warn about every bad packet; the modules decoding files
+/// already report failures once per file.
+const QUIET_TARGETS: [&str; 1] = ["symphonia"];
+
+/// Installs the global logger. With `log_dir`, records are also appended to
+/// its log file. Levels come from [`FILTER_ENV`] when it is set.
+pub fn init(log_dir: Option<&Path>) -> Result<(), String> {
+    let filter = env::var(FILTER_ENV)
+        .map(|spec| LogFilter::parse(&spec))
+        .unwrap_or_default();
+    let file =
+        match log_dir {
+            Some(dir) => Some(RotatingFile::open(dir, MAX_LOG_BYTES).map_err(|error| {
+                format!("failed to open log file in {}: {error}", dir.display())
+            })?),
+            None => None,
+        };
+    let max_level = filter.max_level();
+    log::set_boxed_logger(Box::new(Logger {
+        filter,
+        file: file.map(Mutex::new),
+    }))
+    .map_err(|error| error.to_string())?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+/// The log files in `log_dir`, newest first.
+pub fn log_files(log_dir: &Path) -> Vec<PathBuf> {
+    (0..KEPT_LOG_FILES)
+        .map(|index| log_dir.join(log_file_name(index)))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Formats `time` as an RFC 3339 UTC timestamp with milliseconds.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let elapsed = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = elapsed.as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let seconds_of_day = seconds % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60,
+        elapsed.subsec_millis()
+    )
+}
+
+/// Converts days since 1970-01-01 to a proleptic Gregorian date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Records from this crate are named after their module, e.g. `discogs`.
+fn short_target(target: &str) -> &str {
+    match target.strip_prefix(CRATE_TARGET) {
+        Some("") => "app",
+        Some(rest) => rest.strip_prefix("::").unwrap_or(rest),
+        None => target,
+    }
+}
+
+fn log_file_name(index: usize) -> String {
+    if index == 0 {
+        LOG_FILE_NAME.to_string()
+    } else {
+        format!("app.{index}.log")
+    }
+}
+
+/// Levels per target. This crate logs at `info` unless told otherwise;
+/// dependencies only log warnings, or errors for [`QUIET_TARGETS`], unless
+/// they are named.
+#[derive(Debug, Clone, PartialEq)]
+struct LogFilter {
+    level: LevelFilter,
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self {
+            level: LevelFilter::Info,
+            targets: Vec::new(),
+        }
+    }
+}
+
+impl LogFilter {
+    fn parse(spec: &str) -> Self {
+        let mut filter = Self::default();
+        for directive in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            match directive.split_once('=') {
+                Some((target, level)) => match level.trim().parse() {
+                    Ok(level) => filter.targets.push((target.trim().to_string(), level)),
+                    Err(_) => eprintln!("ignoring log directive '{directive}': unknown level"),
+                },
+                None => match directive.parse() {
+                    Ok(level) => filter.level = level,
+                    Err(_) => eprintln!("ignoring log directive '{directive}': unknown level"),
+                },
+            }
+        }
+        // The longest matching target wins.
+        filter
+            .targets
+            .sort_by_key(|(target, _)| Reverse(target.len()));
+        filter
+    }
+
+    fn level_for(&self, target: &str) -> LevelFilter {
+        let short = short_target(target);
+        let named = self.targets.iter().find(|(name, _)| {
+            [target, short].iter().any(|candidate| {
+                candidate
+                    .strip_prefix(name.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+        });
+        match named {
+            Some((_, level)) => *level,
+            None if short != target => self.level,
+            None if target.starts_with("soundcloud_library") => self.level,
+            None if QUIET_TARGETS.iter().any(|quiet| target.starts_with(quiet)) => {
+                self.level.min(LevelFilter::Error)
+            }
+            None => self.level.min(LevelFilter::Warn),
+        }
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.level, Ord::max)
+    }
+}
+
+struct Logger {
+    filter: LogFilter,
+    file: Option<Mutex<RotatingFile>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "{} {:<5} {}: {}\n",
+            format_timestamp(SystemTime::now()),
+            record.level(),
+            short_target(record.target()),
+            record.args()
+        );
+        let _ = io::stderr().write_all(line.as_bytes());
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                // A failing log file must not take the app down; stderr
+                // still has the record.
+                let _ = file.write_line(&line);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                let _ = file.file.flush();
+            }
+        }
+    }
+}
+
+struct RotatingFile {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+}
+
+impl RotatingFile {
+    fn open(dir: &Path, max_bytes: u64) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(LOG_FILE_NAME))?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            file,
+            size,
+            max_bytes,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shifts `app.log` to `app.1.log` and so on, dropping the oldest file.
+    fn rotate(&mut self) -> io::Result<()> {
+        let oldest = self.dir.join(log_file_name(KEPT_LOG_FILES - 1));
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+        for index in (0..KEPT_LOG_FILES - 1).rev() {
+            let from = self.dir.join(log_file_name(index));
+            if from.exists() {
+                fs::rename(from, self.dir.join(log_file_name(index + 1)))?;
+            }
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(LOG_FILE_NAME))?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn crate_targets_are_shortened_to_their_module() {
+        assert_eq!(short_target("soundcloud_wrapper_tauri_lib"), "app");
+        assert_eq!(
+            short_target("soundcloud_wrapper_tauri_lib::library::queries"),
+            "library::queries"
+        );
+        assert_eq!(short_target("reqwest::connect"), "reqwest::connect");
+    }
+
+    #[test]
+    fn filter_applies_the_longest_matching_directive() {
+        let filter = LogFilter::parse("warn, discogs=debug, discogs::oauth=error, hyper=info");
+        assert_eq!(
+            filter.level_for("soundcloud_wrapper_tauri_lib::discogs"),
+            LevelFilter::Debug
+        );
+        assert_eq!(
+            filter.level_for("soundcloud_wrapper_tauri_lib::discogs::oauth"),
+            LevelFilter::Error
+        );
+        assert_eq!(
+            filter.level_for("soundcloud_wrapper_tauri_lib::discogsx"),
+            LevelFilter::Warn
+        );
+        assert_eq!(filter.level_for("hyper::client"), LevelFilter::Info);
+        assert_eq!(filter.max_level(), LevelFilter::Debug);
+    }
+
+    #[test]
+    fn dependencies_only_log_warnings_by_default() {
+        let filter = LogFilter::parse("debug");
+        assert_eq!(
+            filter.level_for("soundcloud_wrapper_tauri_lib::scanner"),
+            LevelFilter::Debug
+        );
+        assert_eq!(filter.level_for("soundcloud_library"), LevelFilter::Debug);
+        assert_eq!(filter.level_for("reqwest::connect"), LevelFilter::Warn);
+        assert_eq!(
+            filter.level_for("symphonia_core::probe"),
+            LevelFilter::Error
+        );
+        let filter = LogFilter::parse("symphonia_core=warn");
+        assert_eq!(filter.level_for("symphonia_core::probe"), LevelFilter::Warn);
+        assert_eq!(LogFilter::parse("bogus"), LogFilter::default());
+    }
+
+    #[test]
+    fn rotation_keeps_the_newest_files() {
+        let dir = env::temp_dir().join(format!("logging-rotation-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut file = RotatingFile::open(&dir, 16).unwrap();
+        for index in 0..8 {
+            file.write_line(&format!("line {index:02} ....\n")).unwrap();
+        }
+
+        let files = log_files(&dir);
+        assert_eq!(files.len(), KEPT_LOG_FILES);
+        assert_eq!(fs::read_to_string(&files[0]).unwrap(), "line 07 ....\n");
+        assert_eq!(fs::read_to_string(&files[4]).unwrap(), "line 03 ....\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn timestamps_are_utc() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let time = UNIX_EPOCH + Duration::from_millis(1_709_210_096_789);
+        assert_eq!(format_timestamp(time), "2024-02-29T12:34:56.789Z");
+    }
+}
@@ -0,0 +1,452 @@
+//! EBU R128 loudness measurement (ITU-R BS.1770): integrated loudness,
+//! loudness range and true peak, plus the ReplayGain 2.0 values derived from
+//! them. The meter is fed from the same decode pass as the BPM and key
+//! analysis.
+
+use symphonia::core::audio::{Channels, SignalSpec};
+
+/// ReplayGain 2.0 plays every track back at this loudness.
+const REPLAYGAIN_REFERENCE_LUFS: f64 = -18.0;
+/// Gating blocks are built from 100 ms steps: four for the 400 ms momentary
+/// blocks, thirty for the 3 s short-term blocks.
+const STEP_SECONDS: f64 = 0.1;
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const INTEGRATED_RELATIVE_GATE_LU: f64 = -10.0;
+const RANGE_RELATIVE_GATE_LU: f64 = -20.0;
+/// Weight of surround channels in the channel sum.
+const SURROUND_WEIGHT: f64 = 1.41;
+
+/// BS.1770 interpolation filter for 4x oversampling, one row per phase.
+const TRUE_PEAK_TAPS: usize = 12;
+const TRUE_PEAK_FILTER: [[f32; TRUE_PEAK_TAPS]; 4] = [
+    [
+        0.001_708_984_4,
+        0.010_986_328,
+        -0.019_653_32,
+        0.033_203_125,
+        -0.059_448_242,
+        0.137_329_1,
+        0.972_167_97,
+        -0.102_294_92,
+        0.047_607_42,
+        -0.026_611_328,
+        0.014_892_578,
+        -0.008_300_781,
+    ],
+    [
+        -0.029_174_805,
+        0.029_296_875,
+        -0.051_757_813,
+        0.089_111_33,
+        -0.166_503_9,
+        0.465_087_9,
+        0.779_785_16,
+        -0.200_317_38,
+        0.101_562_5,
+        -0.058_227_54,
+        0.033_081_055,
+        -0.018_920_898,
+    ],
+    [
+        -0.018_920_898,
+        0.033_081_055,
+        -0.058_227_54,
+        0.101_562_5,
+        -0.200_317_38,
+        0.779_785_16,
+        0.465_087_9,
+        -0.166_503_9,
+        0.089_111_33,
+        -0.051_757_813,
+        0.029_296_875,
+        -0.029_174_805,
+    ],
+    [
+        -0.008_300_781,
+        0.014_892_578,
+        -0.026_611_328,
+        0.047_607_42,
+        -0.102_294_92,
+        0.972_167_97,
+        0.137_329_1,
+        -0.059_448_242,
+        0.033_203_125,
+        -0.019_653_32,
+        0.010_986_328,
+        0.001_708_984_4,
+    ],
+];
+/// Above this rate the samples are dense enough to serve as the true peak.
+const TRUE_PEAK_MAX_RATE: u32 = 96_000;
+
+#[derive(Debug, Clone)]
+pub struct Loudness {
+    /// Integrated loudness in LUFS; `None` for silence.
+    pub integrated_lufs: Option<f64>,
+    /// Loudness range in LU; `None` when there is too little audio.
+    pub loudness_range: Option<f64>,
+    /// Highest true peak across channels, in dBTP.
+    pub true_peak_dbtp: f64,
+}
+
+impl Loudness {
+    /// ReplayGain 2.0 track gain in dB.
+    pub fn replaygain_gain(&self) -> Option<f64> {
+        self.integrated_lufs
+            .map(|integrated| REPLAYGAIN_REFERENCE_LUFS - integrated)
+    }
+
+    /// ReplayGain track peak as a linear amplitude, where 1.0 is full scale.
+    pub fn replaygain_peak(&self) -> f64 {
+        10f64.powf(self.true_peak_dbtp / 20.0)
+    }
+}
+
+/// Second order IIR section in direct form I. `a` holds the feedback
+/// coefficients normalized by `a0`.
+#[derive(Clone, Copy)]
+pub(crate) struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    pub(crate) fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self {
+            b,
+            a,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    pub(crate) fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+}
+
+/// The BS.1770 K-weighting filter: a high shelf modelling the head,
+/// followed by a high pass. The coefficients are derived for `rate` the way
+/// libebur128 does, so rates other than 48 kHz are measured correctly.
+fn k_weighting(rate: u32) -> [Biquad; 2] {
+    let rate = f64::from(rate);
+
+    let f0 = 1_681.974_450_955_533;
+    let gain = 3.999_843_853_973_347;
+    let q = 0.707_175_236_955_419_6;
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.499_666_774_154_541_6);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    let f0 = 38.135_470_876_024_44;
+    let q = 0.500_327_037_323_877_3;
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, high_pass]
+}
+
+struct ChannelState {
+    weight: f64,
+    filters: [Biquad; 2],
+    /// The last samples, newest first, for the true-peak interpolation.
+    history: [f32; TRUE_PEAK_TAPS],
+    peak: f32,
+}
+
+/// Accumulates interleaved samples and measures them once the file ends.
+pub(crate) struct LoudnessMeter {
+    channels: Vec<ChannelState>,
+    oversample: bool,
+    step_frames: usize,
+    frames_in_step: usize,
+    step_energy: f64,
+    /// Mean square of the weighted channel sum for every 100 ms step.
+    steps: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    pub(crate) fn new(spec: &SignalSpec) -> Option<Self> {
+        let count = spec.channels.count();
+        if spec.rate == 0 || count == 0 {
+            return None;
+        }
+        let channels = spec
+            .channels
+            .iter()
+            .map(|channel| ChannelState {
+                weight: channel_weight(channel),
+                filters: k_weighting(spec.rate),
+                history: [0.0; TRUE_PEAK_TAPS],
+                peak: 0.0,
+            })
+            .collect();
+        Some(Self {
+            channels,
+            oversample: spec.rate < TRUE_PEAK_MAX_RATE,
+            step_frames: ((f64::from(spec.rate) * STEP_SECONDS).round() as usize).max(1),
+            frames_in_step: 0,
+            step_energy: 0.0,
+            steps: Vec::new(),
+        })
+    }
+
+    /// Feeds interleaved samples in the channel order of the stream.
+    pub(crate) fn consume(&mut self, samples: &[f32]) {
+        let count = self.channels.len();
+        for frame in samples.chunks_exact(count) {
+            for (channel, sample) in self.channels.iter_mut().zip(frame) {
+                let weighted = channel
+                    .filters
+                    .iter_mut()
+                    .fold(f64::from(*sample), |value, filter| filter.process(value));
+                self.step_energy += channel.weight * weighted * weighted;
+
+                channel.history.copy_within(..TRUE_PEAK_TAPS - 1, 1);
+                channel.history[0] = *sample;
+                channel.peak = channel.peak.max(sample.abs());
+                if self.oversample {
+                    for phase in &TRUE_PEAK_FILTER {
+                        let interpolated: f32 = phase
+                            .iter()
+                            .zip(&channel.history)
+                            .map(|(tap, value)| tap * value)
+                            .sum();
+                        channel.peak = channel.peak.max(interpolated.abs());
+                    }
+                }
+            }
+
+            self.frames_in_step += 1;
+            if self.frames_in_step == self.step_frames {
+                self.steps.push(self.step_energy / self.step_frames as f64);
+                self.step_energy = 0.0;
+                self.frames_in_step = 0;
+            }
+        }
+    }
+
+    pub(crate) fn finish(self) -> Option<Loudness> {
+        if self.steps.is_empty() && self.frames_in_step == 0 {
+            return None;
+        }
+        let peak = self
+            .channels
+            .iter()
+            .map(|channel| channel.peak)
+            .fold(0.0f32, f32::max);
+
+        let momentary = block_energies(&self.steps, MOMENTARY_STEPS);
+        let short_term = block_energies(&self.steps, SHORT_TERM_STEPS);
+        Some(Loudness {
+            integrated_lufs: gated_mean(&momentary, INTEGRATED_RELATIVE_GATE_LU).map(lufs),
+            loudness_range: loudness_range(&short_term),
+            true_peak_dbtp: 20.0 * f64::from(peak.max(1e-9)).log10(),
+        })
+    }
+}
+
+fn channel_weight(channel: Channels) -> f64 {
+    if channel == Channels::LFE1 || channel == Channels::LFE2 {
+        0.0
+    } else if [
+        Channels::SIDE_LEFT,
+        Channels::SIDE_RIGHT,
+        Channels::REAR_LEFT,
+        Channels::REAR_RIGHT,
+    ]
+    .contains(&channel)
+    {
+        SURROUND_WEIGHT
+    } else {
+        1.0
+    }
+}
+
+/// Mean energy of every block of `length` steps, moving one step at a time.
+fn block_energies(steps: &[f64], length: usize) -> Vec<f64> {
+    steps
+        .windows(length)
+        .map(|window| window.iter().sum::<f64>() / length as f64)
+        .collect()
+}
+
+fn lufs(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+/// Mean energy of the blocks above the absolute gate and the relative gate
+/// `relative_lu` below their own mean.
+fn gated_mean(blocks: &[f64], relative_lu: f64) -> Option<f64> {
+    let audible: Vec<f64> = blocks
+        .iter()
+        .copied()
+        .filter(|energy| *energy > 0.0 && lufs(*energy) > ABSOLUTE_GATE_LUFS)
+        .collect();
+    if audible.is_empty() {
+        return None;
+    }
+    let threshold = lufs(audible.iter().sum::<f64>() / audible.len() as f64) + relative_lu;
+    let gated: Vec<f64> = audible
+        .into_iter()
+        .filter(|energy| lufs(*energy) > threshold)
+        .collect();
+    (!gated.is_empty()).then(|| gated.iter().sum::<f64>() / gated.len() as f64)
+}
+
+/// EBU Tech 3342: the spread between the 10th and 95th percentile of the
+/// gated short-term loudness.
+fn loudness_range(blocks: &[f64]) -> Option<f64> {
+    let audible: Vec<f64> = blocks
+        .iter()
+        .copied()
+        .filter(|energy| *energy > 0.0 && lufs(*energy) > ABSOLUTE_GATE_LUFS)
+        .collect();
+    if audible.is_empty() {
+        return None;
+    }
+    let threshold =
+        lufs(audible.iter().sum::<f64>() / audible.len() as f64) + RANGE_RELATIVE_GATE_LU;
+    let mut levels: Vec<f64> = audible
+        .into_iter()
+        .map(lufs)
+        .filter(|level| *level > threshold)
+        .collect();
+    if levels.is_empty() {
+        return None;
+    }
+    levels.sort_by(f64::total_cmp);
+    let percentile = |fraction: f64| {
+        let index = ((levels.len() - 1) as f64 * fraction).round() as usize;
+        levels[index]
+    };
+    Some(percentile(0.95) - percentile(0.10))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    /// Measures `seconds` of a sine at `frequency` and `amplitude`, starting
+    /// at `phase`, on each of `channels`.
+    fn measure_sine(
+        channels: Channels,
+        frequency: f64,
+        amplitude: f64,
+        phase: f64,
+        seconds: f64,
+    ) -> Loudness {
+        let spec = SignalSpec::new(RATE, channels);
+        let count = channels.count();
+        let mut meter = LoudnessMeter::new(&spec).unwrap();
+        let samples: Vec<f32> = (0..(seconds * f64::from(RATE)) as usize)
+            .flat_map(|index| {
+                let time = index as f64 / f64::from(RATE);
+                let value =
+                    amplitude * (2.0 * std::f64::consts::PI * frequency * time + phase).sin();
+                std::iter::repeat_n(value as f32, count)
+            })
+            .collect();
+        for chunk in samples.chunks(4_096 * count) {
+            meter.consume(chunk);
+        }
+        meter.finish().unwrap()
+    }
+
+    fn dbfs(level: f64) -> f64 {
+        10f64.powf(level / 20.0)
+    }
+
+    #[test]
+    fn sine_at_minus_20_dbfs_measures_minus_23_lufs() {
+        let loudness = measure_sine(Channels::FRONT_LEFT, 1_000.0, dbfs(-20.0), 0.0, 20.0);
+        let integrated = loudness.integrated_lufs.unwrap();
+        assert!((integrated + 23.0).abs() <= 0.1, "{integrated}");
+        // A steady tone has no loudness range.
+        assert!(loudness.loudness_range.unwrap() < 0.1);
+    }
+
+    #[test]
+    fn stereo_sine_matches_ebu_tech_3341() {
+        // Test case 1: both channels at -23 dBFS measure -23 LUFS.
+        let stereo = Channels::FRONT_LEFT | Channels::FRONT_RIGHT;
+        let loudness = measure_sine(stereo, 1_000.0, dbfs(-23.0), 0.0, 20.0);
+        let integrated = loudness.integrated_lufs.unwrap();
+        assert!((integrated + 23.0).abs() <= 0.1, "{integrated}");
+    }
+
+    #[test]
+    fn true_peak_finds_peaks_between_samples() {
+        let loudness = measure_sine(Channels::FRONT_LEFT, 997.0, 1.0, 0.0, 5.0);
+        assert!(
+            loudness.true_peak_dbtp.abs() <= 0.2,
+            "{}",
+            loudness.true_peak_dbtp
+        );
+
+        // At a quarter of the sample rate and 45 degrees off, every sample
+        // lands at -3 dBFS while the waveform still reaches full scale.
+        let quarter = f64::from(RATE) / 4.0;
+        let loudness = measure_sine(
+            Channels::FRONT_LEFT,
+            quarter,
+            1.0,
+            std::f64::consts::FRAC_PI_4,
+            5.0,
+        );
+        assert!(
+            loudness.true_peak_dbtp.abs() <= 0.5,
+            "{}",
+            loudness.true_peak_dbtp
+        );
+    }
+
+    #[test]
+    fn replaygain_is_relative_to_minus_18_lufs() {
+        let loudness = measure_sine(Channels::FRONT_LEFT, 1_000.0, dbfs(-20.0), 0.0, 20.0);
+        let gain = loudness.replaygain_gain().unwrap();
+        assert!((gain - 5.0).abs() <= 0.1, "{gain}");
+
+        let loudness = Loudness {
+            integrated_lufs: Some(-9.5),
+            loudness_range: None,
+            true_peak_dbtp: -6.0,
+        };
+        assert_eq!(loudness.replaygain_gain(), Some(-8.5));
+        assert!((loudness.replaygain_peak() - 0.501).abs() < 0.001);
+    }
+
+    #[test]
+    fn silence_has_no_loudness() {
+        let loudness = measure_sine(Channels::FRONT_LEFT, 1_000.0, 0.0, 0.0, 5.0);
+        assert_eq!(loudness.integrated_lufs, None);
+        assert_eq!(loudness.replaygain_gain(), None);
+
+        let meter = LoudnessMeter::new(&SignalSpec::new(RATE, Channels::FRONT_LEFT)).unwrap();
+        assert!(meter.finish().is_none());
+    }
+}
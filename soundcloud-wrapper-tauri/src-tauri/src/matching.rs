@@ -0,0 +1,437 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::normalize::normalize_payload;
+use crate::SoundcloudTrackPayload;
+
+pub const DISCOGS_PROVIDER: &str = "discogs";
+pub const MUSICBRAINZ_PROVIDER: &str = "musicbrainz";
+pub const PROVIDERS: [&str; 2] = [DISCOGS_PROVIDER, MUSICBRAINZ_PROVIDER];
+/// Local files are matched with fixed settings; they are not configurable.
+pub const LOCAL_FILES_PROVIDER: &str = "local";
+
+/// Auto-accept thresholds and re-scoring weights for one metadata provider.
+///
+/// All scores are on the 0–100 scale used by Discogs and MusicBrainz. The
+/// thresholds are compared against the blended score produced by
+/// [`MatchSettings::blend`], not the raw provider score.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchSettings {
+    pub provider: String,
+    /// Minimum score for the best candidate when it must also beat the runner-up.
+    pub accept_score: f32,
+    /// Required gap between the best and the second-best candidate.
+    pub min_margin: f32,
+    /// Score at which the best candidate is accepted regardless of the runner-up.
+    #[serde(default)]
+    pub instant_accept_score: Option<f32>,
+    /// Minimum score when the provider returns a single candidate.
+    pub single_result_min_score: f32,
+    pub provider_weight: f32,
+    pub similarity_weight: f32,
+    pub duration_weight: f32,
+    /// Duration difference at which the duration agreement drops to zero.
+    pub duration_tolerance_ms: i64,
+}
+
+impl MatchSettings {
+    pub fn defaults_for(provider: &str) -> Self {
+        let instant_accept_score = if provider == MUSICBRAINZ_PROVIDER {
+            Some(95.0)
+        } else {
+            None
+        };
+
+        Self {
+            provider: provider.to_string(),
+            accept_score: 85.0,
+            min_margin: 10.0,
+            instant_accept_score,
+            single_result_min_score: 50.0,
+            provider_weight: 0.5,
+            similarity_weight: 0.35,
+            duration_weight: 0.15,
+            duration_tolerance_ms: 10_000,
+        }
+    }
+
+    /// Settings the folder scanner links local files with. Files carry no
+    /// provider score, so only tag similarity and duration count, and a
+    /// lone candidate has to clear the same bar as a clear winner.
+    pub fn local_files() -> Self {
+        Self {
+            provider: LOCAL_FILES_PROVIDER.to_string(),
+            accept_score: 85.0,
+            min_margin: 10.0,
+            instant_accept_score: None,
+            single_result_min_score: 85.0,
+            provider_weight: 0.0,
+            similarity_weight: 0.8,
+            duration_weight: 0.2,
+            duration_tolerance_ms: 5_000,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !PROVIDERS.contains(&self.provider.as_str()) {
+            return Err(format!("unknown match provider '{}'", self.provider));
+        }
+
+        let scores = [
+            ("acceptScore", Some(self.accept_score)),
+            ("minMargin", Some(self.min_margin)),
+            ("instantAcceptScore", self.instant_accept_score),
+            ("singleResultMinScore", Some(self.single_result_min_score)),
+        ];
+        for (name, value) in scores {
+            if let Some(value) = value {
+                if !(0.0..=100.0).contains(&value) {
+                    return Err(format!("{name} must be between 0 and 100"));
+                }
+            }
+        }
+
+        let weights = [
+            self.provider_weight,
+            self.similarity_weight,
+            self.duration_weight,
+        ];
+        if weights
+            .iter()
+            .any(|weight| !weight.is_finite() || *weight < 0.0)
+        {
+            return Err("scoring weights must be non-negative".to_string());
+        }
+        if weights.iter().sum::<f32>() <= 0.0 {
+            return Err("at least one scoring weight must be positive".to_string());
+        }
+        if self.duration_tolerance_ms <= 0 {
+            return Err("durationToleranceMs must be positive".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Blends the provider score with local artist/title similarity and
+    /// duration agreement. Components that cannot be computed (no provider
+    /// score, no duration on either side) are left out and the remaining
+    /// weights are renormalised.
+    pub fn blend(
+        &self,
+        provider_score: Option<f32>,
+        track: &TrackFeatures,
+        candidate: &TrackFeatures,
+    ) -> f32 {
+        let mut components = Vec::with_capacity(3);
+
+        if let Some(score) = provider_score {
+            components.push((self.provider_weight, score.clamp(0.0, 100.0)));
+        }
+        if let Some(similarity) = track.similarity(candidate) {
+            components.push((self.similarity_weight, similarity * 100.0));
+        }
+        if let (Some(expected), Some(actual)) = (track.duration_ms, candidate.duration_ms) {
+            let difference = (expected - actual).abs() as f32;
+            let agreement = 1.0 - (difference / self.duration_tolerance_ms as f32).min(1.0);
+            components.push((self.duration_weight, agreement * 100.0));
+        }
+
+        let total_weight: f32 = components.iter().map(|(weight, _)| weight).sum();
+        if total_weight <= 0.0 {
+            return provider_score.unwrap_or(0.0);
+        }
+
+        components
+            .iter()
+            .map(|(weight, score)| weight * score)
+            .sum::<f32>()
+            / total_weight
+    }
+
+    pub fn is_confident(&self, candidate_count: usize, best_score: f32, second_score: f32) -> bool {
+        if candidate_count == 1 {
+            return best_score >= self.single_result_min_score;
+        }
+
+        if let Some(instant) = self.instant_accept_score {
+            if best_score >= instant {
+                return true;
+            }
+        }
+
+        best_score >= self.accept_score && best_score - second_score >= self.min_margin
+    }
+}
+
+/// The artist, title and duration of either the track being looked up or a
+/// provider candidate.
+#[derive(Debug, Clone, Default)]
+pub struct TrackFeatures {
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub duration_ms: Option<i64>,
+}
+
+impl TrackFeatures {
+    pub fn from_payload(payload: &SoundcloudTrackPayload) -> Self {
+        let normalized = normalize_payload(payload);
+        Self {
+            artist: normalized.artist,
+            title: normalized.title,
+            duration_ms: payload.duration_ms.filter(|value| *value > 0),
+        }
+    }
+
+    /// Discogs search results only carry a combined `"Artist - Title"` string.
+    pub fn from_discogs_result(title: Option<&str>) -> Self {
+        let (artist, title) = match title.and_then(|value| value.split_once(" - ")) {
+            Some((artist, title)) => (Some(artist.to_string()), Some(title.to_string())),
+            None => (None, title.map(|value| value.to_string())),
+        };
+
+        Self {
+            artist,
+            title,
+            duration_ms: None,
+        }
+    }
+
+    pub fn from_musicbrainz_release(release: &Value) -> Self {
+        let artist = release
+            .get("artist-credit")
+            .and_then(|value| value.as_array())
+            .map(|credits| {
+                credits
+                    .iter()
+                    .map(|credit| {
+                        let name = credit
+                            .get("name")
+                            .and_then(|value| value.as_str())
+                            .unwrap_or_default();
+                        let join = credit
+                            .get("joinphrase")
+                            .and_then(|value| value.as_str())
+                            .unwrap_or_default();
+                        format!("{name}{join}")
+                    })
+                    .collect::<String>()
+            })
+            .filter(|value| !value.trim().is_empty());
+
+        Self {
+            artist,
+            title: release
+                .get("title")
+                .and_then(|value| value.as_str())
+                .map(|value| value.to_string()),
+            duration_ms: release.get("length").and_then(|value| value.as_i64()),
+        }
+    }
+
+    /// Returns the artist/title similarity in `0.0..=1.0`, or `None` when either
+    /// side has nothing to compare.
+    pub fn similarity(&self, other: &TrackFeatures) -> Option<f32> {
+        let combined = string_similarity(&self.combined()?, &other.combined()?);
+
+        let fielded = match (&self.artist, &self.title, &other.artist, &other.title) {
+            (Some(artist), Some(title), Some(other_artist), Some(other_title)) => Some(
+                (string_similarity(artist, other_artist) + string_similarity(title, other_title))
+                    / 2.0,
+            ),
+            (_, Some(title), _, Some(other_title)) => Some(string_similarity(title, other_title)),
+            _ => None,
+        };
+
+        Some(fielded.map_or(combined, |value| value.max(combined)))
+    }
+
+    fn combined(&self) -> Option<String> {
+        let combined = [self.artist.as_deref(), self.title.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if combined.trim().is_empty() {
+            None
+        } else {
+            Some(combined)
+        }
+    }
+}
+
+/// Sørensen–Dice coefficient over character bigrams of the normalised inputs.
+pub fn string_similarity(left: &str, right: &str) -> f32 {
+    let left = normalize_for_comparison(left);
+    let right = normalize_for_comparison(right);
+
+    if left.is_empty() || right.is_empty() {
+        return 0.0;
+    }
+    if left == right {
+        return 1.0;
+    }
+
+    let left_bigrams = bigrams(&left);
+    let mut right_bigrams = bigrams(&right);
+    if left_bigrams.is_empty() || right_bigrams.is_empty() {
+        return 0.0;
+    }
+
+    let total = left_bigrams.len() + right_bigrams.len();
+    let mut shared = 0usize;
+    for bigram in left_bigrams {
+        if let Some(position) = right_bigrams.iter().position(|other| *other == bigram) {
+            right_bigrams.swap_remove(position);
+            shared += 1;
+        }
+    }
+
+    (2 * shared) as f32 / total as f32
+}
+
+fn normalize_for_comparison(value: &str) -> String {
+    value
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn bigrams(value: &str) -> Vec<(char, char)> {
+    let chars: Vec<char> = value.chars().collect();
+    chars.windows(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::LibraryStore;
+
+    fn features(artist: &str, title: &str, duration_ms: Option<i64>) -> TrackFeatures {
+        TrackFeatures {
+            artist: Some(artist.to_string()),
+            title: Some(title.to_string()),
+            duration_ms,
+        }
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn blend_weights_every_component() {
+        let settings = MatchSettings::defaults_for(DISCOGS_PROVIDER);
+        let track = features("Bicep", "Glue", Some(240_000));
+
+        let same = features("Bicep", "Glue", Some(240_000));
+        assert_close(settings.blend(Some(80.0), &track, &same), 90.0);
+
+        // Half the tolerance away halves the duration agreement.
+        let shorter = features("Bicep", "Glue", Some(235_000));
+        assert_close(settings.blend(Some(80.0), &track, &shorter), 82.5);
+
+        let unrelated = features("Someone", "Else", Some(400_000));
+        assert!(settings.blend(Some(80.0), &track, &unrelated) < 50.0);
+    }
+
+    #[test]
+    fn blend_renormalises_missing_components() {
+        let settings = MatchSettings::defaults_for(DISCOGS_PROVIDER);
+        let track = features("Bicep", "Glue", None);
+        let candidate = features("Bicep", "Glue", Some(240_000));
+
+        // Without durations the provider and similarity weights share 100%.
+        assert_close(
+            settings.blend(Some(80.0), &track, &candidate),
+            (0.5 * 80.0 + 0.35 * 100.0) / 0.85,
+        );
+        assert_close(settings.blend(None, &track, &candidate), 100.0);
+        assert_close(
+            settings.blend(Some(120.0), &TrackFeatures::default(), &candidate),
+            100.0,
+        );
+        assert_eq!(
+            settings.blend(None, &TrackFeatures::default(), &candidate),
+            0.0
+        );
+    }
+
+    #[test]
+    fn single_result_needs_the_minimum_score() {
+        let settings = MatchSettings::defaults_for(DISCOGS_PROVIDER);
+        assert!(!settings.is_confident(1, 49.0, 0.0));
+        assert!(settings.is_confident(1, 50.0, 0.0));
+    }
+
+    #[test]
+    fn several_results_need_score_and_margin() {
+        let discogs = MatchSettings::defaults_for(DISCOGS_PROVIDER);
+        assert!(discogs.is_confident(2, 90.0, 80.0));
+        assert!(!discogs.is_confident(2, 90.0, 85.0));
+        assert!(!discogs.is_confident(2, 84.0, 10.0));
+        assert!(!discogs.is_confident(2, 99.0, 95.0));
+
+        let musicbrainz = MatchSettings::defaults_for(MUSICBRAINZ_PROVIDER);
+        assert!(musicbrainz.is_confident(2, 99.0, 95.0));
+        assert!(!musicbrainz.is_confident(2, 94.0, 90.0));
+    }
+
+    #[test]
+    fn validation_rejects_out_of_range_settings() {
+        let mut settings = MatchSettings::defaults_for(DISCOGS_PROVIDER);
+        assert_eq!(settings.validate(), Ok(()));
+
+        settings.accept_score = 101.0;
+        assert!(settings.validate().is_err());
+
+        let mut settings = MatchSettings::defaults_for(DISCOGS_PROVIDER);
+        settings.provider_weight = 0.0;
+        settings.similarity_weight = 0.0;
+        settings.duration_weight = 0.0;
+        assert!(settings.validate().is_err());
+
+        let settings = MatchSettings::defaults_for("bandcamp");
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn settings_persist_per_provider() {
+        let store = LibraryStore::open_in_memory().unwrap();
+        let defaults = store.load_match_settings(DISCOGS_PROVIDER).unwrap();
+        assert_eq!(defaults.accept_score, 85.0);
+
+        let mut settings = MatchSettings::defaults_for(DISCOGS_PROVIDER);
+        settings.accept_score = 70.0;
+        settings.duration_weight = 0.0;
+        store.save_match_settings(&settings).unwrap();
+        settings.accept_score = 75.0;
+        store.save_match_settings(&settings).unwrap();
+
+        let saved = store.load_match_settings(DISCOGS_PROVIDER).unwrap();
+        assert_eq!(saved.accept_score, 75.0);
+        assert_eq!(saved.duration_weight, 0.0);
+
+        let listed = store.list_match_settings().unwrap();
+        assert_eq!(listed.len(), PROVIDERS.len());
+        assert_eq!(listed[1].provider, MUSICBRAINZ_PROVIDER);
+        assert_eq!(listed[1].instant_accept_score, Some(95.0));
+    }
+
+    #[test]
+    fn discogs_titles_split_into_artist_and_title() {
+        let candidate = TrackFeatures::from_discogs_result(Some("Bicep - Isles"));
+        assert_eq!(candidate.artist.as_deref(), Some("Bicep"));
+        assert_eq!(candidate.title.as_deref(), Some("Isles"));
+
+        let track = features("Bicep", "Isles", None);
+        assert_eq!(track.similarity(&candidate), Some(1.0));
+        assert_eq!(string_similarity("Glue!", "glue"), 1.0);
+        assert_eq!(string_similarity("", "glue"), 0.0);
+    }
+}
@@ -198,7 +198,7 @@ mod linux {
 
             std::thread::spawn(move || {
                 if let Err(error) = Self::run(app, ready_tx) {
-                    eprintln!("[soundcloud-wrapper] Failed to initialize MPRIS service: {error}");
+                    log::error!("failed to initialize MPRIS service: {error}");
                 }
             });
 
@@ -306,8 +306,8 @@ mod linux {
 
     impl LinuxIntegration {
         pub fn new(_app: &AppHandle) -> Option<Self> {
-            eprintln!(
-                "[soundcloud-wrapper] MPRIS integration disabled. Enable the 'mpris-linux' feature and install GLib development files to activate."
+            log::info!(
+                "MPRIS integration disabled. Enable the 'mpris-linux' feature and install GLib development files to activate."
             );
             None
         }
@@ -409,12 +409,12 @@ mod windows {
                 PlaybackStatus::Stopped => MediaPlaybackStatus::Stopped,
             };
             if let Err(error) = self.smtc.SetPlaybackStatus(status) {
-                eprintln!("[soundcloud-wrapper] Failed to set SMTC status: {error:?}");
+                log::warn!("failed to set SMTC status: {error:?}");
             }
 
             if let Some(metadata) = &update.metadata {
                 if let Err(error) = update_display(&self.smtc, metadata) {
-                    eprintln!("[soundcloud-wrapper] Failed to update SMTC metadata: {error:?}");
+                    log::warn!("failed to update SMTC metadata: {error:?}");
                 }
             }
         }
@@ -1,100 +1,180 @@
 use std::cmp::Ordering;
-use std::env;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 
+use reqwest::header::USER_AGENT;
 use reqwest::{Client, StatusCode};
 use serde_json::{json, Value};
 use tauri::async_runtime;
-use tauri::AppHandle;
-use tauri::Emitter;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 use tokio::time::sleep;
 
+use crate::endpoints::{env_value, ClientIdentity, Endpoint, EndpointSettings, PROJECT_URL};
+use crate::errors::CommandError;
+use crate::events::EventSink;
 use crate::library::LibraryStore;
+use crate::matching::{MatchSettings, TrackFeatures, MUSICBRAINZ_PROVIDER};
+use crate::normalize::normalize_payload;
+use crate::settings::MusicbrainzSettings;
 use crate::SoundcloudTrackPayload;
 
-const SEARCH_URL: &str = "https://musicbrainz.org/ws/2/release/";
+/// The public service allows one request per second per client.
+const OFFICIAL_INTERVAL: Duration = Duration::from_millis(1100);
+/// Mirrors on this machine or the local network have no rate limit of their
+/// own.
+const MIRROR_INTERVAL: Duration = Duration::ZERO;
 const MUSICBRAINZ_AMBIGUITY_EVENT: &str = "app://musicbrainz/lookup-ambiguous";
 const MAX_ATTEMPTS: usize = 3;
 
 #[derive(Clone)]
 pub struct MusicbrainzService {
-    sender: mpsc::Sender<SoundcloudTrackPayload>,
+    sender: mpsc::Sender<MusicbrainzJob>,
+    identity: ClientIdentity,
+    credentials: Arc<RwLock<MusicbrainzCredentials>>,
+}
+
+struct MusicbrainzJob {
+    payload: SoundcloudTrackPayload,
+    done: Option<oneshot::Sender<Result<(), CommandError>>>,
 }
 
 impl MusicbrainzService {
-    pub fn new(app: &AppHandle, library: Arc<Mutex<LibraryStore>>) -> Self {
-        let (sender, mut receiver) = mpsc::channel::<SoundcloudTrackPayload>(32);
-        let credentials = Arc::new(MusicbrainzCredentials::load(app));
-        let client = Client::builder()
-            .user_agent(credentials.user_agent.clone())
-            .build()
-            .expect("failed to build MusicBrainz client");
-        let app_handle = app.clone();
+    pub fn new(
+        events: Arc<dyn EventSink>,
+        library: Arc<Mutex<LibraryStore>>,
+        identity: &ClientIdentity,
+        settings: &MusicbrainzSettings,
+    ) -> Result<Self, CommandError> {
+        Self::spawn(
+            events,
+            library,
+            identity,
+            MusicbrainzCredentials::resolve(settings, identity),
+        )
+    }
+
+    fn spawn(
+        events: Arc<dyn EventSink>,
+        library: Arc<Mutex<LibraryStore>>,
+        identity: &ClientIdentity,
+        credentials: MusicbrainzCredentials,
+    ) -> Result<Self, CommandError> {
+        let (sender, mut receiver) = mpsc::channel::<MusicbrainzJob>(32);
+        let credentials = Arc::new(RwLock::new(credentials));
+        let client = Client::builder().build().map_err(|error| {
+            CommandError::internal(format!("failed to build MusicBrainz client: {error}"))
+        })?;
+        let worker_credentials = Arc::clone(&credentials);
         async_runtime::spawn(async move {
-            let mut rate_limiter = RateLimiter::new(Duration::from_millis(1100));
-            let worker_credentials = Arc::clone(&credentials);
-            while let Some(payload) = receiver.recv().await {
-                if payload.track_id.is_empty() {
-                    continue;
+            let mut rate_limiter = RateLimiter::new(OFFICIAL_INTERVAL);
+            while let Some(MusicbrainzJob { payload, done }) = receiver.recv().await {
+                let mut result = Ok(());
+                if !payload.track_id.is_empty() {
+                    // Jobs already running keep the credentials they started with.
+                    let credentials = worker_credentials
+                        .read()
+                        .map(|credentials| credentials.clone())
+                        .unwrap_or_else(|poisoned| poisoned.into_inner().clone());
+                    result = process_job(
+                        events.as_ref(),
+                        Arc::clone(&library),
+                        &client,
+                        &credentials,
+                        &mut rate_limiter,
+                        payload,
+                    )
+                    .await;
+                }
+                match done {
+                    Some(done) => {
+                        let _ = done.send(result);
+                    }
+                    None => {
+                        if let Err(error) = result {
+                            log::warn!("{error}");
+                        }
+                    }
                 }
-                process_job(
-                    &app_handle,
-                    Arc::clone(&library),
-                    &client,
-                    worker_credentials.as_ref(),
-                    &mut rate_limiter,
-                    payload,
-                )
-                .await;
             }
         });
 
-        Self { sender }
+        Ok(Self {
+            sender,
+            identity: identity.clone(),
+            credentials,
+        })
+    }
+
+    /// Replaces the identity sent with the next lookups.
+    pub fn reconfigure(&self, settings: &MusicbrainzSettings) {
+        let credentials = MusicbrainzCredentials::resolve(settings, &self.identity);
+        match self.credentials.write() {
+            Ok(mut current) => *current = credentials,
+            Err(poisoned) => *poisoned.into_inner() = credentials,
+        }
     }
 
     pub fn queue_lookup(&self, payload: SoundcloudTrackPayload) {
         let mut sender = self.sender.clone();
         async_runtime::spawn(async move {
-            if let Err(error) = sender.send(payload).await {
-                eprintln!("[musicbrainz] failed to enqueue lookup: {error}");
+            let job = MusicbrainzJob {
+                payload,
+                done: None,
+            };
+            if let Err(error) = sender.send(job).await {
+                log::error!("failed to enqueue lookup: {error}");
             }
         });
     }
+
+    /// Runs a lookup through the worker queue and resolves once its outcome
+    /// has been recorded in the library. Requests that failed are recorded
+    /// too, and reported as a network error.
+    pub async fn lookup(&self, payload: SoundcloudTrackPayload) -> Result<(), CommandError> {
+        let (done, finished) = oneshot::channel();
+        self.sender
+            .send(MusicbrainzJob {
+                payload,
+                done: Some(done),
+            })
+            .await
+            .map_err(|_| CommandError::internal("MusicBrainz worker is not running"))?;
+        finished
+            .await
+            .map_err(|_| CommandError::internal("MusicBrainz worker dropped the request"))?
+    }
 }
 
+#[derive(Clone)]
 struct MusicbrainzCredentials {
     user_agent: String,
     token: Option<String>,
 }
 
 impl MusicbrainzCredentials {
-    fn load(app: &AppHandle) -> Self {
-        let package_info = app.package_info();
-        let default_name = package_info.name.clone();
-        let default_version = package_info.version.to_string();
-        let app_name = env::var("MUSICBRAINZ_APP_NAME")
-            .ok()
-            .filter(|value| !value.trim().is_empty())
-            .unwrap_or(default_name);
-        let app_version = env::var("MUSICBRAINZ_APP_VERSION")
-            .ok()
-            .filter(|value| !value.trim().is_empty())
-            .unwrap_or(default_version);
-        let contact = env::var("MUSICBRAINZ_APP_CONTACT")
-            .ok()
-            .filter(|value| !value.trim().is_empty())
-            .unwrap_or_else(|| "https://github.com/your-org/desktop-soundcloud".to_string());
+    /// Settings take precedence over the environment variables, which take
+    /// precedence over the app identity.
+    fn resolve(settings: &MusicbrainzSettings, identity: &ClientIdentity) -> Self {
+        let app_name = setting_or_env(&settings.app_name, "MUSICBRAINZ_APP_NAME")
+            .unwrap_or_else(|| identity.name.clone());
+        let app_version = setting_or_env(&settings.app_version, "MUSICBRAINZ_APP_VERSION")
+            .unwrap_or_else(|| identity.version.clone());
+        let contact = setting_or_env(&settings.contact, "MUSICBRAINZ_APP_CONTACT")
+            .unwrap_or_else(|| PROJECT_URL.to_string());
         let user_agent = format!("{app_name}/{app_version} ({contact})");
-        let token = env::var("MUSICBRAINZ_TOKEN")
-            .ok()
-            .filter(|value| !value.trim().is_empty());
+        let token = setting_or_env(&settings.token, "MUSICBRAINZ_TOKEN");
 
         Self { user_agent, token }
     }
 }
 
+fn setting_or_env(setting: &Option<String>, variable: &str) -> Option<String> {
+    setting
+        .clone()
+        .filter(|value| !value.trim().is_empty())
+        .or_else(|| env_value(variable))
+}
+
 struct RateLimiter {
     last: Option<Instant>,
     interval: Duration,
@@ -108,6 +188,10 @@ impl RateLimiter {
         }
     }
 
+    fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
     async fn wait(&mut self) {
         if let Some(last) = self.last {
             let elapsed = last.elapsed();
@@ -120,13 +204,13 @@ impl RateLimiter {
 }
 
 async fn process_job(
-    app: &AppHandle,
+    events: &dyn EventSink,
     library: Arc<Mutex<LibraryStore>>,
     client: &Client,
     credentials: &MusicbrainzCredentials,
     rate_limiter: &mut RateLimiter,
     payload: SoundcloudTrackPayload,
-) {
+) -> Result<(), CommandError> {
     let track_id = payload.track_id.clone();
     let query = build_search_query(&payload);
 
@@ -135,13 +219,28 @@ async fn process_job(
             if let Err(error) =
                 store.record_musicbrainz_failure(&track_id, &query, "missing title or artist")
             {
-                eprintln!("[musicbrainz] failed to persist lookup failure for {track_id}: {error}");
+                log::error!("failed to persist lookup failure for {track_id}: {error}");
             }
         }
-        return;
+        return Ok(());
     }
 
-    match perform_lookup(client, credentials, rate_limiter, &query).await {
+    let settings = load_settings(&library);
+    let endpoint = load_endpoint(&library);
+    rate_limiter.set_interval(request_interval(&endpoint));
+    let track = TrackFeatures::from_payload(&payload);
+
+    match perform_lookup(
+        client,
+        credentials,
+        &endpoint,
+        rate_limiter,
+        &settings,
+        &track,
+        &query,
+    )
+    .await
+    {
         Ok(LookupResult::Success {
             release,
             confidence,
@@ -150,9 +249,7 @@ async fn process_job(
                 if let Err(error) =
                     store.record_musicbrainz_success(&track_id, &query, &release, confidence)
                 {
-                    eprintln!(
-                        "[musicbrainz] failed to persist lookup success for {track_id}: {error}"
-                    );
+                    log::error!("failed to persist lookup success for {track_id}: {error}");
                 }
             }
         }
@@ -161,13 +258,11 @@ async fn process_job(
                 if let Err(error) =
                     store.record_musicbrainz_ambiguity(&track_id, &query, &candidates)
                 {
-                    eprintln!(
-                        "[musicbrainz] failed to persist lookup ambiguity for {track_id}: {error}"
-                    );
+                    log::error!("failed to persist lookup ambiguity for {track_id}: {error}");
                 }
             }
 
-            if let Err(error) = app.emit(
+            if let Err(error) = events.emit(
                 MUSICBRAINZ_AMBIGUITY_EVENT,
                 json!({
                     "trackId": track_id,
@@ -175,21 +270,66 @@ async fn process_job(
                     "candidates": candidates,
                 }),
             ) {
-                eprintln!("[musicbrainz] failed to emit ambiguity event: {error}");
+                log::error!("failed to emit ambiguity event: {error}");
             }
         }
         Err(failure) => {
+            let request_failed = matches!(failure, LookupFailure::Error(_));
+            let message = failure.into_message();
             if let Ok(mut store) = library.lock() {
-                if let Err(error) =
-                    store.record_musicbrainz_failure(&track_id, &query, &failure.into_message())
-                {
-                    eprintln!(
-                        "[musicbrainz] failed to persist lookup failure for {track_id}: {error}"
-                    );
+                if let Err(error) = store.record_musicbrainz_failure(&track_id, &query, &message) {
+                    log::error!("failed to persist lookup failure for {track_id}: {error}");
                 }
             }
+            if request_failed {
+                return Err(CommandError::network(
+                    "musicbrainz",
+                    format!("lookup for {track_id} failed: {message}"),
+                ));
+            }
         }
     }
+    Ok(())
+}
+
+fn load_settings(library: &Arc<Mutex<LibraryStore>>) -> MatchSettings {
+    match library.lock() {
+        Ok(store) => store
+            .load_match_settings(MUSICBRAINZ_PROVIDER)
+            .unwrap_or_else(|error| {
+                log::warn!("failed to load match settings, using defaults: {error}");
+                MatchSettings::defaults_for(MUSICBRAINZ_PROVIDER)
+            }),
+        Err(_) => MatchSettings::defaults_for(MUSICBRAINZ_PROVIDER),
+    }
+}
+
+fn load_endpoint(library: &Arc<Mutex<LibraryStore>>) -> Endpoint {
+    match library.lock() {
+        Ok(store) => store
+            .load_endpoint_settings(MUSICBRAINZ_PROVIDER)
+            .unwrap_or_else(|error| {
+                log::warn!("failed to load endpoint settings, using defaults: {error}");
+                EndpointSettings::unset(MUSICBRAINZ_PROVIDER)
+            }),
+        Err(_) => EndpointSettings::unset(MUSICBRAINZ_PROVIDER),
+    }
+    .resolve()
+}
+
+/// Uses the configured interval when there is one. Otherwise a mirror on a
+/// loopback or private address is not rate limited, and every other host,
+/// including public mirrors, gets the public service's documented 1 req/s.
+fn request_interval(endpoint: &Endpoint) -> Duration {
+    if let Some(interval) = endpoint.min_interval {
+        return interval;
+    }
+
+    if endpoint.is_private_host() {
+        MIRROR_INTERVAL
+    } else {
+        OFFICIAL_INTERVAL
+    }
 }
 
 enum LookupResult {
@@ -214,17 +354,22 @@ impl LookupFailure {
 async fn perform_lookup(
     client: &Client,
     credentials: &MusicbrainzCredentials,
+    endpoint: &Endpoint,
     rate_limiter: &mut RateLimiter,
+    settings: &MatchSettings,
+    track: &TrackFeatures,
     query: &str,
 ) -> Result<LookupResult, LookupFailure> {
+    let search_url = endpoint.url("release/");
     let mut attempts = 0usize;
     loop {
         attempts += 1;
         rate_limiter.wait().await;
-        let mut request =
-            client
-                .get(SEARCH_URL)
-                .query(&[("fmt", "json"), ("limit", "5"), ("query", query)]);
+        let mut request = client
+            .get(&search_url)
+            .header(USER_AGENT, &credentials.user_agent)
+            .query(&[("fmt", "json"), ("limit", "5"), ("query", query)])
+            .timeout(endpoint.timeout);
 
         if let Some(token) = credentials.token.as_ref() {
             request = request.bearer_auth(token);
@@ -240,7 +385,7 @@ async fn perform_lookup(
                 let body: Value = response.json().await.map_err(|error| {
                     LookupFailure::Error(format!("failed to parse MusicBrainz response: {error}"))
                 })?;
-                return interpret_lookup(body);
+                return interpret_lookup(body, settings, track);
             }
             StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
                 let retry_after = response
@@ -276,19 +421,29 @@ async fn perform_lookup(
     }
 }
 
-fn interpret_lookup(body: Value) -> Result<LookupResult, LookupFailure> {
+fn interpret_lookup(
+    body: Value,
+    settings: &MatchSettings,
+    track: &TrackFeatures,
+) -> Result<LookupResult, LookupFailure> {
     let releases = body
         .get("releases")
         .and_then(|value| value.as_array())
         .ok_or_else(|| LookupFailure::Message("invalid response payload".to_string()))?;
 
     let mut scored: Vec<(f32, Value)> = Vec::new();
-    for release in releases.iter().cloned() {
-        let score = release
+    for mut release in releases.iter().cloned() {
+        let provider_score = release
             .get("score")
             .and_then(|value| value.as_f64())
-            .map(|value| value as f32)
-            .unwrap_or(0.0);
+            .map(|value| value as f32);
+        let candidate = TrackFeatures::from_musicbrainz_release(&release);
+        let score = settings.blend(provider_score, track, &candidate);
+
+        if let Some(object) = release.as_object_mut() {
+            object.insert("providerScore".to_string(), json!(provider_score));
+            object.insert("score".to_string(), json!(score));
+        }
         scored.push((score, release));
     }
 
@@ -298,50 +453,39 @@ fn interpret_lookup(body: Value) -> Result<LookupResult, LookupFailure> {
         ));
     }
 
-    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or_else(|| Ordering::Equal));
+    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
 
-    let mut releases: Vec<Value> = scored.iter().map(|(_, release)| release.clone()).collect();
-    let (mut best_score, best_release) = scored
-        .into_iter()
-        .next()
+    let candidate_count = scored.len();
+    let second_score = scored.get(1).map(|(score, _)| *score).unwrap_or(0.0);
+    let (best_score, best_release) = scored
+        .first()
+        .cloned()
         .ok_or_else(|| LookupFailure::Message("MusicBrainz returned no releases".to_string()))?;
 
-    if best_score <= 0.0 {
-        best_score = 100.0;
-    }
-
-    let second_score = releases
-        .get(1)
-        .and_then(|release| release.get("score"))
-        .and_then(|value| value.as_f64())
-        .map(|value| value as f32)
-        .unwrap_or(0.0);
-
-    let is_confident = releases.len() == 1
-        || best_score >= 95.0
-        || (best_score >= 85.0 && (best_score - second_score) >= 10.0);
-
-    if is_confident {
+    if settings.is_confident(candidate_count, best_score, second_score) {
         Ok(LookupResult::Success {
             release: best_release,
             confidence: best_score,
         })
     } else {
-        releases.truncate(5);
-        Ok(LookupResult::Ambiguous {
-            candidates: releases,
-        })
+        let candidates = scored
+            .into_iter()
+            .take(5)
+            .map(|(_, release)| release)
+            .collect();
+        Ok(LookupResult::Ambiguous { candidates })
     }
 }
 
 fn build_search_query(payload: &SoundcloudTrackPayload) -> String {
     let mut components = Vec::new();
+    let normalized = normalize_payload(payload);
 
-    if let Some(artist) = payload.artist.as_ref().and_then(normalize_term) {
+    if let Some(artist) = normalized.artist.as_ref().and_then(normalize_term) {
         components.push(format!("artist:\"{artist}\""));
     }
 
-    if let Some(title) = payload.title.as_ref().and_then(normalize_term) {
+    if let Some(title) = normalized.title.as_ref().and_then(normalize_term) {
         components.push(format!("recording:\"{title}\""));
     }
 
@@ -366,3 +510,266 @@ fn normalize_term(value: &String) -> Option<String> {
         Some(trimmed.replace('"', "\\\""))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::LibraryStatusRow;
+    use crate::test_support::{
+        fixture, memory_library, payload, point_at, status_row, wait_for, RecordingSink,
+    };
+    use mockito::{Matcher, Server};
+
+    fn spawn_service(
+        server: &Server,
+    ) -> (
+        MusicbrainzService,
+        Arc<Mutex<LibraryStore>>,
+        Arc<RecordingSink>,
+    ) {
+        let library = memory_library();
+        point_at(&library, MUSICBRAINZ_PROVIDER, &server.url());
+        let events = Arc::new(RecordingSink::default());
+        let credentials = MusicbrainzCredentials {
+            user_agent: "SoundCloudWrapperTests/0.0 (tests@example.com)".to_string(),
+            token: None,
+        };
+        let service = MusicbrainzService::spawn(
+            events.clone(),
+            Arc::clone(&library),
+            &ClientIdentity::new("SoundCloudWrapperTests", "0.0"),
+            credentials,
+        )
+        .unwrap();
+        (service, library, events)
+    }
+
+    async fn wait_for_match(
+        library: &Arc<Mutex<LibraryStore>>,
+        track_id: &str,
+    ) -> LibraryStatusRow {
+        wait_for(|| status_row(library, track_id).filter(|row| row.musicbrainz_status.is_some()))
+            .await
+    }
+
+    fn search_mock(server: &mut Server) -> mockito::Mock {
+        server
+            .mock("GET", "/release/")
+            .match_query(Matcher::UrlEncoded("fmt".into(), "json".into()))
+            .with_header("content-type", "application/json")
+    }
+
+    #[test]
+    fn clear_winner_is_accepted() {
+        let settings = MatchSettings::defaults_for(MUSICBRAINZ_PROVIDER);
+        let track = TrackFeatures::from_payload(&payload("sc-1", "Bicep", "Glue", None));
+        let body = serde_json::from_str(&fixture("musicbrainz/search_confident.json")).unwrap();
+
+        match interpret_lookup(body, &settings, &track) {
+            Ok(LookupResult::Success { release, .. }) => {
+                assert_eq!(release["id"], "6b0d5b7b-0d1f-4e8b-9c55-7f3f0c0a1a01");
+                assert_eq!(release["providerScore"], 100.0);
+            }
+            _ => panic!("expected a confident match"),
+        }
+    }
+
+    #[test]
+    fn close_scores_are_ambiguous() {
+        let settings = MatchSettings::defaults_for(MUSICBRAINZ_PROVIDER);
+        let track = TrackFeatures::from_payload(&payload("sc-1", "Bicep", "Glue", None));
+        let body = serde_json::from_str(&fixture("musicbrainz/search_ambiguous.json")).unwrap();
+
+        match interpret_lookup(body, &settings, &track) {
+            Ok(LookupResult::Ambiguous { candidates }) => assert_eq!(candidates.len(), 2),
+            _ => panic!("expected an ambiguous result"),
+        }
+    }
+
+    #[test]
+    fn only_private_mirrors_skip_the_public_rate_limit() {
+        let official = EndpointSettings::unset(MUSICBRAINZ_PROVIDER).resolve();
+        assert_eq!(request_interval(&official), OFFICIAL_INTERVAL);
+
+        let mut mirror = EndpointSettings::unset(MUSICBRAINZ_PROVIDER);
+        mirror.base_url = Some("https://mb.example.com/ws/2".to_string());
+        assert_eq!(request_interval(&mirror.resolve()), OFFICIAL_INTERVAL);
+
+        for base_url in [
+            "http://localhost:5000/ws/2",
+            "http://127.0.0.1:5000/ws/2",
+            "http://192.168.1.20:5000/ws/2",
+            "http://[fd00::20]:5000/ws/2",
+        ] {
+            mirror.base_url = Some(base_url.to_string());
+            assert_eq!(
+                request_interval(&mirror.resolve()),
+                MIRROR_INTERVAL,
+                "{base_url}"
+            );
+        }
+
+        mirror.min_interval_ms = Some(250);
+        assert_eq!(
+            request_interval(&mirror.resolve()),
+            Duration::from_millis(250)
+        );
+    }
+
+    #[tokio::test]
+    async fn confident_match_is_persisted() {
+        let mut server = Server::new_async().await;
+        let search = search_mock(&mut server)
+            .with_body(fixture("musicbrainz/search_confident.json"))
+            .create_async()
+            .await;
+
+        let (service, library, events) = spawn_service(&server);
+        service.queue_lookup(payload("sc-1", "Bicep", "Glue", None));
+
+        let row = wait_for_match(&library, "sc-1").await;
+        assert_eq!(row.musicbrainz_status.as_deref(), Some("success"));
+        assert_eq!(
+            row.musicbrainz_release_id.as_deref(),
+            Some("6b0d5b7b-0d1f-4e8b-9c55-7f3f0c0a1a01")
+        );
+        assert!(events.events(MUSICBRAINZ_AMBIGUITY_EVENT).is_empty());
+        search.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn reconfigured_identity_is_sent_with_the_next_lookup() {
+        let mut server = Server::new_async().await;
+        let search = search_mock(&mut server)
+            .match_header("user-agent", "Crate/2.0 (dj@example.com)")
+            .match_header("authorization", "Bearer secret")
+            .with_body(fixture("musicbrainz/search_confident.json"))
+            .create_async()
+            .await;
+
+        let (service, library, _events) = spawn_service(&server);
+        service.reconfigure(&MusicbrainzSettings {
+            app_name: Some("Crate".to_string()),
+            app_version: Some("2.0".to_string()),
+            contact: Some("dj@example.com".to_string()),
+            token: Some("secret".to_string()),
+        });
+        service.queue_lookup(payload("sc-1", "Bicep", "Glue", None));
+
+        let row = wait_for_match(&library, "sc-1").await;
+        assert_eq!(row.musicbrainz_status.as_deref(), Some("success"));
+        search.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn ambiguous_match_is_stored_and_announced() {
+        let mut server = Server::new_async().await;
+        search_mock(&mut server)
+            .with_body(fixture("musicbrainz/search_ambiguous.json"))
+            .create_async()
+            .await;
+
+        let (service, library, events) = spawn_service(&server);
+        service.queue_lookup(payload("sc-2", "Bicep", "Glue", None));
+
+        let row = wait_for_match(&library, "sc-2").await;
+        assert_eq!(row.musicbrainz_status.as_deref(), Some("ambiguous"));
+        assert_eq!(row.musicbrainz_candidate_count, 2);
+
+        let announced = events.events(MUSICBRAINZ_AMBIGUITY_EVENT);
+        assert_eq!(announced.len(), 1);
+        assert_eq!(announced[0]["trackId"], "sc-2");
+    }
+
+    #[tokio::test]
+    async fn unavailable_service_is_retried() {
+        let mut server = Server::new_async().await;
+        let unavailable = server
+            .mock("GET", "/release/")
+            .match_query(Matcher::Any)
+            .with_status(503)
+            .with_header("Retry-After", "0")
+            .expect(1)
+            .create_async()
+            .await;
+        let search = search_mock(&mut server)
+            .with_body(fixture("musicbrainz/search_confident.json"))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let (service, library, _events) = spawn_service(&server);
+        service.queue_lookup(payload("sc-3", "Bicep", "Glue", None));
+
+        let row = wait_for_match(&library, "sc-3").await;
+        assert_eq!(row.musicbrainz_status.as_deref(), Some("success"));
+        unavailable.assert_async().await;
+        search.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn persistent_rate_limiting_records_failure() {
+        let mut server = Server::new_async().await;
+        let limited = server
+            .mock("GET", "/release/")
+            .match_query(Matcher::Any)
+            .with_status(429)
+            .with_header("Retry-After", "0")
+            .expect(MAX_ATTEMPTS)
+            .create_async()
+            .await;
+
+        let (service, library, _events) = spawn_service(&server);
+        service.queue_lookup(payload("sc-4", "Bicep", "Glue", None));
+
+        let row = wait_for_match(&library, "sc-4").await;
+        assert_eq!(row.musicbrainz_status.as_deref(), Some("error"));
+        assert_eq!(
+            row.musicbrainz_message.as_deref(),
+            Some("rate limited by MusicBrainz")
+        );
+        limited.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn empty_search_records_failure() {
+        let mut server = Server::new_async().await;
+        search_mock(&mut server)
+            .with_body(fixture("musicbrainz/search_empty.json"))
+            .create_async()
+            .await;
+
+        let (service, library, _events) = spawn_service(&server);
+        service.queue_lookup(payload("sc-5", "Nobody", "Nothing", None));
+
+        let row = wait_for_match(&library, "sc-5").await;
+        assert_eq!(row.musicbrainz_status.as_deref(), Some("error"));
+        assert_eq!(
+            row.musicbrainz_message.as_deref(),
+            Some("MusicBrainz returned no releases")
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_requests_are_recorded_and_reported() {
+        let mut server = Server::new_async().await;
+        search_mock(&mut server)
+            .with_body("not json")
+            .create_async()
+            .await;
+
+        let (service, library, _events) = spawn_service(&server);
+        let result = service.lookup(payload("sc-6", "Bicep", "Glue", None)).await;
+        assert!(
+            matches!(result, Err(CommandError::Network { ref service, .. }) if service == "musicbrainz"),
+            "{result:?}"
+        );
+
+        let row = status_row(&library, "sc-6").unwrap();
+        assert_eq!(row.musicbrainz_status.as_deref(), Some("error"));
+        assert!(row
+            .musicbrainz_message
+            .unwrap()
+            .starts_with("failed to parse MusicBrainz response"));
+    }
+}
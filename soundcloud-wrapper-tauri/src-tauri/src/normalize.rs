@@ -0,0 +1,921 @@
+use serde::Serialize;
+
+use crate::SoundcloudTrackPayload;
+
+/// Phrases that mark a bracketed group or a title segment as promotional
+/// rather than part of the track name.
+const PROMO_PHRASES: &[&str] = &[
+    "free download",
+    "free dl",
+    "freedl",
+    "free d l",
+    "premiere",
+    "out now",
+    "exclusive",
+    "preview",
+    "snippet",
+    "teaser",
+    "forthcoming",
+    "coming soon",
+    "available now",
+    "buy free",
+    "click buy",
+    "supported by",
+];
+
+/// Bracket contents that are promotional only when they make up the whole group.
+const PROMO_WORDS: &[&str] = &[
+    "free",
+    "clip",
+    "hq",
+    "hd",
+    "video",
+    "official video",
+    "official audio",
+    "audio",
+    "out soon",
+];
+
+/// Unbracketed phrases stripped from the start or end of a title. Phrases
+/// without the flag are only stripped when an `Artist - Title` pair remains,
+/// so that titles such as `Premiere Night` survive.
+const EDGE_PROMO_PHRASES: &[(&str, bool)] = &[
+    ("free download", true),
+    ("free dl", true),
+    ("premiere", false),
+    ("out now", false),
+];
+
+/// Trailing words that turn a bracketed group into a version descriptor.
+/// The flag says whether the words before it name a remixer or editor.
+const VERSION_KEYWORDS: &[(&str, bool)] = &[
+    ("remix", true),
+    ("rmx", true),
+    ("re-edit", true),
+    ("edit", true),
+    ("rework", true),
+    ("re-work", true),
+    ("bootleg", true),
+    ("flip", true),
+    ("refix", true),
+    ("remake", true),
+    ("mix", true),
+    ("dub", true),
+    ("vip", false),
+    ("version", false),
+    ("reprise", false),
+    ("instrumental", false),
+    ("cover", false),
+];
+
+/// Words that describe a version rather than credit a person.
+const GENERIC_VERSION_WORDS: &[&str] = &[
+    "original",
+    "extended",
+    "radio",
+    "club",
+    "dub",
+    "vocal",
+    "instrumental",
+    "short",
+    "long",
+    "main",
+    "clean",
+    "dirty",
+    "acapella",
+    "album",
+    "single",
+    "edit",
+    "mix",
+    "12\"",
+    "7\"",
+];
+
+const FEATURE_MARKERS: &[&str] = &[" featuring ", " feat. ", " feat ", " ft. ", " ft "];
+const CREDIT_SEPARATORS: &[&str] = &[", ", " & ", " x ", " and "];
+const TITLE_SEPARATORS: &[&str] = &[" - ", " – ", " — ", " -- "];
+
+/// Artist and title parsed out of a raw SoundCloud upload, with promotional
+/// noise removed and credits split into their own fields.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NormalizedTrack {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub featured_artists: Vec<String>,
+    pub remixers: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl NormalizedTrack {
+    pub fn search_terms(&self) -> String {
+        [self.artist.as_deref(), self.title.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+pub fn normalize_payload(payload: &SoundcloudTrackPayload) -> NormalizedTrack {
+    normalize_track(payload.artist.as_deref(), payload.title.as_deref())
+}
+
+/// Normalises a SoundCloud uploader name and title.
+///
+/// When the title carries an `Artist - Title` pair the artist from the title
+/// wins over the uploader, which is frequently a label or promo channel.
+pub fn normalize_track(uploader: Option<&str>, raw_title: Option<&str>) -> NormalizedTrack {
+    let uploader = uploader
+        .map(collapse_whitespace)
+        .filter(|value| !value.is_empty());
+    let raw_title = match raw_title.map(collapse_whitespace) {
+        Some(title) if !title.is_empty() => title,
+        _ => {
+            let (artist, featured) = match uploader {
+                Some(uploader) => {
+                    let (artist, featured) = split_featured(&uploader);
+                    (Some(artist), featured)
+                }
+                None => (None, Vec::new()),
+            };
+            return NormalizedTrack {
+                artist,
+                featured_artists: featured,
+                ..NormalizedTrack::default()
+            };
+        }
+    };
+
+    let mut result = NormalizedTrack::default();
+    let (remainder, groups) = split_bracket_groups(&raw_title);
+    let mut kept_groups = Vec::new();
+
+    for group in groups {
+        let group = collapse_whitespace(&group);
+        if group.is_empty() || is_promo_group(&group) {
+            continue;
+        }
+        if let Some(featured) = strip_feature_prefix(&group) {
+            result.featured_artists.extend(split_credits(&featured));
+            continue;
+        }
+        if let Some((version, remixers)) = classify_version(&group) {
+            if result.version.is_none() {
+                result.version = version;
+            }
+            result.remixers.extend(remixers);
+            continue;
+        }
+        kept_groups.push(group);
+    }
+
+    let remainder = strip_edge_promo(&remove_promo_segments(&remainder.replace('*', " ")));
+    let mut parts = split_title_parts(&remainder);
+
+    if parts.len() >= 3 {
+        if let Some((version, remixers)) = parts.last().and_then(|part| classify_version(part)) {
+            parts.pop();
+            if result.version.is_none() {
+                result.version = version;
+            }
+            result.remixers.extend(remixers);
+        }
+    }
+
+    if parts.len() >= 3 {
+        if let Some(uploader) = uploader.as_deref() {
+            if parts[0].eq_ignore_ascii_case(uploader) {
+                parts.remove(0);
+            }
+        }
+    }
+
+    let (artist, title) = if parts.len() >= 2 {
+        (Some(parts[0].clone()), parts[1..].join(" - "))
+    } else {
+        (
+            uploader.clone(),
+            parts.into_iter().next().unwrap_or_default(),
+        )
+    };
+
+    if let Some(artist) = artist {
+        let (artist, featured) = split_featured(&artist);
+        result.featured_artists.extend(featured);
+        result.artist = Some(artist).filter(|value| !value.is_empty());
+    }
+
+    let (title, featured) = split_featured(&title);
+    result.featured_artists.extend(featured);
+
+    let mut title = title;
+    for group in kept_groups {
+        title = format!("{title} ({group})");
+    }
+    let title = trim_punctuation(&collapse_whitespace(&title));
+    result.title = Some(title).filter(|value| !value.is_empty());
+
+    dedupe(&mut result.featured_artists);
+    dedupe(&mut result.remixers);
+    result
+}
+
+/// Splits top-level `()`, `[]` and `{}` groups out of `input`. The returned
+/// remainder keeps a space where each group was removed.
+fn split_bracket_groups(input: &str) -> (String, Vec<String>) {
+    let mut remainder = String::new();
+    let mut groups = Vec::new();
+    let mut current = String::new();
+    let mut stack: Vec<char> = Vec::new();
+
+    for character in input.chars() {
+        match character {
+            '(' | '[' | '{' => {
+                if !stack.is_empty() {
+                    current.push(character);
+                }
+                stack.push(character);
+            }
+            ')' | ']' | '}' if !stack.is_empty() => {
+                stack.pop();
+                if stack.is_empty() {
+                    groups.push(std::mem::take(&mut current));
+                    remainder.push(' ');
+                } else {
+                    current.push(character);
+                }
+            }
+            _ if !stack.is_empty() => current.push(character),
+            _ => remainder.push(character),
+        }
+    }
+
+    if !current.is_empty() {
+        groups.push(current);
+    }
+
+    (remainder, groups)
+}
+
+fn is_promo_group(group: &str) -> bool {
+    let simplified = simplify(group);
+    PROMO_WORDS.contains(&simplified.as_str())
+        || PROMO_PHRASES
+            .iter()
+            .any(|phrase| contains_phrase(&simplified, phrase))
+}
+
+/// Drops ` | `-separated segments that are purely promotional and strips
+/// `PREMIERE:`-style prefixes.
+fn remove_promo_segments(value: &str) -> String {
+    let kept: Vec<&str> = value
+        .split(" | ")
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty() && !is_promo_group(segment))
+        .collect();
+    let joined = if kept.is_empty() {
+        value.to_string()
+    } else {
+        kept.join(" ")
+    };
+
+    match joined.split_once(':') {
+        Some((prefix, rest)) if is_promo_group(prefix) && !rest.trim().is_empty() => {
+            rest.trim().to_string()
+        }
+        _ => joined,
+    }
+}
+
+fn strip_edge_promo(value: &str) -> String {
+    let mut current = trim_punctuation(&collapse_whitespace(value));
+
+    loop {
+        let lower = current.to_ascii_lowercase();
+        let mut stripped = None;
+
+        for (phrase, always) in EDGE_PROMO_PHRASES {
+            let candidate = if lower.starts_with(phrase)
+                && lower[phrase.len()..]
+                    .chars()
+                    .next()
+                    .is_some_and(|next| !next.is_alphanumeric())
+            {
+                Some(trim_punctuation(&current[phrase.len()..]))
+            } else if lower.ends_with(phrase)
+                && lower[..lower.len() - phrase.len()]
+                    .chars()
+                    .last()
+                    .is_some_and(|previous| !previous.is_alphanumeric())
+            {
+                Some(trim_punctuation(&current[..current.len() - phrase.len()]))
+            } else {
+                None
+            };
+
+            if let Some(candidate) = candidate {
+                let keeps_pair = TITLE_SEPARATORS
+                    .iter()
+                    .any(|separator| candidate.contains(separator));
+                if !candidate.is_empty() && (*always || keeps_pair) {
+                    stripped = Some(candidate);
+                    break;
+                }
+            }
+        }
+
+        match stripped {
+            Some(next) => current = next,
+            None => return current,
+        }
+    }
+}
+
+fn split_title_parts(value: &str) -> Vec<String> {
+    let mut parts = vec![value.to_string()];
+    for separator in TITLE_SEPARATORS {
+        parts = parts
+            .into_iter()
+            .flat_map(|part| {
+                part.split(separator)
+                    .map(|piece| piece.to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+    }
+
+    parts
+        .into_iter()
+        .map(|part| trim_punctuation(&collapse_whitespace(&part)))
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Classifies a bracketed group such as `Bicep Remix` or `Extended Mix`.
+///
+/// Returns `None` when the group is not a version descriptor. `Original Mix`
+/// is recognised but yields no version, since it carries no information.
+fn classify_version(group: &str) -> Option<(Option<String>, Vec<String>)> {
+    let trimmed = trim_punctuation(group);
+    let lower = trimmed.to_ascii_lowercase();
+
+    if lower == "original" || lower == "original mix" || lower == "original version" {
+        return Some((None, Vec::new()));
+    }
+
+    let (keyword, credits_person) = VERSION_KEYWORDS
+        .iter()
+        .find(|(keyword, _)| lower == *keyword || lower.ends_with(&format!(" {keyword}")))?;
+
+    let prefix = trimmed[..trimmed.len() - keyword.len()].trim();
+    let mut remixers = Vec::new();
+
+    if *credits_person && !prefix.is_empty() {
+        let mut words: Vec<&str> = prefix.split_whitespace().collect();
+        while words
+            .last()
+            .is_some_and(|word| GENERIC_VERSION_WORDS.contains(&word.to_ascii_lowercase().as_str()))
+        {
+            words.pop();
+        }
+        let credit = words.join(" ");
+        let all_generic = prefix
+            .split_whitespace()
+            .all(|word| GENERIC_VERSION_WORDS.contains(&word.to_ascii_lowercase().as_str()));
+        if !credit.is_empty() && !all_generic {
+            remixers = split_credits(&credit);
+        }
+    }
+
+    Some((Some(trimmed), remixers))
+}
+
+fn strip_feature_prefix(group: &str) -> Option<String> {
+    let padded = format!(" {group}");
+    let lower = padded.to_ascii_lowercase();
+    FEATURE_MARKERS
+        .iter()
+        .find(|marker| lower.starts_with(*marker))
+        .map(|marker| padded[marker.len()..].trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Splits `Main feat. Guest` into the main part and the featured credits.
+fn split_featured(value: &str) -> (String, Vec<String>) {
+    let lower = value.to_ascii_lowercase();
+    let found = FEATURE_MARKERS
+        .iter()
+        .filter_map(|marker| lower.find(marker).map(|index| (index, marker.len())))
+        .min_by_key(|(index, _)| *index);
+
+    match found {
+        Some((index, length)) => {
+            let main = trim_punctuation(&value[..index]);
+            let featured = split_credits(&value[index + length..]);
+            (main, featured)
+        }
+        None => (trim_punctuation(value), Vec::new()),
+    }
+}
+
+fn split_credits(value: &str) -> Vec<String> {
+    let mut credits = vec![value.to_string()];
+    for separator in CREDIT_SEPARATORS {
+        credits = credits
+            .into_iter()
+            .flat_map(|credit| split_ascii_case_insensitive(&credit, separator))
+            .collect();
+    }
+
+    credits
+        .into_iter()
+        .map(|credit| trim_punctuation(&credit))
+        .filter(|credit| !credit.is_empty())
+        .collect()
+}
+
+fn split_ascii_case_insensitive(value: &str, separator: &str) -> Vec<String> {
+    let lower = value.to_ascii_lowercase();
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    while let Some(offset) = lower[start..].find(separator) {
+        pieces.push(value[start..start + offset].to_string());
+        start += offset + separator.len();
+    }
+    pieces.push(value[start..].to_string());
+    pieces
+}
+
+/// Lowercases and replaces everything but letters and digits with single spaces.
+fn simplify(value: &str) -> String {
+    value
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn contains_phrase(simplified: &str, phrase: &str) -> bool {
+    format!(" {simplified} ").contains(&format!(" {phrase} "))
+}
+
+fn collapse_whitespace(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn trim_punctuation(value: &str) -> String {
+    value
+        .trim_matches(|c: char| {
+            c.is_whitespace()
+                || matches!(c, '-' | '–' | '—' | '|' | ':' | '*' | '"' | '~' | '/' | ',')
+        })
+        .to_string()
+}
+
+fn dedupe(values: &mut Vec<String>) {
+    let mut seen = Vec::new();
+    values.retain(|value| {
+        let key = value.to_lowercase();
+        if seen.contains(&key) {
+            false
+        } else {
+            seen.push(key);
+            true
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Case {
+        uploader: Option<&'static str>,
+        title: &'static str,
+        artist: Option<&'static str>,
+        expected_title: Option<&'static str>,
+        featured: &'static [&'static str],
+        remixers: &'static [&'static str],
+        version: Option<&'static str>,
+    }
+
+    const fn case(
+        uploader: Option<&'static str>,
+        title: &'static str,
+        artist: Option<&'static str>,
+        expected_title: Option<&'static str>,
+    ) -> Case {
+        Case {
+            uploader,
+            title,
+            artist,
+            expected_title,
+            featured: &[],
+            remixers: &[],
+            version: None,
+        }
+    }
+
+    const CASES: &[Case] = &[
+        case(Some("Bicep"), "Glue", Some("Bicep"), Some("Glue")),
+        case(Some("  Bicep "), "  Glue  ", Some("Bicep"), Some("Glue")),
+        case(
+            Some("Ninja Tune"),
+            "Bonobo - Kerala",
+            Some("Bonobo"),
+            Some("Kerala"),
+        ),
+        case(
+            Some("Label"),
+            "Artist – Title",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Label"),
+            "Artist — Title",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(None, "Artist - Title", Some("Artist"), Some("Title")),
+        case(Some("Uploader"), "Title", Some("Uploader"), Some("Title")),
+        case(
+            Some("Label"),
+            "Artist - Title [FREE DL]",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Label"),
+            "Artist - Title (Free Download)",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Label"),
+            "[Premiere] Artist - Title",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Label"),
+            "PREMIERE: Artist - Title",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Label"),
+            "Premiere | Artist - Title",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Label"),
+            "Artist - Title *FREE DOWNLOAD*",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Label"),
+            "FREE DL: Artist - Title",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Label"),
+            "Artist - Title [OUT NOW ON LABEL]",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Label"),
+            "Artist - Title (Clip)",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Label"),
+            "Artist - Title [Exclusive]",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Label"),
+            "Artist - Title (Preview)",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Label"),
+            "Artist - Title (Original Mix)",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Label"),
+            "Artist - Title Out Now",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Label"),
+            "Label - Artist - Title",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Artist"),
+            "Title (Part 1)",
+            Some("Artist"),
+            Some("Title (Part 1)"),
+        ),
+        case(Some("Artist"), "Freedom", Some("Artist"), Some("Freedom")),
+        case(
+            Some("Artist"),
+            "Premiere Night",
+            Some("Artist"),
+            Some("Premiere Night"),
+        ),
+        case(
+            Some("Artist"),
+            "Title *FREE DOWNLOAD*",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Artist"),
+            "Title [Out Now]",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(
+            Some("Label"),
+            "FREE DL Artist - Title",
+            Some("Artist"),
+            Some("Title"),
+        ),
+        case(Some("Artist"), "Artist - Mix", Some("Artist"), Some("Mix")),
+        case(Some("Artist"), "", Some("Artist"), None),
+        case(None, "", None, None),
+        case(
+            Some("Label"),
+            "Sigur Rós - Hoppípolla",
+            Some("Sigur Rós"),
+            Some("Hoppípolla"),
+        ),
+    ];
+
+    #[test]
+    fn parses_artist_and_title() {
+        for case in CASES {
+            let normalized = normalize_track(case.uploader, Some(case.title));
+            assert_eq!(
+                normalized.artist.as_deref(),
+                case.artist,
+                "artist for {:?} / {:?}",
+                case.uploader,
+                case.title
+            );
+            assert_eq!(
+                normalized.title.as_deref(),
+                case.expected_title,
+                "title for {:?} / {:?}",
+                case.uploader,
+                case.title
+            );
+            assert_eq!(
+                normalized.featured_artists, case.featured,
+                "{:?}",
+                case.title
+            );
+            assert_eq!(normalized.remixers, case.remixers, "{:?}", case.title);
+            assert_eq!(
+                normalized.version.as_deref(),
+                case.version,
+                "{:?}",
+                case.title
+            );
+        }
+    }
+
+    const CREDIT_CASES: &[Case] = &[
+        Case {
+            uploader: Some("Label"),
+            title: "Artist - Title (Bicep Remix)",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &[],
+            remixers: &["Bicep"],
+            version: Some("Bicep Remix"),
+        },
+        Case {
+            uploader: Some("Label"),
+            title: "Artist - Title [Dixon Edit]",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &[],
+            remixers: &["Dixon"],
+            version: Some("Dixon Edit"),
+        },
+        Case {
+            uploader: Some("Label"),
+            title: "Artist - Title (Extended Mix)",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &[],
+            remixers: &[],
+            version: Some("Extended Mix"),
+        },
+        Case {
+            uploader: Some("Label"),
+            title: "Artist - Title (Radio Edit)",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &[],
+            remixers: &[],
+            version: Some("Radio Edit"),
+        },
+        Case {
+            uploader: Some("Label"),
+            title: "Artist - Title (Bicep Extended Remix)",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &[],
+            remixers: &["Bicep"],
+            version: Some("Bicep Extended Remix"),
+        },
+        Case {
+            uploader: Some("Label"),
+            title: "Artist - Title (Foo & Bar Remix)",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &[],
+            remixers: &["Foo", "Bar"],
+            version: Some("Foo & Bar Remix"),
+        },
+        Case {
+            uploader: Some("Label"),
+            title: "Artist - Title (Someone Bootleg)",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &[],
+            remixers: &["Someone"],
+            version: Some("Someone Bootleg"),
+        },
+        Case {
+            uploader: Some("Label"),
+            title: "Artist - Title (VIP)",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &[],
+            remixers: &[],
+            version: Some("VIP"),
+        },
+        Case {
+            uploader: Some("Label"),
+            title: "Artist - Title - Someone Remix",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &[],
+            remixers: &["Someone"],
+            version: Some("Someone Remix"),
+        },
+        Case {
+            uploader: Some("Label"),
+            title: "Artist - Title (Someone RMX) [FREE DL]",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &[],
+            remixers: &["Someone"],
+            version: Some("Someone RMX"),
+        },
+        Case {
+            uploader: Some("Label"),
+            title: "Artist feat. Singer - Title",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &["Singer"],
+            remixers: &[],
+            version: None,
+        },
+        Case {
+            uploader: Some("Label"),
+            title: "Artist ft. Singer - Title",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &["Singer"],
+            remixers: &[],
+            version: None,
+        },
+        Case {
+            uploader: Some("Label"),
+            title: "Artist - Title (feat. Singer)",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &["Singer"],
+            remixers: &[],
+            version: None,
+        },
+        Case {
+            uploader: Some("Label"),
+            title: "Artist - Title feat. Singer & Rapper",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &["Singer", "Rapper"],
+            remixers: &[],
+            version: None,
+        },
+        Case {
+            uploader: Some("Artist Featuring Singer"),
+            title: "Title",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &["Singer"],
+            remixers: &[],
+            version: None,
+        },
+        Case {
+            uploader: Some("Label"),
+            title: "Artist - Title (feat. Singer) (Someone Remix) [Premiere]",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &["Singer"],
+            remixers: &["Someone"],
+            version: Some("Someone Remix"),
+        },
+        Case {
+            uploader: Some("Label"),
+            title: "Artist feat. Singer - Title (feat. Singer)",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &["Singer"],
+            remixers: &[],
+            version: None,
+        },
+        Case {
+            uploader: Some("Label"),
+            title: "Artist - Title (Original Mix) (Someone Dub)",
+            artist: Some("Artist"),
+            expected_title: Some("Title"),
+            featured: &[],
+            remixers: &["Someone"],
+            version: Some("Someone Dub"),
+        },
+    ];
+
+    #[test]
+    fn extracts_credits_and_versions() {
+        for case in CREDIT_CASES {
+            let normalized = normalize_track(case.uploader, Some(case.title));
+            assert_eq!(
+                normalized.artist.as_deref(),
+                case.artist,
+                "{:?}",
+                case.title
+            );
+            assert_eq!(
+                normalized.title.as_deref(),
+                case.expected_title,
+                "{:?}",
+                case.title
+            );
+            assert_eq!(
+                normalized.featured_artists, case.featured,
+                "{:?}",
+                case.title
+            );
+            assert_eq!(normalized.remixers, case.remixers, "{:?}", case.title);
+            assert_eq!(
+                normalized.version.as_deref(),
+                case.version,
+                "{:?}",
+                case.title
+            );
+        }
+    }
+
+    #[test]
+    fn missing_title_keeps_uploader() {
+        let normalized = normalize_track(Some("Artist"), None);
+        assert_eq!(normalized.artist.as_deref(), Some("Artist"));
+        assert_eq!(normalized.title, None);
+    }
+
+    #[test]
+    fn search_terms_join_artist_and_title() {
+        let normalized = normalize_track(Some("Label"), Some("Artist - Title [FREE DL]"));
+        assert_eq!(normalized.search_terms(), "Artist Title");
+    }
+
+    #[test]
+    fn splits_bracket_groups_with_nesting() {
+        let (remainder, groups) = split_bracket_groups("Title (Someone [UK] Remix) [Free]");
+        assert_eq!(remainder.trim(), "Title");
+        assert_eq!(groups, vec!["Someone [UK] Remix", "Free"]);
+    }
+}
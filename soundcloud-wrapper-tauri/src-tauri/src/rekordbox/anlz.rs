@@ -0,0 +1,329 @@
+//! Cue points from the ANLZ analysis files of a device export.
+//!
+//! `ANLZ0000.DAT` holds the memory and hot cues in `PCOB` sections. Newer
+//! exports also write `ANLZ0000.EXT`, whose `PCO2` sections repeat them with
+//! their comments and colors. Both files are a `PMAI` header followed by
+//! tagged sections; all integers are big endian.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::{RekordboxCue, RekordboxError};
+
+const FILE_MAGIC: &[u8; 4] = b"PMAI";
+const SECTION_HEADER_SIZE: usize = 12;
+const CUE_LIST: &[u8; 4] = b"PCOB";
+const EXTENDED_CUE_LIST: &[u8; 4] = b"PCO2";
+const CUE_ENTRY: &[u8; 4] = b"PCPT";
+const EXTENDED_CUE_ENTRY: &[u8; 4] = b"PCP2";
+
+/// Entries with this status were deleted in Rekordbox.
+const DISABLED_STATUS: u32 = 0;
+const LOOP_ENTRY: u8 = 2;
+
+/// `Type` values of the cues in a Rekordbox XML export, which the other
+/// importers keep as they are.
+const CUE_TYPE: &str = "0";
+const LOOP_TYPE: &str = "4";
+
+/// Reads the cues of the track analyzed into `dat_path`, preferring the
+/// extended list of the `.EXT` file next to it. Unreadable files are logged
+/// and give no cues, so one bad analysis does not stop an import.
+pub(super) fn read_cues(dat_path: &Path) -> Vec<RekordboxCue> {
+    let ext_path = dat_path.with_extension("EXT");
+    for (path, section) in [
+        (ext_path.as_path(), EXTENDED_CUE_LIST),
+        (dat_path, CUE_LIST),
+    ] {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+            Err(error) => {
+                log::warn!(
+                    "failed to read rekordbox analysis {}: {error}",
+                    path.display()
+                );
+                continue;
+            }
+        };
+        match parse_cues(&data, section) {
+            Ok(cues) if !cues.is_empty() => return cues,
+            Ok(_) => {}
+            Err(error) => {
+                log::warn!(
+                    "failed to parse rekordbox analysis {}: {error}",
+                    path.display()
+                );
+            }
+        }
+    }
+    Vec::new()
+}
+
+/// Collects the cues of every `section` list in an ANLZ file, ordered by
+/// slot and position.
+fn parse_cues(data: &[u8], section: &[u8; 4]) -> Result<Vec<RekordboxCue>, RekordboxError> {
+    if read_bytes(data, 0, 4)? != FILE_MAGIC {
+        return Err(RekordboxError::Export("not an ANLZ file".to_string()));
+    }
+    let mut offset = read_u32(data, 4)? as usize;
+    let mut cues = Vec::new();
+
+    while offset + SECTION_HEADER_SIZE <= data.len() {
+        let tag = read_bytes(data, offset, 4)?;
+        let length = read_u32(data, offset + 8)? as usize;
+        if length < SECTION_HEADER_SIZE {
+            return Err(RekordboxError::Export(format!(
+                "section at offset {offset} is {length} bytes long"
+            )));
+        }
+        if tag == section {
+            let body = read_bytes(
+                data,
+                offset + SECTION_HEADER_SIZE,
+                length - SECTION_HEADER_SIZE,
+            )?;
+            if section == EXTENDED_CUE_LIST {
+                read_extended_cue_list(body, &mut cues)?;
+            } else {
+                read_cue_list(body, &mut cues)?;
+            }
+        }
+        offset += length;
+    }
+
+    cues.sort_by_key(|cue| (cue.slot, cue.position_ms));
+    Ok(cues)
+}
+
+fn read_cue_list(body: &[u8], cues: &mut Vec<RekordboxCue>) -> Result<(), RekordboxError> {
+    let count = read_u16(body, 6)?;
+    let mut offset = 12;
+    for _ in 0..count {
+        let entry = read_entry(body, offset, CUE_ENTRY)?;
+        offset += entry.len();
+        if read_u32(entry, 16)? == DISABLED_STATUS {
+            continue;
+        }
+        cues.push(RekordboxCue {
+            slot: slot(read_u32(entry, 12)?),
+            name: None,
+            color: None,
+            position_ms: i64::from(read_u32(entry, 32)?),
+            end_ms: loop_end(read_u32(entry, 32)?, read_u32(entry, 36)?),
+            cue_type: Some(cue_type(read_u8(entry, 28)?)),
+        });
+    }
+    Ok(())
+}
+
+fn read_extended_cue_list(body: &[u8], cues: &mut Vec<RekordboxCue>) -> Result<(), RekordboxError> {
+    let count = read_u16(body, 4)?;
+    let mut offset = 8;
+    for _ in 0..count {
+        let entry = read_entry(body, offset, EXTENDED_CUE_ENTRY)?;
+        offset += entry.len();
+
+        // Entries written by older versions end before the comment.
+        let mut name = None;
+        let mut color = None;
+        if entry.len() >= 44 {
+            let comment_length = read_u32(entry, 40)? as usize;
+            let comment = read_bytes(entry, 44, comment_length)?;
+            let units: Vec<u16> = comment
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            let comment = String::from_utf16_lossy(&units);
+            let comment = comment.trim_end_matches('\0');
+            name = (!comment.is_empty()).then(|| comment.to_string());
+
+            // A color code, then the red, green and blue the player shows.
+            if let Ok(rgb) = read_bytes(entry, 44 + comment_length + 1, 3) {
+                if rgb.iter().any(|channel| *channel != 0) {
+                    color = Some(format!("#{:02X}{:02X}{:02X}", rgb[0], rgb[1], rgb[2]));
+                }
+            }
+        }
+
+        cues.push(RekordboxCue {
+            slot: slot(read_u32(entry, 12)?),
+            name,
+            color,
+            position_ms: i64::from(read_u32(entry, 20)?),
+            end_ms: loop_end(read_u32(entry, 20)?, read_u32(entry, 24)?),
+            cue_type: Some(cue_type(read_u8(entry, 16)?)),
+        });
+    }
+    Ok(())
+}
+
+/// The entry at `offset`, sized by the length in its header.
+fn read_entry<'a>(
+    body: &'a [u8],
+    offset: usize,
+    magic: &[u8; 4],
+) -> Result<&'a [u8], RekordboxError> {
+    if read_bytes(body, offset, 4)? != magic {
+        return Err(RekordboxError::Export(format!(
+            "expected a cue entry at offset {offset}"
+        )));
+    }
+    let length = read_u32(body, offset + 8)? as usize;
+    if length < SECTION_HEADER_SIZE {
+        return Err(RekordboxError::Export(format!(
+            "cue entry at offset {offset} is {length} bytes long"
+        )));
+    }
+    read_bytes(body, offset, length)
+}
+
+/// Hot cues count from 1 in ANLZ files and 0 is a memory cue. XML exports
+/// number hot cues from 0 and memory cues -1, which is what is stored.
+fn slot(hot_cue: u32) -> i64 {
+    i64::from(hot_cue) - 1
+}
+
+/// Entries that are not loops have no end, or one of `u32::MAX`.
+fn loop_end(time: u32, loop_time: u32) -> Option<i64> {
+    (loop_time != u32::MAX && loop_time > time).then(|| i64::from(loop_time))
+}
+
+fn cue_type(kind: u8) -> String {
+    if kind == LOOP_ENTRY {
+        LOOP_TYPE
+    } else {
+        CUE_TYPE
+    }
+    .to_string()
+}
+
+fn read_bytes(data: &[u8], offset: usize, length: usize) -> Result<&[u8], RekordboxError> {
+    let end = offset.checked_add(length);
+    end.and_then(|end| data.get(offset..end)).ok_or_else(|| {
+        RekordboxError::Export(format!(
+            "read of {length} bytes at offset {offset} is past the end of the data"
+        ))
+    })
+}
+
+fn read_u8(data: &[u8], offset: usize) -> Result<u8, RekordboxError> {
+    Ok(read_bytes(data, offset, 1)?[0])
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, RekordboxError> {
+    let bytes = read_bytes(data, offset, 2)?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, RekordboxError> {
+    let bytes = read_bytes(data, offset, 4)?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(tag: &[u8; 4], body: Vec<u8>) -> Vec<u8> {
+        let mut bytes = tag.to_vec();
+        bytes.extend(12u32.to_be_bytes());
+        bytes.extend(((body.len() + SECTION_HEADER_SIZE) as u32).to_be_bytes());
+        bytes.extend(body);
+        bytes
+    }
+
+    fn anlz_file(sections: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut bytes = FILE_MAGIC.to_vec();
+        bytes.extend(28u32.to_be_bytes());
+        bytes.resize(28, 0);
+        for section in sections {
+            bytes.extend(section);
+        }
+        bytes
+    }
+
+    fn cue_entry(hot_cue: u32, status: u32, kind: u8, time: u32) -> Vec<u8> {
+        let mut entry = CUE_ENTRY.to_vec();
+        entry.extend(28u32.to_be_bytes());
+        entry.extend(56u32.to_be_bytes());
+        entry.extend(hot_cue.to_be_bytes());
+        entry.extend(status.to_be_bytes());
+        entry.extend(0x10000u32.to_be_bytes());
+        entry.extend([0xff; 4]);
+        entry.extend([kind, 0, 0, 0]);
+        entry.extend(time.to_be_bytes());
+        entry.resize(56, 0);
+        entry
+    }
+
+    fn extended_cue_entry(hot_cue: u32, time: u32, comment: &str, rgb: [u8; 3]) -> Vec<u8> {
+        let mut comment: Vec<u8> = comment.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        comment.extend([0, 0]);
+        let mut entry = EXTENDED_CUE_ENTRY.to_vec();
+        entry.extend(16u32.to_be_bytes());
+        entry.extend(((48 + comment.len()) as u32).to_be_bytes());
+        entry.extend(hot_cue.to_be_bytes());
+        entry.extend([1, 0, 0, 0]);
+        entry.extend(time.to_be_bytes());
+        entry.extend(u32::MAX.to_be_bytes());
+        entry.extend([0; 12]);
+        entry.extend((comment.len() as u32).to_be_bytes());
+        entry.extend(comment);
+        entry.push(0x01);
+        entry.extend(rgb);
+        entry
+    }
+
+    #[test]
+    fn reads_memory_and_hot_cues() {
+        let mut hot_cues = 1u32.to_be_bytes().to_vec();
+        hot_cues.extend([0, 0]);
+        hot_cues.extend(2u16.to_be_bytes());
+        hot_cues.extend(0u32.to_be_bytes());
+        hot_cues.extend(cue_entry(2, 1, 2, 64_000));
+        hot_cues.extend(cue_entry(1, 0, 1, 1_000));
+
+        let mut memory_cues = 0u32.to_be_bytes().to_vec();
+        memory_cues.extend([0, 0]);
+        memory_cues.extend(1u16.to_be_bytes());
+        memory_cues.extend(1u32.to_be_bytes());
+        memory_cues.extend(cue_entry(0, 1, 1, 500));
+
+        let data = anlz_file(vec![
+            section(b"PPTH", vec![0; 8]),
+            section(CUE_LIST, hot_cues),
+            section(CUE_LIST, memory_cues),
+        ]);
+        let cues = parse_cues(&data, CUE_LIST).unwrap();
+
+        let cues: Vec<_> = cues
+            .iter()
+            .map(|cue| (cue.slot, cue.position_ms, cue.cue_type.as_deref()))
+            .collect();
+        assert_eq!(cues, [(-1, 500, Some("0")), (1, 64_000, Some("4"))]);
+    }
+
+    #[test]
+    fn extended_cues_have_names_and_colors() {
+        let mut body = 1u32.to_be_bytes().to_vec();
+        body.extend(1u16.to_be_bytes());
+        body.extend([0, 0]);
+        body.extend(extended_cue_entry(1, 32_000, "Drop", [0xe6, 0x28, 0x28]));
+
+        let data = anlz_file(vec![section(EXTENDED_CUE_LIST, body)]);
+        let cues = parse_cues(&data, EXTENDED_CUE_LIST).unwrap();
+
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].slot, 0);
+        assert_eq!(cues[0].position_ms, 32_000);
+        assert_eq!(cues[0].name.as_deref(), Some("Drop"));
+        assert_eq!(cues[0].color.as_deref(), Some("#E62828"));
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_anlz() {
+        assert!(parse_cues(b"RIFF\0\0\0\0", CUE_LIST).is_err());
+    }
+}
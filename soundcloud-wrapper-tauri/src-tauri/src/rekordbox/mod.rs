@@ -0,0 +1,786 @@
+mod anlz;
+mod pdb;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use quick_xml::de::from_reader as from_xml_reader;
+use quick_xml::escape::escape;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::analysis::{AnalysisBuilder, AudioAnalysis};
+use crate::dj_library::{CueKind, DjCue};
+use crate::fingerprint::FingerprintBuilder;
+use crate::integrity::SpectrumBuilder;
+use crate::waveform::{WaveformBuilder, WaveformData};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RekordboxCue {
+    pub slot: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    pub position_ms: i64,
+    /// End of a loop.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cue_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RekordboxTrack {
+    pub rekordbox_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track_reference: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub album: Option<String>,
+    /// The musical key as Rekordbox displays it, e.g. `Am` or `8A`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bpm: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normalized_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_size: Option<u64>,
+    #[serde(skip)]
+    pub fingerprint: Option<Vec<u32>>,
+    pub available: bool,
+    /// Paths of the playlists holding the track, folders joined by `/`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub playlists: Vec<String>,
+    pub cues: Vec<RekordboxCue>,
+}
+
+#[derive(Debug)]
+pub enum RekordboxError {
+    Io(std::io::Error),
+    Database(rusqlite::Error),
+    Xml(quick_xml::DeError),
+    /// A device export or one of its analysis files is malformed.
+    Export(String),
+    Audio(SymphoniaError),
+}
+
+impl fmt::Display for RekordboxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RekordboxError::Io(error) => write!(f, "filesystem error: {error}"),
+            RekordboxError::Database(error) => write!(f, "sqlite error: {error}"),
+            RekordboxError::Xml(error) => write!(f, "xml error: {error}"),
+            RekordboxError::Export(message) => write!(f, "device export error: {message}"),
+            RekordboxError::Audio(error) => write!(f, "audio probe error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for RekordboxError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RekordboxError::Io(error) => Some(error),
+            RekordboxError::Database(error) => Some(error),
+            RekordboxError::Xml(error) => Some(error),
+            RekordboxError::Export(_) => None,
+            RekordboxError::Audio(error) => Some(error),
+        }
+    }
+}
+
+impl From<std::io::Error> for RekordboxError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<rusqlite::Error> for RekordboxError {
+    fn from(value: rusqlite::Error) -> Self {
+        Self::Database(value)
+    }
+}
+
+impl From<quick_xml::DeError> for RekordboxError {
+    fn from(value: quick_xml::DeError) -> Self {
+        Self::Xml(value)
+    }
+}
+
+impl From<SymphoniaError> for RekordboxError {
+    fn from(value: SymphoniaError) -> Self {
+        Self::Audio(value)
+    }
+}
+
+/// Tracks imported without naming a source belong to this one. Its track IDs
+/// are `rekordbox:<id>`, the scheme used before there were several sources.
+pub const DEFAULT_SOURCE_ID: &str = "default";
+
+/// The kinds of Rekordbox library the importer reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RekordboxFormat {
+    /// The `master.db` database Rekordbox 6 and later keep their collection in.
+    MasterDb,
+    /// A collection exported with File > Export Collection in xml format.
+    Xml,
+    /// The `PIONEER/rekordbox/export.pdb` database Rekordbox writes to USB
+    /// drives and SD cards, read from the drive root or the file itself.
+    DeviceExport,
+}
+
+impl RekordboxFormat {
+    pub fn detect(path: &Path) -> Self {
+        if path.is_dir() || pdb::is_export_file(path) {
+            return Self::DeviceExport;
+        }
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("xml") => Self::Xml,
+            _ => Self::MasterDb,
+        }
+    }
+
+    /// XML and device exports only change when someone exports again, so
+    /// they are not watched.
+    pub fn supports_auto_refresh(self) -> bool {
+        match self {
+            Self::MasterDb => true,
+            Self::Xml | Self::DeviceExport => false,
+        }
+    }
+}
+
+pub fn load_tracks(path: &Path) -> Result<Vec<RekordboxTrack>, RekordboxError> {
+    load_tracks_as(path, RekordboxFormat::detect(path))
+}
+
+pub fn load_tracks_as(
+    path: &Path,
+    format: RekordboxFormat,
+) -> Result<Vec<RekordboxTrack>, RekordboxError> {
+    match format {
+        RekordboxFormat::Xml => parse_xml_export(path),
+        RekordboxFormat::MasterDb => parse_master_db(path),
+        RekordboxFormat::DeviceExport => pdb::parse_device_export(path),
+    }
+}
+
+pub fn supports_auto_refresh(path: &Path) -> bool {
+    RekordboxFormat::detect(path).supports_auto_refresh()
+}
+
+/// Modification time of a library file, in milliseconds since the Unix
+/// epoch.
+pub fn modified_at(path: &Path) -> Option<i64> {
+    let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+    let elapsed = modified.duration_since(UNIX_EPOCH).ok()?;
+    Some(elapsed.as_millis() as i64)
+}
+
+/// The track ID of a Rekordbox entry imported from `source_id`.
+pub fn track_id(source_id: &str, rekordbox_id: &str) -> String {
+    if source_id == DEFAULT_SOURCE_ID {
+        format!("rekordbox:{rekordbox_id}")
+    } else {
+        format!("rekordbox:{source_id}:{rekordbox_id}")
+    }
+}
+
+fn parse_master_db(path: &Path) -> Result<Vec<RekordboxTrack>, RekordboxError> {
+    let connection = Connection::open(path)?;
+    let mut cue_statement = connection
+        .prepare("SELECT SongID, HotCueNo, InMsec, OutMsec, Name, Color, Type FROM djmdHotCue")?;
+
+    let mut cue_rows = cue_statement.query([])?;
+    let mut cue_map: HashMap<i64, Vec<RekordboxCue>> = HashMap::new();
+
+    while let Some(row) = cue_rows.next()? {
+        let song_id: i64 = row.get(0)?;
+        let slot: i64 = row.get(1)?;
+        let position: i64 = row.get::<_, Option<i64>>(2)?.unwrap_or_default();
+        // Cues that are not loops end at -1.
+        let end: Option<i64> = row.get(3)?;
+        let name: Option<String> = row.get(4)?;
+        let color: Option<String> = row.get(5)?;
+        let cue_type: Option<String> = row.get(6)?;
+
+        cue_map.entry(song_id).or_default().push(RekordboxCue {
+            slot,
+            name,
+            color,
+            position_ms: position,
+            end_ms: end.filter(|end| *end > position),
+            cue_type,
+        });
+    }
+
+    let mut statement = connection.prepare(
+        "SELECT ID, TrackID, Title, Artist, Album, FilePath, FolderPath, FileName FROM djmdSong",
+    )?;
+
+    let mut rows = statement.query([])?;
+    let mut tracks = Vec::new();
+
+    while let Some(row) = rows.next()? {
+        let rekordbox_id: i64 = row.get(0)?;
+        let rekordbox_id_str = rekordbox_id.to_string();
+        let track_reference: Option<String> = row.get(1)?;
+        let title: Option<String> = row.get(2)?;
+        let artist: Option<String> = row.get(3)?;
+        let album: Option<String> = row.get(4)?;
+        let file_path_value: Option<String> = row.get(5)?;
+        let folder_path: Option<String> = row.get(6)?;
+        let file_name: Option<String> = row.get(7)?;
+
+        let location = resolve_location(&file_path_value, &folder_path, &file_name);
+        let normalized_path = location.as_ref().and_then(|value| decode_location(value));
+
+        let metadata = entry_metadata(normalized_path.as_deref(), &rekordbox_id_str);
+
+        tracks.push(RekordboxTrack {
+            rekordbox_id: rekordbox_id_str,
+            track_reference,
+            title,
+            artist,
+            album,
+            key: None,
+            bpm: None,
+            location,
+            normalized_path,
+            checksum: metadata.checksum,
+            duration_ms: metadata.duration_ms,
+            file_size: metadata.size,
+            fingerprint: metadata.fingerprint,
+            available: metadata.available,
+            playlists: Vec::new(),
+            cues: cue_map.remove(&rekordbox_id).unwrap_or_default(),
+        });
+    }
+
+    Ok(tracks)
+}
+
+#[derive(Debug, Deserialize)]
+struct XmlRoot {
+    #[serde(rename = "COLLECTION")]
+    collection: Option<XmlCollection>,
+}
+
+#[derive(Debug, Deserialize)]
+struct XmlCollection {
+    #[serde(rename = "TRACK", default)]
+    tracks: Vec<XmlTrack>,
+}
+
+#[derive(Debug, Deserialize)]
+struct XmlTrack {
+    #[serde(rename = "@TrackID")]
+    track_id: Option<String>,
+    #[serde(rename = "@Name")]
+    name: Option<String>,
+    #[serde(rename = "@Artist")]
+    artist: Option<String>,
+    #[serde(rename = "@Album")]
+    album: Option<String>,
+    #[serde(rename = "@Location")]
+    location: Option<String>,
+    #[serde(rename = "@RekordboxID")]
+    rekordbox_id: Option<String>,
+    #[serde(rename = "POSITION_MARK", default)]
+    position_marks: Vec<XmlCue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct XmlCue {
+    #[serde(rename = "@Num")]
+    slot: Option<i64>,
+    #[serde(rename = "@Name")]
+    name: Option<String>,
+    #[serde(rename = "@Color")]
+    color: Option<String>,
+    #[serde(rename = "@Type")]
+    cue_type: Option<String>,
+    #[serde(rename = "@Start")]
+    start: Option<f64>,
+    #[serde(rename = "@End")]
+    end: Option<f64>,
+    #[serde(rename = "@Red")]
+    red: Option<u8>,
+    #[serde(rename = "@Green")]
+    green: Option<u8>,
+    #[serde(rename = "@Blue")]
+    blue: Option<u8>,
+}
+
+fn parse_xml_export(path: &Path) -> Result<Vec<RekordboxTrack>, RekordboxError> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+    let root: XmlRoot = from_xml_reader(reader)?;
+    let collection = match root.collection {
+        Some(collection) => collection,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut result = Vec::new();
+
+    for entry in collection.tracks {
+        let rekordbox_id = match entry
+            .rekordbox_id
+            .clone()
+            .or_else(|| entry.track_id.clone())
+        {
+            Some(id) => id,
+            None => {
+                log::warn!(
+                    "skipping rekordbox XML track without an identifier: {:?}",
+                    entry.name
+                );
+                continue;
+            }
+        };
+
+        let normalized_path = entry
+            .location
+            .as_ref()
+            .and_then(|value| decode_location(value));
+
+        let metadata = entry_metadata(normalized_path.as_deref(), &rekordbox_id);
+
+        let cues = entry
+            .position_marks
+            .into_iter()
+            .map(|cue| RekordboxCue {
+                slot: cue.slot.unwrap_or_default(),
+                name: cue.name,
+                // Hot cues carry their color as separate channels.
+                color: cue.color.or_else(|| match (cue.red, cue.green, cue.blue) {
+                    (Some(red), Some(green), Some(blue)) => {
+                        Some(format!("#{red:02X}{green:02X}{blue:02X}"))
+                    }
+                    _ => None,
+                }),
+                position_ms: cue
+                    .start
+                    .map(|value| (value * 1000.0) as i64)
+                    .unwrap_or_default(),
+                end_ms: cue.end.map(|value| (value * 1000.0) as i64),
+                cue_type: cue.cue_type,
+            })
+            .collect();
+
+        result.push(RekordboxTrack {
+            rekordbox_id,
+            track_reference: entry.track_id,
+            title: entry.name,
+            artist: entry.artist,
+            album: entry.album,
+            key: None,
+            bpm: None,
+            location: entry.location.clone(),
+            normalized_path,
+            checksum: metadata.checksum,
+            duration_ms: metadata.duration_ms,
+            file_size: metadata.size,
+            fingerprint: metadata.fingerprint,
+            available: metadata.available,
+            playlists: Vec::new(),
+            cues,
+        });
+    }
+
+    Ok(result)
+}
+
+fn resolve_location(
+    file_path: &Option<String>,
+    folder_path: &Option<String>,
+    file_name: &Option<String>,
+) -> Option<String> {
+    if let Some(path) = file_path.clone() {
+        if !path.is_empty() {
+            return Some(path);
+        }
+    }
+
+    match (folder_path, file_name) {
+        (Some(folder), Some(name)) => {
+            if folder.ends_with('/') {
+                Some(format!("{folder}{name}"))
+            } else {
+                Some(format!("{folder}/{name}"))
+            }
+        }
+        _ => None,
+    }
+}
+
+/// `POSITION_MARK` elements for `cues`, to go into a `TRACK` of an XML
+/// export. Positions are in seconds; hot cues carry their color.
+pub(crate) fn position_marks(cues: &[DjCue]) -> String {
+    let marks: Vec<String> = cues
+        .iter()
+        .map(|cue| {
+            let cue_type = match cue.kind {
+                CueKind::Cue => 0,
+                CueKind::FadeIn => 1,
+                CueKind::FadeOut => 2,
+                CueKind::Load => 3,
+                CueKind::Loop => 4,
+            };
+            let mut mark = format!(
+                r#"<POSITION_MARK Name="{}" Type="{cue_type}" Start="{:.3}""#,
+                escape(cue.name.as_deref().unwrap_or_default()),
+                cue.position_ms / 1000.0,
+            );
+            if let Some(length) = cue.length_ms.filter(|_| cue.kind == CueKind::Loop) {
+                mark.push_str(&format!(
+                    r#" End="{:.3}""#,
+                    (cue.position_ms + length) / 1000.0
+                ));
+            }
+            mark.push_str(&format!(r#" Num="{}""#, cue.hot_cue.map_or(-1, i64::from)));
+            let rgb = cue
+                .color
+                .as_deref()
+                .and_then(|color| color.strip_prefix('#'))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok());
+            if let Some(rgb) = rgb.filter(|_| cue.hot_cue.is_some()) {
+                mark.push_str(&format!(
+                    r#" Red="{}" Green="{}" Blue="{}""#,
+                    (rgb >> 16) & 0xFF,
+                    (rgb >> 8) & 0xFF,
+                    rgb & 0xFF
+                ));
+            }
+            mark.push_str("/>");
+            mark
+        })
+        .collect();
+    marks.join("\n")
+}
+
+fn decode_location(value: &str) -> Option<PathBuf> {
+    if value.starts_with("file://") {
+        if let Ok(url) = url::Url::parse(value) {
+            if url.scheme() == "file" {
+                return url.to_file_path().ok();
+            }
+        }
+    }
+
+    Some(PathBuf::from(value))
+}
+
+pub(crate) struct FileMetadata {
+    pub(crate) checksum: Option<String>,
+    pub(crate) duration_ms: Option<u64>,
+    pub(crate) size: Option<u64>,
+    pub(crate) fingerprint: Option<Vec<u32>>,
+    pub(crate) available: bool,
+}
+
+impl FileMetadata {
+    fn missing() -> Self {
+        Self {
+            checksum: None,
+            duration_ms: None,
+            size: None,
+            fingerprint: None,
+            available: false,
+        }
+    }
+}
+
+/// Metadata of the file behind a library entry; missing when the entry has
+/// no file or it cannot be read.
+pub(crate) fn entry_metadata(path: Option<&Path>, entry_id: &str) -> FileMetadata {
+    path.and_then(|path| match compute_file_metadata(path) {
+        Ok(metadata) => Some(metadata),
+        Err(error) => {
+            log::warn!("failed to compute metadata for library entry {entry_id}: {error}");
+            None
+        }
+    })
+    .unwrap_or_else(FileMetadata::missing)
+}
+
+pub(crate) fn compute_file_metadata(path: &Path) -> Result<FileMetadata, RekordboxError> {
+    let size = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(FileMetadata::missing());
+        }
+        Err(error) => return Err(error.into()),
+    };
+
+    let checksum = compute_checksum(path)?;
+
+    let decoded = decode_audio(
+        path,
+        DecodeOptions {
+            fingerprint: true,
+            ..DecodeOptions::default()
+        },
+    )
+    .unwrap_or_default();
+
+    Ok(FileMetadata {
+        checksum: Some(checksum),
+        duration_ms: decoded.duration_ms,
+        size: Some(size),
+        fingerprint: decoded.fingerprint,
+        available: true,
+    })
+}
+
+/// SHA-256 of the file contents, hex encoded.
+pub(crate) fn compute_checksum(path: &Path) -> Result<String, RekordboxError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let bytes = reader.read(&mut buffer)?;
+        if bytes == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub(crate) fn compute_duration(path: &Path) -> Result<Option<u64>, RekordboxError> {
+    Ok(decode_audio(path, DecodeOptions::default())?.duration_ms)
+}
+
+pub(crate) fn compute_fingerprint(path: &Path) -> Result<Option<Vec<u32>>, RekordboxError> {
+    let options = DecodeOptions {
+        fingerprint: true,
+        ..DecodeOptions::default()
+    };
+    Ok(decode_audio(path, options)?.fingerprint)
+}
+
+/// Estimates the BPM and key of the file and measures its loudness; `None`
+/// when the file holds no audio.
+pub(crate) fn analyze_file(path: &Path) -> Result<Option<AudioAnalysis>, RekordboxError> {
+    let options = DecodeOptions {
+        analysis: true,
+        ..DecodeOptions::default()
+    };
+    Ok(decode_audio(path, options)?.analysis)
+}
+
+pub(crate) fn compute_waveform(path: &Path) -> Result<Option<WaveformData>, RekordboxError> {
+    let options = DecodeOptions {
+        waveform: true,
+        ..DecodeOptions::default()
+    };
+    Ok(decode_audio(path, options)?.waveform)
+}
+
+/// Decodes the file for an integrity check: packets that fail to decode are
+/// counted and skipped, reading stops at the first unreadable packet, and
+/// the spectral cutoff is measured on the way.
+pub(crate) fn inspect_audio(path: &Path) -> Result<AudioInspection, RekordboxError> {
+    let options = DecodeOptions {
+        spectrum: true,
+        skip_bad_packets: true,
+        ..DecodeOptions::default()
+    };
+    Ok(decode_audio(path, options)?.inspection)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct DecodeOptions {
+    fingerprint: bool,
+    analysis: bool,
+    waveform: bool,
+    spectrum: bool,
+    /// Keeps going past packets that fail to decode and stops at a read
+    /// error instead of failing, recording both in the inspection.
+    skip_bad_packets: bool,
+}
+
+#[derive(Debug, Default)]
+struct DecodedAudio {
+    duration_ms: Option<u64>,
+    fingerprint: Option<Vec<u32>>,
+    analysis: Option<AudioAnalysis>,
+    waveform: Option<WaveformData>,
+    inspection: AudioInspection,
+}
+
+/// The stream's format and what went wrong while decoding it.
+#[derive(Debug, Default)]
+pub(crate) struct AudioInspection {
+    pub codec: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub bit_depth: Option<u32>,
+    pub channels: Option<u32>,
+    /// Length of the stream as declared by the container.
+    pub declared_frames: Option<u64>,
+    pub frames: u64,
+    pub decode_errors: u32,
+    pub first_decode_error: Option<String>,
+    /// Why reading stopped before the end of the stream.
+    pub read_error: Option<String>,
+    pub spectral_cutoff_hz: Option<f32>,
+}
+
+/// Decodes the whole file to count its frames. The fingerprint, the BPM,
+/// key and loudness analysis, the waveform overview and the spectral cutoff
+/// are computed in the same pass when requested.
+fn decode_audio(path: &Path, options: DecodeOptions) -> Result<DecodedAudio, RekordboxError> {
+    let file = File::open(path)?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let format_opts = FormatOptions::default();
+    let metadata_opts = MetadataOptions::default();
+    let probed =
+        symphonia::default::get_probe().format(&hint, mss, &format_opts, &metadata_opts)?;
+
+    let mut format = probed.format;
+
+    let track = format
+        .default_track()
+        .ok_or(SymphoniaError::Unsupported("no audio track"))?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+
+    let codecs = symphonia::default::get_codecs();
+    let mut inspection = AudioInspection {
+        codec: codecs
+            .get_codec(params.codec)
+            .map(|descriptor| descriptor.short_name.to_string()),
+        sample_rate: params.sample_rate,
+        bit_depth: params.bits_per_sample,
+        channels: params.channels.map(|channels| channels.count() as u32),
+        declared_frames: params.n_frames,
+        ..AudioInspection::default()
+    };
+
+    let decoder_opts = DecoderOptions::default();
+    let mut decoder = codecs.make(&params, &decoder_opts)?;
+    let mut fingerprinter: Option<FingerprintBuilder> = None;
+    let mut analyzer: Option<AnalysisBuilder> = None;
+    let mut waveform: Option<WaveformBuilder> = None;
+    let mut spectrum: Option<SpectrumBuilder> = None;
+    let mut first_packet = true;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(ref error))
+                if error.kind() == std::io::ErrorKind::UnexpectedEof =>
+            {
+                break;
+            }
+            Err(SymphoniaError::ResetRequired) => {
+                break;
+            }
+            Err(err) if options.skip_bad_packets => {
+                inspection.read_error = Some(err.to_string());
+                break;
+            }
+            Err(err) => return Err(RekordboxError::Audio(err)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(SymphoniaError::DecodeError(error)) if options.skip_bad_packets => {
+                inspection.decode_errors += 1;
+                inspection
+                    .first_decode_error
+                    .get_or_insert_with(|| error.to_string());
+                continue;
+            }
+            Err(SymphoniaError::IoError(error)) if options.skip_bad_packets => {
+                inspection.decode_errors += 1;
+                inspection
+                    .first_decode_error
+                    .get_or_insert_with(|| error.to_string());
+                continue;
+            }
+            Err(err) if options.skip_bad_packets => {
+                inspection.read_error = Some(err.to_string());
+                break;
+            }
+            Err(err) => return Err(RekordboxError::Audio(err)),
+        };
+        let spec = *decoded.spec();
+        inspection.sample_rate.get_or_insert(spec.rate);
+        inspection
+            .channels
+            .get_or_insert(spec.channels.count() as u32);
+        inspection.frames += decoded.frames() as u64;
+        if first_packet {
+            first_packet = false;
+            if options.fingerprint {
+                fingerprinter = FingerprintBuilder::start(spec.rate, spec.channels.count() as u32);
+            }
+            if options.analysis {
+                analyzer = AnalysisBuilder::start(&spec);
+            }
+            if options.waveform {
+                waveform = WaveformBuilder::start(&spec);
+            }
+            if options.spectrum {
+                spectrum = SpectrumBuilder::start(&spec);
+            }
+        }
+        if let Some(fingerprinter) = fingerprinter.as_mut() {
+            fingerprinter.consume(decoded.clone());
+        }
+        if let Some(analyzer) = analyzer.as_mut() {
+            analyzer.consume(decoded.clone());
+        }
+        if let Some(waveform) = waveform.as_mut() {
+            waveform.consume(decoded.clone());
+        }
+        if let Some(spectrum) = spectrum.as_mut() {
+            spectrum.consume(decoded);
+        }
+    }
+
+    inspection.spectral_cutoff_hz = spectrum.and_then(SpectrumBuilder::finish);
+    let duration_ms = inspection
+        .sample_rate
+        .filter(|rate| *rate > 0)
+        .map(|rate| (inspection.frames as f64 / f64::from(rate) * 1000.0) as u64);
+    Ok(DecodedAudio {
+        duration_ms,
+        fingerprint: fingerprinter.and_then(FingerprintBuilder::finish),
+        analysis: analyzer.and_then(AnalysisBuilder::finish),
+        waveform: waveform.and_then(WaveformBuilder::finish),
+        inspection,
+    })
+}
@@ -0,0 +1,727 @@
+//! Reader for the DeviceSQL `export.pdb` database Rekordbox writes to USB
+//! drives and SD cards for CDJs.
+//!
+//! The file is a list of fixed size pages. The header on page 0 points to
+//! the first and last page of every table, and the pages of a table are
+//! chained through their `next_page` field. Rows live in the page heap; an
+//! index at the end of the page lists their offsets in groups of 16. All
+//! integers are little endian. The layout follows the reverse engineering
+//! of the Deep Symmetry crate-digger project.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::{anlz, entry_metadata, RekordboxError, RekordboxTrack};
+
+const EXPORT_FILE_NAME: &str = "export.pdb";
+
+/// Folders holding the `rekordbox` directory, relative to the drive root.
+/// Some players write the hidden variant.
+const PIONEER_DIRS: [&str; 2] = ["PIONEER", ".PIONEER"];
+
+const TABLE_POINTERS_OFFSET: usize = 0x1c;
+const TABLE_POINTER_SIZE: usize = 16;
+const HEAP_OFFSET: usize = 0x28;
+const ROW_GROUP_SIZE: usize = 0x24;
+const ROWS_PER_GROUP: usize = 16;
+/// Set in the page flags of index pages, which hold no rows.
+const INDEX_PAGE_FLAG: u8 = 0x40;
+
+const TRACKS_TABLE: u32 = 0;
+const ARTISTS_TABLE: u32 = 2;
+const ALBUMS_TABLE: u32 = 3;
+const KEYS_TABLE: u32 = 5;
+const PLAYLIST_TREE_TABLE: u32 = 7;
+const PLAYLIST_ENTRIES_TABLE: u32 = 8;
+
+/// Track rows keep the offsets of their 21 strings from here on.
+const TRACK_STRINGS_OFFSET: usize = 0x5e;
+const TRACK_ANALYZE_PATH: usize = 14;
+const TRACK_TITLE: usize = 17;
+const TRACK_FILE_PATH: usize = 20;
+
+/// Artist and album rows with these subtypes store a 16 bit name offset
+/// instead of an 8 bit one.
+const ARTIST_FAR_NAME: u16 = 0x64;
+const ALBUM_FAR_NAME: u16 = 0x84;
+
+/// Folders are nested at most this deep when building playlist paths, so a
+/// corrupt tree with a cycle cannot loop forever.
+const MAX_PLAYLIST_DEPTH: usize = 32;
+
+/// A device export found on a drive.
+#[derive(Debug, Clone, PartialEq)]
+struct DeviceExport {
+    /// The drive root. Paths in the database are relative to it.
+    root: PathBuf,
+    database: PathBuf,
+}
+
+impl DeviceExport {
+    fn resolve(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+}
+
+pub(super) fn is_export_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.eq_ignore_ascii_case(EXPORT_FILE_NAME))
+}
+
+/// Finds the export when `path` is the drive root, its `PIONEER` or
+/// `rekordbox` folder, or `export.pdb` itself.
+fn locate(path: &Path) -> Option<DeviceExport> {
+    if path.is_file() {
+        if !is_export_file(path) {
+            return None;
+        }
+        let rekordbox_dir = path.parent()?;
+        let pioneer_dir = rekordbox_dir
+            .parent()
+            .filter(|_| has_name(rekordbox_dir, &["rekordbox"]))
+            .filter(|dir| has_name(dir, &PIONEER_DIRS));
+        let root = match pioneer_dir {
+            Some(dir) => dir.parent().unwrap_or(dir),
+            None => rekordbox_dir,
+        };
+        return Some(DeviceExport {
+            root: root.to_path_buf(),
+            database: path.to_path_buf(),
+        });
+    }
+
+    let mut candidates: Vec<PathBuf> = PIONEER_DIRS
+        .iter()
+        .map(|dir| path.join(dir).join("rekordbox").join(EXPORT_FILE_NAME))
+        .collect();
+    candidates.push(path.join("rekordbox").join(EXPORT_FILE_NAME));
+    candidates.push(path.join(EXPORT_FILE_NAME));
+    candidates
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+        .and_then(|candidate| locate(&candidate))
+}
+
+fn has_name(path: &Path, names: &[&str]) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| names.iter().any(|known| name.eq_ignore_ascii_case(known)))
+}
+
+pub(super) fn parse_device_export(path: &Path) -> Result<Vec<RekordboxTrack>, RekordboxError> {
+    let export = locate(path).ok_or_else(|| {
+        RekordboxError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "no PIONEER/rekordbox/{EXPORT_FILE_NAME} under {}",
+                path.display()
+            ),
+        ))
+    })?;
+    let database = ExportDatabase::read(&fs::read(&export.database)?)?;
+    let mut playlists = database.playlists_by_track();
+
+    let mut tracks = Vec::with_capacity(database.tracks.len());
+    for row in &database.tracks {
+        let rekordbox_id = row.id.to_string();
+        let normalized_path = row.file_path.as_deref().map(|path| export.resolve(path));
+        let metadata = entry_metadata(normalized_path.as_deref(), &rekordbox_id);
+        let cues = row
+            .analyze_path
+            .as_deref()
+            .map(|path| anlz::read_cues(&export.resolve(path)))
+            .unwrap_or_default();
+
+        tracks.push(RekordboxTrack {
+            track_reference: None,
+            title: row.title.clone(),
+            artist: database.artists.get(&row.artist_id).cloned(),
+            album: database.albums.get(&row.album_id).cloned(),
+            key: database.keys.get(&row.key_id).cloned(),
+            bpm: (row.tempo > 0).then(|| f64::from(row.tempo) / 100.0),
+            // The database only knows paths on the drive, which are
+            // meaningless once it is mounted somewhere.
+            location: normalized_path
+                .as_ref()
+                .map(|path| path.to_string_lossy().into_owned()),
+            normalized_path,
+            checksum: metadata.checksum,
+            duration_ms: metadata
+                .duration_ms
+                .or((row.duration_secs > 0).then(|| u64::from(row.duration_secs) * 1000)),
+            file_size: metadata.size,
+            fingerprint: metadata.fingerprint,
+            available: metadata.available,
+            playlists: playlists.remove(&row.id).unwrap_or_default(),
+            cues,
+            rekordbox_id,
+        });
+    }
+
+    Ok(tracks)
+}
+
+#[derive(Debug, Default)]
+struct ExportDatabase {
+    tracks: Vec<TrackRow>,
+    artists: HashMap<u32, String>,
+    albums: HashMap<u32, String>,
+    keys: HashMap<u32, String>,
+    playlists: HashMap<u32, PlaylistNode>,
+    entries: Vec<PlaylistEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TrackRow {
+    id: u32,
+    artist_id: u32,
+    album_id: u32,
+    key_id: u32,
+    /// BPM times 100.
+    tempo: u32,
+    duration_secs: u16,
+    title: Option<String>,
+    file_path: Option<String>,
+    analyze_path: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PlaylistNode {
+    parent_id: u32,
+    name: String,
+    is_folder: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PlaylistEntry {
+    position: u32,
+    track_id: u32,
+    playlist_id: u32,
+}
+
+impl ExportDatabase {
+    fn read(data: &[u8]) -> Result<Self, RekordboxError> {
+        let mut database = Self::default();
+        for row in table_rows(data, TRACKS_TABLE)? {
+            database.tracks.push(read_track(row)?);
+        }
+        for row in table_rows(data, ARTISTS_TABLE)? {
+            let far = read_u16(row, 0)? == ARTIST_FAR_NAME;
+            let name_offset = if far {
+                read_u16(row, 0x0a)? as usize
+            } else {
+                read_u8(row, 0x09)? as usize
+            };
+            if let Some(name) = read_string(row, name_offset)? {
+                database.artists.insert(read_u32(row, 0x04)?, name);
+            }
+        }
+        for row in table_rows(data, ALBUMS_TABLE)? {
+            let far = read_u16(row, 0)? == ALBUM_FAR_NAME;
+            let name_offset = if far {
+                read_u16(row, 0x16)? as usize
+            } else {
+                read_u8(row, 0x15)? as usize
+            };
+            if let Some(name) = read_string(row, name_offset)? {
+                database.albums.insert(read_u32(row, 0x0c)?, name);
+            }
+        }
+        for row in table_rows(data, KEYS_TABLE)? {
+            if let Some(name) = read_string(row, 0x08)? {
+                database.keys.insert(read_u32(row, 0)?, name);
+            }
+        }
+        for row in table_rows(data, PLAYLIST_TREE_TABLE)? {
+            database.playlists.insert(
+                read_u32(row, 0x0c)?,
+                PlaylistNode {
+                    parent_id: read_u32(row, 0)?,
+                    name: read_string(row, 0x14)?.unwrap_or_default(),
+                    is_folder: read_u32(row, 0x10)? != 0,
+                },
+            );
+        }
+        for row in table_rows(data, PLAYLIST_ENTRIES_TABLE)? {
+            database.entries.push(PlaylistEntry {
+                position: read_u32(row, 0)?,
+                track_id: read_u32(row, 0x04)?,
+                playlist_id: read_u32(row, 0x08)?,
+            });
+        }
+        Ok(database)
+    }
+
+    /// The playlists holding each track, in the order they appear in the
+    /// playlist tree.
+    fn playlists_by_track(&self) -> HashMap<u32, Vec<String>> {
+        let mut entries = self.entries.clone();
+        entries.sort_by_key(|entry| (entry.playlist_id, entry.position));
+
+        let mut result: HashMap<u32, Vec<String>> = HashMap::new();
+        for entry in entries {
+            let Some(path) = self.playlist_path(entry.playlist_id) else {
+                continue;
+            };
+            let playlists = result.entry(entry.track_id).or_default();
+            if !playlists.contains(&path) {
+                playlists.push(path);
+            }
+        }
+        result
+    }
+
+    fn playlist_path(&self, playlist_id: u32) -> Option<String> {
+        let playlist = self.playlists.get(&playlist_id)?;
+        if playlist.is_folder {
+            return None;
+        }
+        let mut names = vec![playlist.name.as_str()];
+        let mut parent_id = playlist.parent_id;
+        while let Some(folder) = self.playlists.get(&parent_id) {
+            if names.len() >= MAX_PLAYLIST_DEPTH {
+                break;
+            }
+            names.push(folder.name.as_str());
+            parent_id = folder.parent_id;
+        }
+        names.reverse();
+        Some(names.join("/"))
+    }
+}
+
+fn read_track(row: &[u8]) -> Result<TrackRow, RekordboxError> {
+    let string = |index: usize| -> Result<Option<String>, RekordboxError> {
+        let offset = read_u16(row, TRACK_STRINGS_OFFSET + 2 * index)?;
+        read_string(row, offset as usize)
+    };
+    Ok(TrackRow {
+        id: read_u32(row, 0x48)?,
+        artist_id: read_u32(row, 0x44)?,
+        album_id: read_u32(row, 0x40)?,
+        key_id: read_u32(row, 0x20)?,
+        tempo: read_u32(row, 0x38)?,
+        duration_secs: read_u16(row, 0x54)?,
+        title: string(TRACK_TITLE)?,
+        file_path: string(TRACK_FILE_PATH)?,
+        analyze_path: string(TRACK_ANALYZE_PATH)?,
+    })
+}
+
+/// The rows of the table of `table_type`, each running from its start to
+/// the end of its page. A table the export does not have is empty.
+fn table_rows(data: &[u8], table_type: u32) -> Result<Vec<&[u8]>, RekordboxError> {
+    let page_size = read_u32(data, 0x04)? as usize;
+    if page_size < HEAP_OFFSET + ROW_GROUP_SIZE {
+        return Err(RekordboxError::Export(format!(
+            "invalid page size {page_size}"
+        )));
+    }
+    let table_count = read_u32(data, 0x08)? as usize;
+
+    for table in 0..table_count {
+        let pointer = TABLE_POINTERS_OFFSET + table * TABLE_POINTER_SIZE;
+        if read_u32(data, pointer)? != table_type {
+            continue;
+        }
+        let first_page = read_u32(data, pointer + 8)?;
+        let last_page = read_u32(data, pointer + 12)?;
+
+        let mut rows = Vec::new();
+        let mut visited = HashSet::new();
+        let mut page_index = first_page;
+        loop {
+            if !visited.insert(page_index) {
+                return Err(RekordboxError::Export(format!(
+                    "pages of table {table_type} form a cycle at page {page_index}"
+                )));
+            }
+            let start = page_index as usize * page_size;
+            let page = data.get(start..start + page_size).ok_or_else(|| {
+                RekordboxError::Export(format!("page {page_index} is past the end of the file"))
+            })?;
+            if page[0x1b] & INDEX_PAGE_FLAG == 0 {
+                page_rows(page, &mut rows)?;
+            }
+            if page_index == last_page {
+                break;
+            }
+            page_index = read_u32(page, 0x0c)?;
+        }
+        return Ok(rows);
+    }
+
+    Ok(Vec::new())
+}
+
+fn page_rows<'a>(page: &'a [u8], rows: &mut Vec<&'a [u8]>) -> Result<(), RekordboxError> {
+    let small_count = read_u8(page, 0x18)? as usize;
+    let large_count = read_u16(page, 0x22)? as usize;
+    // The large count is only maintained once a page outgrows the small one.
+    let count = if large_count > small_count && large_count != 0x1fff {
+        large_count
+    } else {
+        small_count
+    };
+    if count == 0 {
+        return Ok(());
+    }
+    let groups = (count - 1) / ROWS_PER_GROUP + 1;
+    if HEAP_OFFSET + groups * ROW_GROUP_SIZE > page.len() {
+        return Err(RekordboxError::Export(format!(
+            "page claims {count} rows, more than fit"
+        )));
+    }
+
+    for group in 0..groups {
+        let base = page.len() - group * ROW_GROUP_SIZE;
+        let present = read_u16(page, base - 4)?;
+        for slot in 0..ROWS_PER_GROUP {
+            if group * ROWS_PER_GROUP + slot >= count {
+                break;
+            }
+            if present & (1 << slot) == 0 {
+                continue;
+            }
+            let offset = read_u16(page, base - 6 - 2 * slot)? as usize;
+            let row = page
+                .get(HEAP_OFFSET + offset..)
+                .filter(|row| !row.is_empty())
+                .ok_or_else(|| {
+                    RekordboxError::Export(format!("row offset {offset} is outside its page"))
+                })?;
+            rows.push(row);
+        }
+    }
+    Ok(())
+}
+
+/// Reads a DeviceSQL string. A set low bit in the first byte marks a short
+/// ASCII string whose length, header included, is in the other seven bits.
+/// Otherwise the byte is the kind of a long string, followed by its length,
+/// header included, and a padding byte.
+fn read_string(row: &[u8], offset: usize) -> Result<Option<String>, RekordboxError> {
+    let header = read_u8(row, offset)?;
+    let text = if header & 1 == 1 {
+        let length = (header >> 1) as usize;
+        String::from_utf8_lossy(read_bytes(row, offset + 1, length.saturating_sub(1))?).into_owned()
+    } else {
+        let length = read_u16(row, offset + 1)? as usize;
+        let bytes = read_bytes(row, offset + 4, length.saturating_sub(4))?;
+        match header {
+            0x40 => String::from_utf8_lossy(bytes).into_owned(),
+            0x90 => {
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                    .collect();
+                String::from_utf16_lossy(&units)
+            }
+            _ => {
+                log::debug!("skipping device export string of unknown kind {header:#x}");
+                return Ok(None);
+            }
+        }
+    };
+    let text = text.trim_end_matches('\0');
+    Ok((!text.is_empty()).then(|| text.to_string()))
+}
+
+fn read_bytes(data: &[u8], offset: usize, length: usize) -> Result<&[u8], RekordboxError> {
+    let end = offset.checked_add(length);
+    end.and_then(|end| data.get(offset..end)).ok_or_else(|| {
+        RekordboxError::Export(format!(
+            "read of {length} bytes at offset {offset} is past the end of the data"
+        ))
+    })
+}
+
+fn read_u8(data: &[u8], offset: usize) -> Result<u8, RekordboxError> {
+    Ok(read_bytes(data, offset, 1)?[0])
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, RekordboxError> {
+    let bytes = read_bytes(data, offset, 2)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, RekordboxError> {
+    let bytes = read_bytes(data, offset, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    const PAGE_SIZE: usize = 1024;
+
+    fn put_u16(data: &mut [u8], offset: usize, value: usize) {
+        data[offset..offset + 2].copy_from_slice(&(value as u16).to_le_bytes());
+    }
+
+    fn put_u32(data: &mut [u8], offset: usize, value: usize) {
+        data[offset..offset + 4].copy_from_slice(&(value as u32).to_le_bytes());
+    }
+
+    /// An export holding `tables`, each a table type and the rows of each
+    /// of its pages.
+    fn export(tables: &[(u32, Vec<Vec<Vec<u8>>>)]) -> Vec<u8> {
+        let page_count: usize = tables.iter().map(|(_, pages)| pages.len()).sum();
+        let mut data = vec![0u8; PAGE_SIZE * (page_count + 1)];
+        put_u32(&mut data, 0x04, PAGE_SIZE);
+        put_u32(&mut data, 0x08, tables.len());
+
+        let mut page_index = 1;
+        for (table, (table_type, pages)) in tables.iter().enumerate() {
+            let pointer = TABLE_POINTERS_OFFSET + table * TABLE_POINTER_SIZE;
+            put_u32(&mut data, pointer, *table_type as usize);
+            put_u32(&mut data, pointer + 8, page_index);
+            put_u32(&mut data, pointer + 12, page_index + pages.len() - 1);
+
+            for rows in pages {
+                let page = &mut data[page_index * PAGE_SIZE..(page_index + 1) * PAGE_SIZE];
+                put_u32(page, 0x04, page_index);
+                put_u32(page, 0x0c, page_index + 1);
+                page[0x18] = rows.len() as u8;
+                page[0x1b] = 0x24;
+                let mut heap = 0;
+                let mut present = 0;
+                for (slot, row) in rows.iter().enumerate() {
+                    page[HEAP_OFFSET + heap..HEAP_OFFSET + heap + row.len()].copy_from_slice(row);
+                    put_u16(page, PAGE_SIZE - 6 - 2 * slot, heap);
+                    present |= 1 << slot;
+                    heap += row.len();
+                }
+                put_u16(page, PAGE_SIZE - 4, present);
+                page_index += 1;
+            }
+        }
+        data
+    }
+
+    fn short_string(text: &str) -> Vec<u8> {
+        let mut bytes = vec![(((text.len() + 1) << 1) | 1) as u8];
+        bytes.extend(text.bytes());
+        bytes
+    }
+
+    fn utf16_string(text: &str) -> Vec<u8> {
+        let units: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let mut bytes = vec![0x90];
+        bytes.extend(((units.len() + 4) as u16).to_le_bytes());
+        bytes.push(0);
+        bytes.extend(units);
+        bytes
+    }
+
+    fn track_row(id: usize, artist_id: usize, title: &str, file_path: &str) -> Vec<u8> {
+        let mut row = vec![0u8; TRACK_STRINGS_OFFSET + 2 * 21];
+        put_u16(&mut row, 0, 0x24);
+        put_u32(&mut row, 0x20, 1);
+        put_u32(&mut row, 0x38, 12_450);
+        put_u32(&mut row, 0x40, 7);
+        put_u32(&mut row, 0x44, artist_id);
+        put_u32(&mut row, 0x48, id);
+        put_u16(&mut row, 0x54, 241);
+        let empty = row.len();
+        row.push(0x03);
+        for index in 0..21 {
+            let offset = match index {
+                TRACK_TITLE => {
+                    let offset = row.len();
+                    row.extend(short_string(title));
+                    offset
+                }
+                TRACK_FILE_PATH => {
+                    let offset = row.len();
+                    row.extend(short_string(file_path));
+                    offset
+                }
+                _ => empty,
+            };
+            put_u16(&mut row, TRACK_STRINGS_OFFSET + 2 * index, offset);
+        }
+        row
+    }
+
+    fn artist_row(id: usize, name: Vec<u8>, far: bool) -> Vec<u8> {
+        let mut row = vec![0u8; if far { 12 } else { 10 }];
+        put_u32(&mut row, 0x04, id);
+        row[0x08] = 0x03;
+        if far {
+            put_u16(&mut row, 0, ARTIST_FAR_NAME as usize);
+            put_u16(&mut row, 0x0a, 12);
+        } else {
+            put_u16(&mut row, 0, 0x60);
+            row[0x09] = 10;
+        }
+        row.extend(name);
+        row
+    }
+
+    fn album_row(id: usize, name: &str) -> Vec<u8> {
+        let mut row = vec![0u8; 0x16];
+        put_u16(&mut row, 0, 0x80);
+        put_u32(&mut row, 0x0c, id);
+        row[0x14] = 0x03;
+        row[0x15] = 0x16;
+        row.extend(short_string(name));
+        row
+    }
+
+    fn key_row(id: usize, name: &str) -> Vec<u8> {
+        let mut row = vec![0u8; 8];
+        put_u32(&mut row, 0, id);
+        put_u32(&mut row, 4, id);
+        row.extend(short_string(name));
+        row
+    }
+
+    fn playlist_row(id: usize, parent_id: usize, name: &str, is_folder: bool) -> Vec<u8> {
+        let mut row = vec![0u8; 0x14];
+        put_u32(&mut row, 0, parent_id);
+        put_u32(&mut row, 0x0c, id);
+        put_u32(&mut row, 0x10, usize::from(is_folder));
+        row.extend(short_string(name));
+        row
+    }
+
+    fn entry_row(position: usize, track_id: usize, playlist_id: usize) -> Vec<u8> {
+        let mut row = vec![0u8; 12];
+        put_u32(&mut row, 0, position);
+        put_u32(&mut row, 4, track_id);
+        put_u32(&mut row, 8, playlist_id);
+        row
+    }
+
+    fn sample_export() -> Vec<u8> {
+        export(&[
+            (
+                TRACKS_TABLE,
+                vec![
+                    vec![track_row(1, 10, "Eple", "/Contents/Royksopp/Eple.mp3")],
+                    vec![track_row(
+                        2,
+                        11,
+                        "Windowlicker",
+                        "/Contents/Windowlicker.flac",
+                    )],
+                ],
+            ),
+            (
+                ARTISTS_TABLE,
+                vec![vec![
+                    artist_row(10, utf16_string("Röyksopp"), true),
+                    artist_row(11, short_string("Aphex Twin"), false),
+                ]],
+            ),
+            (ALBUMS_TABLE, vec![vec![album_row(7, "Melody A.M.")]]),
+            (KEYS_TABLE, vec![vec![key_row(1, "Am")]]),
+            (
+                PLAYLIST_TREE_TABLE,
+                vec![vec![
+                    playlist_row(1, 0, "Sets", true),
+                    playlist_row(2, 1, "Warmup", false),
+                    playlist_row(3, 0, "Peak", false),
+                ]],
+            ),
+            (
+                PLAYLIST_ENTRIES_TABLE,
+                vec![vec![
+                    entry_row(1, 1, 3),
+                    entry_row(2, 2, 3),
+                    entry_row(1, 1, 2),
+                ]],
+            ),
+        ])
+    }
+
+    #[test]
+    fn reads_tracks_with_their_names_keys_and_playlists() {
+        let database = ExportDatabase::read(&sample_export()).unwrap();
+
+        assert_eq!(
+            database.tracks,
+            vec![
+                TrackRow {
+                    id: 1,
+                    artist_id: 10,
+                    album_id: 7,
+                    key_id: 1,
+                    tempo: 12_450,
+                    duration_secs: 241,
+                    title: Some("Eple".to_string()),
+                    file_path: Some("/Contents/Royksopp/Eple.mp3".to_string()),
+                    analyze_path: None,
+                },
+                TrackRow {
+                    id: 2,
+                    artist_id: 11,
+                    album_id: 7,
+                    key_id: 1,
+                    tempo: 12_450,
+                    duration_secs: 241,
+                    title: Some("Windowlicker".to_string()),
+                    file_path: Some("/Contents/Windowlicker.flac".to_string()),
+                    analyze_path: None,
+                },
+            ]
+        );
+        assert_eq!(database.artists[&10], "Röyksopp");
+        assert_eq!(database.artists[&11], "Aphex Twin");
+        assert_eq!(database.albums[&7], "Melody A.M.");
+        assert_eq!(database.keys[&1], "Am");
+
+        let playlists = database.playlists_by_track();
+        assert_eq!(playlists[&1], ["Sets/Warmup", "Peak"]);
+        assert_eq!(playlists[&2], ["Peak"]);
+    }
+
+    #[test]
+    fn rejects_page_chains_that_loop() {
+        let mut data = export(&[(TRACKS_TABLE, vec![vec![track_row(1, 0, "A", "/a.mp3")]])]);
+        // The last page is never reached and the only page points to itself.
+        put_u32(&mut data, TABLE_POINTERS_OFFSET + 12, 9);
+        put_u32(&mut data, PAGE_SIZE + 0x0c, 1);
+
+        let error = ExportDatabase::read(&data).unwrap_err();
+        assert!(error.to_string().contains("cycle"), "{error}");
+    }
+
+    #[test]
+    fn imports_the_export_of_a_drive_root() {
+        let root = env::temp_dir().join(format!("pdb-drive-{}", std::process::id()));
+        let rekordbox_dir = root.join("PIONEER").join("rekordbox");
+        fs::create_dir_all(&rekordbox_dir).unwrap();
+        let database = rekordbox_dir.join(EXPORT_FILE_NAME);
+        fs::write(&database, sample_export()).unwrap();
+
+        for path in [&root, &root.join("PIONEER"), &rekordbox_dir, &database] {
+            let export = locate(path).unwrap();
+            assert_eq!(export.root, root, "located from {}", path.display());
+            assert_eq!(export.database, database);
+        }
+        assert_eq!(locate(&root.join("PIONEER").join("missing")), None);
+
+        let tracks = parse_device_export(&root).unwrap();
+        let eple = &tracks[0];
+        assert_eq!(eple.rekordbox_id, "1");
+        assert_eq!(eple.artist.as_deref(), Some("Röyksopp"));
+        assert_eq!(eple.key.as_deref(), Some("Am"));
+        assert_eq!(eple.bpm, Some(124.5));
+        assert_eq!(
+            eple.normalized_path,
+            Some(root.join("Contents").join("Royksopp").join("Eple.mp3"))
+        );
+        assert!(!eple.available);
+        assert_eq!(eple.duration_ms, Some(241_000));
+        assert_eq!(eple.playlists, ["Sets/Warmup", "Peak"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,390 @@
+//! Finds local assets whose files were moved or renamed and proposes their new
+//! paths, matching on checksum, or on size and duration when no checksum was
+//! recorded.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use crate::errors::CommandError;
+use crate::library::{lock, AssetRelocation, LibraryStore, LocalAssetLocation, RelocationMatch};
+use crate::rekordbox::{compute_checksum, compute_duration};
+use crate::scanner::{collect_audio_files, file_stamp};
+
+/// Largest duration difference still treated as the same recording when
+/// matching on size and duration.
+const DURATION_TOLERANCE_MS: i64 = 1_000;
+
+/// Outcome of a relocation pass.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelocationReport {
+    pub checked: u32,
+    pub missing: u32,
+    pub proposals: Vec<AssetRelocation>,
+    pub unresolved: Vec<UnresolvedAsset>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnresolvedAsset {
+    pub track_id: String,
+    pub location: String,
+    pub reason: String,
+}
+
+struct Candidate {
+    path: PathBuf,
+    size: i64,
+    modified_at: i64,
+}
+
+/// Refreshes the availability of every local asset, then searches the watched
+/// folders and `extra_roots` for the missing ones. Nothing is relinked until
+/// the proposals are passed to [`apply_relocations`].
+pub fn find_relocations(
+    library: &Arc<Mutex<LibraryStore>>,
+    extra_roots: &[String],
+) -> Result<RelocationReport, CommandError> {
+    let (assets, mut roots) = {
+        let store = lock(library)?;
+        let assets = store.list_local_asset_locations()?;
+        let roots: Vec<String> = store
+            .list_watched_folders()?
+            .into_iter()
+            .map(|folder| folder.path)
+            .collect();
+        (assets, roots)
+    };
+    roots.extend(extra_roots.iter().cloned());
+
+    let mut report = RelocationReport {
+        checked: assets.len() as u32,
+        ..RelocationReport::default()
+    };
+    let mut missing = Vec::new();
+    for asset in assets {
+        let exists = Path::new(&asset.location).is_file();
+        if exists != asset.available {
+            lock(library)?.set_local_asset_available(&asset.track_id, exists)?;
+        }
+        if !exists {
+            missing.push(asset);
+        }
+    }
+    report.missing = missing.len() as u32;
+    if missing.is_empty() {
+        return Ok(report);
+    }
+
+    let candidates = collect_candidates(&roots);
+    let mut checksums: HashMap<PathBuf, Option<String>> = HashMap::new();
+    let mut durations: HashMap<PathBuf, Option<i64>> = HashMap::new();
+    let mut claimed: HashSet<PathBuf> = HashSet::new();
+
+    for asset in missing {
+        let pool: Vec<&Candidate> = candidates
+            .iter()
+            .filter(|candidate| !claimed.contains(&candidate.path))
+            .filter(|candidate| match asset.file_size {
+                Some(size) => candidate.size == size,
+                None => same_extension(&candidate.path, &asset.location),
+            })
+            .collect();
+
+        let found = match (&asset.checksum, asset.file_size, asset.duration_ms) {
+            (Some(expected), _, _) => pool
+                .iter()
+                .find(|candidate| {
+                    checksum_of(library, &mut checksums, candidate).as_deref()
+                        == Some(expected.as_str())
+                })
+                .map(|candidate| (candidate.path.clone(), RelocationMatch::Checksum)),
+            (None, Some(_), Some(expected)) => {
+                let matching: Vec<&&Candidate> = pool
+                    .iter()
+                    .filter(|candidate| {
+                        duration_of(&mut durations, candidate)
+                            .map(|duration| (duration - expected).abs() <= DURATION_TOLERANCE_MS)
+                            .unwrap_or(false)
+                    })
+                    .collect();
+                if matching.len() > 1 {
+                    report.unresolved.push(unresolved(
+                        &asset,
+                        "several files match its size and duration",
+                    ));
+                    continue;
+                }
+                matching
+                    .first()
+                    .map(|candidate| (candidate.path.clone(), RelocationMatch::SizeAndDuration))
+            }
+            _ => {
+                report.unresolved.push(unresolved(
+                    &asset,
+                    "no checksum, or size and duration, was recorded",
+                ));
+                continue;
+            }
+        };
+
+        match found {
+            Some((path, matched_by)) => {
+                claimed.insert(path.clone());
+                report.proposals.push(AssetRelocation {
+                    track_id: asset.track_id.clone(),
+                    old_location: asset.location.clone(),
+                    new_location: path.to_string_lossy().into_owned(),
+                    matched_by,
+                });
+            }
+            None => report
+                .unresolved
+                .push(unresolved(&asset, "no matching file in the search roots")),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Relinks the assets in `relocations` whose new file still exists and
+/// returns the ones that were applied.
+pub fn apply_relocations(
+    library: &Arc<Mutex<LibraryStore>>,
+    relocations: &[AssetRelocation],
+) -> Result<Vec<AssetRelocation>, CommandError> {
+    let existing: Vec<AssetRelocation> = relocations
+        .iter()
+        .filter(|relocation| Path::new(&relocation.new_location).is_file())
+        .cloned()
+        .collect();
+    lock(library)?
+        .relocate_local_assets(&existing)
+        .map_err(CommandError::from)
+}
+
+fn collect_candidates(roots: &[String]) -> Vec<Candidate> {
+    let mut paths = Vec::new();
+    for root in roots {
+        let root = Path::new(root);
+        if root.is_dir() {
+            collect_audio_files(root, &mut paths);
+        } else {
+            log::warn!("skipping {}: not a directory", root.display());
+        }
+    }
+    paths.sort();
+    paths.dedup();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let (size, modified_at) = file_stamp(&path)?;
+            Some(Candidate {
+                path,
+                size,
+                modified_at,
+            })
+        })
+        .collect()
+}
+
+/// Reuses the checksum from the last folder scan when the file is unchanged.
+fn checksum_of(
+    library: &Arc<Mutex<LibraryStore>>,
+    cache: &mut HashMap<PathBuf, Option<String>>,
+    candidate: &Candidate,
+) -> Option<String> {
+    if let Some(checksum) = cache.get(&candidate.path) {
+        return checksum.clone();
+    }
+
+    let location = candidate.path.to_string_lossy();
+    let scanned = lock(library)
+        .ok()
+        .and_then(|store| store.find_scanned_file(&location).ok().flatten())
+        .filter(|file| file.size == candidate.size && file.modified_at == candidate.modified_at)
+        .and_then(|file| file.checksum);
+    let checksum = scanned.or_else(|| match compute_checksum(&candidate.path) {
+        Ok(checksum) => Some(checksum),
+        Err(error) => {
+            log::warn!("failed to hash {location}: {error}");
+            None
+        }
+    });
+
+    cache.insert(candidate.path.clone(), checksum.clone());
+    checksum
+}
+
+fn duration_of(cache: &mut HashMap<PathBuf, Option<i64>>, candidate: &Candidate) -> Option<i64> {
+    *cache.entry(candidate.path.clone()).or_insert_with(|| {
+        compute_duration(&candidate.path)
+            .ok()
+            .flatten()
+            .map(|value| value as i64)
+    })
+}
+
+fn same_extension(path: &Path, location: &str) -> bool {
+    let extension = |path: &Path| {
+        path.extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+    };
+    extension(path) == extension(Path::new(location))
+}
+
+fn unresolved(asset: &LocalAssetLocation, reason: &str) -> UnresolvedAsset {
+    UnresolvedAsset {
+        track_id: asset.track_id.clone(),
+        location: asset.location.clone(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::LocalAssetRecord;
+    use crate::test_support::{memory_library, write_wav};
+    use std::env;
+    use std::fs;
+
+    fn search_root(name: &str) -> PathBuf {
+        let root = env::temp_dir().join(format!("relocate-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn record_missing(
+        library: &Arc<Mutex<LibraryStore>>,
+        checksum: Option<String>,
+        file_size: Option<i64>,
+        duration_ms: Option<i64>,
+    ) {
+        library
+            .lock()
+            .unwrap()
+            .record_local_asset(&LocalAssetRecord {
+                track_id: "sc-1".to_string(),
+                location: "/gone/track.wav".to_string(),
+                checksum,
+                available: true,
+                duration_ms,
+                file_size,
+                rekordbox_cues: None,
+            })
+            .unwrap();
+    }
+
+    fn relocate(library: &Arc<Mutex<LibraryStore>>, root: &Path) -> RelocationReport {
+        find_relocations(library, &[root.to_string_lossy().into_owned()]).unwrap()
+    }
+
+    #[test]
+    fn checksum_is_matched_before_size_and_duration() {
+        let root = search_root("checksum");
+        let original = root.join("renamed.wav");
+        write_wav(&original, 1_000);
+        write_wav(&root.join("other.wav"), -1_000);
+        let checksum = compute_checksum(&original).unwrap();
+        let size = fs::metadata(&original).unwrap().len() as i64;
+        let library = memory_library();
+        record_missing(&library, Some(checksum), Some(size), Some(1_000));
+
+        let report = relocate(&library, &root);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(report.missing, 1);
+        assert!(report.unresolved.is_empty());
+        assert_eq!(report.proposals.len(), 1);
+        assert_eq!(report.proposals[0].new_location, original.to_string_lossy());
+        assert_eq!(report.proposals[0].matched_by, RelocationMatch::Checksum);
+    }
+
+    #[test]
+    fn size_and_duration_match_without_a_checksum() {
+        let root = search_root("size");
+        let original = root.join("renamed.wav");
+        write_wav(&original, 1_000);
+        let size = fs::metadata(&original).unwrap().len() as i64;
+        let library = memory_library();
+        record_missing(&library, None, Some(size), Some(1_000));
+
+        let report = relocate(&library, &root);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(report.proposals.len(), 1);
+        assert_eq!(report.proposals[0].new_location, original.to_string_lossy());
+        assert_eq!(
+            report.proposals[0].matched_by,
+            RelocationMatch::SizeAndDuration
+        );
+    }
+
+    #[test]
+    fn several_size_and_duration_matches_are_left_unresolved() {
+        let root = search_root("ambiguous");
+        write_wav(&root.join("a.wav"), 1_000);
+        write_wav(&root.join("b.wav"), -1_000);
+        let size = fs::metadata(root.join("a.wav")).unwrap().len() as i64;
+        let library = memory_library();
+        record_missing(&library, None, Some(size), Some(1_000));
+
+        let report = relocate(&library, &root);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(report.proposals.is_empty());
+        assert_eq!(report.unresolved.len(), 1);
+        assert_eq!(
+            report.unresolved[0].reason,
+            "several files match its size and duration"
+        );
+    }
+
+    #[test]
+    fn assets_without_checksum_or_size_are_not_guessed() {
+        let root = search_root("unknown");
+        write_wav(&root.join("renamed.wav"), 1_000);
+        let library = memory_library();
+        record_missing(&library, None, None, Some(1_000));
+
+        let report = relocate(&library, &root);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(report.proposals.is_empty());
+        assert_eq!(
+            report.unresolved[0].reason,
+            "no checksum, or size and duration, was recorded"
+        );
+    }
+
+    #[test]
+    fn only_proposals_whose_file_exists_are_applied() {
+        let root = search_root("apply");
+        let original = root.join("renamed.wav");
+        write_wav(&original, 1_000);
+        let library = memory_library();
+        record_missing(&library, None, None, None);
+        let proposal = |location: &Path| AssetRelocation {
+            track_id: "sc-1".to_string(),
+            old_location: "/gone/track.wav".to_string(),
+            new_location: location.to_string_lossy().into_owned(),
+            matched_by: RelocationMatch::Checksum,
+        };
+
+        let applied = apply_relocations(
+            &library,
+            &[proposal(&root.join("missing.wav")), proposal(&original)],
+        )
+        .unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(applied.len(), 1);
+        assert_eq!(applied[0].new_location, original.to_string_lossy());
+    }
+}
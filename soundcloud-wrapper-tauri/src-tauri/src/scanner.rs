@@ -0,0 +1,425 @@
+//! Walks the watched music folders, reads each audio file's tags and links the
+//! files to liked SoundCloud tracks that have no local copy yet.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+use serde::Serialize;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
+use symphonia::core::probe::Hint;
+
+use crate::errors::CommandError;
+use crate::library::{
+    lock, LibraryStore, LocalLinkOutcome, LocalLinkStatus, LocalLinkTarget, ScannedFileRecord,
+};
+use crate::matching::{MatchSettings, TrackFeatures};
+use crate::normalize::normalize_track;
+use crate::rekordbox::compute_file_metadata;
+
+/// Extensions symphonia is built to decode in this crate.
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "m4a", "mp4", "aac", "ogg", "oga", "wav"];
+
+/// Tracks scoring below this are not offered as candidates at all.
+const CANDIDATE_MIN_SCORE: f32 = 60.0;
+const MAX_CANDIDATES: usize = 5;
+
+/// Counts reported back after a scan.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanSummary {
+    pub folders: u32,
+    pub scanned: u32,
+    pub unchanged: u32,
+    pub linked: u32,
+    pub ambiguous: u32,
+    pub unmatched: u32,
+    pub failed: u32,
+}
+
+#[derive(Debug, Default)]
+struct FileTags {
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+}
+
+pub fn scan_watched_folders(
+    library: &Arc<Mutex<LibraryStore>>,
+) -> Result<ScanSummary, CommandError> {
+    let folders = lock(library)?.list_watched_folders()?;
+    let paths: Vec<String> = folders.into_iter().map(|folder| folder.path).collect();
+    scan_folders(library, &paths)
+}
+
+/// Scans `folders` recursively. Files whose size and modification time did
+/// not change since the last scan are not read again; unmatched ones are
+/// matched again in case new likes arrived. Links the user confirmed are
+/// left alone.
+pub fn scan_folders(
+    library: &Arc<Mutex<LibraryStore>>,
+    folders: &[String],
+) -> Result<ScanSummary, CommandError> {
+    let mut targets = lock(library)?.list_local_link_targets()?;
+    let mut summary = ScanSummary::default();
+
+    for folder in folders {
+        let root = Path::new(folder);
+        if !root.is_dir() {
+            log::warn!("skipping {folder}: not a directory");
+            continue;
+        }
+        summary.folders += 1;
+
+        let known: HashMap<String, (ScannedFileRecord, LocalLinkStatus)> = lock(library)?
+            .list_scanned_files(folder)?
+            .into_iter()
+            .map(|(file, status)| (file.location.clone(), (file, status)))
+            .collect();
+
+        let mut paths = Vec::new();
+        collect_audio_files(root, &mut paths);
+
+        for path in paths {
+            let location = path.to_string_lossy().into_owned();
+            let (size, modified_at) = match file_stamp(&path) {
+                Some(stamp) => stamp,
+                None => {
+                    summary.failed += 1;
+                    continue;
+                }
+            };
+
+            let file = match known.get(&location) {
+                Some((_, LocalLinkStatus::Confirmed)) => {
+                    summary.unchanged += 1;
+                    continue;
+                }
+                Some((file, status)) if file.size == size && file.modified_at == modified_at => {
+                    if *status != LocalLinkStatus::Unmatched {
+                        summary.unchanged += 1;
+                        continue;
+                    }
+                    file.clone()
+                }
+                _ => match read_file(&path, folder, size, modified_at) {
+                    Some(file) => file,
+                    None => {
+                        summary.failed += 1;
+                        continue;
+                    }
+                },
+            };
+            summary.scanned += 1;
+
+            let outcome = link_file(&file, &targets);
+            match outcome.status {
+                LocalLinkStatus::Linked => summary.linked += 1,
+                LocalLinkStatus::Ambiguous => summary.ambiguous += 1,
+                _ => summary.unmatched += 1,
+            }
+            if let Some(track_id) = &outcome.track_id {
+                targets.retain(|target| &target.track_id != track_id);
+            }
+
+            lock(library)?.record_scanned_file(&file, &outcome)?;
+        }
+
+        lock(library)?.mark_folder_scanned(folder)?;
+    }
+
+    Ok(summary)
+}
+
+pub(crate) fn collect_audio_files(directory: &Path, paths: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(error) => {
+            log::warn!("failed to read {}: {error}", directory.display());
+            return;
+        }
+    };
+
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => collect_audio_files(&path, paths),
+            Ok(file_type) if file_type.is_file() && is_audio_file(&path) => paths.push(path),
+            _ => {}
+        }
+    }
+}
+
+fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| {
+            AUDIO_EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+        .unwrap_or(false)
+}
+
+pub(crate) fn file_stamp(path: &Path) -> Option<(i64, i64)> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(error) => {
+            log::warn!("failed to stat {}: {error}", path.display());
+            return None;
+        }
+    };
+    let modified_at = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default();
+    Some((metadata.len() as i64, modified_at))
+}
+
+fn read_file(path: &Path, folder: &str, size: i64, modified_at: i64) -> Option<ScannedFileRecord> {
+    let metadata = match compute_file_metadata(path) {
+        Ok(metadata) if metadata.available => metadata,
+        Ok(_) => return None,
+        Err(error) => {
+            log::warn!("failed to read {}: {error}", path.display());
+            return None;
+        }
+    };
+    let tags = read_tags(path);
+
+    Some(ScannedFileRecord {
+        location: path.to_string_lossy().into_owned(),
+        folder: folder.to_string(),
+        size,
+        modified_at,
+        checksum: metadata.checksum,
+        duration_ms: metadata.duration_ms.map(|value| value as i64),
+        title: tags.title,
+        artist: tags.artist,
+        album: tags.album,
+        fingerprint: metadata.fingerprint,
+    })
+}
+
+/// Reads ID3, Vorbis comment and MP4 tags. Tags stored ahead of the container
+/// (ID3v2) are read first; the container's own tags fill in what is missing.
+fn read_tags(path: &Path) -> FileTags {
+    let mut tags = FileTags::default();
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return tags,
+    };
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let mut probed = match symphonia::default::get_probe().format(
+        &hint,
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    ) {
+        Ok(probed) => probed,
+        Err(error) => {
+            log::warn!("failed to probe {}: {error}", path.display());
+            return tags;
+        }
+    };
+
+    if let Some(metadata) = probed.metadata.get() {
+        if let Some(revision) = metadata.current() {
+            apply_tags(&mut tags, revision);
+        }
+    }
+    if let Some(revision) = probed.format.metadata().current() {
+        apply_tags(&mut tags, revision);
+    }
+    tags
+}
+
+fn apply_tags(tags: &mut FileTags, revision: &MetadataRevision) {
+    for tag in revision.tags() {
+        let slot = match tag.std_key {
+            Some(StandardTagKey::TrackTitle) => &mut tags.title,
+            Some(StandardTagKey::Artist) => &mut tags.artist,
+            Some(StandardTagKey::Album) => &mut tags.album,
+            _ => continue,
+        };
+        let value = tag.value.to_string().trim().to_string();
+        if slot.is_none() && !value.is_empty() {
+            *slot = Some(value);
+        }
+    }
+}
+
+/// Scores `file` against every target and decides whether the best one is
+/// clear enough to link automatically.
+fn link_file(file: &ScannedFileRecord, targets: &[LocalLinkTarget]) -> LocalLinkOutcome {
+    let settings = MatchSettings::local_files();
+    let features = file_features(file);
+    let mut scored: Vec<(String, f32)> = targets
+        .iter()
+        .filter_map(|target| {
+            let candidate = TrackFeatures {
+                artist: target.artist.clone(),
+                title: target.title.clone(),
+                duration_ms: target.duration_ms.filter(|value| *value > 0),
+            };
+            // Without artist or title to compare the duration alone would
+            // decide, which is no evidence of the same track.
+            features.similarity(&candidate)?;
+            let score = settings.blend(None, &features, &candidate);
+            (score >= CANDIDATE_MIN_SCORE).then(|| (target.track_id.clone(), score))
+        })
+        .collect();
+    scored.sort_by(|left, right| right.1.partial_cmp(&left.1).unwrap_or(Ordering::Equal));
+    scored.truncate(MAX_CANDIDATES);
+
+    let best = scored.first().map(|(_, score)| *score);
+    let second = scored.get(1).map(|(_, score)| *score).unwrap_or(0.0);
+    match best {
+        None => LocalLinkOutcome {
+            status: LocalLinkStatus::Unmatched,
+            track_id: None,
+            confidence: None,
+            candidates: Vec::new(),
+        },
+        Some(best) if settings.is_confident(scored.len(), best, second) => LocalLinkOutcome {
+            status: LocalLinkStatus::Linked,
+            track_id: Some(scored[0].0.clone()),
+            confidence: Some(best),
+            candidates: Vec::new(),
+        },
+        Some(best) => LocalLinkOutcome {
+            status: LocalLinkStatus::Ambiguous,
+            track_id: None,
+            confidence: Some(best),
+            candidates: scored,
+        },
+    }
+}
+
+/// Untagged files are matched on their file name, which is commonly
+/// `Artist - Title`.
+fn file_features(file: &ScannedFileRecord) -> TrackFeatures {
+    let stem = Path::new(&file.location)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned());
+    let title = file.title.clone().or(stem);
+    let normalized = normalize_track(file.artist.as_deref(), title.as_deref());
+
+    TrackFeatures {
+        artist: normalized.artist,
+        title: normalized.title,
+        duration_ms: file.duration_ms.filter(|value| *value > 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(location: &str, artist: Option<&str>, title: Option<&str>) -> ScannedFileRecord {
+        ScannedFileRecord {
+            location: location.to_string(),
+            folder: "/music".to_string(),
+            size: 1,
+            modified_at: 0,
+            checksum: None,
+            duration_ms: Some(240_000),
+            title: title.map(str::to_string),
+            artist: artist.map(str::to_string),
+            album: None,
+            fingerprint: None,
+        }
+    }
+
+    fn target(track_id: &str, artist: &str, title: &str, duration_ms: i64) -> LocalLinkTarget {
+        LocalLinkTarget {
+            track_id: track_id.to_string(),
+            artist: Some(artist.to_string()),
+            title: Some(title.to_string()),
+            duration_ms: Some(duration_ms),
+        }
+    }
+
+    #[test]
+    fn clear_match_is_linked() {
+        let targets = [
+            target("sc-1", "Bicep", "Glue", 240_500),
+            target("sc-2", "Bonobo", "Kerala", 240_000),
+        ];
+        let outcome = link_file(&file("/music/a.mp3", Some("Bicep"), Some("Glue")), &targets);
+        assert_eq!(outcome.status, LocalLinkStatus::Linked);
+        assert_eq!(outcome.track_id.as_deref(), Some("sc-1"));
+        assert!(outcome.confidence.is_some_and(|score| score > 95.0));
+    }
+
+    #[test]
+    fn untagged_files_match_on_their_name() {
+        let targets = [target("sc-1", "Bicep", "Glue", 240_000)];
+        let outcome = link_file(&file("/music/Bicep - Glue.mp3", None, None), &targets);
+        assert_eq!(outcome.track_id.as_deref(), Some("sc-1"));
+    }
+
+    #[test]
+    fn close_candidates_need_confirmation() {
+        let targets = [
+            target("sc-1", "Bicep", "Glue", 240_000),
+            target("sc-2", "Bicep", "Glue", 241_000),
+        ];
+        let outcome = link_file(&file("/music/a.mp3", Some("Bicep"), Some("Glue")), &targets);
+        assert_eq!(outcome.status, LocalLinkStatus::Ambiguous);
+        assert_eq!(outcome.candidates.len(), 2);
+        assert_eq!(outcome.candidates[0].0, "sc-1");
+    }
+
+    #[test]
+    fn weak_or_missing_tags_stay_unmatched() {
+        let targets = [target("sc-1", "Bicep", "Glue", 240_000)];
+        let outcome = link_file(
+            &file("/music/a.mp3", Some("Someone"), Some("Else")),
+            &targets,
+        );
+        assert_eq!(outcome.status, LocalLinkStatus::Unmatched);
+
+        // A matching duration alone is not a match.
+        let untitled = LocalLinkTarget {
+            track_id: "sc-2".to_string(),
+            artist: None,
+            title: None,
+            duration_ms: Some(240_000),
+        };
+        let outcome = link_file(
+            &file("/music/a.mp3", Some("Bicep"), Some("Glue")),
+            &[untitled],
+        );
+        assert_eq!(outcome.status, LocalLinkStatus::Unmatched);
+    }
+
+    #[test]
+    fn a_poisoned_library_lock_is_reported_as_such() {
+        let library = crate::test_support::memory_library();
+        let poisoner = Arc::clone(&library);
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("poison the library lock");
+        })
+        .join();
+
+        let error = scan_watched_folders(&library).unwrap_err();
+        assert_eq!(error, CommandError::lock_poisoned("library store"));
+    }
+}
@@ -0,0 +1,771 @@
+//! App preferences stored as JSON in the app config directory. Library data
+//! and per-provider match and endpoint settings stay in the library database;
+//! this file holds what configures the app itself.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::rekordbox::{RekordboxFormat, DEFAULT_SOURCE_ID};
+
+pub const SETTINGS_FILE_NAME: &str = "settings.json";
+/// Emitted with the new [`AppSettings`] after they were saved.
+pub const SETTINGS_CHANGED_EVENT: &str = "app://settings/changed";
+/// Version written to the settings file. Bump it together with a new entry in
+/// [`MIGRATIONS`] whenever a field is renamed or reinterpreted.
+pub const SETTINGS_VERSION: u32 = 2;
+
+pub const DEFAULT_SIDEBAR_WIDTH: u32 = 320;
+pub const MIN_SIDEBAR_WIDTH: u32 = 160;
+pub const MAX_SIDEBAR_WIDTH: u32 = 960;
+
+/// Upgrades a settings object from one version to the next.
+type Migration = fn(&mut serde_json::Map<String, Value>);
+
+/// `MIGRATIONS[n]` upgrades version `n + 1` to `n + 2`.
+const MIGRATIONS: &[Migration] = &[migrate_rekordbox_sources];
+/// Source IDs become part of track IDs, so they are kept short and plain.
+const MAX_SOURCE_ID_LENGTH: usize = 32;
+
+const MODIFIERS: [&str; 14] = [
+    "shift",
+    "ctrl",
+    "control",
+    "alt",
+    "option",
+    "super",
+    "cmd",
+    "command",
+    "cmdorctrl",
+    "cmdorcontrol",
+    "commandorctrl",
+    "commandorcontrol",
+    "meta",
+    "hyper",
+];
+
+/// Every field has a default, so files written by older versions and partial
+/// updates load as long as the fields they do have are valid.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppSettings {
+    pub version: u32,
+    pub musicbrainz: MusicbrainzSettings,
+    pub window: WindowSettings,
+    pub shortcuts: ShortcutSettings,
+    pub rekordbox: RekordboxSettings,
+}
+
+/// The identity sent to MusicBrainz. Fields left unset fall back to the
+/// `MUSICBRAINZ_APP_NAME`, `MUSICBRAINZ_APP_VERSION`,
+/// `MUSICBRAINZ_APP_CONTACT` and `MUSICBRAINZ_TOKEN` environment variables.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MusicbrainzSettings {
+    pub app_name: Option<String>,
+    pub app_version: Option<String>,
+    pub contact: Option<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WindowSettings {
+    /// Width of the library sidebar in physical pixels. The SoundCloud and
+    /// Bandcamp panes are placed to its right.
+    pub sidebar_width: u32,
+}
+
+/// Global shortcuts in the `Modifier+Key` format of the global shortcut
+/// plugin, e.g. `CmdOrCtrl+Alt+P`. An empty string disables the shortcut.
+/// The media keys are always registered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ShortcutSettings {
+    pub play_pause: String,
+    pub next_track: String,
+    pub previous_track: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RekordboxSettings {
+    pub sources: Vec<RekordboxSource>,
+}
+
+/// A Rekordbox library the app imports from, such as the collection on a
+/// laptop or an export on a USB drive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RekordboxSource {
+    /// Namespace of the source's tracks: `rekordbox:<id>` for the default
+    /// source, `rekordbox:<source>:<id>` for the others.
+    pub id: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    pub path: String,
+    pub format: RekordboxFormat,
+    /// Re-imports the library every time its file changes.
+    #[serde(default)]
+    pub auto_refresh: bool,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            musicbrainz: MusicbrainzSettings::default(),
+            window: WindowSettings::default(),
+            shortcuts: ShortcutSettings::default(),
+            rekordbox: RekordboxSettings::default(),
+        }
+    }
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            sidebar_width: DEFAULT_SIDEBAR_WIDTH,
+        }
+    }
+}
+
+impl Default for ShortcutSettings {
+    fn default() -> Self {
+        Self {
+            play_pause: "CmdOrCtrl+Alt+P".to_string(),
+            next_track: "CmdOrCtrl+Alt+N".to_string(),
+            previous_track: "CmdOrCtrl+Alt+B".to_string(),
+        }
+    }
+}
+
+/// A setting rejected by [`AppSettings::validate`]. `field` is the camelCase
+/// path of the setting, e.g. `window.sidebarWidth`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidSetting {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for InvalidSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+impl std::error::Error for InvalidSetting {}
+
+fn invalid(field: &'static str, message: impl Into<String>) -> InvalidSetting {
+    InvalidSetting {
+        field,
+        message: message.into(),
+    }
+}
+
+impl AppSettings {
+    pub fn validate(&self) -> Result<(), InvalidSetting> {
+        // The user agent is `name/version (contact)`; only the name and
+        // version must not contain a slash, as the contact may be a URL.
+        let musicbrainz = [
+            ("musicbrainz.appName", &self.musicbrainz.app_name, true),
+            (
+                "musicbrainz.appVersion",
+                &self.musicbrainz.app_version,
+                true,
+            ),
+            ("musicbrainz.contact", &self.musicbrainz.contact, false),
+        ];
+        for (field, value, in_product) in musicbrainz {
+            let Some(value) = value.as_deref() else {
+                continue;
+            };
+            if in_product && value.contains('/') {
+                return Err(invalid(field, "must not contain '/'"));
+            }
+            if value.contains(['(', ')', '\n', '\r']) {
+                return Err(invalid(field, "must not contain '(', ')' or line breaks"));
+            }
+        }
+        if self
+            .musicbrainz
+            .token
+            .as_deref()
+            .is_some_and(|token| token.contains(char::is_whitespace))
+        {
+            return Err(invalid("musicbrainz.token", "must not contain whitespace"));
+        }
+
+        let width = self.window.sidebar_width;
+        if !(MIN_SIDEBAR_WIDTH..=MAX_SIDEBAR_WIDTH).contains(&width) {
+            return Err(invalid(
+                "window.sidebarWidth",
+                format!("must be between {MIN_SIDEBAR_WIDTH} and {MAX_SIDEBAR_WIDTH} pixels"),
+            ));
+        }
+
+        let shortcuts = self.shortcuts.entries();
+        for (index, (field, shortcut)) in shortcuts.iter().enumerate() {
+            if shortcut.is_empty() {
+                continue;
+            }
+            validate_shortcut(shortcut).map_err(|message| invalid(field, message))?;
+            let duplicate = shortcuts[..index]
+                .iter()
+                .any(|(_, other)| other.eq_ignore_ascii_case(shortcut));
+            if duplicate {
+                return Err(invalid(field, format!("'{shortcut}' is already in use")));
+            }
+        }
+
+        let sources = &self.rekordbox.sources;
+        for (index, source) in sources.iter().enumerate() {
+            validate_source_id(&source.id)
+                .map_err(|message| invalid("rekordbox.sources", message))?;
+            if source.path.is_empty() {
+                return Err(invalid(
+                    "rekordbox.sources",
+                    format!("source '{}' has no path", source.id),
+                ));
+            }
+            if source.auto_refresh && !source.format.supports_auto_refresh() {
+                return Err(invalid(
+                    "rekordbox.sources",
+                    format!("source '{}' cannot be refreshed automatically", source.id),
+                ));
+            }
+            if let Some(other) = sources[..index]
+                .iter()
+                .find(|other| other.id == source.id || other.path == source.path)
+            {
+                return Err(invalid(
+                    "rekordbox.sources",
+                    format!("sources '{}' and '{}' overlap", other.id, source.id),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops blank optional values, so that `""` and a missing field both
+    /// mean "unset".
+    pub fn normalized(mut self) -> Self {
+        self.version = SETTINGS_VERSION;
+        for value in [
+            &mut self.musicbrainz.app_name,
+            &mut self.musicbrainz.app_version,
+            &mut self.musicbrainz.contact,
+            &mut self.musicbrainz.token,
+        ] {
+            *value = value
+                .take()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty());
+        }
+        for shortcut in [
+            &mut self.shortcuts.play_pause,
+            &mut self.shortcuts.next_track,
+            &mut self.shortcuts.previous_track,
+        ] {
+            *shortcut = shortcut.trim().to_string();
+        }
+        for source in &mut self.rekordbox.sources {
+            source.id = source.id.trim().to_string();
+            source.path = source.path.trim().to_string();
+            source.label = source
+                .label
+                .take()
+                .map(|label| label.trim().to_string())
+                .filter(|label| !label.is_empty());
+        }
+        self
+    }
+}
+
+impl RekordboxSettings {
+    pub fn source(&self, id: &str) -> Option<&RekordboxSource> {
+        self.sources.iter().find(|source| source.id == id)
+    }
+
+    pub fn source_for_path(&self, path: &str) -> Option<&RekordboxSource> {
+        self.sources.iter().find(|source| source.path == path)
+    }
+
+    /// An unused source ID. The first source takes over the default
+    /// namespace, so tracks imported before sources existed keep their IDs.
+    pub fn new_source_id(&self, label: Option<&str>, path: &str) -> String {
+        if self.source(DEFAULT_SOURCE_ID).is_none() {
+            return DEFAULT_SOURCE_ID.to_string();
+        }
+        let stem = Path::new(path)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default();
+        let mut base = String::new();
+        for c in label.unwrap_or(stem).chars() {
+            if c.is_ascii_alphanumeric() {
+                base.push(c.to_ascii_lowercase());
+            } else if !base.is_empty() && !base.ends_with('-') {
+                base.push('-');
+            }
+        }
+        // Leaves room for a `-<n>` suffix.
+        base.truncate(MAX_SOURCE_ID_LENGTH - 3);
+        let mut base = base.trim_end_matches('-').to_string();
+        if base.is_empty() {
+            base = "source".to_string();
+        }
+        let mut id = base.clone();
+        let mut suffix = 2;
+        while self.source(&id).is_some() {
+            id = format!("{base}-{suffix}");
+            suffix += 1;
+        }
+        id
+    }
+}
+
+pub fn validate_source_id(id: &str) -> Result<(), String> {
+    let valid = !id.is_empty()
+        && id.len() <= MAX_SOURCE_ID_LENGTH
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "source ID '{id}' must be 1 to {MAX_SOURCE_ID_LENGTH} lowercase letters, digits or dashes"
+        ))
+    }
+}
+
+impl ShortcutSettings {
+    pub fn entries(&self) -> [(&'static str, &str); 3] {
+        [
+            ("shortcuts.playPause", &self.play_pause),
+            ("shortcuts.nextTrack", &self.next_track),
+            ("shortcuts.previousTrack", &self.previous_track),
+        ]
+    }
+}
+
+/// Checks the shape of a shortcut: modifiers followed by one key. Whether the
+/// key exists is only known when the shortcut is registered.
+fn validate_shortcut(shortcut: &str) -> Result<(), String> {
+    let parts: Vec<&str> = shortcut.split('+').map(str::trim).collect();
+    if parts.iter().any(|part| part.is_empty()) {
+        return Err(format!("'{shortcut}' has an empty key"));
+    }
+    let (key, modifiers) = parts.split_last().expect("split yields one part");
+    if is_modifier(key) {
+        return Err(format!("'{shortcut}' must end with a key"));
+    }
+    if let Some(part) = modifiers.iter().find(|part| !is_modifier(part)) {
+        return Err(format!("'{part}' in '{shortcut}' is not a modifier"));
+    }
+    Ok(())
+}
+
+fn is_modifier(part: &str) -> bool {
+    MODIFIERS.contains(&part.to_ascii_lowercase().as_str())
+}
+
+/// The current settings and the file they are saved to.
+pub struct SettingsStore {
+    path: PathBuf,
+    current: Mutex<AppSettings>,
+}
+
+impl SettingsStore {
+    /// Loads the settings file in `config_dir`. A missing file yields the
+    /// defaults. A file that cannot be read is set aside as
+    /// `settings.json.invalid` so the next save does not overwrite it.
+    pub fn load(config_dir: &Path) -> Self {
+        let path = config_dir.join(SETTINGS_FILE_NAME);
+        let settings = match fs::read_to_string(&path) {
+            Ok(contents) => match parse(&contents) {
+                Ok(settings) => settings,
+                Err(error) => {
+                    log::error!("ignoring {}: {error}", path.display());
+                    let invalid = path.with_extension("json.invalid");
+                    if let Err(error) = fs::rename(&path, &invalid) {
+                        log::warn!("failed to set aside {}: {error}", path.display());
+                    }
+                    AppSettings::default()
+                }
+            },
+            Err(error) if error.kind() == io::ErrorKind::NotFound => AppSettings::default(),
+            Err(error) => {
+                log::error!("failed to read {}: {error}", path.display());
+                AppSettings::default()
+            }
+        };
+        Self {
+            path,
+            current: Mutex::new(settings),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self) -> AppSettings {
+        self.current
+            .lock()
+            .map(|settings| settings.clone())
+            .unwrap_or_default()
+    }
+
+    /// Validates and saves `settings`, then makes them current.
+    pub fn update(&self, settings: AppSettings) -> Result<AppSettings, SettingsError> {
+        self.modify(|current| *current = settings)
+    }
+
+    /// Applies `change` to the current settings and saves the result. The
+    /// lock is held throughout, so concurrent changes cannot overwrite each
+    /// other.
+    pub fn modify(
+        &self,
+        change: impl FnOnce(&mut AppSettings),
+    ) -> Result<AppSettings, SettingsError> {
+        let mut current = self
+            .current
+            .lock()
+            .map_err(|_| SettingsError::LockPoisoned)?;
+        let mut settings = current.clone();
+        change(&mut settings);
+        let settings = settings.normalized();
+        settings.validate().map_err(SettingsError::Invalid)?;
+        write_atomically(&self.path, &settings).map_err(|error| SettingsError::Io {
+            path: self.path.clone(),
+            error,
+        })?;
+        *current = settings.clone();
+        Ok(settings)
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Invalid(InvalidSetting),
+    Io { path: PathBuf, error: io::Error },
+    LockPoisoned,
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Invalid(error) => write!(f, "invalid setting {error}"),
+            SettingsError::Io { path, error } => {
+                write!(f, "failed to save {}: {error}", path.display())
+            }
+            SettingsError::LockPoisoned => write!(f, "settings lock poisoned"),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+/// Parses a settings file, migrating it to [`SETTINGS_VERSION`] first.
+fn parse(contents: &str) -> Result<AppSettings, String> {
+    let mut value: Value = serde_json::from_str(contents).map_err(|error| error.to_string())?;
+    migrate(&mut value, MIGRATIONS)?;
+    let settings: AppSettings = serde_json::from_value(value).map_err(|error| error.to_string())?;
+    let settings = settings.normalized();
+    settings.validate().map_err(|error| error.to_string())?;
+    Ok(settings)
+}
+
+/// Files without a version predate versioning and count as version 1. Files
+/// from a newer version are loaded as far as their fields are known.
+fn migrate(value: &mut Value, migrations: &[Migration]) -> Result<(), String> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| "settings must be a JSON object".to_string())?;
+    let version = match object.get("version") {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .filter(|version| *version > 0)
+            .ok_or_else(|| format!("invalid settings version {version}"))?,
+    };
+    let latest = migrations.len() as u32 + 1;
+    if version > latest {
+        log::warn!(
+            "settings were written by a newer version ({version}), unknown fields are dropped"
+        );
+        return Ok(());
+    }
+    for migration in &migrations[version as usize - 1..] {
+        migration(object);
+    }
+    object.insert("version".to_string(), Value::from(latest));
+    Ok(())
+}
+
+/// Version 1 had a single watched `master.db` in `rekordbox.watchPath`.
+fn migrate_rekordbox_sources(object: &mut serde_json::Map<String, Value>) {
+    let Some(rekordbox) = object.get_mut("rekordbox").and_then(Value::as_object_mut) else {
+        return;
+    };
+    let sources: Vec<Value> = rekordbox
+        .remove("watchPath")
+        .as_ref()
+        .and_then(Value::as_str)
+        .filter(|path| !path.trim().is_empty())
+        .map(|path| {
+            json!({
+                "id": DEFAULT_SOURCE_ID,
+                "path": path,
+                "format": RekordboxFormat::MasterDb,
+                "autoRefresh": true,
+            })
+        })
+        .into_iter()
+        .collect();
+    rekordbox.insert("sources".to_string(), Value::from(sources));
+}
+
+/// Writes to a temporary file first, so a crash mid-write keeps the previous
+/// settings.
+fn write_atomically(path: &Path, settings: &AppSettings) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let contents = serde_json::to_string_pretty(settings)?;
+    let temporary = path.with_extension("json.tmp");
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoints::PROJECT_URL;
+    use std::env;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("settings-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn partial_files_fill_in_defaults() {
+        let settings = parse(r#"{"window": {"sidebarWidth": 280}, "extra": true}"#).unwrap();
+        assert_eq!(settings.window.sidebar_width, 280);
+        assert_eq!(settings.shortcuts, ShortcutSettings::default());
+        assert_eq!(settings.version, SETTINGS_VERSION);
+    }
+
+    #[test]
+    fn validation_names_the_rejected_field() {
+        let mut settings = AppSettings::default();
+        settings.window.sidebar_width = 40;
+        assert_eq!(
+            settings.validate().unwrap_err().field,
+            "window.sidebarWidth"
+        );
+
+        let mut settings = AppSettings::default();
+        settings.shortcuts.next_track = "cmdorctrl+alt+p".to_string();
+        assert_eq!(
+            settings.validate().unwrap_err().field,
+            "shortcuts.nextTrack"
+        );
+
+        let mut settings = AppSettings::default();
+        settings.shortcuts.play_pause = "Alt+Shift".to_string();
+        assert_eq!(
+            settings.validate().unwrap_err().field,
+            "shortcuts.playPause"
+        );
+        settings.shortcuts.play_pause = "Ctrl++P".to_string();
+        assert!(settings.validate().is_err());
+        settings.shortcuts.play_pause = "Hyper+F13".to_string();
+        assert!(settings.validate().is_ok());
+        settings.shortcuts.play_pause = String::new();
+        assert!(settings.validate().is_ok());
+
+        let mut settings = AppSettings::default();
+        settings.musicbrainz.contact = Some("me (at) example.com".to_string());
+        assert_eq!(
+            settings.validate().unwrap_err().field,
+            "musicbrainz.contact"
+        );
+        settings.musicbrainz.app_name = Some("Crate/2".to_string());
+        settings.musicbrainz.contact = None;
+        assert_eq!(
+            settings.validate().unwrap_err().field,
+            "musicbrainz.appName"
+        );
+    }
+
+    #[test]
+    fn contacts_may_be_urls() {
+        let mut settings = AppSettings::default();
+        settings.musicbrainz.contact = Some(PROJECT_URL.to_string());
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn migrations_run_from_the_file_version() {
+        fn rename_width(object: &mut serde_json::Map<String, Value>) {
+            if let Some(width) = object.remove("sidebarWidth") {
+                object.insert("window".to_string(), json!({ "sidebarWidth": width }));
+            }
+        }
+        fn noop(_: &mut serde_json::Map<String, Value>) {}
+
+        let mut value = json!({ "sidebarWidth": 300 });
+        migrate(&mut value, &[rename_width, noop]).unwrap();
+        assert_eq!(
+            value,
+            json!({ "version": 3, "window": { "sidebarWidth": 300 } })
+        );
+
+        let mut value = json!({ "version": 2, "sidebarWidth": 300 });
+        migrate(&mut value, &[rename_width, noop]).unwrap();
+        assert_eq!(value, json!({ "version": 3, "sidebarWidth": 300 }));
+
+        let mut value = json!({ "version": 9 });
+        migrate(&mut value, &[rename_width]).unwrap();
+        assert_eq!(value, json!({ "version": 9 }));
+        assert!(migrate(&mut json!({ "version": 0 }), &[]).is_err());
+        assert!(migrate(&mut json!([]), &[]).is_err());
+    }
+
+    fn source(id: &str, path: &str) -> RekordboxSource {
+        RekordboxSource {
+            id: id.to_string(),
+            label: None,
+            path: path.to_string(),
+            format: RekordboxFormat::detect(Path::new(path)),
+            auto_refresh: false,
+        }
+    }
+
+    #[test]
+    fn watched_database_becomes_the_default_source() {
+        let settings = parse(r#"{"rekordbox": {"watchPath": "/music/master.db"}}"#).unwrap();
+        assert_eq!(
+            settings.rekordbox.sources,
+            [RekordboxSource {
+                auto_refresh: true,
+                ..source("default", "/music/master.db")
+            }]
+        );
+
+        let settings = parse(r#"{"version": 1, "rekordbox": {"watchPath": null}}"#).unwrap();
+        assert!(settings.rekordbox.sources.is_empty());
+    }
+
+    #[test]
+    fn sources_need_distinct_ids_and_paths() {
+        let mut settings = AppSettings::default();
+        settings.rekordbox.sources = vec![
+            source("default", "/music/master.db"),
+            source("usb", "/Volumes/USB/rekordbox.xml"),
+        ];
+        assert!(settings.validate().is_ok());
+
+        settings.rekordbox.sources[1].auto_refresh = true;
+        assert_eq!(settings.validate().unwrap_err().field, "rekordbox.sources");
+        settings.rekordbox.sources[1] = source("Laptop 2", "/music/other.db");
+        assert!(settings.validate().is_err());
+        settings.rekordbox.sources[1] = source("usb", "/music/master.db");
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn new_sources_get_unused_ids() {
+        let mut rekordbox = RekordboxSettings::default();
+        assert_eq!(rekordbox.new_source_id(None, "/music/master.db"), "default");
+
+        rekordbox
+            .sources
+            .push(source("default", "/music/master.db"));
+        assert_eq!(
+            rekordbox.new_source_id(Some("USB Stick #1"), "/Volumes/USB/export.pdb"),
+            "usb-stick-1"
+        );
+        rekordbox
+            .sources
+            .push(source("rekordbox", "/a/rekordbox.xml"));
+        assert_eq!(
+            rekordbox.new_source_id(None, "/b/rekordbox.xml"),
+            "rekordbox-2"
+        );
+        assert_eq!(rekordbox.new_source_id(Some("???"), "/c/..."), "source");
+    }
+
+    #[test]
+    fn updates_are_saved_and_reloaded() {
+        let dir = temp_dir("roundtrip");
+        let store = SettingsStore::load(&dir);
+        assert_eq!(store.get(), AppSettings::default());
+
+        let updated = store
+            .modify(|settings| {
+                settings.musicbrainz.token = Some("  ".to_string());
+                settings
+                    .rekordbox
+                    .sources
+                    .push(source("default", "/music/master.db"));
+            })
+            .unwrap();
+        assert_eq!(updated.musicbrainz.token, None);
+
+        let mut invalid = updated.clone();
+        invalid.window.sidebar_width = 0;
+        assert!(matches!(
+            store.update(invalid),
+            Err(SettingsError::Invalid(_))
+        ));
+
+        let reloaded = SettingsStore::load(&dir);
+        assert_eq!(reloaded.get(), updated);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn concurrent_changes_are_all_kept() {
+        let dir = temp_dir("concurrent");
+        let store = SettingsStore::load(&dir);
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    store
+                        .modify(|settings| settings.window.sidebar_width += 10)
+                        .unwrap();
+                });
+            }
+        });
+        assert_eq!(store.get().window.sidebar_width, DEFAULT_SIDEBAR_WIDTH + 80);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unreadable_files_are_set_aside() {
+        let dir = temp_dir("invalid");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(SETTINGS_FILE_NAME), "{ not json").unwrap();
+
+        let store = SettingsStore::load(&dir);
+        assert_eq!(store.get(), AppSettings::default());
+        assert!(!store.path().exists());
+        assert_eq!(
+            fs::read_to_string(dir.join("settings.json.invalid")).unwrap(),
+            "{ not json"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,681 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde_json::{json, Value};
+use tauri::async_runtime;
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+use url::Url;
+
+use crate::endpoints::{env_value, ClientIdentity};
+use crate::errors::CommandError;
+use crate::events::EventSink;
+use crate::library::LibraryStore;
+use crate::normalize::normalize_payload;
+use crate::SoundcloudTrackPayload;
+
+const STORE_LINKS_EVENT: &str = "app://stores/links-resolved";
+const BEATPORT_WEB_URL: &str = "https://www.beatport.com";
+const BEATPORT_API_URL: &str = "https://api.beatport.com/v4";
+const TRAXSOURCE_WEB_URL: &str = "https://www.traxsource.com";
+const MAX_CANDIDATES: usize = 5;
+
+/// A digital music store that can be searched for a purchasable copy of a track.
+///
+/// Every store can build a human-facing search URL. Stores that expose structured
+/// search results additionally return a request for them and know how to turn the
+/// response into candidate matches.
+pub trait StoreProvider: Send + Sync {
+    fn id(&self) -> &'static str;
+
+    fn search_url(&self, query: &StoreQuery) -> Option<Url>;
+
+    fn structured_search(&self, _client: &Client, _query: &StoreQuery) -> Option<RequestBuilder> {
+        None
+    }
+
+    fn parse_results(&self, _body: &Value) -> Vec<StoreCandidate> {
+        Vec::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StoreQuery {
+    pub artist: Option<String>,
+    pub title: Option<String>,
+}
+
+impl StoreQuery {
+    pub fn from_payload(payload: &SoundcloudTrackPayload) -> Self {
+        let normalized = normalize_payload(payload);
+        Self {
+            artist: normalized.artist.as_deref().and_then(clean_term),
+            title: normalized.title.as_deref().and_then(clean_term),
+        }
+    }
+
+    pub fn terms(&self) -> String {
+        [self.artist.as_deref(), self.title.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.artist.is_none() && self.title.is_none()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StoreCandidate {
+    pub product_id: Option<String>,
+    pub title: Option<String>,
+    pub artists: Vec<String>,
+    pub label: Option<String>,
+    pub price: Option<f64>,
+    pub currency: Option<String>,
+    pub format: Option<String>,
+    pub url: Option<String>,
+    pub raw: Value,
+}
+
+impl StoreCandidate {
+    fn into_payload(self, store: &str, score: f32) -> Value {
+        json!({
+            "store": store,
+            "id": self.product_id,
+            "title": self.title,
+            "artists": self.artists,
+            "label": self.label,
+            "price": self.price,
+            "currency": self.currency,
+            "format": self.format,
+            "url": self.url,
+            "score": score,
+            "raw": self.raw,
+        })
+    }
+}
+
+/// Beatport exposes structured track search through its v4 catalog API, which
+/// requires a bearer token. Without a token only the search link is produced.
+pub struct BeatportStore {
+    web_url: String,
+    api_url: String,
+    token: Option<String>,
+}
+
+impl BeatportStore {
+    pub fn new(
+        web_url: impl Into<String>,
+        api_url: impl Into<String>,
+        token: Option<String>,
+    ) -> Self {
+        Self {
+            web_url: web_url.into(),
+            api_url: api_url.into(),
+            token,
+        }
+    }
+
+    fn from_env() -> Self {
+        let token = env_value("BEATPORT_TOKEN");
+        Self::new(BEATPORT_WEB_URL, BEATPORT_API_URL, token)
+    }
+}
+
+impl StoreProvider for BeatportStore {
+    fn id(&self) -> &'static str {
+        "beatport"
+    }
+
+    fn search_url(&self, query: &StoreQuery) -> Option<Url> {
+        let mut url = Url::parse(&format!(
+            "{}/search/tracks",
+            self.web_url.trim_end_matches('/')
+        ))
+        .ok()?;
+        url.query_pairs_mut().append_pair("q", &query.terms());
+        Some(url)
+    }
+
+    fn structured_search(&self, client: &Client, query: &StoreQuery) -> Option<RequestBuilder> {
+        let token = self.token.as_ref()?;
+        let endpoint = format!("{}/catalog/search/", self.api_url.trim_end_matches('/'));
+        let per_page = MAX_CANDIDATES.to_string();
+        Some(client.get(endpoint).bearer_auth(token).query(&[
+            ("q", query.terms().as_str()),
+            ("type", "tracks"),
+            ("per_page", per_page.as_str()),
+        ]))
+    }
+
+    fn parse_results(&self, body: &Value) -> Vec<StoreCandidate> {
+        let tracks = body
+            .get("tracks")
+            .and_then(|value| value.get("data").or(Some(value)))
+            .or_else(|| body.get("results"))
+            .and_then(|value| value.as_array());
+
+        let Some(tracks) = tracks else {
+            return Vec::new();
+        };
+
+        tracks
+            .iter()
+            .map(|track| {
+                let name = track.get("name").and_then(|value| value.as_str());
+                let mix_name = track.get("mix_name").and_then(|value| value.as_str());
+                let title = match (name, mix_name) {
+                    (Some(name), Some(mix)) if !mix.is_empty() => Some(format!("{name} ({mix})")),
+                    (Some(name), _) => Some(name.to_string()),
+                    _ => None,
+                };
+                let product_id = json_id(track.get("id"));
+                let slug = track.get("slug").and_then(|value| value.as_str());
+                let url = match (&product_id, slug) {
+                    (Some(id), Some(slug)) => Some(format!(
+                        "{}/track/{slug}/{id}",
+                        self.web_url.trim_end_matches('/')
+                    )),
+                    _ => None,
+                };
+                let price = track.get("price");
+
+                StoreCandidate {
+                    product_id,
+                    title,
+                    artists: track
+                        .get("artists")
+                        .and_then(|value| value.as_array())
+                        .map(|artists| {
+                            artists
+                                .iter()
+                                .filter_map(|artist| artist.get("name"))
+                                .filter_map(|name| name.as_str())
+                                .map(|name| name.to_string())
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    label: track
+                        .get("release")
+                        .and_then(|release| release.get("label"))
+                        .and_then(|label| label.get("name"))
+                        .and_then(|value| value.as_str())
+                        .map(|value| value.to_string()),
+                    price: price
+                        .and_then(|price| price.get("value"))
+                        .and_then(|value| value.as_f64()),
+                    currency: price
+                        .and_then(|price| price.get("code"))
+                        .and_then(|value| value.as_str())
+                        .map(|value| value.to_string()),
+                    format: track
+                        .get("format")
+                        .or_else(|| track.get("audio_format"))
+                        .and_then(|value| value.as_str())
+                        .map(|value| value.to_string()),
+                    url,
+                    raw: track.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Traxsource has no public search API, so it only contributes a search link.
+pub struct TraxsourceStore {
+    web_url: String,
+}
+
+impl TraxsourceStore {
+    pub fn new(web_url: impl Into<String>) -> Self {
+        Self {
+            web_url: web_url.into(),
+        }
+    }
+}
+
+impl StoreProvider for TraxsourceStore {
+    fn id(&self) -> &'static str {
+        "traxsource"
+    }
+
+    fn search_url(&self, query: &StoreQuery) -> Option<Url> {
+        let mut url = Url::parse(&format!("{}/search", self.web_url.trim_end_matches('/'))).ok()?;
+        url.query_pairs_mut().append_pair("term", &query.terms());
+        Some(url)
+    }
+}
+
+pub fn default_providers() -> Vec<Arc<dyn StoreProvider>> {
+    vec![
+        Arc::new(BeatportStore::from_env()),
+        Arc::new(TraxsourceStore::new(TRAXSOURCE_WEB_URL)),
+    ]
+}
+
+#[derive(Clone)]
+pub struct StoreLinkService {
+    sender: mpsc::Sender<SoundcloudTrackPayload>,
+}
+
+impl StoreLinkService {
+    pub fn new(
+        events: Arc<dyn EventSink>,
+        library: Arc<Mutex<LibraryStore>>,
+        providers: Vec<Arc<dyn StoreProvider>>,
+        identity: &ClientIdentity,
+    ) -> Result<Self, CommandError> {
+        let (sender, mut receiver) = mpsc::channel::<SoundcloudTrackPayload>(32);
+        let client = Client::builder()
+            .user_agent(identity.user_agent())
+            .build()
+            .map_err(|error| {
+                CommandError::internal(format!("failed to build store client: {error}"))
+            })?;
+        async_runtime::spawn(async move {
+            let mut rate_limiter = RateLimiter::new(Duration::from_millis(1000));
+            while let Some(payload) = receiver.recv().await {
+                if payload.track_id.is_empty() {
+                    continue;
+                }
+                process_job(
+                    events.as_ref(),
+                    Arc::clone(&library),
+                    &client,
+                    &providers,
+                    &mut rate_limiter,
+                    payload,
+                )
+                .await;
+            }
+        });
+
+        Ok(Self { sender })
+    }
+
+    pub fn queue_lookup(&self, payload: SoundcloudTrackPayload) {
+        let sender = self.sender.clone();
+        async_runtime::spawn(async move {
+            if let Err(error) = sender.send(payload).await {
+                log::error!("failed to enqueue lookup: {error}");
+            }
+        });
+    }
+}
+
+struct RateLimiter {
+    last: Option<Instant>,
+    interval: Duration,
+}
+
+impl RateLimiter {
+    fn new(interval: Duration) -> Self {
+        Self {
+            last: None,
+            interval,
+        }
+    }
+
+    async fn wait(&mut self) {
+        if let Some(last) = self.last {
+            let elapsed = last.elapsed();
+            if elapsed < self.interval {
+                sleep(self.interval - elapsed).await;
+            }
+        }
+        self.last = Some(Instant::now());
+    }
+}
+
+async fn process_job(
+    events: &dyn EventSink,
+    library: Arc<Mutex<LibraryStore>>,
+    client: &Client,
+    providers: &[Arc<dyn StoreProvider>],
+    rate_limiter: &mut RateLimiter,
+    payload: SoundcloudTrackPayload,
+) {
+    let track_id = payload.track_id.clone();
+    let query = StoreQuery::from_payload(&payload);
+    let terms = query.terms();
+    let mut resolved = Vec::new();
+
+    for provider in providers {
+        let store = provider.id();
+        let search_url = provider.search_url(&query).map(|url| url.to_string());
+
+        if query.is_empty() {
+            if let Ok(mut store_guard) = library.lock() {
+                if let Err(error) = store_guard.record_store_failure(
+                    &track_id,
+                    store,
+                    &terms,
+                    search_url.as_deref(),
+                    "missing title or artist",
+                ) {
+                    log::error!("failed to persist {store} failure for {track_id}: {error}");
+                }
+            }
+            continue;
+        }
+
+        let outcome = match provider.structured_search(client, &query) {
+            Some(request) => {
+                rate_limiter.wait().await;
+                fetch_candidates(provider.as_ref(), request, &query).await
+            }
+            None => Ok(Vec::new()),
+        };
+
+        let persisted = match library.lock() {
+            Ok(mut store_guard) => match &outcome {
+                Ok(candidates) => store_guard.record_store_links(
+                    &track_id,
+                    store,
+                    &terms,
+                    search_url.as_deref(),
+                    candidates,
+                ),
+                Err(reason) => store_guard.record_store_failure(
+                    &track_id,
+                    store,
+                    &terms,
+                    search_url.as_deref(),
+                    reason,
+                ),
+            },
+            Err(_) => {
+                log::error!("failed to acquire library store lock for {track_id}");
+                continue;
+            }
+        };
+
+        if let Err(error) = persisted {
+            log::error!("failed to persist {store} links for {track_id}: {error}");
+            continue;
+        }
+
+        resolved.push(json!({
+            "store": store,
+            "searchUrl": search_url,
+            "candidates": outcome.unwrap_or_default(),
+        }));
+    }
+
+    if let Err(error) = events.emit(
+        STORE_LINKS_EVENT,
+        json!({
+            "trackId": track_id,
+            "query": terms,
+            "stores": resolved,
+        }),
+    ) {
+        log::error!("failed to emit links event: {error}");
+    }
+}
+
+async fn fetch_candidates(
+    provider: &dyn StoreProvider,
+    request: RequestBuilder,
+    query: &StoreQuery,
+) -> Result<Vec<Value>, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|error| format!("request failed: {error}"))?;
+
+    match response.status() {
+        StatusCode::OK => {}
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            return Err(format!("unauthorized {} request", provider.id()));
+        }
+        status => {
+            return Err(format!("search returned status {status}"));
+        }
+    }
+
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|error| format!("failed to parse {} response: {error}", provider.id()))?;
+
+    let mut scored: Vec<(f32, StoreCandidate)> = provider
+        .parse_results(&body)
+        .into_iter()
+        .map(|candidate| (score_candidate(query, &candidate), candidate))
+        .collect();
+    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    Ok(scored
+        .into_iter()
+        .take(MAX_CANDIDATES)
+        .map(|(score, candidate)| candidate.into_payload(provider.id(), score))
+        .collect())
+}
+
+/// Scores a candidate between 0 and 100 by the share of query words that
+/// appear in the candidate's artists and title.
+fn score_candidate(query: &StoreQuery, candidate: &StoreCandidate) -> f32 {
+    let query_words = words(&query.terms());
+    if query_words.is_empty() {
+        return 0.0;
+    }
+
+    let haystack = words(&format!(
+        "{} {}",
+        candidate.artists.join(" "),
+        candidate.title.clone().unwrap_or_default()
+    ));
+    let matched = query_words
+        .iter()
+        .filter(|word| haystack.contains(word))
+        .count();
+    (matched as f32 / query_words.len() as f32) * 100.0
+}
+
+fn words(value: &str) -> Vec<String> {
+    value
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+fn clean_term(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
+
+fn json_id(value: Option<&Value>) -> Option<String> {
+    match value {
+        Some(Value::String(id)) => Some(id.clone()),
+        Some(Value::Number(number)) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{memory_library, payload, wait_for, RecordingSink};
+    use mockito::{Matcher, Server};
+
+    const BEATPORT_RESULTS: &str = r#"{
+        "tracks": [
+            {
+                "id": 2,
+                "name": "Glue",
+                "mix_name": "Remix",
+                "slug": "glue",
+                "artists": [{ "name": "Someone Else" }],
+                "price": { "value": 1.49, "code": "EUR" }
+            },
+            {
+                "id": 1,
+                "name": "Glue",
+                "mix_name": "Original Mix",
+                "slug": "glue",
+                "artists": [{ "name": "Bicep" }],
+                "release": { "label": { "name": "Ninja Tune" } },
+                "price": { "value": 1.99, "code": "EUR" },
+                "format": "wav"
+            }
+        ]
+    }"#;
+
+    fn spawn_service(
+        providers: Vec<Arc<dyn StoreProvider>>,
+    ) -> (
+        StoreLinkService,
+        Arc<Mutex<LibraryStore>>,
+        Arc<RecordingSink>,
+    ) {
+        let library = memory_library();
+        let events = Arc::new(RecordingSink::default());
+        let service = StoreLinkService::new(
+            events.clone(),
+            Arc::clone(&library),
+            providers,
+            &ClientIdentity::new("SoundCloudWrapperTests", "0.0"),
+        )
+        .unwrap();
+        (service, library, events)
+    }
+
+    async fn wait_for_links(
+        library: &Arc<Mutex<LibraryStore>>,
+        track_id: &str,
+        stores: usize,
+    ) -> Vec<crate::library::StoreLinkRecord> {
+        wait_for(|| {
+            let links = library.lock().unwrap().list_store_links(track_id).unwrap();
+            (links.len() == stores).then_some(links)
+        })
+        .await
+    }
+
+    #[test]
+    fn search_links_use_normalized_terms() {
+        let query = StoreQuery::from_payload(&payload(
+            "sc-1",
+            "Ninja Tune",
+            "Bicep - Glue (Original Mix) [FREE DL]",
+            None,
+        ));
+        assert_eq!(query.terms(), "Bicep Glue");
+
+        let beatport = BeatportStore::new(BEATPORT_WEB_URL, BEATPORT_API_URL, None);
+        assert_eq!(
+            beatport.search_url(&query).unwrap().as_str(),
+            "https://www.beatport.com/search/tracks?q=Bicep+Glue"
+        );
+        let traxsource = TraxsourceStore::new(TRAXSOURCE_WEB_URL);
+        assert_eq!(
+            traxsource.search_url(&query).unwrap().as_str(),
+            "https://www.traxsource.com/search?term=Bicep+Glue"
+        );
+    }
+
+    #[test]
+    fn candidates_are_scored_by_matching_words() {
+        let query = StoreQuery {
+            artist: Some("Bicep".to_string()),
+            title: Some("Glue".to_string()),
+        };
+        let beatport = BeatportStore::new(BEATPORT_WEB_URL, BEATPORT_API_URL, None);
+        let body: Value = serde_json::from_str(BEATPORT_RESULTS).unwrap();
+        let candidates = beatport.parse_results(&body);
+        assert_eq!(candidates.len(), 2);
+
+        let exact = &candidates[1];
+        assert_eq!(exact.title.as_deref(), Some("Glue (Original Mix)"));
+        assert_eq!(exact.label.as_deref(), Some("Ninja Tune"));
+        assert_eq!(
+            exact.url.as_deref(),
+            Some("https://www.beatport.com/track/glue/1")
+        );
+        assert_eq!(score_candidate(&query, exact), 100.0);
+        assert_eq!(score_candidate(&query, &candidates[0]), 50.0);
+    }
+
+    #[tokio::test]
+    async fn structured_results_are_ranked_and_persisted() {
+        let mut server = Server::new_async().await;
+        let search = server
+            .mock("GET", "/catalog/search/")
+            .match_header("authorization", "Bearer secret")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("q".into(), "Bicep Glue".into()),
+                Matcher::UrlEncoded("type".into(), "tracks".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(BEATPORT_RESULTS)
+            .create_async()
+            .await;
+
+        let (service, library, events) = spawn_service(vec![
+            Arc::new(BeatportStore::new(
+                BEATPORT_WEB_URL,
+                server.url(),
+                Some("secret".to_string()),
+            )),
+            Arc::new(TraxsourceStore::new(TRAXSOURCE_WEB_URL)),
+        ]);
+        service.queue_lookup(payload("sc-1", "Bicep", "Glue", None));
+
+        let links = wait_for_links(&library, "sc-1", 2).await;
+        let beatport = &links[0];
+        assert_eq!(beatport.store, "beatport");
+        assert_eq!(beatport.status, "candidates");
+        assert_eq!(beatport.candidates.len(), 2);
+        assert_eq!(beatport.candidates[0].product_id.as_deref(), Some("1"));
+        assert_eq!(beatport.candidates[0].price, Some(1.99));
+
+        let traxsource = &links[1];
+        assert_eq!(traxsource.store, "traxsource");
+        assert_eq!(traxsource.status, "link_only");
+        assert!(traxsource.candidates.is_empty());
+
+        let resolved = wait_for(|| events.events(STORE_LINKS_EVENT).pop()).await;
+        assert_eq!(resolved["trackId"], "sc-1");
+        assert_eq!(resolved["stores"].as_array().map(Vec::len), Some(2));
+        search.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn rejected_token_records_failure_with_search_link() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("GET", "/catalog/search/")
+            .match_query(Matcher::Any)
+            .with_status(401)
+            .create_async()
+            .await;
+
+        let (service, library, _events) = spawn_service(vec![Arc::new(BeatportStore::new(
+            BEATPORT_WEB_URL,
+            server.url(),
+            Some("expired".to_string()),
+        ))]);
+        service.queue_lookup(payload("sc-2", "Bicep", "Glue", None));
+
+        let links = wait_for_links(&library, "sc-2", 1).await;
+        assert_eq!(links[0].status, "error");
+        assert_eq!(
+            links[0].message.as_deref(),
+            Some("unauthorized beatport request")
+        );
+        assert_eq!(
+            links[0].search_url.as_deref(),
+            Some("https://www.beatport.com/search/tracks?q=Bicep+Glue")
+        );
+    }
+}
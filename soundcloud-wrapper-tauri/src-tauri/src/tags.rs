@@ -0,0 +1,877 @@
+//! Writes the metadata of a track's Discogs and MusicBrainz matches, and its
+//! measured ReplayGain, into the tags of its local file, keeping the original
+//! values so they can be put back.
+
+use std::env;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use lofty::config::WriteOptions;
+use lofty::picture::{MimeType, Picture, PictureType};
+use lofty::prelude::*;
+use lofty::probe::Probe;
+use lofty::tag::{ItemKey, Tag};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::endpoints::ClientIdentity;
+use crate::errors::CommandError;
+use crate::library::{lock, LibraryStore, TagBackup, TagSourceRecord};
+use crate::rekordbox::compute_checksum;
+use crate::scanner::file_stamp;
+
+const COVER_ART_BASE_URL: &str = "https://coverartarchive.org";
+const COVER_ART_BASE_URL_ENV: &str = "COVER_ART_BASE_URL";
+/// Name the front cover goes by in the diff and the backup.
+const ARTWORK_FIELD: &str = "artwork";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TagField {
+    Artist,
+    Title,
+    Album,
+    Label,
+    CatalogNumber,
+    Year,
+    MusicbrainzReleaseId,
+    MusicbrainzReleaseGroupId,
+    MusicbrainzArtistId,
+    MusicbrainzRecordingId,
+    ReplaygainTrackGain,
+    ReplaygainTrackPeak,
+}
+
+impl TagField {
+    const ALL: [TagField; 12] = [
+        TagField::Artist,
+        TagField::Title,
+        TagField::Album,
+        TagField::Label,
+        TagField::CatalogNumber,
+        TagField::Year,
+        TagField::MusicbrainzReleaseId,
+        TagField::MusicbrainzReleaseGroupId,
+        TagField::MusicbrainzArtistId,
+        TagField::MusicbrainzRecordingId,
+        TagField::ReplaygainTrackGain,
+        TagField::ReplaygainTrackPeak,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            TagField::Artist => "artist",
+            TagField::Title => "title",
+            TagField::Album => "album",
+            TagField::Label => "label",
+            TagField::CatalogNumber => "catalogNumber",
+            TagField::Year => "year",
+            TagField::MusicbrainzReleaseId => "musicbrainzReleaseId",
+            TagField::MusicbrainzReleaseGroupId => "musicbrainzReleaseGroupId",
+            TagField::MusicbrainzArtistId => "musicbrainzArtistId",
+            TagField::MusicbrainzRecordingId => "musicbrainzRecordingId",
+            TagField::ReplaygainTrackGain => "replaygainTrackGain",
+            TagField::ReplaygainTrackPeak => "replaygainTrackPeak",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|field| field.as_str() == value)
+    }
+
+    fn item_key(&self) -> ItemKey {
+        match self {
+            TagField::Artist => ItemKey::TrackArtist,
+            TagField::Title => ItemKey::TrackTitle,
+            TagField::Album => ItemKey::AlbumTitle,
+            TagField::Label => ItemKey::Label,
+            TagField::CatalogNumber => ItemKey::CatalogNumber,
+            TagField::Year => ItemKey::RecordingDate,
+            TagField::MusicbrainzReleaseId => ItemKey::MusicBrainzReleaseId,
+            TagField::MusicbrainzReleaseGroupId => ItemKey::MusicBrainzReleaseGroupId,
+            TagField::MusicbrainzArtistId => ItemKey::MusicBrainzArtistId,
+            TagField::MusicbrainzRecordingId => ItemKey::MusicBrainzRecordingId,
+            TagField::ReplaygainTrackGain => ItemKey::ReplayGainTrackGain,
+            TagField::ReplaygainTrackPeak => ItemKey::ReplayGainTrackPeak,
+        }
+    }
+}
+
+/// One tag the writer changes, or would change in a dry run.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagChange {
+    pub field: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagWriteReport {
+    pub track_id: String,
+    pub location: String,
+    pub dry_run: bool,
+    pub changes: Vec<TagChange>,
+    /// Checksum of the file after writing; unset in a dry run or when
+    /// nothing changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+}
+
+/// The values the matches provide, before they are compared with the file.
+#[derive(Debug, Default)]
+struct TagValues {
+    fields: Vec<(TagField, String)>,
+    artwork_url: Option<String>,
+}
+
+struct Artwork {
+    mime: Option<String>,
+    data: Vec<u8>,
+}
+
+pub struct TagWriter {
+    library: Arc<Mutex<LibraryStore>>,
+    client: Client,
+    cover_art_url: String,
+}
+
+impl TagWriter {
+    pub fn new(
+        library: Arc<Mutex<LibraryStore>>,
+        identity: &ClientIdentity,
+    ) -> Result<Self, CommandError> {
+        let client = Client::builder()
+            .user_agent(format!("{}/{}", identity.name, identity.version))
+            .build()
+            .map_err(|error| {
+                CommandError::internal(format!("failed to build artwork client: {error}"))
+            })?;
+        let cover_art_url = env::var(COVER_ART_BASE_URL_ENV)
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| COVER_ART_BASE_URL.to_string());
+        Ok(Self {
+            library,
+            client,
+            cover_art_url,
+        })
+    }
+
+    /// Writes artist, title, album, label, catalog number, year, MusicBrainz
+    /// IDs (including the AcoustID recording) and a front cover into the
+    /// track's local file. Only tags that
+    /// differ are touched, and artwork is only added to files without a
+    /// front cover. With `dry_run` the changes are reported but not written.
+    pub async fn write(
+        &self,
+        track_id: &str,
+        dry_run: bool,
+    ) -> Result<TagWriteReport, CommandError> {
+        let source = self.load_source(track_id)?;
+        if source.discogs_release.is_none()
+            && source.musicbrainz_release.is_none()
+            && source.recording_id.is_none()
+        {
+            return Err(CommandError::invalid_field(
+                "trackId",
+                format!("track '{track_id}' has no Discogs, MusicBrainz or AcoustID match"),
+            ));
+        }
+
+        let values = tag_values(&source, &self.cover_art_url);
+        self.apply(&source, values, dry_run).await
+    }
+
+    /// Writes the ReplayGain 2.0 track gain and peak measured by the audio
+    /// analysis into the track's local file. Like [`TagWriter::write`], the
+    /// original values are backed up and `dry_run` only reports the changes.
+    pub async fn write_replaygain(
+        &self,
+        track_id: &str,
+        dry_run: bool,
+    ) -> Result<TagWriteReport, CommandError> {
+        let source = self.load_source(track_id)?;
+        let (Some(gain), Some(peak)) = (source.replaygain_gain, source.replaygain_peak) else {
+            return Err(CommandError::invalid_field(
+                "trackId",
+                format!("track '{track_id}' has no loudness measurement of its current file"),
+            ));
+        };
+        let values = TagValues {
+            fields: vec![
+                (TagField::ReplaygainTrackGain, format!("{gain:.2} dB")),
+                (TagField::ReplaygainTrackPeak, format!("{peak:.6}")),
+            ],
+            artwork_url: None,
+        };
+        self.apply(&source, values, dry_run).await
+    }
+
+    async fn apply(
+        &self,
+        source: &TagSourceRecord,
+        values: TagValues,
+        dry_run: bool,
+    ) -> Result<TagWriteReport, CommandError> {
+        let track_id = source.track_id.as_str();
+        let path = Path::new(&source.location);
+        let mut tag = read_primary_tag(path)?;
+        let has_cover = front_cover(&tag).is_some();
+
+        let mut changes = Vec::new();
+        let mut backup_fields = Vec::new();
+        for (field, value) in &values.fields {
+            let before = tag
+                .get_string(&field.item_key())
+                .map(|current| current.to_string());
+            if before.as_deref() == Some(value.as_str()) {
+                continue;
+            }
+            if !tag.insert_text(field.item_key(), value.clone()) {
+                // The file's tag format has no place for this field.
+                continue;
+            }
+            backup_fields.push((field.as_str().to_string(), before.clone()));
+            changes.push(TagChange {
+                field: field.as_str().to_string(),
+                before,
+                after: Some(value.clone()),
+            });
+        }
+
+        let artwork_url = values.artwork_url.filter(|_| !has_cover);
+        if let Some(url) = &artwork_url {
+            if !dry_run {
+                match self.fetch_artwork(url).await {
+                    Ok(artwork) => {
+                        tag.push_picture(Picture::new_unchecked(
+                            PictureType::CoverFront,
+                            artwork.mime.as_deref().map(MimeType::from_str),
+                            None,
+                            artwork.data,
+                        ));
+                    }
+                    Err(error) => {
+                        log::warn!("failed to fetch artwork for {track_id}: {error}");
+                    }
+                }
+            }
+            if dry_run || front_cover(&tag).is_some() {
+                changes.push(TagChange {
+                    field: ARTWORK_FIELD.to_string(),
+                    before: None,
+                    after: Some(url.clone()),
+                });
+            }
+        }
+
+        let mut report = TagWriteReport {
+            track_id: source.track_id.clone(),
+            location: source.location.clone(),
+            dry_run,
+            changes,
+            checksum: None,
+        };
+        if dry_run || report.changes.is_empty() {
+            return Ok(report);
+        }
+
+        let artwork_added = report
+            .changes
+            .iter()
+            .any(|change| change.field == ARTWORK_FIELD);
+        self.extend_backup(source, backup_fields, artwork_added)?;
+        tag.save_to_path(path, WriteOptions::default())
+            .map_err(|error| {
+                CommandError::io(
+                    path.display().to_string(),
+                    format!("failed to write tags: {error}"),
+                )
+            })?;
+        report.checksum = Some(self.record_rewrite(&source.track_id, &source.location)?);
+        Ok(report)
+    }
+
+    /// Puts back the tags the writer overwrote and forgets the backup.
+    pub fn restore(&self, track_id: &str) -> Result<TagWriteReport, CommandError> {
+        let backup = self
+            .lock()?
+            .load_tag_backup(track_id)?
+            .ok_or_else(|| CommandError::not_found("tag backup", track_id))?;
+        let location = self
+            .lock()?
+            .load_tag_source(track_id)?
+            .map(|source| source.location)
+            .unwrap_or_else(|| backup.location.clone());
+
+        let path = Path::new(&location);
+        let mut tag = read_primary_tag(path)?;
+        let mut changes = Vec::new();
+        for (name, original) in &backup.fields {
+            let Some(field) = TagField::from_str(name) else {
+                continue;
+            };
+            let before = tag
+                .get_string(&field.item_key())
+                .map(|current| current.to_string());
+            match original {
+                Some(value) => {
+                    tag.insert_text(field.item_key(), value.clone());
+                }
+                None => {
+                    tag.remove_key(&field.item_key());
+                }
+            }
+            changes.push(TagChange {
+                field: name.clone(),
+                before,
+                after: original.clone(),
+            });
+        }
+        if backup.artwork_added {
+            tag.remove_picture_type(PictureType::CoverFront);
+            changes.push(TagChange {
+                field: ARTWORK_FIELD.to_string(),
+                before: None,
+                after: None,
+            });
+        }
+
+        tag.save_to_path(path, WriteOptions::default())
+            .map_err(|error| {
+                CommandError::io(
+                    path.display().to_string(),
+                    format!("failed to write tags: {error}"),
+                )
+            })?;
+        let checksum = self.record_rewrite(track_id, &location)?;
+        self.lock()?.delete_tag_backup(track_id)?;
+
+        Ok(TagWriteReport {
+            track_id: track_id.to_string(),
+            location,
+            dry_run: false,
+            changes,
+            checksum: Some(checksum),
+        })
+    }
+
+    fn load_source(&self, track_id: &str) -> Result<TagSourceRecord, CommandError> {
+        self.lock()?
+            .load_tag_source(track_id)?
+            .ok_or_else(|| CommandError::not_found("local file", track_id))
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, LibraryStore>, CommandError> {
+        Ok(lock(&self.library)?)
+    }
+
+    /// Adds the original values of newly overwritten fields to the backup.
+    /// Fields already in it keep the value from the very first write.
+    fn extend_backup(
+        &self,
+        source: &TagSourceRecord,
+        fields: Vec<(String, Option<String>)>,
+        artwork_added: bool,
+    ) -> Result<(), CommandError> {
+        let store = self.lock()?;
+        let mut backup = store
+            .load_tag_backup(&source.track_id)?
+            .unwrap_or_else(|| TagBackup {
+                track_id: source.track_id.clone(),
+                location: source.location.clone(),
+                fields: Default::default(),
+                artwork_added: false,
+            });
+        backup.location = source.location.clone();
+        for (field, original) in fields {
+            backup.fields.entry(field).or_insert(original);
+        }
+        backup.artwork_added |= artwork_added;
+        Ok(store.save_tag_backup(&backup)?)
+    }
+
+    fn record_rewrite(&self, track_id: &str, location: &str) -> Result<String, CommandError> {
+        let path = Path::new(location);
+        let checksum = compute_checksum(path).map_err(|error| CommandError::io(location, error))?;
+        let (size, modified_at) =
+            file_stamp(path).ok_or_else(|| CommandError::io(location, "failed to stat file"))?;
+        self.lock()?
+            .record_rewritten_file(track_id, location, &checksum, size, modified_at)?;
+        Ok(checksum)
+    }
+
+    async fn fetch_artwork(&self, url: &str) -> Result<Artwork, String> {
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|error| error.to_string())?;
+        let mime = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(';').next().unwrap_or(value).trim().to_string());
+        let data = response.bytes().await.map_err(|error| error.to_string())?;
+        Ok(Artwork {
+            mime,
+            data: data.to_vec(),
+        })
+    }
+}
+
+/// Reads the file's primary tag, or an empty one of the format's preferred
+/// type when the file has none.
+fn read_primary_tag(path: &Path) -> Result<Tag, CommandError> {
+    let tagged_file = Probe::open(path)
+        .and_then(|probe| probe.read())
+        .map_err(|error| {
+            CommandError::io(
+                path.display().to_string(),
+                format!("failed to read tags: {error}"),
+            )
+        })?;
+    Ok(tagged_file
+        .primary_tag()
+        .cloned()
+        .unwrap_or_else(|| Tag::new(tagged_file.primary_tag_type())))
+}
+
+fn front_cover(tag: &Tag) -> Option<&Picture> {
+    tag.pictures()
+        .iter()
+        .find(|picture| picture.pic_type() == PictureType::CoverFront)
+}
+
+/// Artist and title come from the track itself, since a release is often
+/// credited to "Various". The release supplies the rest, Discogs first.
+fn tag_values(source: &TagSourceRecord, cover_art_url: &str) -> TagValues {
+    let discogs = source.discogs_release.as_ref();
+    let musicbrainz = source.musicbrainz_release.as_ref();
+    let mut values = TagValues::default();
+    let mut push = |field: TagField, value: Option<String>| {
+        if let Some(value) = value.map(|value| value.trim().to_string()) {
+            if !value.is_empty() {
+                values.fields.push((field, value));
+            }
+        }
+    };
+
+    push(
+        TagField::Artist,
+        source
+            .artist
+            .clone()
+            .or_else(|| discogs.and_then(discogs_artist))
+            .or_else(|| musicbrainz.and_then(musicbrainz_artist)),
+    );
+    push(
+        TagField::Title,
+        source.title.as_ref().map(|title| match &source.version {
+            Some(version) if !version.trim().is_empty() => format!("{title} ({version})"),
+            _ => title.clone(),
+        }),
+    );
+    push(
+        TagField::Album,
+        discogs
+            .and_then(|release| json_str(release, "title"))
+            .or_else(|| musicbrainz.and_then(|release| json_str(release, "title"))),
+    );
+    push(
+        TagField::Label,
+        discogs
+            .and_then(|release| discogs_label(release, "name"))
+            .or_else(|| musicbrainz.and_then(|release| musicbrainz_label(release, "label"))),
+    );
+    push(
+        TagField::CatalogNumber,
+        discogs
+            .and_then(|release| discogs_label(release, "catno"))
+            .or_else(|| {
+                musicbrainz.and_then(|release| musicbrainz_label(release, "catalog-number"))
+            }),
+    );
+    push(
+        TagField::Year,
+        discogs
+            .and_then(discogs_year)
+            .or_else(|| musicbrainz.and_then(musicbrainz_year)),
+    );
+    if let Some(release) = musicbrainz {
+        push(TagField::MusicbrainzReleaseId, json_str(release, "id"));
+        push(
+            TagField::MusicbrainzReleaseGroupId,
+            release
+                .get("release-group")
+                .and_then(|group| json_str(group, "id")),
+        );
+        push(
+            TagField::MusicbrainzArtistId,
+            release
+                .get("artist-credit")
+                .and_then(|credits| credits.get(0))
+                .and_then(|credit| credit.get("artist"))
+                .and_then(|artist| json_str(artist, "id")),
+        );
+    }
+    push(
+        TagField::MusicbrainzRecordingId,
+        source.recording_id.clone(),
+    );
+
+    values.artwork_url = discogs.and_then(discogs_image).or_else(|| {
+        musicbrainz
+            .and_then(|release| json_str(release, "id"))
+            .map(|id| {
+                format!(
+                    "{}/release/{id}/front-500",
+                    cover_art_url.trim_end_matches('/')
+                )
+            })
+    });
+    values
+}
+
+fn json_str(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(|value| value.as_str())
+        .map(|value| value.to_string())
+}
+
+/// Discogs disambiguates artists with a numeric suffix, as in "Burial (2)".
+fn strip_discogs_suffix(name: &str) -> &str {
+    match name.rsplit_once(" (") {
+        Some((base, suffix))
+            if suffix.ends_with(')')
+                && suffix[..suffix.len() - 1]
+                    .chars()
+                    .all(|c| c.is_ascii_digit()) =>
+        {
+            base
+        }
+        _ => name,
+    }
+}
+
+fn discogs_artist(release: &Value) -> Option<String> {
+    let artists = release.get("artists")?.as_array()?;
+    let mut names = String::new();
+    for artist in artists {
+        let name = artist
+            .get("anv")
+            .and_then(|value| value.as_str())
+            .filter(|value| !value.is_empty())
+            .or_else(|| artist.get("name").and_then(|value| value.as_str()))?;
+        names.push_str(strip_discogs_suffix(name));
+        match artist.get("join").and_then(|value| value.as_str()) {
+            Some(join) if !join.trim().is_empty() => {
+                names.push(' ');
+                names.push_str(join.trim());
+                names.push(' ');
+            }
+            _ => names.push_str(", "),
+        }
+    }
+    let names = names.trim_end_matches([',', ' ']).to_string();
+    (!names.is_empty()).then_some(names)
+}
+
+fn discogs_label(release: &Value, key: &str) -> Option<String> {
+    release
+        .get("labels")
+        .and_then(|labels| labels.get(0))
+        .and_then(|label| json_str(label, key))
+        .map(|value| strip_discogs_suffix(&value).to_string())
+        .filter(|value| !value.eq_ignore_ascii_case("none"))
+}
+
+fn discogs_year(release: &Value) -> Option<String> {
+    match release.get("year") {
+        Some(Value::Number(year)) => year
+            .as_u64()
+            .filter(|year| *year > 0)
+            .map(|year| year.to_string()),
+        Some(Value::String(year)) if year != "0" => Some(year.clone()),
+        _ => None,
+    }
+}
+
+fn discogs_image(release: &Value) -> Option<String> {
+    let images = release.get("images")?.as_array()?;
+    images
+        .iter()
+        .find(|image| image.get("type").and_then(|value| value.as_str()) == Some("primary"))
+        .or_else(|| images.first())
+        .and_then(|image| json_str(image, "uri"))
+}
+
+fn musicbrainz_artist(release: &Value) -> Option<String> {
+    let credits = release.get("artist-credit")?.as_array()?;
+    let names: String = credits
+        .iter()
+        .map(|credit| {
+            let name = credit
+                .get("name")
+                .or_else(|| credit.get("artist").and_then(|artist| artist.get("name")))
+                .and_then(|value| value.as_str())
+                .unwrap_or_default();
+            let join = credit
+                .get("joinphrase")
+                .and_then(|value| value.as_str())
+                .unwrap_or_default();
+            format!("{name}{join}")
+        })
+        .collect();
+    let names = names.trim().to_string();
+    (!names.is_empty()).then_some(names)
+}
+
+fn musicbrainz_label(release: &Value, key: &str) -> Option<String> {
+    let info = release.get("label-info")?.as_array()?.first()?;
+    match key {
+        "label" => info.get("label").and_then(|label| json_str(label, "name")),
+        _ => json_str(info, key),
+    }
+}
+
+fn musicbrainz_year(release: &Value) -> Option<String> {
+    let date = json_str(release, "date")?;
+    let year: String = date.chars().take(4).collect();
+    (year.len() == 4 && year.chars().all(|c| c.is_ascii_digit())).then_some(year)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::{AcoustidMatchRecord, AcoustidMatchStatus, LocalAssetRecord, TrackRecord};
+    use crate::test_support::{memory_library, write_wav};
+    use serde_json::json;
+    use std::fs;
+
+    fn source(discogs: Option<Value>, musicbrainz: Option<Value>) -> TagSourceRecord {
+        TagSourceRecord {
+            track_id: "sc-1".to_string(),
+            location: "/music/glue.mp3".to_string(),
+            artist: Some("Bicep".to_string()),
+            title: Some("Glue".to_string()),
+            version: Some("Extended Mix".to_string()),
+            discogs_release: discogs,
+            musicbrainz_release: musicbrainz,
+            recording_id: None,
+            replaygain_gain: None,
+            replaygain_peak: None,
+        }
+    }
+
+    fn value(values: &TagValues, field: TagField) -> Option<&str> {
+        values
+            .fields
+            .iter()
+            .find(|(candidate, _)| *candidate == field)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn discogs_suffixes_are_stripped() {
+        assert_eq!(strip_discogs_suffix("Burial (2)"), "Burial");
+        assert_eq!(strip_discogs_suffix("Burial (Live)"), "Burial (Live)");
+        assert_eq!(strip_discogs_suffix("Burial"), "Burial");
+    }
+
+    #[test]
+    fn discogs_artists_are_joined() {
+        let release = json!({
+            "artists": [
+                { "name": "Burial (2)", "join": "&" },
+                { "name": "Four Tet", "anv": "" },
+                { "name": "Thom Yorke", "anv": "Thom" }
+            ]
+        });
+        assert_eq!(
+            discogs_artist(&release).as_deref(),
+            Some("Burial & Four Tet, Thom")
+        );
+        assert_eq!(discogs_artist(&json!({ "artists": [] })), None);
+    }
+
+    #[test]
+    fn unknown_years_are_skipped() {
+        assert_eq!(
+            discogs_year(&json!({ "year": 2017 })).as_deref(),
+            Some("2017")
+        );
+        assert_eq!(
+            discogs_year(&json!({ "year": "2017" })).as_deref(),
+            Some("2017")
+        );
+        assert_eq!(discogs_year(&json!({ "year": 0 })), None);
+        assert_eq!(discogs_year(&json!({ "year": "0" })), None);
+        assert_eq!(
+            musicbrainz_year(&json!({ "date": "2017-01-20" })).as_deref(),
+            Some("2017")
+        );
+        assert_eq!(musicbrainz_year(&json!({ "date": "" })), None);
+        assert_eq!(musicbrainz_year(&json!({ "date": "20??" })), None);
+    }
+
+    #[test]
+    fn discogs_release_supplies_the_release_fields() {
+        let release = json!({
+            "title": "Bicep",
+            "year": 2017,
+            "artists": [{ "name": "Various" }],
+            "labels": [{ "name": "Ninja Tune (2)", "catno": "ZEN12" }],
+            "images": [
+                { "type": "secondary", "uri": "https://img/back.jpg" },
+                { "type": "primary", "uri": "https://img/front.jpg" }
+            ]
+        });
+        let values = tag_values(&source(Some(release), None), COVER_ART_BASE_URL);
+
+        assert_eq!(value(&values, TagField::Artist), Some("Bicep"));
+        assert_eq!(value(&values, TagField::Title), Some("Glue (Extended Mix)"));
+        assert_eq!(value(&values, TagField::Album), Some("Bicep"));
+        assert_eq!(value(&values, TagField::Label), Some("Ninja Tune"));
+        assert_eq!(value(&values, TagField::CatalogNumber), Some("ZEN12"));
+        assert_eq!(value(&values, TagField::Year), Some("2017"));
+        assert_eq!(value(&values, TagField::MusicbrainzReleaseId), None);
+        assert_eq!(values.artwork_url.as_deref(), Some("https://img/front.jpg"));
+    }
+
+    #[test]
+    fn musicbrainz_release_supplies_ids_and_cover_art() {
+        let release = json!({
+            "id": "rel-1",
+            "title": "Bicep",
+            "date": "2017-09-01",
+            "release-group": { "id": "group-1" },
+            "artist-credit": [{ "name": "Bicep", "artist": { "id": "artist-1" } }],
+            "label-info": [{ "catalog-number": "ZEN12", "label": { "name": "Ninja Tune" } }]
+        });
+        let values = tag_values(&source(None, Some(release)), "https://covers/");
+
+        assert_eq!(value(&values, TagField::Label), Some("Ninja Tune"));
+        assert_eq!(value(&values, TagField::Year), Some("2017"));
+        assert_eq!(
+            value(&values, TagField::MusicbrainzReleaseId),
+            Some("rel-1")
+        );
+        assert_eq!(
+            value(&values, TagField::MusicbrainzReleaseGroupId),
+            Some("group-1")
+        );
+        assert_eq!(
+            value(&values, TagField::MusicbrainzArtistId),
+            Some("artist-1")
+        );
+        assert_eq!(
+            values.artwork_url.as_deref(),
+            Some("https://covers/release/rel-1/front-500")
+        );
+    }
+
+    /// A library with `sc-1` linked to a fresh WAV file and matched to an
+    /// AcoustID recording, so writing needs no release payloads or artwork.
+    fn writer_with_file(name: &str) -> (TagWriter, std::path::PathBuf) {
+        let path = env::temp_dir().join(format!("tags-{name}-{}.wav", std::process::id()));
+        write_wav(&path, 1_000);
+        let library = memory_library();
+        {
+            let store = library.lock().unwrap();
+            let track: TrackRecord = serde_json::from_value(json!({
+                "track_id": "sc-1",
+                "artist": "Bicep",
+                "title": "Glue",
+            }))
+            .unwrap();
+            store.upsert_track(&track).unwrap();
+            store
+                .record_local_asset(&LocalAssetRecord {
+                    track_id: "sc-1".to_string(),
+                    location: path.to_string_lossy().into_owned(),
+                    checksum: None,
+                    available: true,
+                    duration_ms: None,
+                    file_size: None,
+                    rekordbox_cues: None,
+                })
+                .unwrap();
+            store
+                .record_acoustid_match(&AcoustidMatchRecord {
+                    track_id: "sc-1".to_string(),
+                    status: AcoustidMatchStatus::Success,
+                    acoustid_id: Some("acoustid-1".to_string()),
+                    recording_id: Some("recording-1".to_string()),
+                    score: Some(0.98),
+                    message: None,
+                })
+                .unwrap();
+        }
+        let writer = TagWriter::new(library, &ClientIdentity::new("test", "0.0.0")).unwrap();
+        (writer, path)
+    }
+
+    fn fields(report: &TagWriteReport) -> Vec<&str> {
+        report
+            .changes
+            .iter()
+            .map(|change| change.field.as_str())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn dry_run_reports_changes_without_touching_the_file() {
+        let (writer, path) = writer_with_file("dry-run");
+        let before = fs::read(&path).unwrap();
+
+        let report = writer.write("sc-1", true).await.unwrap();
+        let after = fs::read(&path).unwrap();
+        let backup = writer.lock().unwrap().load_tag_backup("sc-1").unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(report.dry_run);
+        // The WAV file's RIFF INFO tag has no MusicBrainz fields, so the
+        // recording is skipped rather than reported.
+        assert_eq!(fields(&report), ["artist", "title"]);
+        assert_eq!(report.checksum, None);
+        assert_eq!(before, after);
+        assert!(backup.is_none());
+    }
+
+    #[tokio::test]
+    async fn restore_puts_back_the_original_tags() {
+        let (writer, path) = writer_with_file("restore");
+
+        let written = writer.write("sc-1", false).await.unwrap();
+        let tag = read_primary_tag(&path).unwrap();
+        assert_eq!(tag.get_string(&ItemKey::TrackArtist), Some("Bicep"));
+        assert_eq!(tag.get_string(&ItemKey::TrackTitle), Some("Glue"));
+        let backup = writer
+            .lock()
+            .unwrap()
+            .load_tag_backup("sc-1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(backup.fields.get("title"), Some(&None));
+
+        let restored = writer.restore("sc-1").unwrap();
+        let tag = read_primary_tag(&path).unwrap();
+        let backup = writer.lock().unwrap().load_tag_backup("sc-1").unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(written.checksum.is_some());
+        assert_eq!(restored.changes.len(), 2);
+        assert_eq!(tag.get_string(&ItemKey::TrackArtist), None);
+        assert_eq!(tag.get_string(&ItemKey::TrackTitle), None);
+        assert!(backup.is_none());
+    }
+}
@@ -0,0 +1,143 @@
+//! Shared fixtures for the worker integration tests and the CLI tests: an
+//! in-memory library, an event sink that records what the services emit,
+//! helpers to point a provider at a mock HTTP server, and a writer for small
+//! WAV files. Not part of the library's API.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::endpoints::EndpointSettings;
+use crate::events::EventSink;
+use crate::library::{LibraryStatusRow, LibraryStore, StatusFilter};
+use crate::SoundcloudTrackPayload;
+
+const WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+pub struct RecordingSink {
+    events: Mutex<Vec<(String, Value)>>,
+}
+
+impl RecordingSink {
+    pub fn events(&self, name: &str) -> Vec<Value> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(event, _)| event == name)
+            .map(|(_, payload)| payload.clone())
+            .collect()
+    }
+}
+
+impl EventSink for RecordingSink {
+    fn emit(&self, event: &str, payload: Value) -> Result<(), String> {
+        self.events
+            .lock()
+            .unwrap()
+            .push((event.to_string(), payload));
+        Ok(())
+    }
+}
+
+pub fn memory_library() -> Arc<Mutex<LibraryStore>> {
+    Arc::new(Mutex::new(
+        LibraryStore::open_in_memory().expect("in-memory library"),
+    ))
+}
+
+/// Routes `provider` to `base_url` without pacing so tests run at full speed.
+pub fn point_at(library: &Arc<Mutex<LibraryStore>>, provider: &str, base_url: &str) {
+    let settings = EndpointSettings {
+        provider: provider.to_string(),
+        base_url: Some(base_url.to_string()),
+        min_interval_ms: Some(0),
+        timeout_ms: Some(5_000),
+    };
+    library
+        .lock()
+        .unwrap()
+        .save_endpoint_settings(&settings)
+        .expect("save endpoint settings");
+}
+
+pub fn payload(
+    track_id: &str,
+    artist: &str,
+    title: &str,
+    duration_ms: Option<i64>,
+) -> SoundcloudTrackPayload {
+    serde_json::from_value(json!({
+        "trackId": track_id,
+        "soundcloudId": track_id,
+        "artist": artist,
+        "title": title,
+        "durationMs": duration_ms,
+    }))
+    .expect("valid payload")
+}
+
+/// Reads `tests/fixtures/<path>` relative to the crate root.
+pub fn fixture(path: &str) -> String {
+    let location = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(path);
+    fs::read_to_string(&location)
+        .unwrap_or_else(|error| panic!("failed to read fixture {}: {error}", location.display()))
+}
+
+pub fn status_row(library: &Arc<Mutex<LibraryStore>>, track_id: &str) -> Option<LibraryStatusRow> {
+    library
+        .lock()
+        .unwrap()
+        .list_library_status(&StatusFilter::default())
+        .expect("list library status")
+        .rows
+        .into_iter()
+        .find(|row| row.track_id == track_id)
+}
+
+/// Polls `check` until it yields a value; the workers run on their own task
+/// and persist asynchronously.
+pub async fn wait_for<T>(mut check: impl FnMut() -> Option<T>) -> T {
+    let deadline = tokio::time::Instant::now() + WAIT_TIMEOUT;
+    loop {
+        if let Some(value) = check() {
+            return value;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            panic!("timed out waiting for worker");
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+/// Writes one second of 8 kHz mono PCM whose samples all hold `level`, so
+/// files written with different levels share a size and duration but not
+/// a checksum.
+pub fn write_wav(path: &Path, level: i16) {
+    let samples = 8_000u32;
+    let data_len = samples * 2;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&8_000u32.to_le_bytes());
+    bytes.extend_from_slice(&16_000u32.to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for _ in 0..samples {
+        bytes.extend_from_slice(&level.to_le_bytes());
+    }
+    fs::write(path, bytes).unwrap();
+}
@@ -0,0 +1,571 @@
+//! Waveform overviews of local files. A file is decoded once into a detailed
+//! peak/RMS overview with low, mid and high band levels, which is cached in a
+//! compact binary file under the app data directory. Requests for fewer
+//! columns are served by merging the cached ones.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use symphonia::core::audio::{AudioBufferRef, SampleBuffer, SignalSpec};
+
+use crate::errors::CommandError;
+use crate::library::{lock, LibraryStore};
+use crate::loudness::Biquad;
+use crate::rekordbox::{compute_waveform, RekordboxCue};
+use crate::scanner::file_stamp;
+
+/// Directory under the app data directory that holds the cached overviews.
+pub const CACHE_DIR_NAME: &str = "waveforms";
+/// Columns per second in the cached overview, as in Rekordbox's detailed
+/// waveform.
+const COLUMNS_PER_SECOND: u32 = 150;
+/// Columns returned when the caller does not ask for a resolution.
+const DEFAULT_RESOLUTION: u32 = 1_000;
+const LOW_CUTOFF_HZ: f64 = 200.0;
+const HIGH_CUTOFF_HZ: f64 = 2_000.0;
+
+const CACHE_EXTENSION: &str = "wave";
+const CACHE_MAGIC: &[u8; 4] = b"SCWF";
+const CACHE_VERSION: u8 = 1;
+/// Peak, RMS, low, mid and high, one byte each.
+const VALUES_PER_COLUMN: usize = 5;
+const HEADER_LEN: usize = 4 + 1 + 4 + 8 + 8 + 8 + 4;
+
+/// Numbers the partial files of concurrent cache writes.
+static PARTIAL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Levels of one column, as linear amplitudes scaled to 0-255.
+#[derive(Debug, Clone, Copy, Default)]
+struct Column {
+    peak: u8,
+    rms: u8,
+    low: u8,
+    mid: u8,
+    high: u8,
+}
+
+/// A decoded overview at the cache resolution.
+#[derive(Debug, Clone)]
+pub(crate) struct WaveformData {
+    duration_ms: u64,
+    columns: Vec<Column>,
+}
+
+/// A waveform as sent to the frontend. Each level is one value per column,
+/// from 0 (silence) to 255 (full scale).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Waveform {
+    pub track_id: String,
+    pub duration_ms: u64,
+    pub resolution: u32,
+    pub peak: Vec<u8>,
+    pub rms: Vec<u8>,
+    pub low: Vec<u8>,
+    pub mid: Vec<u8>,
+    pub high: Vec<u8>,
+    /// Rekordbox cues of the track, to overlay on the waveform.
+    pub cues: Vec<RekordboxCue>,
+}
+
+/// Running sums of the column being built.
+#[derive(Default)]
+struct Accumulator {
+    frames: usize,
+    peak: f32,
+    square: f64,
+    low: f64,
+    mid: f64,
+    high: f64,
+}
+
+/// Builds the overview from decoded audio.
+pub(crate) struct WaveformBuilder {
+    frames_per_column: usize,
+    rate: u32,
+    low: Biquad,
+    mid: [Biquad; 2],
+    high: Biquad,
+    current: Accumulator,
+    columns: Vec<Column>,
+    total_frames: u64,
+    buffer: Option<(usize, SampleBuffer<f32>)>,
+}
+
+impl WaveformBuilder {
+    pub(crate) fn start(spec: &SignalSpec) -> Option<Self> {
+        if spec.rate == 0 || spec.channels.count() == 0 {
+            return None;
+        }
+        let rate = f64::from(spec.rate);
+        Some(Self {
+            frames_per_column: ((spec.rate / COLUMNS_PER_SECOND) as usize).max(1),
+            rate: spec.rate,
+            low: low_pass(rate, LOW_CUTOFF_HZ),
+            mid: [
+                high_pass(rate, LOW_CUTOFF_HZ),
+                low_pass(rate, HIGH_CUTOFF_HZ),
+            ],
+            high: high_pass(rate, HIGH_CUTOFF_HZ),
+            current: Accumulator::default(),
+            columns: Vec::new(),
+            total_frames: 0,
+            buffer: None,
+        })
+    }
+
+    pub(crate) fn consume(&mut self, decoded: AudioBufferRef<'_>) {
+        let spec = *decoded.spec();
+        let channels = spec.channels.count();
+        let capacity = decoded.capacity();
+        let frames = decoded.frames();
+        if channels == 0 {
+            return;
+        }
+
+        let (size, mut buffer) = match self.buffer.take() {
+            Some((size, buffer)) if size >= capacity => (size, buffer),
+            _ => (capacity, SampleBuffer::new(capacity as u64, spec)),
+        };
+        buffer.copy_interleaved_ref(decoded);
+        for frame in buffer.samples()[..frames * channels].chunks_exact(channels) {
+            self.push_frame(frame);
+        }
+        self.buffer = Some((size, buffer));
+        self.total_frames += frames as u64;
+    }
+
+    fn push_frame(&mut self, frame: &[f32]) {
+        let peak = frame
+            .iter()
+            .fold(0.0f32, |peak, value| peak.max(value.abs()));
+        let mono = f64::from(frame.iter().sum::<f32>() / frame.len() as f32);
+        let low = self.low.process(mono);
+        let above_low = self.mid[0].process(mono);
+        let mid = self.mid[1].process(above_low);
+        let high = self.high.process(mono);
+
+        let current = &mut self.current;
+        current.frames += 1;
+        current.peak = current.peak.max(peak);
+        current.square += mono * mono;
+        current.low += low * low;
+        current.mid += mid * mid;
+        current.high += high * high;
+        if current.frames == self.frames_per_column {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        let current = std::mem::take(&mut self.current);
+        if current.frames == 0 {
+            return;
+        }
+        let rms = |sum: f64| level((sum / current.frames as f64).sqrt());
+        self.columns.push(Column {
+            peak: level(f64::from(current.peak)),
+            rms: rms(current.square),
+            low: rms(current.low),
+            mid: rms(current.mid),
+            high: rms(current.high),
+        });
+    }
+
+    pub(crate) fn finish(mut self) -> Option<WaveformData> {
+        self.flush();
+        if self.columns.is_empty() {
+            return None;
+        }
+        Some(WaveformData {
+            duration_ms: self.total_frames * 1000 / u64::from(self.rate),
+            columns: self.columns,
+        })
+    }
+}
+
+fn level(amplitude: f64) -> u8 {
+    (amplitude.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Butterworth low pass from the Audio EQ Cookbook.
+fn low_pass(rate: f64, cutoff: f64) -> Biquad {
+    let (cos, alpha) = cookbook_terms(rate, cutoff);
+    let a0 = 1.0 + alpha;
+    Biquad::new(
+        [
+            (1.0 - cos) / 2.0 / a0,
+            (1.0 - cos) / a0,
+            (1.0 - cos) / 2.0 / a0,
+        ],
+        [-2.0 * cos / a0, (1.0 - alpha) / a0],
+    )
+}
+
+/// Butterworth high pass from the Audio EQ Cookbook.
+fn high_pass(rate: f64, cutoff: f64) -> Biquad {
+    let (cos, alpha) = cookbook_terms(rate, cutoff);
+    let a0 = 1.0 + alpha;
+    Biquad::new(
+        [
+            (1.0 + cos) / 2.0 / a0,
+            -(1.0 + cos) / a0,
+            (1.0 + cos) / 2.0 / a0,
+        ],
+        [-2.0 * cos / a0, (1.0 - alpha) / a0],
+    )
+}
+
+fn cookbook_terms(rate: f64, cutoff: f64) -> (f64, f64) {
+    // Keep the cutoff below Nyquist for low sample rates.
+    let cutoff = cutoff.min(rate * 0.45);
+    let omega = 2.0 * std::f64::consts::PI * cutoff / rate;
+    (
+        omega.cos(),
+        omega.sin() / (2.0 * std::f64::consts::FRAC_1_SQRT_2),
+    )
+}
+
+/// Cached overviews, one file per audio file, in `directory`.
+pub struct WaveformCache {
+    directory: PathBuf,
+}
+
+impl WaveformCache {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+
+    /// Returns the waveform of the track's local file with `resolution`
+    /// columns, or fewer when the file is too short for that many. The file
+    /// is only decoded when it has no cached overview or changed since.
+    pub fn waveform(
+        &self,
+        library: &Arc<Mutex<LibraryStore>>,
+        track_id: &str,
+        resolution: Option<u32>,
+    ) -> Result<Waveform, CommandError> {
+        let source = lock(library)?
+            .load_waveform_source(track_id)?
+            .ok_or_else(|| CommandError::not_found("local file", track_id))?;
+        let path = Path::new(&source.location);
+        let (size, modified_at) = file_stamp(path)
+            .ok_or_else(|| CommandError::io(source.location.as_str(), "failed to stat file"))?;
+
+        let cache_path = self.cache_path(&source.location);
+        let data = match read_cache(&cache_path, size, modified_at) {
+            Some(data) => data,
+            None => {
+                let data = compute_waveform(path)
+                    .map_err(|error| CommandError::io(source.location.as_str(), error))?
+                    .ok_or_else(|| CommandError::io(source.location.as_str(), "holds no audio"))?;
+                if let Err(error) = write_cache(&cache_path, &data, size, modified_at) {
+                    log::warn!("failed to cache {}: {error}", cache_path.display());
+                }
+                data
+            }
+        };
+
+        let columns = resample(&data.columns, resolution.unwrap_or(DEFAULT_RESOLUTION));
+        Ok(Waveform {
+            track_id: track_id.to_string(),
+            duration_ms: data.duration_ms,
+            resolution: columns.len() as u32,
+            peak: columns.iter().map(|column| column.peak).collect(),
+            rms: columns.iter().map(|column| column.rms).collect(),
+            low: columns.iter().map(|column| column.low).collect(),
+            mid: columns.iter().map(|column| column.mid).collect(),
+            high: columns.iter().map(|column| column.high).collect(),
+            cues: source.cues,
+        })
+    }
+
+    /// Deletes the overviews of files that are no longer local assets, e.g.
+    /// because they were relocated or relinked. Returns how many were
+    /// deleted.
+    pub fn prune(&self, library: &Arc<Mutex<LibraryStore>>) -> Result<usize, CommandError> {
+        let known: HashSet<PathBuf> = lock(library)?
+            .list_local_asset_locations()?
+            .iter()
+            .map(|asset| self.cache_path(&asset.location))
+            .collect();
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(error) => {
+                return Err(CommandError::io(
+                    self.directory.display().to_string(),
+                    error,
+                ))
+            }
+        };
+
+        let mut pruned = 0;
+        for entry in entries {
+            let path = entry
+                .map_err(|error| CommandError::io(self.directory.display().to_string(), error))?
+                .path();
+            let is_cache = path
+                .extension()
+                .is_some_and(|extension| extension == CACHE_EXTENSION);
+            if !is_cache || known.contains(&path) {
+                continue;
+            }
+            match fs::remove_file(&path) {
+                Ok(()) => pruned += 1,
+                Err(error) => log::warn!("failed to delete {}: {error}", path.display()),
+            }
+        }
+        Ok(pruned)
+    }
+
+    /// Cache files are named after the audio file's path, so a relinked
+    /// track gets a fresh overview.
+    fn cache_path(&self, location: &str) -> PathBuf {
+        let digest = Sha256::digest(location.as_bytes());
+        self.directory.join(format!("{digest:x}.{CACHE_EXTENSION}"))
+    }
+}
+
+/// Merges columns down to `resolution`: the highest peak and the combined
+/// RMS of the columns each new one covers.
+fn resample(columns: &[Column], resolution: u32) -> Vec<Column> {
+    let resolution = (resolution.max(1) as usize).min(columns.len());
+    if resolution == columns.len() {
+        return columns.to_vec();
+    }
+    (0..resolution)
+        .map(|index| {
+            let from = index * columns.len() / resolution;
+            let to = ((index + 1) * columns.len() / resolution).max(from + 1);
+            let group = &columns[from..to];
+            let rms = |value: fn(&Column) -> u8| {
+                let sum: f64 = group
+                    .iter()
+                    .map(|column| f64::from(value(column)).powi(2))
+                    .sum();
+                (sum / group.len() as f64).sqrt().round() as u8
+            };
+            Column {
+                peak: group.iter().map(|column| column.peak).max().unwrap_or(0),
+                rms: rms(|column| column.rms),
+                low: rms(|column| column.low),
+                mid: rms(|column| column.mid),
+                high: rms(|column| column.high),
+            }
+        })
+        .collect()
+}
+
+/// Layout, little endian: magic, version, columns per second, source size,
+/// source modification time, duration in ms, column count, then the five
+/// levels of each column.
+fn write_cache(path: &Path, data: &WaveformData, size: i64, modified_at: i64) -> io::Result<()> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN + data.columns.len() * VALUES_PER_COLUMN);
+    bytes.extend_from_slice(CACHE_MAGIC);
+    bytes.push(CACHE_VERSION);
+    bytes.extend_from_slice(&COLUMNS_PER_SECOND.to_le_bytes());
+    bytes.extend_from_slice(&size.to_le_bytes());
+    bytes.extend_from_slice(&modified_at.to_le_bytes());
+    bytes.extend_from_slice(&data.duration_ms.to_le_bytes());
+    bytes.extend_from_slice(&(data.columns.len() as u32).to_le_bytes());
+    for column in &data.columns {
+        bytes.extend_from_slice(&[column.peak, column.rms, column.low, column.mid, column.high]);
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Write beside the target first so a reader never sees half a file. The
+    // name is unique so concurrent requests for one file do not collide.
+    let partial = path.with_extension(format!(
+        "{}-{}.partial",
+        std::process::id(),
+        PARTIAL_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let result = fs::write(&partial, bytes).and_then(|()| fs::rename(&partial, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+/// Reads a cached overview, or `None` when it is missing, unreadable or was
+/// made from a different version of the file.
+fn read_cache(path: &Path, size: i64, modified_at: i64) -> Option<WaveformData> {
+    let bytes = fs::read(path).ok()?;
+    if bytes.len() < HEADER_LEN || &bytes[..4] != CACHE_MAGIC || bytes[4] != CACHE_VERSION {
+        return None;
+    }
+    let field = |offset: usize, length: usize| &bytes[offset..offset + length];
+    let columns_per_second = u32::from_le_bytes(field(5, 4).try_into().ok()?);
+    let cached_size = i64::from_le_bytes(field(9, 8).try_into().ok()?);
+    let cached_modified_at = i64::from_le_bytes(field(17, 8).try_into().ok()?);
+    let duration_ms = u64::from_le_bytes(field(25, 8).try_into().ok()?);
+    let count = u32::from_le_bytes(field(33, 4).try_into().ok()?) as usize;
+    if columns_per_second != COLUMNS_PER_SECOND
+        || cached_size != size
+        || cached_modified_at != modified_at
+        || bytes.len() != HEADER_LEN + count * VALUES_PER_COLUMN
+    {
+        return None;
+    }
+
+    let columns = bytes[HEADER_LEN..]
+        .chunks_exact(VALUES_PER_COLUMN)
+        .map(|values| Column {
+            peak: values[0],
+            rms: values[1],
+            low: values[2],
+            mid: values[3],
+            high: values[4],
+        })
+        .collect();
+    Some(WaveformData {
+        duration_ms,
+        columns,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::LocalAssetRecord;
+    use crate::test_support::memory_library;
+    use std::env;
+
+    fn cache_dir(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("waveform-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn column(peak: u8, rms: u8) -> Column {
+        Column {
+            peak,
+            rms,
+            low: rms,
+            mid: rms / 2,
+            high: 0,
+        }
+    }
+
+    fn levels(columns: &[Column]) -> Vec<[u8; 5]> {
+        columns
+            .iter()
+            .map(|column| [column.peak, column.rms, column.low, column.mid, column.high])
+            .collect()
+    }
+
+    #[test]
+    fn cache_round_trips_and_checks_its_header() {
+        let directory = cache_dir("round-trip");
+        let path = directory.join("track.wave");
+        let data = WaveformData {
+            duration_ms: 1_234,
+            columns: vec![column(255, 180), column(10, 4), column(0, 0)],
+        };
+        write_cache(&path, &data, 4_096, 1_700_000_000).unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(&bytes[..4], CACHE_MAGIC);
+        assert_eq!(bytes.len(), HEADER_LEN + 3 * VALUES_PER_COLUMN);
+        let cached = read_cache(&path, 4_096, 1_700_000_000).unwrap();
+        assert_eq!(cached.duration_ms, 1_234);
+        assert_eq!(levels(&cached.columns), levels(&data.columns));
+        // Only the target file is left behind.
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 1);
+
+        // A changed source file invalidates the overview.
+        assert!(read_cache(&path, 4_097, 1_700_000_000).is_none());
+        assert!(read_cache(&path, 4_096, 1_700_000_001).is_none());
+
+        let mut corrupt = bytes.clone();
+        corrupt[0] = b'X';
+        fs::write(&path, &corrupt).unwrap();
+        assert!(read_cache(&path, 4_096, 1_700_000_000).is_none());
+
+        let mut other_version = bytes.clone();
+        other_version[4] = CACHE_VERSION + 1;
+        fs::write(&path, &other_version).unwrap();
+        assert!(read_cache(&path, 4_096, 1_700_000_000).is_none());
+
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(read_cache(&path, 4_096, 1_700_000_000).is_none());
+        assert!(read_cache(&directory.join("missing.wave"), 4_096, 0).is_none());
+    }
+
+    #[test]
+    fn resample_keeps_the_peak_and_combines_the_rms() {
+        let columns = vec![
+            column(100, 30),
+            column(200, 40),
+            column(50, 0),
+            column(60, 0),
+            column(0, 0),
+            column(255, 255),
+        ];
+        let merged = resample(&columns, 3);
+        assert_eq!(
+            levels(&merged),
+            vec![
+                [200, 35, 35, 18, 0],
+                [60, 0, 0, 0, 0],
+                [255, 180, 180, 90, 0]
+            ]
+        );
+
+        // Uneven groups still cover every column once.
+        let merged = resample(&columns, 4);
+        assert_eq!(merged.len(), 4);
+        assert_eq!(merged.iter().map(|column| column.peak).max(), Some(255));
+
+        // More columns than cached are never invented.
+        assert_eq!(resample(&columns, 10).len(), 6);
+        assert_eq!(levels(&resample(&columns, 1)), vec![[255, 106, 106, 53, 0]]);
+        assert_eq!(resample(&columns, 0).len(), 1);
+    }
+
+    #[test]
+    fn prune_deletes_overviews_of_paths_that_are_no_longer_assets() {
+        let directory = cache_dir("prune");
+        let cache = WaveformCache::new(directory.clone());
+        let library = memory_library();
+        assert_eq!(cache.prune(&library).unwrap(), 0);
+
+        library
+            .lock()
+            .unwrap()
+            .record_local_asset(&LocalAssetRecord {
+                track_id: "sc-1".to_string(),
+                location: "/music/kept.wav".to_string(),
+                checksum: None,
+                available: true,
+                duration_ms: None,
+                file_size: None,
+                rekordbox_cues: None,
+            })
+            .unwrap();
+        let data = WaveformData {
+            duration_ms: 10,
+            columns: vec![column(1, 1)],
+        };
+        let kept = cache.cache_path("/music/kept.wav");
+        let stale = cache.cache_path("/music/moved.wav");
+        let unrelated = directory.join("notes.txt");
+        write_cache(&kept, &data, 1, 1).unwrap();
+        write_cache(&stale, &data, 1, 1).unwrap();
+        fs::write(&unrelated, "keep").unwrap();
+
+        assert_eq!(cache.prune(&library).unwrap(), 1);
+        assert!(kept.exists());
+        assert!(!stale.exists());
+        assert!(unrelated.exists());
+    }
+}
@@ -5,6 +5,8 @@ import { listen, emit, type UnlistenFn } from "@tauri-apps/api/event";
 const DISCOGS_AMBIGUITY_EVENT = "app://discogs/lookup-ambiguous";
 const MUSICBRAINZ_AMBIGUITY_EVENT = "app://musicbrainz/lookup-ambiguous";
 const JOB_PROGRESS_EVENT = "app://jobs/progress";
+const SETTINGS_CHANGED_EVENT = "app://settings/changed";
+const DEFAULT_SIDEBAR_WIDTH = 320;
 const DEFAULT_PAGE_SIZE = 50;
 
 type Nullable<T> = T | null;
@@ -36,6 +38,8 @@ type LibraryStatusRow = {
   soundcloudPermalinkUrl?: string | null;
   soundcloudLikedAt?: string | null;
   localLocation?: string | null;
+  healthStatus?: "healthy" | "suspect" | "damaged" | null;
+  healthMessage?: string | null;
 };
 
 type LibraryStatusPage = {
@@ -50,6 +54,7 @@ type FilterState = {
   unresolvedDiscogsOnly: boolean;
   likedOnly: boolean;
   rekordboxOnly: boolean;
+  unhealthyOnly: boolean;
 };
 
 type DiscogsCandidatePayload = {
@@ -125,6 +130,63 @@ type JobRecord = {
   updatedAt: number;
 };
 
+type AppSettings = {
+  version: number;
+  musicbrainz: {
+    appName?: string | null;
+    appVersion?: string | null;
+    contact?: string | null;
+    token?: string | null;
+  };
+  window: {
+    sidebarWidth: number;
+  };
+  shortcuts: {
+    playPause: string;
+    nextTrack: string;
+    previousTrack: string;
+  };
+  rekordbox: {
+    sources: RekordboxSource[];
+  };
+};
+
+type RekordboxSource = {
+  id: string;
+  label?: string | null;
+  path: string;
+  format: "masterDb" | "xml" | "deviceExport";
+  autoRefresh: boolean;
+};
+
+type DjSoftware = "traktor" | "serato";
+
+type DiagnosticsExport = {
+  path: string;
+  logFiles: number;
+};
+
+type CommandErrorCode =
+  | "notFound"
+  | "validation"
+  | "database"
+  | "io"
+  | "rekordbox"
+  | "lockPoisoned"
+  | "network"
+  | "internal";
+
+type CommandError = {
+  code: CommandErrorCode;
+  message: string;
+  entity?: string;
+  id?: string;
+  field?: string | null;
+  path?: string | null;
+  resource?: string;
+  service?: string;
+};
+
 type AsyncState<T> =
   | { status: "idle" }
   | { status: "loading" }
@@ -313,6 +375,12 @@ const Checkbox = ({
   </label>
 );
 
+const isCommandError = (error: unknown): error is CommandError =>
+  typeof error === "object" &&
+  error !== null &&
+  typeof (error as CommandError).code === "string" &&
+  typeof (error as CommandError).message === "string";
+
 const getErrorMessage = (error: unknown) => {
   if (typeof error === "string") {
     return error;
@@ -320,6 +388,9 @@ const getErrorMessage = (error: unknown) => {
   if (error instanceof Error) {
     return error.message;
   }
+  if (isCommandError(error)) {
+    return error.message;
+  }
   try {
     return JSON.stringify(error);
   } catch (_jsonError) {
@@ -331,11 +402,13 @@ type ViewMode = "soundcloud" | "bandcamp" | "database" | "filesystem";
 
 const App = () => {
   const [mode, setMode] = useState<ViewMode>("soundcloud");
+  const [sidebarWidth, setSidebarWidth] = useState(DEFAULT_SIDEBAR_WIDTH);
   const [filters, setFilters] = useState<FilterState>({
     missingAssetsOnly: false,
     unresolvedDiscogsOnly: false,
     likedOnly: false,
     rekordboxOnly: false,
+    unhealthyOnly: false,
   });
   const [tracks, setTracks] = useState<LibraryStatusRow[]>([]);
   const [totalTracks, setTotalTracks] = useState(0);
@@ -380,6 +453,7 @@ const App = () => {
       unresolvedDiscogsOnly: filters.unresolvedDiscogsOnly,
       likedOnly: filters.likedOnly,
       rekordboxOnly: filters.rekordboxOnly,
+      unhealthyOnly: filters.unhealthyOnly,
       limit: DEFAULT_PAGE_SIZE,
     }),
     [filters]
@@ -424,6 +498,38 @@ const App = () => {
     });
   }, [fetchTracks]);
 
+  useEffect(() => {
+    let unlisten: UnlistenFn | null = null;
+    invoke<AppSettings>("get_settings")
+      .then((settings) => setSidebarWidth(settings.window.sidebarWidth))
+      .catch((error) => {
+        console.warn("No se pudieron cargar los ajustes", error);
+      });
+    listen<AppSettings>(SETTINGS_CHANGED_EVENT, (event) => {
+      if (event.payload?.window) {
+        setSidebarWidth(event.payload.window.sidebarWidth);
+      }
+    })
+      .then((dispose) => {
+        unlisten = dispose;
+      })
+      .catch((error) => {
+        console.warn("No se pudo suscribir a cambios de ajustes", error);
+      });
+    return () => {
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, []);
+
+  useEffect(() => {
+    document.documentElement.style.setProperty(
+      "--sidebar-width",
+      `${sidebarWidth}px`
+    );
+  }, [sidebarWidth]);
+
   // Notify backend to show/hide SC/BC webviews to the right of the sidebar
   useEffect(() => {
     (async () => {
@@ -433,7 +539,7 @@ const App = () => {
         // ignore
       }
     })();
-  }, [mode]);
+  }, [mode, sidebarWidth]);
 
   const switchMode = (next: ViewMode) => {
     setMode(next);
@@ -958,17 +1064,60 @@ const App = () => {
   const handleImportRekordbox = async () => {
     try {
       const selected = window.prompt(
-        "Introduce la ruta del archivo de biblioteca de Rekordbox",
+        "Introduce la ruta de la biblioteca de Rekordbox o de la unidad USB",
         ""
       );
       if (!selected) {
         setStatusMessage({ type: "info", text: "Importación cancelada" });
         return;
       }
-      await invoke("import_rekordbox_library", { dbPath: selected });
+      const source = await invoke<RekordboxSource>(
+        "import_rekordbox_library",
+        { dbPath: selected }
+      );
       setStatusMessage({
         type: "success",
-        text: "Importación de Rekordbox iniciada",
+        text: source.autoRefresh
+          ? `Rekordbox importado en la fuente ${source.label ?? source.id}; se actualizará automáticamente`
+          : `Rekordbox importado en la fuente ${source.label ?? source.id}`,
+      });
+    } catch (error) {
+      setStatusMessage({ type: "error", text: getErrorMessage(error) });
+    }
+  };
+
+  const handleImportDjLibrary = async (software: DjSoftware) => {
+    const name = software === "traktor" ? "Traktor" : "Serato";
+    try {
+      const selected = window.prompt(
+        software === "traktor"
+          ? "Introduce la ruta de collection.nml de Traktor o de su carpeta"
+          : "Introduce la ruta de la carpeta _Serato_",
+        ""
+      );
+      if (!selected) {
+        setStatusMessage({ type: "info", text: "Importación cancelada" });
+        return;
+      }
+      const count = await invoke<number>("import_dj_library", {
+        software,
+        path: selected,
+      });
+      setStatusMessage({
+        type: "success",
+        text: `${count} pistas importadas de ${name}`,
+      });
+    } catch (error) {
+      setStatusMessage({ type: "error", text: getErrorMessage(error) });
+    }
+  };
+
+  const handleExportDiagnostics = async () => {
+    try {
+      const result = await invoke<DiagnosticsExport>("export_diagnostics");
+      setStatusMessage({
+        type: "success",
+        text: `Diagnóstico exportado a ${result.path}`,
       });
     } catch (error) {
       setStatusMessage({ type: "error", text: getErrorMessage(error) });
@@ -1159,6 +1308,22 @@ const App = () => {
                 Importar biblioteca Rekordbox
               </button>
             </li>
+            <li>
+              <button
+                type="button"
+                className="nav-list__button"
+                onClick={() => handleImportDjLibrary("traktor")}>
+                Importar colección Traktor
+              </button>
+            </li>
+            <li>
+              <button
+                type="button"
+                className="nav-list__button"
+                onClick={() => handleImportDjLibrary("serato")}>
+                Importar biblioteca Serato
+              </button>
+            </li>
           </ul>
           <h3 className="nav-list__group">Vistas guardadas</h3>
           <ul className="nav-list nav-list--sub">
@@ -1204,6 +1369,16 @@ const App = () => {
                 En Rekordbox
               </button>
             </li>
+            <li>
+              <button
+                type="button"
+                className={`nav-list__button ${
+                  filters.unhealthyOnly ? "is-active" : ""
+                }`}
+                onClick={() => handleFilterChange("unhealthyOnly")(true)}>
+                Archivos dañados o sospechosos
+              </button>
+            </li>
           </ul>
         </nav>
         <section className="sidebar__filters">
@@ -1233,6 +1408,12 @@ const App = () => {
               checked={filters.rekordboxOnly}
               onChange={handleFilterChange("rekordboxOnly")}
             />
+            <Checkbox
+              id="filter-unhealthy"
+              label="Solo archivos dañados o sospechosos"
+              checked={filters.unhealthyOnly}
+              onChange={handleFilterChange("unhealthyOnly")}
+            />
           </div>
         </section>
         <section className="sidebar__list" aria-live="polite">
@@ -1292,6 +1473,12 @@ const App = () => {
                       {row.inRekordbox && (
                         <Badge label="Rekordbox" variant="neutral" />
                       )}
+                      {row.healthStatus === "damaged" && (
+                        <Badge label="Archivo dañado" variant="danger" />
+                      )}
+                      {row.healthStatus === "suspect" && (
+                        <Badge label="Posible upsampling" variant="warning" />
+                      )}
                       {conflict && (
                         <Badge label="Conflicto" variant="warning" />
                       )}
@@ -1356,6 +1543,24 @@ const App = () => {
               onClick={handleImportRekordbox}>
               Importar biblioteca de Rekordbox
             </button>
+            <button
+              type="button"
+              className="button"
+              onClick={() => handleImportDjLibrary("traktor")}>
+              Importar colección de Traktor
+            </button>
+            <button
+              type="button"
+              className="button"
+              onClick={() => handleImportDjLibrary("serato")}>
+              Importar biblioteca de Serato
+            </button>
+            <button
+              type="button"
+              className="button button--ghost"
+              onClick={handleExportDiagnostics}>
+              Exportar diagnóstico
+            </button>
           </div>
           {statusMessage && (
             <div